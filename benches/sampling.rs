@@ -0,0 +1,64 @@
+//! Benchmarks for percentage sampling on the request hot path.
+//!
+//! Run with `cargo bench --bench sampling`. The `thread_rng` group is the
+//! previous implementation, kept as a baseline for comparison.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::Rng;
+use std::time::{Duration, Instant};
+use zentinel_agent_chaos::config::Targeting;
+use zentinel_agent_chaos::targeting::CompiledTargeting;
+
+const THREADS: usize = 8;
+
+/// Run `f` on `THREADS` threads at once, `iters` times each.
+fn contended<F: Fn() + Sync>(iters: u64, f: F) -> Duration {
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..iters {
+                    f();
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn bench_should_apply(c: &mut Criterion) {
+    let targeting = CompiledTargeting::new(&Targeting {
+        percentage: 10,
+        ..Targeting::default()
+    })
+    .unwrap();
+
+    let mut group = c.benchmark_group("should_apply");
+    group.bench_function("single_thread", |b| {
+        b.iter(|| black_box(targeting.should_apply()))
+    });
+    group.bench_function("contended", |b| {
+        b.iter_custom(|iters| {
+            contended(iters, || {
+                black_box(targeting.should_apply());
+            })
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("thread_rng");
+    group.bench_function("single_thread", |b| {
+        b.iter(|| black_box(rand::thread_rng().gen_range(0..100u8) < 10))
+    });
+    group.bench_function("contended", |b| {
+        b.iter_custom(|iters| {
+            contended(iters, || {
+                black_box(rand::thread_rng().gen_range(0..100u8) < 10);
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_should_apply);
+criterion_main!(benches);
@@ -0,0 +1,228 @@
+//! Automatic abort when upstream health degrades.
+//!
+//! Chaos should stop contributing as soon as the service is genuinely
+//! unhealthy. The tracker records the status of real (non-chaos) upstream
+//! responses, and optionally of a periodic probe, over a sliding window.
+//! When the 5xx rate crosses the threshold it trips, suppressing all
+//! injections until the cooldown elapses.
+
+use crate::config::AutoAbortConfig;
+use crate::probe::{probe, ProbeUrl};
+use crate::window::SlidingWindow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Upstream error-rate tracker with trip and cooldown.
+pub struct AutoAbort {
+    config: AutoAbortConfig,
+    probe_url: Option<ProbeUrl>,
+    started: Instant,
+    samples: SlidingWindow,
+    errors: SlidingWindow,
+    tripped: AtomicBool,
+    /// Second (since start) at which the abort last tripped.
+    tripped_at: AtomicU64,
+}
+
+impl AutoAbort {
+    /// Create a tracker from configuration.
+    pub fn new(config: AutoAbortConfig) -> Self {
+        let probe_url = config
+            .probe_url
+            .as_deref()
+            .and_then(|url| match ProbeUrl::parse(url) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    warn!(error = %e, "Ignoring invalid auto-abort probe URL");
+                    None
+                }
+            });
+        Self {
+            samples: SlidingWindow::new(config.window_s),
+            errors: SlidingWindow::new(config.window_s),
+            config,
+            probe_url,
+            started: Instant::now(),
+            tripped: AtomicBool::new(false),
+            tripped_at: AtomicU64::new(0),
+        }
+    }
+
+    fn now_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Record an upstream response status.
+    pub fn record_status(&self, status: u16) {
+        self.record_sample_at(self.now_secs(), status >= 500);
+    }
+
+    /// Record a health sample at second `now`, tripping if the error rate
+    /// crosses the threshold.
+    pub fn record_sample_at(&self, now: u64, is_error: bool) {
+        self.samples.record(now, 1);
+        if !is_error {
+            return;
+        }
+        self.errors.record(now, 1);
+
+        let samples = self.samples.sum(now);
+        if samples < self.config.min_requests {
+            return;
+        }
+        let error_rate = self.errors.sum(now) as f64 / samples as f64;
+        if error_rate > self.config.error_rate_threshold
+            && !self.tripped.swap(true, Ordering::AcqRel)
+        {
+            self.tripped_at.store(now, Ordering::Release);
+            warn!(
+                error_rate = error_rate,
+                threshold = self.config.error_rate_threshold,
+                cooldown_s = self.config.cooldown_s,
+                "Upstream error rate above threshold, auto-aborting chaos"
+            );
+        }
+    }
+
+    /// Check whether chaos is currently aborted.
+    pub fn is_tripped(&self) -> bool {
+        self.is_tripped_at(self.now_secs())
+    }
+
+    /// Check whether chaos is aborted at second `now`, recovering once the
+    /// cooldown has elapsed.
+    pub fn is_tripped_at(&self, now: u64) -> bool {
+        if !self.tripped.load(Ordering::Acquire) {
+            return false;
+        }
+        let tripped_at = self.tripped_at.load(Ordering::Acquire);
+        if now < tripped_at + self.config.cooldown_s {
+            return true;
+        }
+        if self
+            .tripped
+            .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            info!("Auto-abort cooldown elapsed, resuming chaos");
+        }
+        false
+    }
+
+    /// Spawn the background probe task if a probe URL is configured.
+    ///
+    /// The task holds only a weak reference and exits once the tracker is
+    /// dropped.
+    pub fn spawn_probe(self: &Arc<Self>) {
+        if self.probe_url.is_none() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(run_probe(Arc::downgrade(self)));
+            }
+            Err(_) => warn!("No async runtime available, auto-abort probe disabled"),
+        }
+    }
+
+    /// Probe the upstream once and record the result.
+    pub async fn probe_once(&self) {
+        let Some(url) = &self.probe_url else {
+            return;
+        };
+        let is_error = match probe(url).await {
+            Ok(status) => status >= 500,
+            Err(e) => {
+                warn!(error = %e, "Auto-abort probe failed");
+                true
+            }
+        };
+        self.record_sample_at(self.now_secs(), is_error);
+    }
+}
+
+async fn run_probe(abort: Weak<AutoAbort>) {
+    loop {
+        let interval = match abort.upgrade() {
+            Some(abort) => {
+                abort.probe_once().await;
+                Duration::from_secs(abort.config.probe_interval_s)
+            }
+            None => return,
+        };
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::tests::mock_server;
+    use std::sync::atomic::AtomicU16;
+
+    fn abort_config() -> AutoAbortConfig {
+        AutoAbortConfig {
+            error_rate_threshold: 0.05,
+            window_s: 60,
+            probe_url: None,
+            probe_interval_s: 10,
+            cooldown_s: 300,
+            min_requests: 20,
+        }
+    }
+
+    #[test]
+    fn test_trips_above_threshold() {
+        let abort = AutoAbort::new(abort_config());
+        for _ in 0..95 {
+            abort.record_sample_at(0, false);
+        }
+        for _ in 0..5 {
+            abort.record_sample_at(0, true);
+        }
+        // Exactly 5% is not above the threshold
+        assert!(!abort.is_tripped_at(0));
+
+        abort.record_sample_at(1, true);
+        assert!(abort.is_tripped_at(1));
+    }
+
+    #[test]
+    fn test_needs_minimum_samples() {
+        let abort = AutoAbort::new(abort_config());
+        for _ in 0..10 {
+            abort.record_sample_at(0, true);
+        }
+        assert!(!abort.is_tripped_at(0));
+    }
+
+    #[test]
+    fn test_recovers_after_cooldown() {
+        let abort = AutoAbort::new(abort_config());
+        for _ in 0..20 {
+            abort.record_sample_at(0, true);
+        }
+        assert!(abort.is_tripped_at(299));
+        assert!(!abort.is_tripped_at(300));
+        assert!(!abort.is_tripped_at(301));
+    }
+
+    #[tokio::test]
+    async fn test_probe_failures_trip() {
+        let status = Arc::new(AtomicU16::new(200));
+        let mut config = abort_config();
+        config.probe_url = Some(mock_server(Arc::clone(&status)).await);
+        config.min_requests = 2;
+        let abort = AutoAbort::new(config);
+
+        abort.probe_once().await;
+        abort.probe_once().await;
+        assert!(!abort.is_tripped());
+
+        status.store(503, Ordering::SeqCst);
+        abort.probe_once().await;
+        assert!(abort.is_tripped());
+    }
+}
@@ -0,0 +1,302 @@
+//! Access log parsing for the `replay` subcommand.
+//!
+//! Supports the Common and Combined log formats written by most web
+//! servers and proxies, and JSON lines with configurable field names.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// `host ident user [time] "request" status bytes`, optionally followed by
+/// `"referer" "user-agent"`.
+const CLF_PATTERN: &str = r#"^\S+ \S+ \S+ \[([^\]]+)\] "((?:[^"\\]|\\.)*)" \d{3} \S+(?: "((?:[^"\\]|\\.)*)" "((?:[^"\\]|\\.)*)")?"#;
+
+/// Timestamp format of the Common Log Format, e.g. `10/Oct/2000:13:55:36 -0700`.
+const CLF_TIME_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+/// Access log line format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// NCSA Common Log Format.
+    Common,
+    /// Common Log Format plus referer and user agent.
+    Combined,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Field names read from JSON lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonFields {
+    pub method: String,
+    /// Request path, optionally with a query string.
+    pub path: String,
+    /// Object of header names to values (strings or arrays of strings).
+    pub headers: String,
+    /// RFC 3339 timestamp.
+    pub time: String,
+}
+
+impl Default for JsonFields {
+    fn default() -> Self {
+        Self {
+            method: "method".to_string(),
+            path: "path".to_string(),
+            headers: "headers".to_string(),
+            time: "time".to_string(),
+        }
+    }
+}
+
+/// A request read from an access log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub method: String,
+    pub path: String,
+    /// Headers, with lowercased names.
+    pub headers: HashMap<String, String>,
+    /// When the request was served, if the line records it.
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Parses access log lines in one format.
+#[derive(Debug, Clone)]
+pub struct LogParser {
+    format: LogFormat,
+    fields: JsonFields,
+    clf: Regex,
+}
+
+impl LogParser {
+    /// Create a parser. `fields` only applies to [`LogFormat::Json`].
+    pub fn new(format: LogFormat, fields: JsonFields) -> Self {
+        Self {
+            format,
+            fields,
+            clf: Regex::new(CLF_PATTERN).expect("valid access log regex"),
+        }
+    }
+
+    /// Parse one line.
+    pub fn parse(&self, line: &str) -> Result<LogEntry> {
+        match self.format {
+            LogFormat::Common | LogFormat::Combined => self.parse_clf(line),
+            LogFormat::Json => self.parse_json(line),
+        }
+    }
+
+    fn parse_clf(&self, line: &str) -> Result<LogEntry> {
+        let captures = self
+            .clf
+            .captures(line)
+            .ok_or_else(|| anyhow!("not a {} log line", self.format_name()))?;
+        let time = DateTime::parse_from_str(&captures[1], CLF_TIME_FORMAT)
+            .with_context(|| format!("invalid timestamp '{}'", &captures[1]))?
+            .with_timezone(&Utc);
+
+        let mut request = captures[2].split_whitespace();
+        let (Some(method), Some(path)) = (request.next(), request.next()) else {
+            bail!("invalid request line '{}'", &captures[2]);
+        };
+        if !path.starts_with('/') {
+            bail!("invalid request path '{}'", path);
+        }
+
+        let mut headers = HashMap::new();
+        if self.format == LogFormat::Combined {
+            let (Some(referer), Some(user_agent)) = (captures.get(3), captures.get(4)) else {
+                bail!("missing referer and user agent");
+            };
+            for (name, value) in [("referer", referer), ("user-agent", user_agent)] {
+                if value.as_str() != "-" {
+                    headers.insert(name.to_string(), unescape(value.as_str()));
+                }
+            }
+        }
+
+        Ok(LogEntry {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers,
+            time: Some(time),
+        })
+    }
+
+    fn parse_json(&self, line: &str) -> Result<LogEntry> {
+        let value: serde_json::Value = serde_json::from_str(line).context("invalid JSON")?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| anyhow!("expected a JSON object"))?;
+        let string = |field: &str| -> Result<Option<&str>> {
+            match object.get(field) {
+                None | Some(serde_json::Value::Null) => Ok(None),
+                Some(serde_json::Value::String(s)) => Ok(Some(s)),
+                Some(_) => bail!("field '{}' must be a string", field),
+            }
+        };
+
+        let method = string(&self.fields.method)?
+            .ok_or_else(|| anyhow!("missing field '{}'", self.fields.method))?;
+        let path = string(&self.fields.path)?
+            .ok_or_else(|| anyhow!("missing field '{}'", self.fields.path))?;
+        let time = string(&self.fields.time)?
+            .map(|time| {
+                DateTime::parse_from_rfc3339(time)
+                    .map(|time| time.with_timezone(&Utc))
+                    .with_context(|| format!("invalid timestamp '{}'", time))
+            })
+            .transpose()?;
+
+        let mut headers = HashMap::new();
+        match object.get(&self.fields.headers) {
+            None | Some(serde_json::Value::Null) => {}
+            Some(serde_json::Value::Object(map)) => {
+                for (name, value) in map {
+                    let value = match value {
+                        serde_json::Value::String(s) => s.as_str(),
+                        serde_json::Value::Array(values) => match values.first() {
+                            Some(serde_json::Value::String(s)) => s.as_str(),
+                            _ => continue,
+                        },
+                        _ => bail!("header '{}' must be a string", name),
+                    };
+                    headers.insert(name.to_lowercase(), value.to_string());
+                }
+            }
+            Some(_) => bail!("field '{}' must be an object", self.fields.headers),
+        }
+
+        Ok(LogEntry {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers,
+            time,
+        })
+    }
+
+    fn format_name(&self) -> &'static str {
+        match self.format {
+            LogFormat::Common => "common",
+            LogFormat::Combined => "combined",
+            LogFormat::Json => "JSON",
+        }
+    }
+}
+
+/// Undo backslash escaping in a quoted log field.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                out.push(escaped);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn parser(format: LogFormat) -> LogParser {
+        LogParser::new(format, JsonFields::default())
+    }
+
+    #[test]
+    fn test_parse_combined() {
+        let line = r#"203.0.113.9 - alice [10/Oct/2024:13:55:36 -0700] "POST /api/payments?id=1 HTTP/1.1" 200 2326 "https://shop.example/" "curl/8.0 \"beta\"""#;
+        let entry = parser(LogFormat::Combined).parse(line).unwrap();
+        assert_eq!(entry.method, "POST");
+        assert_eq!(entry.path, "/api/payments?id=1");
+        assert_eq!(
+            entry.time,
+            Some(Utc.with_ymd_and_hms(2024, 10, 10, 20, 55, 36).unwrap())
+        );
+        assert_eq!(entry.headers["referer"], "https://shop.example/");
+        assert_eq!(entry.headers["user-agent"], "curl/8.0 \"beta\"");
+
+        // The common format ignores the trailing fields
+        let entry = parser(LogFormat::Common).parse(line).unwrap();
+        assert!(entry.headers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_common_rejects_malformed_lines() {
+        let parser = parser(LogFormat::Common);
+        assert!(parser
+            .parse(r#"::1 - - [10/Oct/2024:13:55:36 +0000] "GET / HTTP/1.1" 200 -"#)
+            .is_ok());
+
+        for (line, error) in [
+            ("", "not a common log line"),
+            ("garbage", "not a common log line"),
+            (
+                r#"::1 - - [yesterday] "GET / HTTP/1.1" 200 -"#,
+                "invalid timestamp 'yesterday'",
+            ),
+            (
+                r#"::1 - - [10/Oct/2024:13:55:36 +0000] "-" 400 -"#,
+                "invalid request line '-'",
+            ),
+            (
+                r#"::1 - - [10/Oct/2024:13:55:36 +0000] "GET http://evil/ HTTP/1.1" 400 -"#,
+                "invalid request path 'http://evil/'",
+            ),
+        ] {
+            let err = parser.parse(line).unwrap_err().to_string();
+            assert_eq!(err, error, "{}", line);
+        }
+
+        let err = self::parser(LogFormat::Combined)
+            .parse(r#"::1 - - [10/Oct/2024:13:55:36 +0000] "GET / HTTP/1.1" 200 -"#)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "missing referer and user agent");
+    }
+
+    #[test]
+    fn test_parse_json_lines() {
+        let parser = LogParser::new(
+            LogFormat::Json,
+            JsonFields {
+                method: "verb".to_string(),
+                path: "uri".to_string(),
+                ..JsonFields::default()
+            },
+        );
+        let entry = parser
+            .parse(
+                r#"{"verb":"GET","uri":"/api/x","time":"2024-10-10T12:00:00Z","headers":{"X-User-Id":"42","accept":["text/html","*/*"]}}"#,
+            )
+            .unwrap();
+        assert_eq!(entry.method, "GET");
+        assert_eq!(entry.path, "/api/x");
+        assert_eq!(entry.headers["x-user-id"], "42");
+        assert_eq!(entry.headers["accept"], "text/html");
+        assert!(entry.time.is_some());
+
+        for (line, error) in [
+            ("{", "invalid JSON"),
+            ("[1]", "expected a JSON object"),
+            (r#"{"uri":"/"}"#, "missing field 'verb'"),
+            (r#"{"verb":1,"uri":"/"}"#, "field 'verb' must be a string"),
+            (
+                r#"{"verb":"GET","uri":"/","time":"noon"}"#,
+                "invalid timestamp 'noon'",
+            ),
+            (
+                r#"{"verb":"GET","uri":"/","headers":"x"}"#,
+                "field 'headers' must be an object",
+            ),
+        ] {
+            let err = parser.parse(line).unwrap_err().to_string();
+            assert_eq!(err, error, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,1042 @@
+//! Embedded HTTP admin API, enabled with `--admin-address`.
+//!
+//! Served by the minimal listener in [`crate::http`]: one request per
+//! connection, answered and closed.
+//!
+//! - `GET /status` summarizes the agent
+//! - `GET /experiments` lists experiments with their live state
+//! - `POST /experiments` with an experiment as JSON adds it, and
+//!   `DELETE /experiments/{id}` removes an experiment added this way
+//! - `POST /experiments/{id}/enable` and `/disable` toggle an experiment
+//! - `POST /experiments/{id}/percentage` with `{"percentage": N}` changes
+//!   its percentage
+//! - `POST /experiments/{id}/trigger-once` faults the next matching request
+//!   regardless of percentage, or the next `N` with `?count=N`
+//! - `POST /chaos/pause` and `/chaos/resume` pause and resume injections;
+//!   `{"duration_s": N}` resumes them on its own after `N` seconds
+//! - `POST /chaos/undrain` lifts a drain requested by the proxy
+//! - `GET /events` streams injections as Server-Sent Events
+//! - `GET /metrics` serves Prometheus metrics, without a token, when
+//!   `--metrics-address` names the same address
+//!
+//! Changes are layered over the configuration file and last until the
+//! process exits. Added experiments are dropped on reload unless
+//! `settings.persist_runtime` is set.
+//!
+//! Every endpoint other than `GET` requires `admin.auth_token` as an
+//! `Authorization: Bearer` header; reads do too unless
+//! `admin.allow_unauthenticated_reads` is set. Without a token configured
+//! the API is read-only. Each change is logged to the
+//! `zentinel_agent_chaos::audit` target with the caller's address and the
+//! previous and new values.
+
+use crate::agent::{ChaosControl, InjectionRecord};
+use crate::bypass::constant_time_eq;
+use crate::config::AdminConfig;
+use crate::http::{self, read_request, Request, READ_TIMEOUT};
+use crate::prometheus;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Longest bounded pause, one week; longer pauses should be open-ended.
+const MAX_PAUSE_S: u64 = 7 * 24 * 3600;
+
+/// Interval of comment lines on an idle event stream, which keep proxies
+/// from closing it and notice clients that went away.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Bind the admin listener and serve it in a background task. With
+/// `metrics`, the listener also serves Prometheus metrics at `/metrics`.
+pub async fn spawn_admin_server(
+    address: SocketAddr,
+    control: ChaosControl,
+    metrics: bool,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("cannot bind admin address {}", address))?;
+    info!(address = %listener.local_addr()?, metrics, "Admin API listening");
+    Ok(serve_with(listener, control, metrics))
+}
+
+/// Serve admin requests from a bound listener in a background task.
+pub fn serve(listener: TcpListener, control: ChaosControl) -> JoinHandle<()> {
+    serve_with(listener, control, false)
+}
+
+fn serve_with(listener: TcpListener, control: ChaosControl, metrics: bool) -> JoinHandle<()> {
+    http::serve(listener, "Admin", move |stream, peer| {
+        let control = control.clone();
+        async move { handle(stream, peer, &control, metrics).await }
+    })
+}
+
+/// Answer to an admin request.
+enum Response {
+    /// A JSON document with a status code.
+    Json(u16, Value),
+    /// Switch the connection to the injection event stream.
+    Events,
+}
+
+/// Values an admin request changed.
+struct Change {
+    previous: Value,
+    new: Value,
+}
+
+/// A change made through the admin API.
+#[derive(Debug, Serialize)]
+struct AuditRecord {
+    timestamp: DateTime<Utc>,
+    remote_addr: SocketAddr,
+    /// Method and path, e.g. `POST /experiments/api-errors/disable`.
+    endpoint: String,
+    previous: Value,
+    new: Value,
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    control: &ChaosControl,
+    metrics: bool,
+) -> Result<()> {
+    let (read, mut write) = stream.split();
+    let mut reader = BufReader::new(read);
+    let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => {
+            respond(&mut write, 400, &json!({ "error": e.to_string() })).await?;
+            return Err(e);
+        }
+        Err(_) => bail!("timed out reading the request"),
+    };
+    debug!(method = %request.method, path = %request.path, "Admin request");
+
+    // Scrapes need no token, as on a separate metrics listener
+    if metrics && request.path == "/metrics" {
+        return prometheus::respond(&mut write, &request, control).await;
+    }
+
+    let (response, audit) = dispatch(&request, peer, control);
+    if let Some(record) = audit {
+        info!(
+            target: "zentinel_agent_chaos::audit",
+            timestamp = %record.timestamp.to_rfc3339(),
+            remote_addr = %record.remote_addr,
+            endpoint = %record.endpoint,
+            previous = %record.previous,
+            new = %record.new,
+            "Admin API change"
+        );
+    }
+    match response {
+        Response::Json(status, body) => respond(&mut write, status, &body).await,
+        Response::Events => stream_events(&mut write, control.subscribe_injections()).await,
+    }
+}
+
+/// Authorize and route a request, returning the audit record of the
+/// change it made, if any.
+fn dispatch(
+    request: &Request,
+    peer: SocketAddr,
+    control: &ChaosControl,
+) -> (Response, Option<AuditRecord>) {
+    let endpoint = format!("{} {}", request.method, request.path);
+    if let Err(response) = authorize(request, &control.admin_config()) {
+        if matches!(response, Response::Json(401, _)) {
+            control.record_admin_unauthorized();
+            warn!(peer = %peer, endpoint = %endpoint, "Admin request without a valid token");
+        }
+        return (response, None);
+    }
+
+    let (response, change) = route(request, control);
+    let audit = change.map(|change| AuditRecord {
+        timestamp: Utc::now(),
+        remote_addr: peer,
+        endpoint,
+        previous: change.previous,
+        new: change.new,
+    });
+    (response, audit)
+}
+
+/// Check the bearer token a request needs, if any.
+fn authorize(request: &Request, admin: &AdminConfig) -> Result<(), Response> {
+    let mutating = request.method != "GET";
+    let Some(token) = &admin.auth_token else {
+        if mutating {
+            return Err(error(
+                403,
+                "admin.auth_token is not configured, the admin API is read-only",
+            ));
+        }
+        return Ok(());
+    };
+    if !mutating && admin.allow_unauthenticated_reads {
+        return Ok(());
+    }
+
+    let presented = request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim());
+    match presented {
+        Some(presented) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => Ok(()),
+        Some(_) => Err(error(401, "invalid bearer token")),
+        None => Err(error(401, "missing bearer token")),
+    }
+}
+
+fn route(request: &Request, control: &ChaosControl) -> (Response, Option<Change>) {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["status"]) => (ok(&control.status()), None),
+        ("GET", ["experiments"]) => (ok(&control.experiments()), None),
+        ("POST", ["experiments"]) => create(control, &request.body),
+        ("DELETE", ["experiments", id]) => delete(control, id),
+        ("POST", ["experiments", id, "enable"]) => set_enabled(control, id, true),
+        ("POST", ["experiments", id, "disable"]) => set_enabled(control, id, false),
+        ("POST", ["experiments", id, "percentage"]) => set_percentage(control, id, &request.body),
+        ("POST", ["experiments", id, "trigger-once"]) => trigger(control, id, &request.query),
+        ("POST", ["chaos", "pause"]) => pause(control, &request.body),
+        ("POST", ["chaos", "resume"]) => {
+            let was_paused = control.resume();
+            if was_paused {
+                info!("Chaos injections resumed via admin API");
+            }
+            paused(was_paused, false)
+        }
+        ("POST", ["chaos", "undrain"]) => undrain(control),
+        ("GET", ["events"]) => (Response::Events, None),
+        (
+            _,
+            ["status"]
+            | ["experiments"]
+            | ["experiments", _]
+            | ["experiments", _, "enable" | "disable" | "percentage" | "trigger-once"]
+            | ["chaos", "pause" | "resume" | "undrain"]
+            | ["events"],
+        ) => (error(405, "method not allowed"), None),
+        _ => (error(404, "no such endpoint"), None),
+    }
+}
+
+fn pause(control: &ChaosControl, body: &[u8]) -> (Response, Option<Change>) {
+    let duration = match parse_pause_duration(body) {
+        Ok(duration) => duration,
+        Err(e) => return (error(400, &e.to_string()), None),
+    };
+    let was_running = match duration {
+        Some(duration) => {
+            let was_running = control.pause_for(duration);
+            warn!(
+                duration_s = duration.as_secs(),
+                "Chaos injections paused via admin API"
+            );
+            was_running
+        }
+        None => {
+            let was_running = control.pause();
+            if was_running {
+                warn!("Chaos injections paused via admin API");
+            }
+            was_running
+        }
+    };
+    let Some(duration) = duration else {
+        return paused(!was_running, true);
+    };
+    let change = Change {
+        previous: json!({ "paused": !was_running }),
+        new: json!({ "paused": true, "duration_s": duration.as_secs() }),
+    };
+    let body = json!({ "paused": true, "remaining_seconds": duration.as_secs() });
+    (ok(&body), Some(change))
+}
+
+/// Read an optional `{"duration_s": N}`, `None` for an empty body.
+fn parse_pause_duration(body: &[u8]) -> Result<Option<Duration>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let body: Value = serde_json::from_slice(body).context("expected a JSON object")?;
+    let Some(duration) = body.get("duration_s") else {
+        return Ok(None);
+    };
+    duration
+        .as_u64()
+        .filter(|s| (1..=MAX_PAUSE_S).contains(s))
+        .map(|s| Some(Duration::from_secs(s)))
+        .ok_or_else(|| {
+            anyhow!(
+                "duration_s must be between 1 and {} seconds, got {}",
+                MAX_PAUSE_S,
+                duration
+            )
+        })
+}
+
+fn paused(previous: bool, paused: bool) -> (Response, Option<Change>) {
+    let change = Change {
+        previous: json!({ "paused": previous }),
+        new: json!({ "paused": paused }),
+    };
+    (ok(&json!({ "paused": paused })), Some(change))
+}
+
+fn undrain(control: &ChaosControl) -> (Response, Option<Change>) {
+    match control.undrain() {
+        Ok(was_draining) => {
+            let change = Change {
+                previous: json!({ "draining": was_draining }),
+                new: json!({ "draining": false }),
+            };
+            (ok(&json!({ "draining": false })), Some(change))
+        }
+        Err(e) => (error(409, &e.to_string()), None),
+    }
+}
+
+fn set_enabled(control: &ChaosControl, id: &str, enabled: bool) -> (Response, Option<Change>) {
+    if control.experiment(id).is_none() {
+        return (error(404, &format!("no experiment '{}'", id)), None);
+    }
+    match control.set_experiment_enabled(id, enabled) {
+        Ok(previous) => {
+            info!(
+                experiment = id,
+                previous, enabled, "Experiment changed via admin API"
+            );
+            let change = Change {
+                previous: json!({ "enabled": previous }),
+                new: json!({ "enabled": enabled }),
+            };
+            (experiment(control, id), Some(change))
+        }
+        Err(e) => (error(409, &e.to_string()), None),
+    }
+}
+
+fn set_percentage(control: &ChaosControl, id: &str, body: &[u8]) -> (Response, Option<Change>) {
+    if control.experiment(id).is_none() {
+        return (error(404, &format!("no experiment '{}'", id)), None);
+    }
+    let percentage = match parse_percentage(body) {
+        Ok(percentage) => percentage,
+        Err(e) => return (error(400, &e.to_string()), None),
+    };
+    match control.set_experiment_percentage(id, percentage) {
+        Ok(previous) => {
+            info!(
+                experiment = id,
+                previous, percentage, "Experiment percentage changed via admin API"
+            );
+            let change = Change {
+                previous: json!({ "percentage": previous }),
+                new: json!({ "percentage": percentage }),
+            };
+            (experiment(control, id), Some(change))
+        }
+        Err(e) => (error(400, &e.to_string()), None),
+    }
+}
+
+fn trigger(control: &ChaosControl, id: &str, query: &str) -> (Response, Option<Change>) {
+    if control.experiment(id).is_none() {
+        return (error(404, &format!("no experiment '{}'", id)), None);
+    }
+    let count = match parse_count(query) {
+        Ok(count) => count,
+        Err(e) => return (error(400, &e.to_string()), None),
+    };
+    match control.trigger_experiment(id, count) {
+        Ok(previous) => {
+            info!(experiment = id, count, "Experiment armed via admin API");
+            let change = Change {
+                previous: json!({ "armed": previous }),
+                new: json!({ "armed": count }),
+            };
+            (experiment(control, id), Some(change))
+        }
+        Err(e) => (error(400, &e.to_string()), None),
+    }
+}
+
+fn create(control: &ChaosControl, body: &[u8]) -> (Response, Option<Change>) {
+    let parsed = serde_json::from_slice::<Value>(body)
+        .context("expected a JSON object")
+        .and_then(|body| Ok(serde_yaml::to_value(body)?))
+        .and_then(|value| control.parse_experiment(value));
+    let new = match parsed {
+        Ok(new) => new,
+        Err(e) => return (error(400, &format!("{:#}", e)), None),
+    };
+    if control.experiment(&new.id).is_some() {
+        let message = format!("experiment '{}' already exists", new.id);
+        return (error(409, &message), None);
+    }
+    let id = new.id.clone();
+    let change = Change {
+        previous: Value::Null,
+        new: serde_json::to_value(&new).unwrap_or_default(),
+    };
+    match control.add_experiment(new) {
+        Ok(()) => {
+            info!(experiment = %id, "Experiment added via admin API");
+            let response = match experiment(control, &id) {
+                Response::Json(200, body) => Response::Json(201, body),
+                other => other,
+            };
+            (response, Some(change))
+        }
+        Err(e) => (error(400, &format!("{:#}", e)), None),
+    }
+}
+
+fn delete(control: &ChaosControl, id: &str) -> (Response, Option<Change>) {
+    if control.experiment(id).is_none() {
+        return (error(404, &format!("no experiment '{}'", id)), None);
+    }
+    match control.remove_experiment(id) {
+        Ok(previous) => {
+            info!(experiment = id, "Experiment removed via admin API");
+            let change = Change {
+                previous: serde_json::to_value(&previous).unwrap_or_default(),
+                new: Value::Null,
+            };
+            (ok(&json!({ "removed": id })), Some(change))
+        }
+        Err(e) => (error(409, &e.to_string()), None),
+    }
+}
+
+/// Read `count=N` from a query string, 1 when absent.
+fn parse_count(query: &str) -> Result<u32> {
+    let value = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == "count")
+        .map(|(_, value)| value);
+    match value {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("count must be a number, got '{}'", value)),
+        None => Ok(1),
+    }
+}
+
+/// Read `{"percentage": N}`.
+fn parse_percentage(body: &[u8]) -> Result<u8> {
+    let body: Value = serde_json::from_slice(body).context("expected a JSON object")?;
+    let percentage = body
+        .get("percentage")
+        .ok_or_else(|| anyhow!("missing field 'percentage'"))?;
+    percentage
+        .as_u64()
+        .filter(|p| *p <= 100)
+        .map(|p| p as u8)
+        .ok_or_else(|| anyhow!("percentage must be between 0 and 100, got {}", percentage))
+}
+
+fn experiment(control: &ChaosControl, id: &str) -> Response {
+    match control.experiment(id) {
+        Some(status) => ok(&status),
+        None => error(404, &format!("no experiment '{}'", id)),
+    }
+}
+
+fn ok(body: &impl Serialize) -> Response {
+    match serde_json::to_value(body) {
+        Ok(body) => Response::Json(200, body),
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+fn error(status: u16, message: &str) -> Response {
+    Response::Json(status, json!({ "error": message }))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Write a complete JSON response.
+async fn respond<W: AsyncWrite + Unpin>(stream: &mut W, status: u16, body: &Value) -> Result<()> {
+    let body = body.to_string();
+    let challenge = if status == 401 {
+        "WWW-Authenticate: Bearer\r\n"
+    } else {
+        ""
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        challenge,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Stream injections as `injection` events until the client goes away.
+async fn stream_events<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    mut events: broadcast::Receiver<InjectionRecord>,
+) -> Result<()> {
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+        )
+        .await?;
+    stream.flush().await?;
+
+    let start = tokio::time::Instant::now() + KEEP_ALIVE_INTERVAL;
+    let mut keep_alive = tokio::time::interval_at(start, KEEP_ALIVE_INTERVAL);
+    loop {
+        let chunk = tokio::select! {
+            event = events.recv() => match event {
+                Ok(record) => format!("event: injection\ndata: {}\n\n", serde_json::to_string(&record)?),
+                Err(RecvError::Lagged(skipped)) => format!(": skipped {} events\n\n", skipped),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = keep_alive.tick() => ": keep-alive\n\n".to_string(),
+        };
+        stream.write_all(chunk.as_bytes()).await?;
+        stream.flush().await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChaosAgent, Config};
+    use std::collections::HashMap;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+    use zentinel_agent_protocol::v2::{AgentHandlerV2, DrainReason, ShutdownReason};
+
+    const TOKEN: &str = "s3cret";
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+admin:
+  auth_token: "s3cret"
+safety:
+  max_affected_percent: 100
+experiments:
+  - id: "api-errors"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 100
+    fault: { type: error, status: 503 }
+"#,
+        )
+        .unwrap()
+    }
+
+    fn agent() -> ChaosAgent {
+        ChaosAgent::new(config())
+    }
+
+    async fn start(agent: &ChaosAgent) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve(listener, agent.control());
+        addr
+    }
+
+    /// Send a request and read the response head.
+    async fn request(addr: SocketAddr, line: &str) -> (BufReader<TcpStream>, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{}\r\nHost: admin\r\nAuthorization: Bearer {}\r\n\r\n",
+            line, TOKEN
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        loop {
+            let n = reader.read_line(&mut head).await.unwrap();
+            if n == 0 || head.ends_with("\r\n\r\n") {
+                return (reader, head);
+            }
+        }
+    }
+
+    /// Send an authorized request with a body and read the JSON response.
+    async fn call(addr: SocketAddr, target: &str, body: &str) -> (u16, Value) {
+        let authorization = format!("Bearer {}", TOKEN);
+        call_as(addr, Some(&authorization), target, body).await
+    }
+
+    /// Send a request with the given `Authorization` header.
+    async fn call_as(
+        addr: SocketAddr,
+        authorization: Option<&str>,
+        target: &str,
+        body: &str,
+    ) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let authorization = authorization
+            .map(|value| format!("Authorization: {}\r\n", value))
+            .unwrap_or_default();
+        let request = format!(
+            "{} HTTP/1.1\r\nHost: admin\r\n{}Content-Length: {}\r\n\r\n{}",
+            target,
+            authorization,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_metrics_share_the_listener() {
+        let agent = agent();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve_with(listener, agent.control(), true);
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: admin\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("\nchaos_requests_total 0\n"));
+
+        // The admin API still answers, and still needs its token
+        let (status, _) = call(addr, "GET /status", "").await;
+        assert_eq!(status, 200);
+        let (status, _) = call_as(addr, None, "GET /status", "").await;
+        assert_eq!(status, 401);
+
+        // Without metrics the path is unknown to the admin API
+        let addr = start(&agent).await;
+        let (status, _) = call(addr, "GET /metrics", "").await;
+        assert_eq!(status, 404);
+    }
+
+    /// Read the next event, skipping comments.
+    async fn next_event(reader: &mut BufReader<TcpStream>) -> String {
+        let mut event = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            if line == "\n" && !event.is_empty() {
+                return event;
+            }
+            if !line.starts_with(':') {
+                event.push_str(line.trim_end_matches('\n'));
+                event.push('\n');
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_stream_injections_to_every_subscriber() {
+        let agent = agent();
+        let addr = start(&agent).await;
+        let (mut first, head) = request(addr, "GET /events HTTP/1.1").await;
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        assert!(head.contains("Content-Type: text/event-stream\r\n"));
+        let (mut second, _) = request(addr, "GET /events?since=now HTTP/1.1").await;
+
+        assert!(agent
+            .evaluate("GET", "/api/orders", &HashMap::new())
+            .await
+            .is_some());
+
+        for reader in [&mut first, &mut second] {
+            let event = tokio::time::timeout(Duration::from_secs(5), next_event(reader))
+                .await
+                .unwrap();
+            let data = event
+                .strip_prefix("event: injection\ndata: ")
+                .unwrap_or_else(|| panic!("unexpected event {:?}", event));
+            let record: serde_json::Value = serde_json::from_str(data.trim_end()).unwrap();
+            assert_eq!(record["experiment_id"], "api-errors");
+            assert_eq!(record["path"], "/api/orders");
+            assert_eq!(record["fault"], "error");
+            assert_eq!(record["status"], 503);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_experiment_overrides() {
+        let agent = agent();
+        let addr = start(&agent).await;
+        let headers = HashMap::new();
+
+        let (status, body) = call(addr, "GET /experiments", "").await;
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["id"], "api-errors");
+        assert_eq!(body[0]["enabled"], true);
+        assert_eq!(body[0]["percentage"], 100);
+        assert!(body[0].get("runtime_override").is_none());
+
+        let (status, body) = call(
+            addr,
+            "POST /experiments/api-errors/percentage",
+            r#"{"percentage": 0}"#,
+        )
+        .await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["percentage"], 0);
+        assert_eq!(body["effective_percentage"], 0.0);
+        assert_eq!(body["runtime_override"]["percentage"], 0);
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+
+        for (body, error) in [
+            (
+                r#"{"percentage": 101}"#,
+                "percentage must be between 0 and 100, got 101",
+            ),
+            (r#"{"percent": 5}"#, "missing field 'percentage'"),
+            ("5%", "expected a JSON object"),
+        ] {
+            let (status, response) =
+                call(addr, "POST /experiments/api-errors/percentage", body).await;
+            assert_eq!((status, response["error"].as_str()), (400, Some(error)));
+        }
+        let (status, _) = call(addr, "POST /experiments/nope/enable", "").await;
+        assert_eq!(status, 404);
+
+        call(
+            addr,
+            "POST /experiments/api-errors/percentage",
+            r#"{"percentage": 100}"#,
+        )
+        .await;
+        let (status, body) = call(addr, "POST /experiments/api-errors/disable", "").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["enabled"], false);
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+
+        // Overrides outlive a reload of the file configuration
+        agent.control().reload(config()).unwrap();
+        let (_, body) = call(addr, "GET /experiments", "").await;
+        assert_eq!(body[0]["enabled"], false);
+        assert_eq!(body[0]["runtime_override"]["enabled"], false);
+
+        call(addr, "POST /experiments/api-errors/enable", "").await;
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        let (_, body) = call(addr, "GET /experiments", "").await;
+        assert_eq!(body[0]["injections"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_runtime_experiments() {
+        let agent = agent();
+        let addr = start(&agent).await;
+        let mut headers = HashMap::new();
+        let experiment = r#"{
+            "id": "canary-errors",
+            "targeting": {"headers": {"x-canary": "1"}, "percentage": 100},
+            "fault": {"type": "error", "status": 500}
+        }"#;
+
+        let (status, body) = call(addr, "POST /experiments", experiment).await;
+        assert_eq!(status, 201, "{}", body);
+        assert_eq!(body["id"], "canary-errors");
+        assert_eq!(body["ephemeral"], true);
+        let (_, body) = call(addr, "GET /experiments", "").await;
+        assert_eq!(body[0]["ephemeral"], false);
+        assert_eq!(body[1]["id"], "canary-errors");
+        headers.insert("x-canary".to_string(), vec!["1".to_string()]);
+        assert!(agent.evaluate("GET", "/other", &headers).await.is_some());
+
+        let (status, body) = call(addr, "POST /experiments", experiment).await;
+        assert_eq!(status, 409);
+        assert_eq!(body["error"], "experiment 'canary-errors' already exists");
+        for body in [
+            "{}",
+            r#"{"id": "bad", "fault": {"type": "error", "status": 999}}"#,
+            "not json",
+        ] {
+            let (status, _) = call(addr, "POST /experiments", body).await;
+            assert_eq!(status, 400, "{}", body);
+        }
+
+        let (status, body) = call(addr, "DELETE /experiments/api-errors", "").await;
+        assert_eq!(status, 409);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("defined in the configuration"));
+        let (status, _) = call(addr, "DELETE /experiments/nope", "").await;
+        assert_eq!(status, 404);
+        let (status, body) = call(addr, "DELETE /experiments/canary-errors", "").await;
+        assert_eq!(status, 200, "{}", body);
+        assert!(agent.evaluate("GET", "/other", &headers).await.is_none());
+
+        // Runtime experiments are dropped on reload unless persisted
+        call(addr, "POST /experiments", experiment).await;
+        agent.control().reload(config()).unwrap();
+        let (_, body) = call(addr, "GET /experiments", "").await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        let mut persistent = config();
+        persistent.settings.persist_runtime = true;
+        agent.control().reload(persistent.clone()).unwrap();
+        call(addr, "POST /experiments", experiment).await;
+        agent.control().reload(persistent).unwrap();
+        let (_, body) = call(addr, "GET /experiments", "").await;
+        assert_eq!(body[1]["id"], "canary-errors");
+        assert_eq!(body[1]["ephemeral"], true);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_once() {
+        let mut config = config();
+        config.experiments[0].targeting.percentage = 0;
+        let agent = ChaosAgent::new(config);
+        let addr = start(&agent).await;
+        let headers = HashMap::new();
+
+        let (status, body) = call(addr, "POST /experiments/api-errors/trigger-once", "").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["enabled"], true);
+        assert_eq!(body["scheduled"], true);
+        assert_eq!(body["armed"], 1);
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+
+        let (_, body) = call(
+            addr,
+            "POST /experiments/api-errors/trigger-once?count=3",
+            "",
+        )
+        .await;
+        assert_eq!(body["armed"], 3);
+        for (query, error) in [
+            ("count=0", "count must be between 1 and 100, got 0"),
+            ("count=many", "count must be a number, got 'many'"),
+        ] {
+            let target = format!("POST /experiments/api-errors/trigger-once?{}", query);
+            let (status, body) = call(addr, &target, "").await;
+            assert_eq!((status, body["error"].as_str()), (400, Some(error)));
+        }
+        let (status, _) = call(addr, "POST /experiments/nope/trigger-once", "").await;
+        assert_eq!(status, 404);
+        assert_eq!(agent.total_triggered_injections(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_and_status() {
+        let agent = agent();
+        let addr = start(&agent).await;
+        let headers = HashMap::new();
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+
+        let (status, body) = call(addr, "GET /status", "").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["enabled"], true);
+        assert_eq!(body["paused"], false);
+        assert_eq!(body["draining"], false);
+        assert_eq!(body["schedule"]["active"], true);
+        assert_eq!(body["schedule"]["seconds_remaining"], -1);
+        assert_eq!(body["requests_total"], 1);
+        assert_eq!(body["faults_injected"], 1);
+        assert_eq!(body["experiments_enabled"], 1);
+
+        let (status, body) = call(addr, "POST /chaos/pause", "").await;
+        assert_eq!((status, body["paused"].as_bool()), (200, Some(true)));
+        assert!(agent.is_suspended());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        let (_, body) = call(addr, "GET /status", "").await;
+        assert_eq!(body["paused"], true);
+
+        call(addr, "POST /chaos/resume", "").await;
+        assert!(!agent.is_suspended());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+
+        // A bounded pause reports the time left and lifts on its own
+        let (status, body) = call(addr, "POST /chaos/pause", r#"{"duration_s": 1800}"#).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["remaining_seconds"], 1800);
+        let (_, body) = call(addr, "GET /status", "").await;
+        assert_eq!(body["paused"], true);
+        let remaining = body["pause_remaining_seconds"].as_u64().unwrap();
+        assert!((1799..=1800).contains(&remaining), "{}", remaining);
+
+        call(addr, "POST /chaos/pause", r#"{"duration_s": 1}"#).await;
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(!agent.is_suspended());
+        let (_, body) = call(addr, "GET /status", "").await;
+        assert_eq!(body["paused"], false);
+        assert!(body.get("pause_remaining_seconds").is_none());
+
+        for body in [
+            r#"{"duration_s": 0}"#,
+            r#"{"duration_s": "30m"}"#,
+            r#"{"duration_s": 18446744073709551615}"#,
+            "soon",
+        ] {
+            let (status, _) = call(addr, "POST /chaos/pause", body).await;
+            assert_eq!(status, 400, "{}", body);
+        }
+        assert!(!agent.is_suspended());
+
+        // A drain from the proxy is lifted by undrain, a shutdown is not
+        agent.on_drain(60_000, DrainReason::Maintenance).await;
+        let (_, body) = call(addr, "GET /status", "").await;
+        assert_eq!(body["draining"], true);
+        let (status, body) = call(addr, "POST /chaos/undrain", "").await;
+        assert_eq!((status, body["draining"].as_bool()), (200, Some(false)));
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+
+        agent.on_shutdown(ShutdownReason::Graceful, 0).await;
+        let (status, body) = call(addr, "POST /chaos/undrain", "").await;
+        assert_eq!(status, 409, "{}", body);
+        assert!(agent.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_and_malformed_requests() {
+        let agent = agent();
+        let addr = start(&agent).await;
+        let (_, head) = request(addr, "GET /nope HTTP/1.1").await;
+        assert!(head.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", head);
+        let (_, head) = request(addr, "POST /events HTTP/1.1").await;
+        assert!(head.starts_with("HTTP/1.1 405 "), "{}", head);
+        let (_, head) = request(addr, "nonsense").await;
+        assert!(head.starts_with("HTTP/1.1 400 "), "{}", head);
+        let (status, _) = call(addr, "GET /chaos/pause", "").await;
+        assert_eq!(status, 405);
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() {
+        let agent = agent();
+        let addr = start(&agent).await;
+
+        for authorization in [None, Some("Bearer wrong"), Some("Basic czNjcmV0")] {
+            let (status, body) = call_as(
+                addr,
+                authorization,
+                "POST /experiments/api-errors/disable",
+                "",
+            )
+            .await;
+            assert_eq!(status, 401, "{:?}", authorization);
+            assert!(body["error"].as_str().unwrap().contains("bearer token"));
+            let (status, _) = call_as(addr, authorization, "GET /status", "").await;
+            assert_eq!(status, 401);
+        }
+        assert_eq!(agent.total_admin_unauthorized(), 6);
+        assert!(agent.control().experiment("api-errors").unwrap().enabled);
+
+        let (_, head) = request(addr, "POST /chaos/pause HTTP/1.1").await;
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        let (status, body) = call_as(addr, Some("bearer s3cret"), "POST /chaos/resume", "").await;
+        assert_eq!((status, body["paused"].as_bool()), (200, Some(false)));
+        assert_eq!(agent.total_admin_unauthorized(), 6);
+
+        // Reads can be opened up, changes never are
+        let mut config = config();
+        config.admin.allow_unauthenticated_reads = true;
+        agent.control().reload(config.clone()).unwrap();
+        let (status, _) = call_as(addr, None, "GET /experiments", "").await;
+        assert_eq!(status, 200);
+        let (status, _) = call_as(addr, None, "POST /chaos/pause", "").await;
+        assert_eq!(status, 401);
+
+        // Without a token the API is read-only
+        config.admin = AdminConfig::default();
+        agent.control().reload(config).unwrap();
+        let (status, _) = call_as(addr, None, "GET /status", "").await;
+        assert_eq!(status, 200);
+        let (status, body) = call(addr, "POST /chaos/pause", "").await;
+        assert_eq!(status, 403, "{}", body);
+        assert!(!agent.is_suspended());
+    }
+
+    #[test]
+    fn test_audit_records() {
+        let agent = agent();
+        let control = agent.control();
+        let peer: SocketAddr = "10.0.0.7:40000".parse().unwrap();
+        let request = |method: &str, path: &str, body: &str| Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: String::new(),
+            authorization: Some(format!("Bearer {}", TOKEN)),
+            body: body.as_bytes().to_vec(),
+        };
+
+        let before = Utc::now();
+        let (_, audit) = dispatch(
+            &request(
+                "POST",
+                "/experiments/api-errors/percentage",
+                r#"{"percentage": 25}"#,
+            ),
+            peer,
+            &control,
+        );
+        let record = audit.unwrap();
+        assert!(record.timestamp >= before);
+        assert_eq!(record.remote_addr, peer);
+        assert_eq!(record.endpoint, "POST /experiments/api-errors/percentage");
+        assert_eq!(record.previous, json!({ "percentage": 100 }));
+        assert_eq!(record.new, json!({ "percentage": 25 }));
+
+        let (_, audit) = dispatch(&request("POST", "/chaos/pause", ""), peer, &control);
+        let record = serde_json::to_value(audit.unwrap()).unwrap();
+        assert_eq!(record["remote_addr"], "10.0.0.7:40000");
+        assert_eq!(record["previous"], json!({ "paused": false }));
+        assert_eq!(record["new"], json!({ "paused": true }));
+
+        // Reads, failed changes and refused requests are not audited
+        for (method, path, body) in [
+            ("GET", "/status", ""),
+            ("POST", "/experiments/nope/disable", ""),
+            ("POST", "/experiments/api-errors/percentage", "{}"),
+        ] {
+            let (_, audit) = dispatch(&request(method, path, body), peer, &control);
+            assert!(audit.is_none(), "{} {}", method, path);
+        }
+        let mut unauthorized = request("POST", "/chaos/resume", "");
+        unauthorized.authorization = None;
+        let (response, audit) = dispatch(&unauthorized, peer, &control);
+        assert!(matches!(response, Response::Json(401, _)));
+        assert!(audit.is_none());
+        assert!(agent.is_suspended());
+    }
+}
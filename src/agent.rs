@@ -1,619 +1,5538 @@
 //! Chaos Engineering agent implementation.
 
-use crate::config::{Config, Experiment, Schedule};
-use crate::faults::{apply_fault, FaultResult};
-use crate::targeting::{is_excluded_path, CompiledTargeting};
+use crate::abort::AutoAbort;
+use crate::budget::{InjectionBudget, BUDGET_WINDOW_SECS};
+use crate::bypass::BypassVerifier;
+use crate::clients::AffectedClients;
+use crate::config::{
+    AdminConfig, Config, Experiment, FailMode, Fault, LatencyTiming, MetricsMode, Schedule,
+};
+use crate::cooldown::ClientCooldown;
+use crate::drain::DrainState;
+use crate::escalation::Escalation;
+use crate::faults::{apply_body_fault, apply_fault, apply_response_fault, FaultResult};
+use crate::histogram::Histogram;
+use crate::killswitch::KillSwitch;
+use crate::pause::RuntimePause;
+use crate::precheck::Precheck;
+use crate::registry::{Registry, SkipCounters};
+use crate::rng::{with_request_rng, SeededRng};
+use crate::targeting::{is_excluded_path, CompiledTargeting, HTTP_VERSION_HEADER};
+use anyhow::{anyhow, bail, Result};
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
-use chrono::{Datelike, NaiveTime, Timelike, Utc};
-use chrono_tz::Tz;
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use tracing::{debug, info, warn};
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Timelike, Utc};
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{debug, error, info, warn};
 use zentinel_agent_protocol::v2::{
     AgentCapabilities, AgentFeatures, AgentHandlerV2, CounterMetric, DrainReason, GaugeMetric,
     HealthStatus, MetricsReport, ShutdownReason,
 };
-use zentinel_agent_protocol::{AgentResponse, EventType, RequestHeadersEvent};
+use zentinel_agent_protocol::{
+    AgentResponse, EventType, RequestHeadersEvent, ResponseHeadersEvent,
+};
 use zentinel_agent_sdk::prelude::*;
 
-/// Chaos Engineering agent.
-pub struct ChaosAgent {
-    config: Arc<Config>,
-    compiled_experiments: Vec<CompiledExperiment>,
-    /// Injection counts per experiment.
-    injection_counts: Arc<HashMap<String, AtomicU64>>,
-    /// Total requests processed.
-    requests_total: AtomicU64,
-    /// Total faults injected.
-    faults_injected: AtomicU64,
-    /// Whether the agent is draining (not accepting new fault injections).
-    draining: AtomicBool,
-}
-
-/// Pre-compiled experiment for efficient matching.
-struct CompiledExperiment {
-    id: String,
-    enabled: bool,
-    targeting: CompiledTargeting,
-    experiment: Experiment,
-}
+/// Request header naming an experiment to fire regardless of percentage.
+const FORCE_HEADER: &str = "x-chaos-force";
 
-impl ChaosAgent {
-    /// Create a new Chaos agent.
-    pub fn new(config: Config) -> Self {
-        let compiled_experiments: Vec<CompiledExperiment> = config
-            .experiments
-            .iter()
-            .map(|exp| CompiledExperiment {
-                id: exp.id.clone(),
-                enabled: exp.enabled,
-                targeting: CompiledTargeting::new(&exp.targeting),
-                experiment: exp.clone(),
-            })
-            .collect();
+/// Response header marking a forced injection.
+const FORCED_HEADER: &str = "x-chaos-forced";
 
-        let injection_counts: HashMap<String, AtomicU64> = config
-            .experiments
-            .iter()
-            .map(|exp| (exp.id.clone(), AtomicU64::new(0)))
-            .collect();
+/// Response header marking an injection armed through
+/// [`ChaosControl::trigger_experiment`].
+const TRIGGERED_HEADER: &str = "x-chaos-triggered";
 
-        let enabled_count = compiled_experiments.iter().filter(|e| e.enabled).count();
-        info!(
-            experiments = compiled_experiments.len(),
-            enabled = enabled_count,
-            dry_run = config.settings.dry_run,
-            "Chaos agent initialized"
-        );
+/// Most requests a single trigger can arm an experiment for.
+pub const MAX_TRIGGER_COUNT: u32 = 100;
 
-        Self {
-            config: Arc::new(config),
-            compiled_experiments,
-            injection_counts: Arc::new(injection_counts),
-            requests_total: AtomicU64::new(0),
-            faults_injected: AtomicU64::new(0),
-            draining: AtomicBool::new(false),
-        }
-    }
+/// Sliding window length for the request and fault rate gauges.
+pub(crate) const RATE_WINDOW_SECS: u64 = 10;
 
-    /// Check if the agent is currently draining.
-    pub fn is_draining(&self) -> bool {
-        self.draining.load(Ordering::Relaxed)
-    }
+/// Reasons a request can be excluded by the safety config, as used in the
+/// `chaos_excluded_total` metric label.
+pub(crate) const EXCLUSION_REASONS: &[&str] = &["path", "method", "header"];
 
-    /// Get total requests processed.
-    pub fn total_requests(&self) -> u64 {
-        self.requests_total.load(Ordering::Relaxed)
-    }
+/// Reasons a whole request passes through untouched, as used in the
+/// `chaos_skipped_total` metric label.
+pub(crate) const REQUEST_SKIP_REASONS: &[&str] = &[
+    "disabled",
+    "paused",
+    "draining",
+    "auto_aborted",
+    "excluded_path",
+    "excluded_method",
+    "excluded_header",
+    "bypassed",
+    "budget",
+    "rate_limited",
+];
 
-    /// Get total faults injected.
-    pub fn total_faults_injected(&self) -> u64 {
-        self.faults_injected.load(Ordering::Relaxed)
-    }
+/// Reasons a matching experiment is passed over, as used in the
+/// `chaos_experiment_skipped_total` metric label.
+const EXPERIMENT_SKIP_REASONS: &[&str] = &[
+    "disabled",
+    "outside_schedule",
+    "precheck",
+    "percentage",
+    "budget",
+    "rate_limited",
+];
 
-    /// Flatten multi-value headers to single values.
-    fn flatten_headers(headers: &HashMap<String, Vec<String>>) -> HashMap<String, String> {
-        headers
-            .iter()
-            .map(|(k, v)| (k.to_lowercase(), v.first().cloned().unwrap_or_default()))
-            .collect()
-    }
+/// Bucket bounds for the `chaos_match_eval_nanos` histogram, from 1µs to 10ms.
+pub(crate) const MATCH_EVAL_BUCKETS_NANOS: &[u64] = &[
+    1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000, 5_000_000, 10_000_000,
+];
 
-    /// Check if chaos is currently active based on schedule.
-    fn is_within_schedule(&self) -> bool {
-        if self.config.safety.schedule.is_empty() {
-            return true; // No schedule = always active
-        }
+/// Bucket bounds for the `chaos_injected_delay_ms` histogram, from 10ms to
+/// 30s.
+const INJECTED_DELAY_BUCKETS_MS: &[u64] =
+    &[10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
 
-        self.config.safety.schedule.iter().any(Self::check_schedule)
-    }
+/// Values of the `state` label of the `chaos_experiment_state` gauge.
+const EXPERIMENT_STATES: &[&str] = &[
+    "enabled",
+    "disabled",
+    "expired",
+    "exhausted",
+    "outside_schedule",
+];
 
-    fn check_schedule(schedule: &Schedule) -> bool {
-        // Parse timezone
-        let tz: Tz = schedule
-            .timezone
-            .parse()
-            .unwrap_or_else(|_| "UTC".parse().unwrap());
+/// Fault panics after which the agent reports itself degraded.
+const FAULT_PANIC_DEGRADE_THRESHOLD: u64 = 3;
 
-        let now = Utc::now().with_timezone(&tz);
-        let day = now.weekday();
-        let time =
-            NaiveTime::from_hms_opt(now.hour(), now.minute(), now.second()).unwrap_or_default();
+/// Injection events buffered per subscriber; a subscriber that falls
+/// further behind misses the oldest ones.
+const INJECTION_EVENT_CAPACITY: usize = 1024;
 
-        // Check if current day is in the schedule
-        if !schedule.days.contains(&day) {
-            return false;
-        }
+/// Chaos Engineering agent.
+pub struct ChaosAgent {
+    /// Configuration-derived state, swapped atomically on reload.
+    state: Arc<ArcSwap<AgentState>>,
+    /// Whether injections are paused at runtime (temporary kill switch).
+    pause: Arc<RuntimePause>,
+    /// Counters, shared with control handles and the metrics endpoints.
+    registry: Arc<Registry>,
+    /// Whether the agent is draining (not accepting new fault injections).
+    draining: Arc<DrainState>,
+    /// When the agent was created, for sliding-window accounting.
+    started: Instant,
+    /// When a time-limited run starts draining, see
+    /// [`ChaosAgent::with_max_duration`].
+    run_until: Option<Instant>,
+    /// The last `settings.recent_injections` injections, oldest first.
+    recent: Mutex<VecDeque<InjectionRecord>>,
+    /// Live injection events, see [`ChaosAgent::subscribe_injections`].
+    injection_events: broadcast::Sender<InjectionRecord>,
+    /// Runtime changes to experiments, re-applied on every reload.
+    overrides: Arc<Mutex<HashMap<String, ExperimentOverride>>>,
+    /// Experiments added at runtime, in the order they were added.
+    runtime_experiments: Arc<Mutex<Vec<Experiment>>>,
+    /// Counter values in the previous metrics report, by series, when
+    /// `settings.metrics_mode` is `delta`.
+    reported_counters: Mutex<HashMap<String, u64>>,
+    /// Adjustments applied to pushed configurations, see
+    /// [`ChaosAgent::with_config_hook`].
+    config_hook: Option<ConfigHook>,
+}
 
-        // Check if current time is within the window
-        time >= schedule.start && time <= schedule.end
-    }
+/// Adjustments applied to a configuration before it is installed, such as
+/// command-line overrides.
+pub type ConfigHook = Arc<dyn Fn(&mut Config) -> Result<()> + Send + Sync>;
 
-    /// Find matching experiments for a request.
-    fn find_matching_experiments(
-        &self,
-        method: &str,
-        path: &str,
-        headers: &HashMap<String, String>,
-    ) -> Vec<&CompiledExperiment> {
-        self.compiled_experiments
-            .iter()
-            .filter(|exp| exp.enabled && exp.targeting.matches(method, path, headers))
-            .collect()
-    }
+/// A fault the agent injected, kept for "what did chaos just do" debugging.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InjectionRecord {
+    /// Experiment that injected the fault.
+    pub experiment_id: String,
+    /// When the fault was injected, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Request method.
+    pub method: String,
+    /// Request path.
+    pub path: String,
+    /// Fault type name.
+    pub fault: &'static str,
+    /// Status returned to the client, if the fault replaced the response.
+    pub status: Option<u16>,
+    /// Whether the injection was forced by the force header.
+    pub forced: bool,
+    /// Whether the injection was armed through the admin API.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub triggered: bool,
+    /// Whether a dry run only logged the fault.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+}
 
-    /// Increment injection count for an experiment.
-    fn increment_injection_count(&self, experiment_id: &str) {
-        if let Some(counter) = self.injection_counts.get(experiment_id) {
-            counter.fetch_add(1, Ordering::Relaxed);
-        }
-    }
+/// Configuration and everything compiled from it.
+pub(crate) struct AgentState {
+    pub(crate) config: Config,
+    pub(crate) compiled_experiments: Vec<CompiledExperiment>,
+    /// Global budget for the share of requests receiving faults.
+    budget: InjectionBudget,
+    /// Limit on faults holding requests open, if configured.
+    fault_slots: Option<Semaphore>,
+    /// Upstream health tracker, if auto-abort is configured.
+    auto_abort: Option<Arc<AutoAbort>>,
+    /// Control file suppressing all injections, if configured.
+    kill_switch: Option<Arc<KillSwitch>>,
+    /// Distinct clients affected, if the client cap is configured.
+    affected_clients: Option<Arc<AffectedClients>>,
+    /// Bypass header verifier, if configured.
+    pub(crate) bypass: Option<BypassVerifier>,
+    /// Excluded paths after path normalization.
+    excluded_paths: Vec<String>,
+    /// Excluded methods, uppercased.
+    excluded_methods: Vec<String>,
+    /// Excluded header names (lowercased) with the value to match, if any.
+    excluded_headers: Vec<(String, Option<String>)>,
+    /// Experiments left out because their targeting failed to compile,
+    /// with the number of matchers that did not compile.
+    invalid_matchers: Vec<(String, usize)>,
+    /// Version of a configuration pushed by the proxy, if that is where
+    /// the active configuration came from.
+    version: Option<String>,
+    /// Whether the single fault allowed by `settings.once` was injected.
+    once_spent: AtomicBool,
+    /// Per-request generators when `settings.seed` is set. The sequence
+    /// starts over with each configuration.
+    rng: Option<SeededRng>,
+}
 
-    /// Get injection count for an experiment.
-    pub fn get_injection_count(&self, experiment_id: &str) -> u64 {
-        self.injection_counts
-            .get(experiment_id)
-            .map(|c| c.load(Ordering::Relaxed))
-            .unwrap_or(0)
-    }
+/// Position of an instant relative to the global schedule windows.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    /// Whether chaos is within the schedule (and allowed by date rules).
+    pub active: bool,
+    /// Seconds until the active window ends; -1 when there is no schedule,
+    /// 0 when inactive.
+    pub seconds_remaining: i64,
+    /// Seconds until the next window starts; -1 when there is none.
+    pub seconds_until_next: i64,
 }
 
-#[async_trait]
-impl Agent for ChaosAgent {
-    fn name(&self) -> &str {
-        "chaos"
-    }
+/// A runtime change to one experiment, layered over its configuration
+/// until the process exits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentOverride {
+    /// Replaces `enabled`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Replaces `targeting.percentage`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<u8>,
+}
 
-    async fn on_request(&self, request: &Request) -> Decision {
-        // Increment request counter
-        self.requests_total.fetch_add(1, Ordering::Relaxed);
+/// Live state of one experiment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentStatus {
+    pub id: String,
+    pub enabled: bool,
+    pub description: String,
+    /// Faults injected, counted against `max_injections`.
+    pub injections: u64,
+    /// When the experiment last faulted a request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_injection: Option<DateTime<Utc>>,
+    /// Percentage after runtime overrides and the
+    /// `safety.max_affected_percent` clamp.
+    pub percentage: u8,
+    /// Percentage after `settings.intensity` as well.
+    pub effective_percentage: f64,
+    /// Whether the experiment is within its schedule windows now.
+    pub scheduled: bool,
+    /// Whether the experiment was added at runtime rather than defined in
+    /// the configuration.
+    pub ephemeral: bool,
+    /// Matching requests still to be faulted regardless of percentage,
+    /// see [`ChaosControl::trigger_experiment`].
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub armed: u32,
+    /// Safety checks keeping the experiment from injecting right now.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppressions: Vec<String>,
+    /// Runtime override in effect, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_override: Option<ExperimentOverride>,
+}
 
-        // Check global kill switch
-        if !self.config.settings.enabled {
-            debug!("Chaos agent disabled globally");
-            return Decision::allow();
-        }
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
 
-        // Check if draining - don't inject new faults
-        if self.is_draining() {
-            debug!("Agent is draining, skipping fault injection");
-            return Decision::allow();
-        }
+/// Summary of a running agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentStatus {
+    /// Global kill switch, `settings.enabled`.
+    pub enabled: bool,
+    /// Whether injections are paused at runtime.
+    pub paused: bool,
+    /// Seconds until a bounded pause lifts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_remaining_seconds: Option<u64>,
+    pub draining: bool,
+    pub dry_run: bool,
+    pub uptime_seconds: u64,
+    /// Version of a configuration pushed by the proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_version: Option<String>,
+    /// Global schedule windows.
+    pub schedule: ScheduleStatus,
+    /// Whether auto-abort has stopped injections.
+    pub auto_aborted: bool,
+    /// Whether the global budget, `safety.max_affected_percent` across all
+    /// experiments, is refusing injections.
+    pub budget_exhausted: bool,
+    pub requests_total: u64,
+    pub faults_injected: u64,
+    pub config_reload_errors: u64,
+    pub experiments: usize,
+    pub experiments_enabled: usize,
+}
 
-        // Check schedule
-        if !self.is_within_schedule() {
-            debug!("Outside scheduled chaos window");
-            return Decision::allow();
-        }
+/// Upstream response handed to response-phase faults.
+#[derive(Clone, Copy)]
+struct UpstreamResponse<'a> {
+    status: u16,
+    body: Option<&'a [u8]>,
+}
 
-        let method = request.method();
-        let path = request.path();
-        let headers = Self::flatten_headers(request.headers());
+/// Point in the request lifecycle that faults are evaluated for.
+#[derive(Clone, Copy)]
+enum Phase<'a> {
+    /// Request headers, before the request is proxied.
+    Request,
+    /// The buffered request body, for body-phase faults.
+    RequestBody(&'a [u8]),
+    /// The upstream response, for response-phase faults.
+    Response(UpstreamResponse<'a>),
+}
 
-        // Check excluded paths
-        if is_excluded_path(path, &self.config.safety.excluded_paths) {
-            debug!(path = path, "Path is excluded from chaos");
-            return Decision::allow();
+impl Phase<'_> {
+    /// Position of the phase in the request lifecycle, which picks its
+    /// seeded stream.
+    fn index(&self) -> u64 {
+        match self {
+            Phase::Request => 0,
+            Phase::RequestBody(_) => 1,
+            Phase::Response(_) => 2,
         }
+    }
 
-        // Find matching experiments
-        let matching = self.find_matching_experiments(method, path, &headers);
-        if matching.is_empty() {
-            debug!(path = path, method = method, "No matching experiments");
-            return Decision::allow();
+    /// Whether `fault` is applied in this phase.
+    fn applies(&self, fault: &Fault) -> bool {
+        match self {
+            Phase::Request => !fault.is_body_phase() && !fault.is_response_phase(),
+            Phase::RequestBody(_) => fault.is_body_phase(),
+            Phase::Response(_) => fault.is_response_phase(),
         }
+    }
+}
 
-        // Apply the first matching experiment that passes percentage check
-        for exp in matching {
-            if !exp.targeting.should_apply() {
-                debug!(
-                    experiment = %exp.id,
-                    "Experiment matched but not selected by percentage"
-                );
-                continue;
-            }
+/// Why a matching experiment is tried.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    /// Subject to the percentage roll.
+    Sampled,
+    /// Named by the force header.
+    Forced,
+    /// Armed through the admin API.
+    Triggered,
+}
 
-            // Apply the fault
-            let result = apply_fault(
-                &exp.experiment.fault,
-                &exp.id,
-                self.config.settings.dry_run,
-                self.config.settings.log_injections,
-            )
-            .await;
+/// Result of trying a single matching experiment.
+enum ExperimentOutcome {
+    /// The experiment did not fire; try the next one.
+    Skipped,
+    /// A safety limit suppressed injection; pass the request through.
+    Suppressed,
+    /// The fault was applied.
+    Applied(FaultResult),
+    /// Applying the fault failed inside the agent.
+    Failed,
+}
 
-            self.increment_injection_count(&exp.id);
-            self.faults_injected.fetch_add(1, Ordering::Relaxed);
+/// Runtime switches kept outside [`AgentState`] that spare requests.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Gates {
+    /// Injections are paused at runtime.
+    pub(crate) paused: bool,
+    /// The agent is draining.
+    pub(crate) draining: bool,
+}
 
-            match result {
-                FaultResult::Allow { delay } => {
-                    if let Some(d) = delay {
-                        debug!(
-                            experiment = %exp.id,
-                            delay_ms = d.as_millis(),
-                            "Fault applied with delay, allowing request"
-                        );
-                    }
-                    // For latency faults, we've already applied the delay
-                    // Allow the request to continue
-                    return Decision::allow().with_tag(format!("chaos:{}", exp.id));
-                }
-                FaultResult::Block(decision) => {
-                    return *decision;
-                }
-            }
-        }
+/// Why a whole request passes through before any experiment is tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Spared {
+    /// `settings.enabled` is false.
+    Disabled,
+    /// The kill switch file exists.
+    KillSwitch,
+    /// Injections are paused at runtime.
+    Paused,
+    /// The agent is draining.
+    Draining,
+    /// `settings.once` is set and its single fault was injected.
+    OnceSpent,
+    /// Upstream errors tripped the auto-abort.
+    AutoAborted,
+    /// Excluded by `safety`, with the exclusion kind.
+    Excluded(&'static str),
+    /// The request carries a valid bypass token.
+    Bypassed,
+}
 
-        // No experiment was applied
-        Decision::allow()
+impl Spared {
+    /// Skip reason the request is counted under, if it is counted.
+    fn skip_reason(self) -> Option<String> {
+        let reason = match self {
+            Spared::Disabled => "disabled",
+            Spared::KillSwitch => return None,
+            Spared::Paused => "paused",
+            Spared::Draining => "draining",
+            Spared::OnceSpent => "budget",
+            Spared::AutoAborted => "auto_aborted",
+            Spared::Excluded(kind) => return Some(format!("excluded_{}", kind)),
+            Spared::Bypassed => "bypassed",
+        };
+        Some(reason.to_string())
     }
+}
 
-    async fn on_response(&self, _request: &Request, _response: &Response) -> Decision {
-        // Chaos agent only operates on requests
-        Decision::allow()
+impl std::fmt::Display for Spared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Spared::Disabled => write!(f, "chaos is disabled (settings.enabled: false)"),
+            Spared::KillSwitch => write!(f, "the kill switch file exists"),
+            Spared::Paused => write!(f, "injections are paused"),
+            Spared::Draining => write!(f, "the agent is draining"),
+            Spared::OnceSpent => write!(f, "the single fault of settings.once was injected"),
+            Spared::AutoAborted => write!(f, "chaos auto-aborted on upstream errors"),
+            Spared::Excluded(kind) => write!(f, "excluded by safety.excluded_{}s", kind),
+            Spared::Bypassed => write!(f, "carries a valid bypass token"),
+        }
     }
+}
 
-    async fn on_configure(&self, config: serde_json::Value) -> Result<(), String> {
-        // v2 configuration update support
-        if config.is_null() {
-            return Ok(());
-        }
+/// Outcome of the checks run before any percentage roll, shared by the
+/// agent and the simulator.
+pub(crate) enum Plan<'a> {
+    /// The whole request passes through.
+    Spared(Spared),
+    /// Experiments to try, in order.
+    Matching {
+        /// Enabled experiments matching the request within their schedule.
+        experiments: Vec<&'a CompiledExperiment>,
+        /// The matching experiment named by the force header, which is the
+        /// only one tried and skips the roll.
+        forced: Option<&'a CompiledExperiment>,
+    },
+}
 
-        // Log the configuration update
-        info!(config = %config, "Received configuration update");
+/// Pre-compiled experiment for efficient matching.
+pub(crate) struct CompiledExperiment {
+    pub(crate) id: String,
+    /// Changeable at runtime through [`ChaosControl::set_experiment_enabled`].
+    enabled: AtomicBool,
+    pub(crate) targeting: CompiledTargeting,
+    pub(crate) experiment: Experiment,
+    /// Faults injected, counted against `max_injections`. Kept across
+    /// reloads while the experiment's id and definition are unchanged.
+    injections: Arc<AtomicU64>,
+    /// Whether `max_injections` has been reached.
+    exhausted: Arc<AtomicBool>,
+    /// Faults a dry run would have injected, counted against
+    /// `max_injections` instead of `injections` so a dry run never uses up
+    /// the live budget. Kept across reloads like `injections`.
+    would_inject: Arc<AtomicU64>,
+    /// Milliseconds since the epoch of the last fault, 0 before the first.
+    /// Kept across reloads like `injections`.
+    last_injection_ms: Arc<AtomicU64>,
+    /// Requests matching the experiment within its schedule, kept across
+    /// reloads like `injections`.
+    matched: Arc<AtomicU64>,
+    /// Matching requests passed over per reason in
+    /// [`EXPERIMENT_SKIP_REASONS`], kept across reloads like `injections`.
+    skips: Arc<SkipCounters>,
+    /// Matching requests to fault regardless of percentage, kept across
+    /// reloads like `injections`.
+    armed: Arc<AtomicU32>,
+    /// Whether the experiment was added at runtime.
+    ephemeral: bool,
+    /// Steady-state gate, if the experiment has a precheck.
+    precheck: Option<Arc<Precheck>>,
+    /// Recently faulted clients, if the experiment has a client cooldown.
+    cooldown: Option<Arc<ClientCooldown>>,
+    /// Current delay, if the experiment injects escalating latency.
+    escalation: Option<Arc<Escalation>>,
+    /// Injected delays, if the fault holds requests. Kept across reloads
+    /// like `injections`.
+    delays: Option<Arc<Histogram>>,
+}
 
-        // For now, we just acknowledge the config - full hot-reload would require
-        // more complex state management
-        Ok(())
-    }
+/// Handle for controlling a running agent from outside the request path,
+/// e.g. from a signal listener.
+#[derive(Clone)]
+pub struct ChaosControl {
+    state: Arc<ArcSwap<AgentState>>,
+    pause: Arc<RuntimePause>,
+    registry: Arc<Registry>,
+    injection_events: broadcast::Sender<InjectionRecord>,
+    overrides: Arc<Mutex<HashMap<String, ExperimentOverride>>>,
+    runtime_experiments: Arc<Mutex<Vec<Experiment>>>,
+    draining: Arc<DrainState>,
+    started: Instant,
+    run_until: Option<Instant>,
 }
 
-/// v2 Protocol implementation for ChaosAgent.
-#[async_trait]
-impl AgentHandlerV2 for ChaosAgent {
-    fn capabilities(&self) -> AgentCapabilities {
-        AgentCapabilities::new(
-            "zentinel-agent-chaos",
-            "Chaos Engineering Agent",
-            env!("CARGO_PKG_VERSION"),
+impl CompiledExperiment {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the experiment has a precheck that is not running, because
+    /// it was compiled disabled.
+    fn precheck_missing(&self) -> bool {
+        self.experiment.precheck.is_some() && self.precheck.is_none()
+    }
+
+    /// Whether the experiment returns a fixture that was never read,
+    /// because it was loaded disabled.
+    fn fixture_missing(&self) -> bool {
+        matches!(
+            self.experiment.fault,
+            Fault::ReturnFixture { body: None, .. }
         )
-        .with_event(EventType::RequestHeaders)
-        .with_features(AgentFeatures {
-            streaming_body: false,
-            websocket: false,
-            guardrails: false,
-            config_push: true,
-            metrics_export: true,
-            concurrent_requests: 100,
-            cancellation: true,
-            flow_control: false,
-            health_reporting: true,
-        })
     }
 
-    async fn on_configure(&self, config: serde_json::Value, _version: Option<String>) -> bool {
-        if config.is_null() {
-            return true;
+    /// Safety checks keeping the experiment from injecting, other than its
+    /// schedule and enabled state.
+    fn suppressions(&self) -> Vec<String> {
+        let mut suppressions = Vec::new();
+        if self.exhausted.load(Ordering::Relaxed) {
+            suppressions.push("max_injections reached".to_string());
+        }
+        if self.precheck.as_ref().is_some_and(|p| !p.is_steady()) {
+            suppressions.push("steady state not confirmed".to_string());
         }
-        info!(config = %config, "Received v2 configuration update");
-        true
+        if self.is_enabled() && self.precheck_missing() {
+            suppressions.push("precheck not running".to_string());
+        }
+        suppressions
     }
 
-    async fn on_request_headers(&self, event: RequestHeadersEvent) -> AgentResponse {
-        // Increment request counter
-        self.requests_total.fetch_add(1, Ordering::Relaxed);
+    /// Use up one armed trigger, returning whether there was one.
+    fn take_trigger(&self) -> bool {
+        self.armed
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |armed| {
+                armed.checked_sub(1)
+            })
+            .is_ok()
+    }
+}
 
-        // Check global kill switch
-        if !self.config.settings.enabled {
-            debug!("Chaos agent disabled globally");
-            return AgentResponse::default_allow();
-        }
+impl ChaosControl {
+    /// Toggle runtime suspension, returning whether injections are now
+    /// suspended.
+    pub fn toggle_suspended(&self) -> bool {
+        self.pause.toggle()
+    }
 
-        // Check if draining - don't inject new faults
-        if self.is_draining() {
-            debug!("Agent is draining, skipping fault injection");
-            return AgentResponse::default_allow();
-        }
+    /// Pause injections until [`resume`](Self::resume) is called, returning
+    /// whether the agent was running.
+    ///
+    /// Unlike a drain, which is terminal, a pause is reversible.
+    pub fn pause(&self) -> bool {
+        self.pause.pause(None)
+    }
 
-        // Check schedule
-        if !self.is_within_schedule() {
-            debug!("Outside scheduled chaos window");
-            return AgentResponse::default_allow();
-        }
+    /// Pause injections for `duration`, after which they resume on their
+    /// own, returning whether the agent was running.
+    pub fn pause_for(&self, duration: Duration) -> bool {
+        self.pause.pause(Some(duration))
+    }
 
-        let method = &event.method;
-        let path = &event.uri;
-        let headers = Self::flatten_headers(&event.headers);
+    /// Resume paused injections, returning whether the agent was paused.
+    /// Has no effect on a draining agent.
+    pub fn resume(&self) -> bool {
+        self.pause.resume()
+    }
 
-        // Check excluded paths
-        if is_excluded_path(path, &self.config.safety.excluded_paths) {
-            debug!(path = path, "Path is excluded from chaos");
-            return AgentResponse::default_allow();
-        }
+    /// Check if injections are suspended at runtime.
+    pub fn is_suspended(&self) -> bool {
+        self.pause.is_paused()
+    }
 
-        // Find matching experiments
-        let matching = self.find_matching_experiments(method, path, &headers);
-        if matching.is_empty() {
-            debug!(path = path, method = method, "No matching experiments");
-            return AgentResponse::default_allow();
-        }
+    /// Time left of a pause started with [`pause_for`](Self::pause_for).
+    pub fn pause_remaining(&self) -> Option<Duration> {
+        self.pause.remaining()
+    }
 
-        // Apply the first matching experiment that passes percentage check
-        for exp in matching {
-            if !exp.targeting.should_apply() {
-                debug!(
-                    experiment = %exp.id,
-                    "Experiment matched but not selected by percentage"
-                );
-                continue;
-            }
+    /// Subscribe to injections as they happen, see
+    /// [`ChaosAgent::subscribe_injections`].
+    pub fn subscribe_injections(&self) -> broadcast::Receiver<InjectionRecord> {
+        self.injection_events.subscribe()
+    }
 
-            // Apply the fault
-            let result = apply_fault(
-                &exp.experiment.fault,
-                &exp.id,
-                self.config.settings.dry_run,
-                self.config.settings.log_injections,
-            )
-            .await;
+    /// Admin API settings of the active configuration.
+    pub fn admin_config(&self) -> AdminConfig {
+        self.state.load().config.admin.clone()
+    }
 
-            self.increment_injection_count(&exp.id);
-            self.faults_injected.fetch_add(1, Ordering::Relaxed);
+    /// Count an admin API request refused for a missing or wrong token.
+    pub fn record_admin_unauthorized(&self) {
+        self.registry
+            .admin_unauthorized
+            .fetch_add(1, Ordering::Relaxed);
+    }
 
-            match result {
-                FaultResult::Allow { delay } => {
-                    if let Some(d) = delay {
-                        debug!(
-                            experiment = %exp.id,
-                            delay_ms = d.as_millis(),
-                            "Fault applied with delay, allowing request"
-                        );
-                    }
-                    return AgentResponse::default_allow();
-                }
-                FaultResult::Block(decision) => {
-                    // Convert SDK Decision to AgentResponse using build()
-                    return (*decision).build();
-                }
-            }
+    /// Check if the agent is draining. Unlike
+    /// [`ChaosAgent::is_draining`], this never starts the drain itself.
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_draining() || self.run_time_elapsed()
+    }
+
+    /// Lift a drain requested by the proxy, returning whether the agent
+    /// was draining.
+    ///
+    /// Fails once the agent is shutting down or its maximum run time has
+    /// passed; those drains are terminal.
+    pub fn undrain(&self) -> Result<bool> {
+        if self.run_time_elapsed() {
+            bail!("the maximum run time has passed, the drain cannot be lifted");
         }
+        let was_draining = self.draining.undrain()?;
+        if was_draining {
+            info!("Drain lifted - resuming fault injection");
+        }
+        Ok(was_draining)
+    }
 
-        AgentResponse::default_allow()
+    fn run_time_elapsed(&self) -> bool {
+        self.run_until.is_some_and(|until| Instant::now() >= until)
     }
 
-    fn health_status(&self) -> HealthStatus {
-        if self.is_draining() {
-            HealthStatus::degraded(
-                "zentinel-agent-chaos",
-                vec!["fault-injection".to_string()],
-                1.0,
-            )
+    /// Time left before a time-limited run drains, see
+    /// [`ChaosAgent::remaining_run_time`].
+    fn remaining_run_time(&self) -> Option<Duration> {
+        self.run_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    /// State of an experiment, one of [`EXPERIMENT_STATES`]: `enabled`
+    /// when it can fault requests now, or the first reason it cannot.
+    /// `expired` means the agent's `--max-duration` run is over.
+    fn experiment_state(&self, state: &AgentState, exp: &CompiledExperiment) -> &'static str {
+        if !exp.is_enabled() {
+            "disabled"
+        } else if self.run_time_elapsed() {
+            "expired"
+        } else if exp.exhausted.load(Ordering::Relaxed) {
+            "exhausted"
+        } else if !state.is_experiment_scheduled_at(exp, Utc::now()) {
+            "outside_schedule"
         } else {
-            HealthStatus::healthy("zentinel-agent-chaos")
+            "enabled"
         }
     }
 
-    fn metrics_report(&self) -> Option<MetricsReport> {
+    /// Collect every metric the agent exports, with counters as running
+    /// totals.
+    ///
+    /// This is the single source for both the proxy's metrics report and
+    /// the Prometheus endpoint.
+    pub fn collect_metrics(&self) -> MetricsReport {
+        let state = self.state.load();
         let mut report = MetricsReport::new("zentinel-agent-chaos", 10_000);
 
+        let registry = &self.registry;
+
         // Add counter metrics
         report.counters.push(CounterMetric::new(
             "chaos_requests_total",
-            self.total_requests(),
+            registry.requests_total.load(Ordering::Relaxed),
         ));
 
         report.counters.push(CounterMetric::new(
             "chaos_faults_injected_total",
-            self.total_faults_injected(),
+            registry.faults_injected.load(Ordering::Relaxed),
         ));
 
-        // Add per-experiment injection counts
-        for (experiment_id, counter) in self.injection_counts.iter() {
-            let mut metric = CounterMetric::new(
-                "chaos_experiment_injections_total",
-                counter.load(Ordering::Relaxed),
-            );
-            metric
-                .labels
-                .insert("experiment".to_string(), experiment_id.clone());
-            report.counters.push(metric);
-        }
+        report.counters.push(CounterMetric::new(
+            "chaos_dry_run_would_inject_total",
+            registry.dry_run_would_inject.load(Ordering::Relaxed),
+        ));
 
-        // Add gauge metrics
-        report.gauges.push(GaugeMetric::new(
-            "chaos_experiments_enabled",
-            self.compiled_experiments
-                .iter()
-                .filter(|e| e.enabled)
-                .count() as f64,
+        report.counters.push(CounterMetric::new(
+            "chaos_budget_suppressed_total",
+            registry.budget_suppressed.load(Ordering::Relaxed),
         ));
 
-        report.gauges.push(GaugeMetric::new(
-            "chaos_agent_enabled",
-            if self.config.settings.enabled {
-                1.0
-            } else {
-                0.0
-            },
+        report.counters.push(CounterMetric::new(
+            "chaos_faults_shed_total",
+            registry.faults_shed.load(Ordering::Relaxed),
         ));
 
-        report.gauges.push(GaugeMetric::new(
-            "chaos_agent_draining",
-            if self.is_draining() { 1.0 } else { 0.0 },
+        // The same skips, under the name dashboards of the delay cap use
+        report.counters.push(CounterMetric::new(
+            "chaos_delay_capacity_skips_total",
+            registry.faults_shed.load(Ordering::Relaxed),
         ));
 
-        Some(report)
-    }
+        report.counters.push(CounterMetric::new(
+            "chaos_fault_panics_total",
+            registry.fault_panics.load(Ordering::Relaxed),
+        ));
 
-    async fn on_shutdown(&self, reason: ShutdownReason, grace_period_ms: u64) {
-        info!(
-            reason = ?reason,
-            grace_period_ms = grace_period_ms,
-            "Chaos agent shutdown requested"
-        );
-        self.draining.store(true, Ordering::SeqCst);
-    }
+        report.counters.push(CounterMetric::new(
+            "chaos_internal_errors_total",
+            registry.internal_errors.load(Ordering::Relaxed),
+        ));
 
-    async fn on_drain(&self, duration_ms: u64, reason: DrainReason) {
-        warn!(
-            reason = ?reason,
-            duration_ms = duration_ms,
-            "Chaos agent drain requested - stopping fault injection"
-        );
-        self.draining.store(true, Ordering::SeqCst);
-    }
+        report.counters.push(CounterMetric::new(
+            "chaos_config_reload_errors_total",
+            registry.reload_errors.load(Ordering::Relaxed),
+        ));
+
+        report.counters.push(CounterMetric::new(
+            "chaos_admin_unauthorized_total",
+            registry.admin_unauthorized.load(Ordering::Relaxed),
+        ));
+
+        report.counters.push(CounterMetric::new(
+            "chaos_bypassed_total",
+            registry.bypassed.load(Ordering::Relaxed),
+        ));
+
+        report.counters.push(CounterMetric::new(
+            "chaos_forced_injections_total",
+            registry.forced_injections.load(Ordering::Relaxed),
+        ));
+
+        report.counters.push(CounterMetric::new(
+            "chaos_triggered_injections_total",
+            registry.triggered_injections.load(Ordering::Relaxed),
+        ));
+
+        for reason in EXCLUSION_REASONS {
+            let mut metric =
+                CounterMetric::new("chaos_excluded_total", registry.exclusion_count(reason));
+            metric
+                .labels
+                .insert("reason".to_string(), reason.to_string());
+            report.counters.push(metric);
+        }
+
+        for reason in REQUEST_SKIP_REASONS {
+            let mut metric = CounterMetric::new("chaos_skipped_total", registry.skips.get(reason));
+            metric
+                .labels
+                .insert("reason".to_string(), reason.to_string());
+            report.counters.push(metric);
+        }
+
+        // Add the experiment matching time histogram, in Prometheus form
+        if state.config.settings.profile_matching {
+            let snapshot = registry.match_eval.snapshot();
+            for (bound, count) in snapshot.buckets {
+                let mut metric = CounterMetric::new("chaos_match_eval_nanos_bucket", count);
+                let le = bound.map_or_else(|| "+Inf".to_string(), |b| b.to_string());
+                metric.labels.insert("le".to_string(), le);
+                report.counters.push(metric);
+            }
+            report.counters.push(CounterMetric::new(
+                "chaos_match_eval_nanos_sum",
+                snapshot.sum,
+            ));
+            report.counters.push(CounterMetric::new(
+                "chaos_match_eval_nanos_count",
+                snapshot.count,
+            ));
+        }
+
+        // Add per-fault-type injection counts (the unlabeled series above is the total)
+        for (fault_type, counter) in registry.fault_type_counts.iter() {
+            let count = counter.load(Ordering::Relaxed);
+            let configured = state
+                .config
+                .experiments
+                .iter()
+                .any(|exp| exp.fault.type_name() == *fault_type);
+            if count == 0 && !configured {
+                continue;
+            }
+            let mut metric = CounterMetric::new("chaos_faults_injected_total", count);
+            metric
+                .labels
+                .insert("fault_type".to_string(), fault_type.to_string());
+            report.counters.push(metric);
+        }
+
+        // Add per-experiment injection, match and skip counts
+        for exp in &state.compiled_experiments {
+            let labeled = |name: &str, value: u64| {
+                let mut metric = CounterMetric::new(name, value);
+                metric
+                    .labels
+                    .insert("experiment".to_string(), exp.id.clone());
+                metric
+            };
+            report.counters.push(labeled(
+                "chaos_experiment_injections_total",
+                exp.injections.load(Ordering::Relaxed),
+            ));
+            report.counters.push(labeled(
+                "chaos_dry_run_would_inject_total",
+                exp.would_inject.load(Ordering::Relaxed),
+            ));
+            report.counters.push(labeled(
+                "chaos_experiment_matched_total",
+                exp.matched.load(Ordering::Relaxed),
+            ));
+            for reason in EXPERIMENT_SKIP_REASONS {
+                let mut metric = labeled("chaos_experiment_skipped_total", exp.skips.get(reason));
+                metric
+                    .labels
+                    .insert("reason".to_string(), reason.to_string());
+                report.counters.push(metric);
+            }
+        }
+
+        // Add per-experiment injected delay histograms, in Prometheus form
+        for exp in &state.compiled_experiments {
+            let Some(delays) = &exp.delays else {
+                continue;
+            };
+            let labeled = |name: &str, value: u64| {
+                let mut metric = CounterMetric::new(name, value);
+                metric
+                    .labels
+                    .insert("experiment".to_string(), exp.id.clone());
+                metric.labels.insert(
+                    "fault_type".to_string(),
+                    exp.experiment.fault.type_name().to_string(),
+                );
+                metric
+            };
+            let snapshot = delays.snapshot();
+            for (bound, count) in snapshot.buckets {
+                let mut metric = labeled("chaos_injected_delay_ms_bucket", count);
+                let le = bound.map_or_else(|| "+Inf".to_string(), |b| b.to_string());
+                metric.labels.insert("le".to_string(), le);
+                report.counters.push(metric);
+            }
+            report
+                .counters
+                .push(labeled("chaos_injected_delay_ms_sum", snapshot.sum));
+            report
+                .counters
+                .push(labeled("chaos_injected_delay_ms_count", snapshot.count));
+        }
+
+        // Add per-experiment effective percentages after safety clamping
+        for exp in &state.compiled_experiments {
+            let mut effective = GaugeMetric::new(
+                "chaos_experiment_effective_percent",
+                exp.targeting.percentage() as f64,
+            );
+            effective
+                .labels
+                .insert("experiment".to_string(), exp.id.clone());
+            report.gauges.push(effective);
+
+            let mut exhausted = GaugeMetric::new(
+                "chaos_experiment_exhausted",
+                if exp.exhausted.load(Ordering::Relaxed) {
+                    1.0
+                } else {
+                    0.0
+                },
+            );
+            exhausted
+                .labels
+                .insert("experiment".to_string(), exp.id.clone());
+            report.gauges.push(exhausted);
+
+            let clamped = exp.targeting.percentage() < exp.experiment.targeting.percentage;
+            let mut metric =
+                GaugeMetric::new("chaos_experiment_clamped", if clamped { 1.0 } else { 0.0 });
+            metric
+                .labels
+                .insert("experiment".to_string(), exp.id.clone());
+            report.gauges.push(metric);
+
+            let last_injection_ms = exp.last_injection_ms.load(Ordering::Relaxed);
+            let mut metric = GaugeMetric::new(
+                "chaos_experiment_last_injection_timestamp_seconds",
+                last_injection_ms as f64 / 1000.0,
+            );
+            metric
+                .labels
+                .insert("experiment".to_string(), exp.id.clone());
+            report.gauges.push(metric);
+
+            // One series per state, 1 for the current one
+            let current = self.experiment_state(&state, exp);
+            for name in EXPERIMENT_STATES {
+                let mut metric = GaugeMetric::new(
+                    "chaos_experiment_state",
+                    if *name == current { 1.0 } else { 0.0 },
+                );
+                metric
+                    .labels
+                    .insert("experiment".to_string(), exp.id.clone());
+                metric.labels.insert("state".to_string(), name.to_string());
+                report.gauges.push(metric);
+            }
+        }
+
+        // Add gauge metrics
+        report.gauges.push(GaugeMetric::new(
+            "chaos_experiments_enabled",
+            state.enabled_count() as f64,
+        ));
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_experiment_compile_errors",
+            state.invalid_matchers.len() as f64,
+        ));
+
+        for (id, invalid) in &state.invalid_matchers {
+            let mut metric = GaugeMetric::new("chaos_experiment_invalid_matchers", *invalid as f64);
+            metric.labels.insert("experiment".to_string(), id.clone());
+            report.gauges.push(metric);
+        }
+
+        if let Some(version) = &state.version {
+            let mut info = GaugeMetric::new("chaos_config_info", 1.0);
+            info.labels.insert("version".to_string(), version.clone());
+            report.gauges.push(info);
+        }
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_agent_enabled",
+            if state.config.settings.enabled {
+                1.0
+            } else {
+                0.0
+            },
+        ));
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_kill_switch_active",
+            if state.is_kill_switch_active() {
+                1.0
+            } else {
+                0.0
+            },
+        ));
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_agent_draining",
+            if self.is_draining() { 1.0 } else { 0.0 },
+        ));
+
+        if let Some(remaining) = self.remaining_run_time() {
+            report.gauges.push(GaugeMetric::new(
+                "chaos_run_remaining_seconds",
+                remaining.as_secs_f64(),
+            ));
+        }
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_intensity",
+            state.config.settings.intensity,
+        ));
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_dry_run_active",
+            if state.config.settings.dry_run {
+                1.0
+            } else {
+                0.0
+            },
+        ));
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_agent_suspended",
+            if self.is_suspended() { 1.0 } else { 0.0 },
+        ));
+
+        // Seconds left of a bounded pause, infinite until resumed
+        let paused = match (self.is_suspended(), self.pause.remaining()) {
+            (false, _) => 0.0,
+            (true, Some(remaining)) => remaining.as_secs_f64().ceil(),
+            (true, None) => f64::INFINITY,
+        };
+        report.gauges.push(GaugeMetric::new("chaos_paused", paused));
+
+        report.gauges.push(GaugeMetric::new(
+            "chaos_auto_aborted",
+            if state.is_auto_aborted() { 1.0 } else { 0.0 },
+        ));
+
+        if let Some((limit, in_use)) = state.fault_slot_usage() {
+            report.gauges.push(GaugeMetric::new(
+                "chaos_concurrent_faults_limit",
+                limit as f64,
+            ));
+            report.gauges.push(GaugeMetric::new(
+                "chaos_concurrent_faults_in_use",
+                in_use as f64,
+            ));
+        }
+
+        let now = self.started.elapsed().as_secs();
+        report.gauges.push(GaugeMetric::new(
+            "chaos_requests_per_second",
+            registry.request_rate.rate(now),
+        ));
+        report.gauges.push(GaugeMetric::new(
+            "chaos_faults_per_second",
+            registry.fault_rate.rate(now),
+        ));
+
+        if let Some(clients) = &state.affected_clients {
+            report.gauges.push(GaugeMetric::new(
+                "chaos_affected_clients",
+                clients.len_at(now) as f64,
+            ));
+        }
+
+        let schedule = state.schedule_status_at(Utc::now());
+        report.gauges.push(GaugeMetric::new(
+            "chaos_schedule_active",
+            if schedule.active { 1.0 } else { 0.0 },
+        ));
+        report.gauges.push(GaugeMetric::new(
+            "chaos_schedule_seconds_remaining",
+            schedule.seconds_remaining as f64,
+        ));
+        report.gauges.push(GaugeMetric::new(
+            "chaos_schedule_seconds_until_next",
+            schedule.seconds_until_next as f64,
+        ));
+
+        report
+    }
+
+    /// Summarize the running agent.
+    pub fn status(&self) -> AgentStatus {
+        let state = self.state.load();
+        AgentStatus {
+            enabled: state.config.settings.enabled,
+            paused: self.is_suspended(),
+            pause_remaining_seconds: self
+                .pause_remaining()
+                .map(|d| d.as_secs_f64().ceil() as u64),
+            draining: self.is_draining(),
+            dry_run: state.config.settings.dry_run,
+            uptime_seconds: self.started.elapsed().as_secs(),
+            config_version: state.version.clone(),
+            schedule: state.schedule_status_at(Utc::now()),
+            auto_aborted: state.is_auto_aborted(),
+            budget_exhausted: state.budget.is_exhausted(self.started.elapsed().as_secs()),
+            requests_total: self.registry.requests_total.load(Ordering::Relaxed),
+            faults_injected: self.registry.faults_injected.load(Ordering::Relaxed),
+            config_reload_errors: self.registry.reload_errors.load(Ordering::Relaxed),
+            experiments: state.compiled_experiments.len(),
+            experiments_enabled: state.enabled_count(),
+        }
+    }
+
+    /// Live state of every experiment, in evaluation order.
+    pub fn experiments(&self) -> Vec<ExperimentStatus> {
+        let state = self.state.load();
+        let overrides = self.lock_overrides();
+        state
+            .compiled_experiments
+            .iter()
+            .map(|exp| state.experiment_status(exp, overrides.get(&exp.id).copied()))
+            .collect()
+    }
+
+    /// Live state of one experiment.
+    pub fn experiment(&self, id: &str) -> Option<ExperimentStatus> {
+        self.experiments().into_iter().find(|exp| exp.id == id)
+    }
+
+    /// Enable or disable an experiment, returning whether it was enabled.
+    ///
+    /// The change lasts until the process exits, surviving reloads that
+    /// keep the experiment. An experiment with a steady-state precheck can
+    /// only be enabled here if its precheck is already running.
+    pub fn set_experiment_enabled(&self, id: &str, enabled: bool) -> Result<bool> {
+        let mut overrides = self.lock_overrides();
+        let state = self.state.load();
+        let exp = state.compiled_experiment(id)?;
+        if enabled && exp.precheck_missing() {
+            bail!(
+                "experiment '{}' has a precheck that is not running, enable it in the configuration",
+                id
+            );
+        }
+        if enabled && exp.fixture_missing() {
+            bail!(
+                "experiment '{}' has a fixture that was never read, enable it in the configuration",
+                id
+            );
+        }
+        let previous = exp.enabled.swap(enabled, Ordering::Relaxed);
+        overrides.entry(id.to_string()).or_default().enabled = Some(enabled);
+        Ok(previous)
+    }
+
+    /// Change an experiment's percentage, returning the previous one.
+    ///
+    /// The new percentage is clamped to `safety.max_affected_percent` and
+    /// lasts like [`set_experiment_enabled`](Self::set_experiment_enabled).
+    pub fn set_experiment_percentage(&self, id: &str, percentage: u8) -> Result<u8> {
+        if percentage > 100 {
+            bail!("percentage must be between 0 and 100, got {}", percentage);
+        }
+        let mut overrides = self.lock_overrides();
+        let state = self.state.load();
+        let exp = state.compiled_experiment(id)?;
+        if exp.targeting.uses_every_n() {
+            bail!(
+                "experiment '{}' faults every_n requests, change its spacing in the configuration",
+                id
+            );
+        }
+        let previous = exp.targeting.percentage();
+        exp.targeting
+            .set_percentage(percentage, state.config.safety.max_affected_percent);
+        overrides.entry(id.to_string()).or_default().percentage = Some(percentage);
+        Ok(previous)
+    }
+
+    /// Arm an experiment so the next `count` requests matching it are
+    /// faulted regardless of percentage, returning how many were armed
+    /// before.
+    ///
+    /// Only enabled experiments within their schedule match requests, so
+    /// an armed experiment fires once both hold. Triggered injections are
+    /// counted separately, outside experiment statistics and budgets.
+    pub fn trigger_experiment(&self, id: &str, count: u32) -> Result<u32> {
+        if count == 0 || count > MAX_TRIGGER_COUNT {
+            bail!(
+                "count must be between 1 and {}, got {}",
+                MAX_TRIGGER_COUNT,
+                count
+            );
+        }
+        let state = self.state.load();
+        let exp = state.compiled_experiment(id)?;
+        Ok(exp.armed.swap(count, Ordering::AcqRel))
+    }
+
+    fn lock_overrides(&self) -> MutexGuard<'_, HashMap<String, ExperimentOverride>> {
+        self.overrides.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Deserialize an experiment against the active configuration's
+    /// templates, see [`Config::experiment_from_value`].
+    pub fn parse_experiment(&self, value: serde_yaml::Value) -> Result<Experiment> {
+        self.state.load().config.experiment_from_value(value)
+    }
+
+    /// Add an experiment to the live set.
+    ///
+    /// The experiment is validated on its own and against the active
+    /// configuration, and starts with fresh counters. It is dropped on the
+    /// next reload unless `settings.persist_runtime` is set, and is never
+    /// written to a configuration file.
+    pub fn add_experiment(&self, experiment: Experiment) -> Result<()> {
+        experiment.validate()?;
+        let mut runtime = self.lock_runtime_experiments();
+        let previous = self.state.load();
+        if previous.compiled_experiment(&experiment.id).is_ok() {
+            bail!("experiment '{}' already exists", experiment.id);
+        }
+        let mut config = previous.config.clone();
+        config.experiments.push(experiment.clone());
+        config.validate()?;
+        check_targeting(&config)?;
+        info!(experiment = %experiment.id, "Experiment added at runtime");
+        runtime.push(experiment);
+        self.swap_in(config, previous.version.clone(), &runtime);
+        Ok(())
+    }
+
+    /// Remove an experiment added with [`add_experiment`](Self::add_experiment),
+    /// returning it.
+    ///
+    /// Experiments defined in the configuration are only removed by
+    /// editing it.
+    pub fn remove_experiment(&self, id: &str) -> Result<Experiment> {
+        let mut runtime = self.lock_runtime_experiments();
+        let previous = self.state.load();
+        previous.compiled_experiment(id)?;
+        let Some(index) = runtime.iter().position(|exp| exp.id == id) else {
+            bail!(
+                "experiment '{}' is defined in the configuration, disable it instead",
+                id
+            );
+        };
+        let removed = runtime.remove(index);
+        let mut config = previous.config.clone();
+        config.experiments.retain(|exp| exp.id != id);
+        info!(experiment = %id, "Runtime experiment removed");
+        self.swap_in(config, previous.version.clone(), &runtime);
+        Ok(removed)
+    }
+
+    fn lock_runtime_experiments(&self) -> MutexGuard<'_, Vec<Experiment>> {
+        self.runtime_experiments
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Reset every experiment's injection counter and `max_injections`
+    /// budget.
+    pub fn reset_injection_counts(&self) {
+        for exp in &self.state.load().compiled_experiments {
+            exp.injections.store(0, Ordering::Relaxed);
+            exp.would_inject.store(0, Ordering::Relaxed);
+            exp.exhausted.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Validate and swap in a new configuration.
+    ///
+    /// Injection counters carry over for experiments whose id and
+    /// definition are unchanged. On error the current configuration stays
+    /// active.
+    pub fn reload(&self, config: Config) -> Result<()> {
+        self.install(config, None)
+    }
+
+    /// Validate and swap in a configuration, recording its pushed version.
+    fn install(&self, config: Config, version: Option<String>) -> Result<()> {
+        config.validate()?;
+        check_targeting(&config)?;
+        let mut runtime = self.lock_runtime_experiments();
+        let config = with_runtime_experiments(config, &mut runtime);
+        let state = self.swap_in(config, version, &runtime);
+        info!(
+            experiments = state.compiled_experiments.len(),
+            enabled = state.enabled_count(),
+            version = state.version.as_deref().unwrap_or("-"),
+            "Chaos agent configuration reloaded"
+        );
+        Ok(())
+    }
+
+    /// Compile a validated configuration, apply runtime changes and make
+    /// it the active one.
+    fn swap_in(
+        &self,
+        config: Config,
+        version: Option<String>,
+        runtime: &[Experiment],
+    ) -> Arc<AgentState> {
+        let previous = self.state.load();
+        let mut state = AgentState::new(config, Some(&previous));
+        state.version = version;
+        for exp in &mut state.compiled_experiments {
+            exp.ephemeral = runtime.iter().any(|r| r.id == exp.id);
+        }
+        let mut overrides = self.lock_overrides();
+        state.apply_overrides(&mut overrides);
+        let state = Arc::new(state);
+        self.state.store(Arc::clone(&state));
+        state
+    }
+
+    /// Load and swap in a new configuration, counting failures.
+    ///
+    /// On error, whether `load` failed or the result is invalid, the
+    /// current configuration stays active.
+    pub fn reload_with<F>(&self, load: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<Config>,
+    {
+        let result = load().and_then(|config| self.reload(config));
+        if result.is_err() {
+            self.registry.reload_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// Compile every experiment's targeting, failing on the first that does
+/// not compile.
+///
+/// A new agent leaves such experiments out and reports them, but a reload
+/// or push is rejected instead, so a broken matcher never replaces a
+/// working configuration.
+fn check_targeting(config: &Config) -> Result<()> {
+    for exp in &config.experiments {
+        CompiledTargeting::with_settings(&exp.targeting, &config.settings)
+            .map_err(|e| anyhow!("experiments[{}].targeting: {}", exp.id, e))?;
+    }
+    Ok(())
+}
+
+/// Count one more on `counter` unless it has reached `max`, atomically.
+fn claim_below(counter: &AtomicU64, max: u64) -> bool {
+    counter
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            (count < max).then_some(count + 1)
+        })
+        .is_ok()
+}
+
+/// Share of an experiment's percentage left with `run_time_left` before a
+/// time-limited run ends: 1.0 until its `ramp_down_seconds`, then falling
+/// linearly to 0.0 at the end.
+pub(crate) fn ramp_down_scale(experiment: &Experiment, run_time_left: Option<Duration>) -> f64 {
+    let (Some(left), Some(ramp_down)) = (run_time_left, experiment.ramp_down_seconds) else {
+        return 1.0;
+    };
+    (left.as_secs_f64() / ramp_down as f64).min(1.0)
 }
 
-// Safety: ChaosAgent is Send + Sync because all its fields are Send + Sync
-unsafe impl Send for ChaosAgent {}
-unsafe impl Sync for ChaosAgent {}
+/// Milliseconds since the Unix epoch.
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Turn running totals into counts since the previous report, remembering
+/// the totals in `reported`. A counter that went backwards, because a
+/// changed experiment started over, reports its whole value.
+fn counter_deltas(counters: &mut [CounterMetric], reported: &mut HashMap<String, u64>) {
+    for counter in counters {
+        let mut labels: Vec<_> = counter.labels.iter().collect();
+        labels.sort();
+        let series = format!("{}{:?}", counter.name, labels);
+        let previous = reported.insert(series, counter.value).unwrap_or(0);
+        counter.value = counter.value.checked_sub(previous).unwrap_or(counter.value);
+    }
+}
+
+/// Carry runtime experiments into a reloaded configuration when it sets
+/// `settings.persist_runtime`, dropping them otherwise.
+///
+/// Runtime experiments whose id the configuration now defines, or that no
+/// longer validate against it, are dropped as well.
+fn with_runtime_experiments(config: Config, runtime: &mut Vec<Experiment>) -> Config {
+    if runtime.is_empty() {
+        return config;
+    }
+    if !config.settings.persist_runtime {
+        info!(
+            experiments = runtime.len(),
+            "Dropping runtime experiments on reload"
+        );
+        runtime.clear();
+        return config;
+    }
+    runtime.retain(|exp| {
+        let defined = config.experiments.iter().any(|e| e.id == exp.id);
+        if defined {
+            warn!(
+                experiment = %exp.id,
+                "Configuration now defines runtime experiment, dropping the runtime one"
+            );
+        }
+        !defined
+    });
+    let mut merged = config.clone();
+    merged.experiments.extend(runtime.iter().cloned());
+    match merged.validate() {
+        Ok(()) => merged,
+        Err(e) => {
+            warn!(
+                error = %e,
+                "Runtime experiments are invalid under the new configuration, dropping them"
+            );
+            runtime.clear();
+            config
+        }
+    }
+}
+
+impl AgentState {
+    /// Compile a configuration, carrying counters over from `previous`.
+    fn new(config: Config, previous: Option<&AgentState>) -> Self {
+        Self::compile(config, previous, true)
+    }
+
+    /// Compile a configuration for inspection only, without starting
+    /// precheck or auto-abort probes.
+    pub(crate) fn offline(config: Config) -> Self {
+        Self::compile(config, None, false)
+    }
+
+    fn compile(mut config: Config, previous: Option<&AgentState>, probes: bool) -> Self {
+        let max_percent = config.safety.max_affected_percent;
+        let normalization = config.settings.path_normalization;
+        let mut invalid_matchers = Vec::new();
+        // Configurations built in code skip the loaders that read fixtures
+        let mut missing_fixtures = Vec::new();
+        for exp in &mut config.experiments {
+            if let Err(e) = exp.load_fixture() {
+                error!(
+                    experiment = %exp.id,
+                    error = %e,
+                    "Failed to load fixture, disabling experiment"
+                );
+                missing_fixtures.push(exp.id.clone());
+            }
+        }
+        let compiled_experiments: Vec<CompiledExperiment> = config
+            .experiments
+            .iter()
+            .filter(|exp| !missing_fixtures.contains(&exp.id))
+            .filter_map(|exp| {
+                // A broken matcher must never widen the experiment, so the
+                // whole experiment is left out
+                let mut targeting =
+                    match CompiledTargeting::with_settings(&exp.targeting, &config.settings) {
+                        Ok(targeting) => targeting,
+                        Err(e) => {
+                            let invalid = CompiledTargeting::invalid_matchers(
+                                &exp.targeting,
+                                &config.settings,
+                            );
+                            error!(
+                                experiment = %exp.id,
+                                error = %e,
+                                invalid_matchers = invalid,
+                                "Failed to compile experiment targeting, disabling experiment"
+                            );
+                            invalid_matchers.push((exp.id.clone(), invalid));
+                            return None;
+                        }
+                    };
+                if targeting.clamp_percentage(max_percent) {
+                    warn!(
+                        experiment = %exp.id,
+                        percentage = exp.targeting.percentage,
+                        max_affected_percent = max_percent,
+                        "Experiment percentage exceeds safety limit, clamping"
+                    );
+                }
+
+                // A changed definition is a new experiment with fresh counters
+                let unchanged = previous.and_then(|p| {
+                    p.compiled_experiments
+                        .iter()
+                        .find(|c| c.id == exp.id && c.experiment == *exp)
+                });
+                let (injections, exhausted, would_inject, last_injection_ms, armed, matched) =
+                    unchanged
+                        .map(|p| {
+                            (
+                                Arc::clone(&p.injections),
+                                Arc::clone(&p.exhausted),
+                                Arc::clone(&p.would_inject),
+                                Arc::clone(&p.last_injection_ms),
+                                Arc::clone(&p.armed),
+                                Arc::clone(&p.matched),
+                            )
+                        })
+                        .unwrap_or_default();
+                let skips = unchanged.map_or_else(
+                    || Arc::new(SkipCounters::new(EXPERIMENT_SKIP_REASONS)),
+                    |p| Arc::clone(&p.skips),
+                );
+                let cooldown = match unchanged {
+                    Some(p) => p.cooldown.clone(),
+                    None => exp
+                        .targeting
+                        .client_cooldown
+                        .clone()
+                        .map(|c| Arc::new(ClientCooldown::new(c))),
+                };
+
+                let escalation = match (unchanged, &exp.fault) {
+                    (Some(p), _) => p.escalation.clone(),
+                    (
+                        None,
+                        Fault::EscalatingLatency {
+                            base_ms,
+                            factor,
+                            max_ms,
+                            reset_after_seconds,
+                        },
+                    ) => Some(Arc::new(Escalation::new(
+                        *base_ms,
+                        *factor,
+                        *max_ms,
+                        *reset_after_seconds,
+                    ))),
+                    (None, _) => None,
+                };
+
+                let delays = match unchanged {
+                    Some(p) => p.delays.clone(),
+                    None => exp
+                        .fault
+                        .holds_request()
+                        .then(|| Arc::new(Histogram::new(INJECTED_DELAY_BUCKETS_MS))),
+                };
+
+                // Keep the running probe (and its last result) when unchanged
+                let precheck = match unchanged {
+                    Some(p) => p.precheck.clone(),
+                    None => exp
+                        .precheck
+                        .as_ref()
+                        .filter(|_| exp.enabled && probes)
+                        .and_then(|c| Precheck::new(&exp.id, c))
+                        .map(|precheck| {
+                            let precheck = Arc::new(precheck);
+                            precheck.spawn();
+                            precheck
+                        }),
+                };
+
+                Some(CompiledExperiment {
+                    id: exp.id.clone(),
+                    enabled: AtomicBool::new(exp.enabled),
+                    targeting,
+                    experiment: exp.clone(),
+                    injections,
+                    exhausted,
+                    would_inject,
+                    last_injection_ms,
+                    matched,
+                    skips,
+                    armed,
+                    ephemeral: false,
+                    precheck,
+                    cooldown,
+                    escalation,
+                    delays,
+                })
+            })
+            .collect();
+
+        // Keep the tracker (and its trip state) when the settings are unchanged
+        let auto_abort = match (&config.safety.auto_abort, previous) {
+            (Some(c), Some(p))
+                if p.config.safety.auto_abort.as_ref() == Some(c) && p.auto_abort.is_some() =>
+            {
+                p.auto_abort.clone()
+            }
+            (Some(c), _) if probes => {
+                let auto_abort = Arc::new(AutoAbort::new(c.clone()));
+                auto_abort.spawn_probe();
+                Some(auto_abort)
+            }
+            _ => None,
+        };
+
+        // Keep the kill switch (and its logged state) when the file is unchanged
+        let kill_switch = match (&config.safety.kill_switch_file, previous) {
+            (Some(path), Some(p)) if p.config.safety.kill_switch_file.as_ref() == Some(path) => {
+                p.kill_switch.clone()
+            }
+            (Some(path), _) => Some(Arc::new(KillSwitch::new(path))),
+            (None, _) => None,
+        };
+
+        let affected_clients = match (&config.safety.max_affected_clients, previous) {
+            (Some(c), Some(p)) if p.config.safety.max_affected_clients.as_ref() == Some(c) => {
+                p.affected_clients.clone()
+            }
+            (Some(c), _) => Some(Arc::new(AffectedClients::new(c.clone()))),
+            (None, _) => None,
+        };
+
+        Self {
+            budget: InjectionBudget::new(max_percent, BUDGET_WINDOW_SECS),
+            fault_slots: config.safety.max_concurrent_faults.map(Semaphore::new),
+            auto_abort,
+            kill_switch,
+            affected_clients,
+            bypass: config.safety.bypass.as_ref().map(BypassVerifier::new),
+            excluded_paths: config
+                .safety
+                .excluded_paths
+                .iter()
+                .map(|p| normalization.apply(p).into_owned())
+                .collect(),
+            excluded_methods: config
+                .safety
+                .excluded_methods
+                .iter()
+                .map(|m| m.to_uppercase())
+                .collect(),
+            excluded_headers: config
+                .safety
+                .excluded_headers
+                .iter()
+                .map(|(name, value)| (name.to_lowercase(), value.clone()))
+                .collect(),
+            invalid_matchers,
+            version: None,
+            once_spent: AtomicBool::new(false),
+            rng: config.settings.seed.map(SeededRng::new),
+            compiled_experiments,
+            config,
+        }
+    }
+
+    /// Return why a request is excluded from chaos, if it is.
+    ///
+    /// `headers` must have lowercased names.
+    pub(crate) fn exclusion_reason(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Option<&'static str> {
+        let normalized = self.config.settings.path_normalization.apply(path);
+        if is_excluded_path(&normalized, &self.excluded_paths) {
+            return Some("path");
+        }
+        if self
+            .excluded_methods
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(method))
+        {
+            return Some("method");
+        }
+        let header_excluded = self.excluded_headers.iter().any(|(name, expected)| {
+            headers
+                .get(name)
+                .is_some_and(|value| expected.as_ref().is_none_or(|e| e == value))
+        });
+        if header_excluded {
+            return Some("header");
+        }
+        None
+    }
+
+    /// Concurrent fault limit and the number of slots in use, if a limit
+    /// is configured.
+    fn fault_slot_usage(&self) -> Option<(usize, usize)> {
+        let limit = self.config.safety.max_concurrent_faults?;
+        let available = self.fault_slots.as_ref()?.available_permits();
+        Some((limit, limit.saturating_sub(available)))
+    }
+
+    /// Apply runtime overrides to freshly compiled experiments, dropping
+    /// those that no longer apply.
+    fn apply_overrides(&self, overrides: &mut HashMap<String, ExperimentOverride>) {
+        let max_percent = self.config.safety.max_affected_percent;
+        overrides.retain(|id, runtime| {
+            let Ok(exp) = self.compiled_experiment(id) else {
+                warn!(experiment = %id, "Experiment is gone, dropping its runtime override");
+                return false;
+            };
+            match runtime.enabled {
+                Some(true) if exp.precheck_missing() => {
+                    warn!(
+                        experiment = %id,
+                        "Experiment precheck is not running, dropping its runtime enable"
+                    );
+                    runtime.enabled = None;
+                }
+                Some(true) if exp.fixture_missing() => {
+                    warn!(
+                        experiment = %id,
+                        "Experiment fixture was never read, dropping its runtime enable"
+                    );
+                    runtime.enabled = None;
+                }
+                Some(enabled) => exp.enabled.store(enabled, Ordering::Relaxed),
+                None => {}
+            }
+            if let Some(percentage) = runtime.percentage {
+                exp.targeting.set_percentage(percentage, max_percent);
+            }
+            *runtime != ExperimentOverride::default()
+        });
+    }
+
+    fn compiled_experiment(&self, id: &str) -> Result<&CompiledExperiment> {
+        self.compiled_experiments
+            .iter()
+            .find(|exp| exp.id == id)
+            .ok_or_else(|| anyhow!("no experiment '{}'", id))
+    }
+
+    fn experiment_status(
+        &self,
+        exp: &CompiledExperiment,
+        runtime_override: Option<ExperimentOverride>,
+    ) -> ExperimentStatus {
+        let rate = exp.targeting.scaled_rate(
+            self.config.settings.intensity,
+            self.config.safety.max_affected_percent,
+        );
+        ExperimentStatus {
+            id: exp.id.clone(),
+            enabled: exp.is_enabled(),
+            description: exp.experiment.description.clone(),
+            injections: exp.injections.load(Ordering::Relaxed),
+            last_injection: match exp.last_injection_ms.load(Ordering::Relaxed) {
+                0 => None,
+                ms => DateTime::from_timestamp_millis(ms as i64),
+            },
+            percentage: exp.targeting.percentage(),
+            effective_percentage: rate * 100.0,
+            scheduled: self.is_experiment_scheduled_at(exp, Utc::now()),
+            ephemeral: exp.ephemeral,
+            armed: exp.armed.load(Ordering::Relaxed),
+            suppressions: exp.suppressions(),
+            runtime_override,
+        }
+    }
+
+    fn enabled_count(&self) -> usize {
+        self.compiled_experiments
+            .iter()
+            .filter(|e| e.is_enabled())
+            .count()
+    }
+
+    fn is_auto_aborted(&self) -> bool {
+        self.auto_abort
+            .as_ref()
+            .is_some_and(|auto_abort| auto_abort.is_tripped())
+    }
+
+    fn is_kill_switch_active(&self) -> bool {
+        self.kill_switch
+            .as_ref()
+            .is_some_and(|kill_switch| kill_switch.is_active())
+    }
+
+    /// Check if the global schedule is active at the given instant.
+    fn is_within_schedule_at(&self, now: DateTime<Utc>) -> bool {
+        self.is_within_windows_at(&self.config.safety.schedule, now)
+    }
+
+    /// Check if an experiment is active at the given instant. Its own
+    /// schedule, if any, overrides the global one; `targeting.days`
+    /// further limits either.
+    pub(crate) fn is_experiment_scheduled_at(
+        &self,
+        exp: &CompiledExperiment,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let days = &exp.experiment.targeting.days;
+        let timezone = &self.config.safety.timezone;
+        if !days.is_empty() && !days.contains(&now.with_timezone(timezone).weekday()) {
+            return false;
+        }
+        if exp.experiment.schedule.is_empty() {
+            self.is_within_schedule_at(now)
+        } else {
+            self.is_within_windows_at(&exp.experiment.schedule, now)
+        }
+    }
+
+    fn is_within_windows_at(&self, windows: &[Schedule], now: DateTime<Utc>) -> bool {
+        let safety = &self.config.safety;
+
+        if windows.is_empty() {
+            // No schedule = always active, subject to date rules
+            return safety.is_date_allowed(now.with_timezone(&safety.timezone).date_naive());
+        }
+
+        windows.iter().any(|schedule| {
+            Self::check_schedule_at(schedule, now)
+                && safety.is_date_allowed(now.with_timezone(&schedule.timezone).date_naive())
+        })
+    }
+
+    /// Check whether a schedule window is active at the given instant.
+    ///
+    /// A window whose start is after its end wraps midnight; the day check
+    /// then applies to the day the window started on.
+    fn check_schedule_at(schedule: &Schedule, now: DateTime<Utc>) -> bool {
+        let now = now.with_timezone(&schedule.timezone);
+        let day = now.weekday();
+        let time =
+            NaiveTime::from_hms_opt(now.hour(), now.minute(), now.second()).unwrap_or_default();
+
+        if schedule.start <= schedule.end {
+            // Same-day window: current day must be in the schedule
+            return schedule.days.contains(&day) && time >= schedule.start && time <= schedule.end;
+        }
+
+        // Overnight window: either the evening part on a scheduled day, or
+        // the early-morning part on the day after a scheduled day
+        (time >= schedule.start && schedule.days.contains(&day))
+            || (time <= schedule.end && schedule.days.contains(&day.pred()))
+    }
+
+    /// Report whether the global schedule is active at the given instant,
+    /// how long the active window lasts and when the next one starts.
+    ///
+    /// With several active windows the one ending last is reported.
+    fn schedule_status_at(&self, now: DateTime<Utc>) -> ScheduleStatus {
+        let active = self.is_within_schedule_at(now);
+        let windows = &self.config.safety.schedule;
+        if windows.is_empty() {
+            return ScheduleStatus {
+                active,
+                seconds_remaining: -1,
+                seconds_until_next: -1,
+            };
+        }
+
+        let mut ends_at: Option<DateTime<Utc>> = None;
+        let mut next_start: Option<DateTime<Utc>> = None;
+        for (start, end) in windows
+            .iter()
+            .flat_map(|s| Self::window_occurrences(s, now))
+        {
+            if start <= now && now <= end {
+                ends_at = ends_at.max(Some(end));
+            } else if start > now && next_start.is_none_or(|next| start < next) {
+                next_start = Some(start);
+            }
+        }
+
+        ScheduleStatus {
+            active,
+            seconds_remaining: match ends_at {
+                Some(end) if active => (end - now).num_seconds(),
+                _ => 0,
+            },
+            seconds_until_next: next_start.map_or(-1, |start| (start - now).num_seconds()),
+        }
+    }
+
+    /// Occurrences of a window from the day before `now` through the next
+    /// week, as UTC start/end pairs. Occurrences starting in a DST gap are
+    /// skipped.
+    fn window_occurrences(
+        schedule: &Schedule,
+        now: DateTime<Utc>,
+    ) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let today = now.with_timezone(&schedule.timezone).date_naive();
+        (-1..=7)
+            .filter_map(|offset| today.checked_add_signed(chrono::Duration::days(offset)))
+            .filter(|date| schedule.days.contains(&date.weekday()))
+            .filter_map(|date| {
+                let end_date = if schedule.start <= schedule.end {
+                    date
+                } else {
+                    date.succ_opt()?
+                };
+                let start = schedule
+                    .timezone
+                    .from_local_datetime(&date.and_time(schedule.start))
+                    .earliest()?;
+                let end = schedule
+                    .timezone
+                    .from_local_datetime(&end_date.and_time(schedule.end))
+                    .latest()?;
+                Some((start.with_timezone(&Utc), end.with_timezone(&Utc)))
+            })
+            .collect()
+    }
+
+    /// Find matching experiments for a request.
+    #[cfg(test)]
+    fn find_matching_experiments(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Vec<&CompiledExperiment> {
+        self.find_matching_experiments_at(method, path, headers, Utc::now())
+    }
+
+    #[cfg(test)]
+    fn find_matching_experiments_at(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        now: DateTime<Utc>,
+    ) -> Vec<&CompiledExperiment> {
+        self.match_experiments_at(method, path, headers, now, |_, _| {})
+    }
+
+    /// Find the enabled experiments matching a request within their
+    /// schedule windows, calling `skipped` with the reason for each one
+    /// whose targeting matches but that is disabled or out of schedule.
+    fn match_experiments_at(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        now: DateTime<Utc>,
+        mut skipped: impl FnMut(&CompiledExperiment, &'static str),
+    ) -> Vec<&CompiledExperiment> {
+        self.compiled_experiments
+            .iter()
+            .filter(|exp| {
+                if !exp.targeting.matches(method, path, headers) {
+                    return false;
+                }
+                let reason = if !exp.is_enabled() {
+                    "disabled"
+                } else if !self.is_experiment_scheduled_at(exp, now) {
+                    "outside_schedule"
+                } else {
+                    return true;
+                };
+                skipped(exp, reason);
+                false
+            })
+            .collect()
+    }
+
+    /// Run the checks that come before any percentage roll: the switches,
+    /// exclusions and bypass tokens that spare the whole request, then
+    /// matching at `now`, calling `skipped` as [`Self::match_experiments_at`]
+    /// does, and the force header. Nothing is counted.
+    ///
+    /// `headers` must have lowercased names.
+    pub(crate) fn plan_at(
+        &self,
+        gates: Gates,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        now: DateTime<Utc>,
+        skipped: impl FnMut(&CompiledExperiment, &'static str),
+    ) -> Plan<'_> {
+        if let Some(spared) = self.spared(gates, method, path, headers) {
+            return Plan::Spared(spared);
+        }
+        let experiments = self.match_experiments_at(method, path, headers, now, skipped);
+        let forced = self.forced_experiment(headers, &experiments);
+        Plan::Matching {
+            experiments,
+            forced,
+        }
+    }
+
+    /// Return why a whole request passes through, if it does.
+    fn spared(
+        &self,
+        gates: Gates,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Option<Spared> {
+        let settings = &self.config.settings;
+        let spared = if !settings.enabled {
+            Spared::Disabled
+        } else if self.is_kill_switch_active() {
+            Spared::KillSwitch
+        } else if gates.paused {
+            Spared::Paused
+        } else if gates.draining {
+            Spared::Draining
+        } else if settings.once && self.once_spent.load(Ordering::Relaxed) {
+            Spared::OnceSpent
+        } else if self.is_auto_aborted() {
+            Spared::AutoAborted
+        } else if let Some(kind) = self.exclusion_reason(method, path, headers) {
+            Spared::Excluded(kind)
+        } else if self
+            .bypass
+            .as_ref()
+            .is_some_and(|bypass| bypass.is_bypassed(path, headers))
+        {
+            Spared::Bypassed
+        } else {
+            return None;
+        };
+        Some(spared)
+    }
+
+    /// Find the experiment named by the force header among the matching
+    /// experiments, if forcing is enabled.
+    fn forced_experiment<'a>(
+        &self,
+        headers: &HashMap<String, String>,
+        matching: &[&'a CompiledExperiment],
+    ) -> Option<&'a CompiledExperiment> {
+        if !self.config.settings.allow_force_header {
+            return None;
+        }
+        let id = headers.get(FORCE_HEADER)?;
+        let forced = matching.iter().find(|exp| exp.id == *id).copied();
+        if forced.is_none() {
+            debug!(
+                experiment = %id,
+                "Force header names no matching experiment, ignoring"
+            );
+        }
+        forced
+    }
+
+    /// Intensity an experiment's percentage roll is scaled by for `path`,
+    /// with `run_time_left` before a time-limited run ends.
+    pub(crate) fn roll_intensity(
+        &self,
+        exp: &CompiledExperiment,
+        path: &str,
+        run_time_left: Option<Duration>,
+    ) -> f64 {
+        self.config.settings.intensity
+            * exp.targeting.path_scale(path)
+            * ramp_down_scale(&exp.experiment, run_time_left)
+    }
+}
+
+impl ChaosAgent {
+    /// Create a new Chaos agent.
+    pub fn new(config: Config) -> Self {
+        let state = AgentState::new(config, None);
+
+        info!(
+            experiments = state.compiled_experiments.len(),
+            enabled = state.enabled_count(),
+            dry_run = state.config.settings.dry_run,
+            "Chaos agent initialized"
+        );
+
+        Self {
+            state: Arc::new(ArcSwap::from_pointee(state)),
+            pause: Arc::new(RuntimePause::new()),
+            registry: Arc::new(Registry::new()),
+            draining: Arc::new(DrainState::new()),
+            started: Instant::now(),
+            run_until: None,
+            recent: Mutex::new(VecDeque::new()),
+            reported_counters: Mutex::new(HashMap::new()),
+            injection_events: broadcast::channel(INJECTION_EVENT_CAPACITY).0,
+            overrides: Arc::new(Mutex::new(HashMap::new())),
+            runtime_experiments: Arc::new(Mutex::new(Vec::new())),
+            config_hook: None,
+        }
+    }
+
+    /// Run `hook` on every configuration pushed by the proxy before it is
+    /// installed, so pushes keep the same overrides as reloads from disk.
+    pub fn with_config_hook(
+        mut self,
+        hook: impl Fn(&mut Config) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.config_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Limit the run: once `max_duration` has passed since the agent was
+    /// created, it drains and injects no new faults.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.run_until = self.started.checked_add(max_duration);
+        self
+    }
+
+    /// Time left before a time-limited run drains, if the run is limited.
+    pub fn remaining_run_time(&self) -> Option<Duration> {
+        self.run_time_left_at(Instant::now())
+    }
+
+    /// Whether a time-limited run is over at `now`.
+    fn run_time_elapsed_at(&self, now: Instant) -> bool {
+        self.run_until.is_some_and(|until| now >= until)
+    }
+
+    /// Time left at `now` before a time-limited run ends, if the run is
+    /// limited.
+    fn run_time_left_at(&self, now: Instant) -> Option<Duration> {
+        self.run_until
+            .map(|until| until.saturating_duration_since(now))
+    }
+
+    /// Get a handle for suspending, resetting and reloading the agent.
+    pub fn control(&self) -> ChaosControl {
+        ChaosControl {
+            state: Arc::clone(&self.state),
+            pause: Arc::clone(&self.pause),
+            registry: Arc::clone(&self.registry),
+            injection_events: self.injection_events.clone(),
+            overrides: Arc::clone(&self.overrides),
+            runtime_experiments: Arc::clone(&self.runtime_experiments),
+            draining: Arc::clone(&self.draining),
+            started: self.started,
+            run_until: self.run_until,
+        }
+    }
+
+    /// Get the number of enabled experiments in the active configuration.
+    pub fn experiments_enabled(&self) -> usize {
+        self.state.load().enabled_count()
+    }
+
+    /// Get total configuration reloads rejected.
+    pub fn total_reload_errors(&self) -> u64 {
+        self.registry.reload_errors.load(Ordering::Relaxed)
+    }
+
+    /// Get total admin API requests refused for a missing or wrong token.
+    pub fn total_admin_unauthorized(&self) -> u64 {
+        self.registry.admin_unauthorized.load(Ordering::Relaxed)
+    }
+
+    /// Get the version of the pushed configuration currently applied, if
+    /// the active configuration was pushed by the proxy.
+    pub fn config_version(&self) -> Option<String> {
+        self.state.load().version.clone()
+    }
+
+    /// Apply a configuration pushed by the proxy.
+    ///
+    /// The JSON must describe a complete configuration. The configuration
+    /// hook, if any, runs before it is installed. On error the current
+    /// configuration stays active and the error is counted as a rejected
+    /// reload.
+    pub fn apply_pushed_config(
+        &self,
+        config: serde_json::Value,
+        version: Option<String>,
+    ) -> Result<()> {
+        let control = self.control();
+        let result = serde_yaml::to_value(config)
+            .map_err(anyhow::Error::from)
+            .and_then(Config::from_value)
+            .and_then(|mut config| {
+                if let Some(hook) = &self.config_hook {
+                    hook(&mut config)?;
+                }
+                Ok(config)
+            })
+            .map_err(|e| anyhow!("Invalid configuration: {}", e))
+            .and_then(|config| control.install(config, version));
+        if result.is_err() {
+            self.registry.reload_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Get the most recent injections, oldest first.
+    pub fn recent_injections(&self) -> Vec<InjectionRecord> {
+        self.lock_recent().iter().cloned().collect()
+    }
+
+    /// Subscribe to injections as they happen.
+    ///
+    /// Publishing never blocks the request path: a subscriber that falls
+    /// more than 1024 events behind skips the oldest ones.
+    pub fn subscribe_injections(&self) -> broadcast::Receiver<InjectionRecord> {
+        self.injection_events.subscribe()
+    }
+
+    /// Remember an injection, dropping the oldest beyond the configured cap,
+    /// and publish it to subscribers.
+    fn record_recent(
+        &self,
+        state: &AgentState,
+        exp: &CompiledExperiment,
+        method: &str,
+        path: &str,
+        result: &FaultResult,
+        selection: Selection,
+    ) {
+        let capacity = state.config.settings.recent_injections;
+        let subscribed = self.injection_events.receiver_count() > 0;
+        if capacity == 0 && !subscribed {
+            return;
+        }
+        let record = InjectionRecord {
+            experiment_id: exp.id.clone(),
+            timestamp_ms: unix_millis(),
+            method: method.to_string(),
+            path: path.to_string(),
+            fault: exp.experiment.fault.type_name(),
+            status: result.status(),
+            forced: selection == Selection::Forced,
+            triggered: selection == Selection::Triggered,
+            dry_run: state.config.settings.dry_run,
+        };
+        if subscribed {
+            let _ = self.injection_events.send(record.clone());
+        }
+        if capacity == 0 {
+            return;
+        }
+        let mut recent = self.lock_recent();
+        while recent.len() >= capacity {
+            recent.pop_front();
+        }
+        recent.push_back(record);
+    }
+
+    fn lock_recent(&self) -> std::sync::MutexGuard<'_, VecDeque<InjectionRecord>> {
+        self.recent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Check if the agent is currently draining, because the proxy asked
+    /// it to or its maximum run time has passed.
+    pub fn is_draining(&self) -> bool {
+        if self.run_time_elapsed_at(Instant::now()) {
+            if self.draining.drain_for_good() {
+                warn!("Maximum run time reached - stopping fault injection");
+            }
+            return true;
+        }
+        self.draining.is_draining()
+    }
+
+    /// Check if injections are suspended at runtime.
+    pub fn is_suspended(&self) -> bool {
+        self.pause.is_paused()
+    }
+
+    /// Get total requests exempted by a valid bypass header.
+    pub fn total_bypassed(&self) -> u64 {
+        self.registry.bypassed.load(Ordering::Relaxed)
+    }
+
+    /// Get total faults injected because of the force header.
+    pub fn total_forced_injections(&self) -> u64 {
+        self.registry.forced_injections.load(Ordering::Relaxed)
+    }
+
+    /// Get total faults injected by armed triggers.
+    pub fn total_triggered_injections(&self) -> u64 {
+        self.registry.triggered_injections.load(Ordering::Relaxed)
+    }
+
+    /// Get total faults shed by the concurrency limit.
+    pub fn total_faults_shed(&self) -> u64 {
+        self.registry.faults_shed.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of experiments disabled because their targeting
+    /// failed to compile.
+    pub fn experiment_compile_errors(&self) -> usize {
+        self.state.load().invalid_matchers.len()
+    }
+
+    /// Get total fault applications that panicked.
+    pub fn total_fault_panics(&self) -> u64 {
+        self.registry.fault_panics.load(Ordering::Relaxed)
+    }
+
+    /// Get total requests hit by an internal error.
+    pub fn total_internal_errors(&self) -> u64 {
+        self.registry.internal_errors.load(Ordering::Relaxed)
+    }
+
+    /// Check if the agent should report degraded health.
+    pub fn is_degraded(&self) -> bool {
+        self.is_draining()
+            || self.is_suspended()
+            || self.is_auto_aborted()
+            || self.total_fault_panics() >= FAULT_PANIC_DEGRADE_THRESHOLD
+    }
+
+    /// Check if chaos is suspended because the upstream is unhealthy.
+    pub fn is_auto_aborted(&self) -> bool {
+        self.state.load().is_auto_aborted()
+    }
+
+    /// Check if chaos is suspended because the kill switch file exists.
+    pub fn is_kill_switch_active(&self) -> bool {
+        self.state.load().is_kill_switch_active()
+    }
+
+    /// Feed an upstream response status to the auto-abort tracker.
+    ///
+    /// Responses produced by chaos itself are ignored so injected errors
+    /// never trip the abort.
+    fn observe_response(&self, status: u16, headers: &HashMap<String, Vec<String>>) {
+        let state = self.state.load();
+        let Some(auto_abort) = &state.auto_abort else {
+            return;
+        };
+        if headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("x-chaos-injected"))
+        {
+            return;
+        }
+        auto_abort.record_status(status);
+    }
+
+    /// Get total requests processed.
+    pub fn total_requests(&self) -> u64 {
+        self.registry.requests_total.load(Ordering::Relaxed)
+    }
+
+    /// Get total faults injected.
+    pub fn total_faults_injected(&self) -> u64 {
+        self.registry.faults_injected.load(Ordering::Relaxed)
+    }
+
+    /// Get total faults a dry run would have injected.
+    pub fn total_dry_run_would_inject(&self) -> u64 {
+        self.registry.dry_run_would_inject.load(Ordering::Relaxed)
+    }
+
+    /// Get injections suppressed by the global budget.
+    pub fn total_budget_suppressed(&self) -> u64 {
+        self.registry.budget_suppressed.load(Ordering::Relaxed)
+    }
+
+    /// Seconds since the agent started.
+    fn now_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Count a request in the totals and the global budget.
+    fn record_request(&self, state: &AgentState) {
+        let now = self.now_secs();
+        self.registry.requests_total.fetch_add(1, Ordering::Relaxed);
+        self.registry.request_rate.record(now, 1);
+        state.budget.record_request(now);
+    }
+
+    /// Check the global budget before injecting, counting suppressions.
+    fn within_budget(&self, state: &AgentState) -> bool {
+        if state.budget.allows_injection(self.now_secs()) {
+            return true;
+        }
+        self.registry
+            .budget_suppressed
+            .fetch_add(1, Ordering::Relaxed);
+        false
+    }
+
+    /// Flatten multi-value headers to single values.
+    fn flatten_headers(headers: &HashMap<String, Vec<String>>) -> HashMap<String, String> {
+        headers
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.first().cloned().unwrap_or_default()))
+            .collect()
+    }
+
+    /// Claim an injection for an experiment, counting it against the
+    /// experiment's `max_injections` budget.
+    ///
+    /// The claim is atomic, so concurrent requests can never push the count
+    /// past the budget.
+    fn claim_injection(&self, exp: &CompiledExperiment) -> bool {
+        let counter = &exp.injections;
+        let Some(max) = exp.experiment.max_injections else {
+            counter.fetch_add(1, Ordering::Relaxed);
+            return true;
+        };
+
+        let claimed = claim_below(counter, max);
+
+        if counter.load(Ordering::Relaxed) >= max && !exp.exhausted.swap(true, Ordering::Relaxed) {
+            warn!(
+                experiment = %exp.id,
+                max_injections = max,
+                "Experiment injection budget exhausted, no further faults will be injected"
+            );
+        }
+        claimed
+    }
+
+    /// Claim an injection a dry run would make, counting it against the
+    /// experiment's `max_injections` budget apart from live injections.
+    fn claim_dry_run_injection(&self, exp: &CompiledExperiment) -> bool {
+        match exp.experiment.max_injections {
+            Some(max) => claim_below(&exp.would_inject, max),
+            None => {
+                exp.would_inject.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+        }
+    }
+
+    /// Evaluate a request in-process, exactly as `on_request_headers` would,
+    /// without a proxy or transport.
+    ///
+    /// This is the supported way to unit-test a configuration: build an
+    /// agent from it, send requests and inspect the responses. Requests
+    /// count towards metrics, budgets and cooldowns like proxied ones.
+    /// Header names are matched case-insensitively; a name given twice
+    /// keeps its first value.
+    ///
+    /// ```
+    /// use zentinel_agent_chaos::builder::ExperimentBuilder;
+    /// use zentinel_agent_chaos::config::SafetyConfig;
+    /// use zentinel_agent_chaos::{ChaosAgent, Config};
+    /// use zentinel_agent_protocol::Decision;
+    ///
+    /// let config = Config {
+    ///     safety: SafetyConfig {
+    ///         max_affected_percent: 100,
+    ///         ..SafetyConfig::default()
+    ///     },
+    ///     experiments: vec![ExperimentBuilder::new("checkout-errors")
+    ///         .path_prefix("/checkout")
+    ///         .header("x-chaos-enabled", "true")
+    ///         .error(503)
+    ///         .build()?],
+    ///     ..Config::default()
+    /// };
+    /// let agent = ChaosAgent::new(config);
+    ///
+    /// # tokio_test::block_on(async {
+    /// let response = agent
+    ///     .evaluate_request("POST", "/checkout", &[("X-Chaos-Enabled", "true")])
+    ///     .await;
+    /// assert!(matches!(response.decision, Decision::Block { status: 503, .. }));
+    ///
+    /// // Requests without the header pass through
+    /// let response = agent.evaluate_request("POST", "/checkout", &[]).await;
+    /// assert!(matches!(response.decision, Decision::Allow));
+    /// # });
+    /// assert_eq!(agent.total_faults_injected(), 1);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub async fn evaluate_request(
+        &self,
+        method: &str,
+        uri: &str,
+        headers: &[(&str, &str)],
+    ) -> AgentResponse {
+        let mut header_map: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, value) in headers {
+            header_map
+                .entry(name.to_lowercase())
+                .or_default()
+                .push(value.to_string());
+        }
+        self.evaluate(method, uri, &header_map)
+            .await
+            .map(Decision::build)
+            .unwrap_or_else(AgentResponse::default_allow)
+    }
+
+    /// Run the safety checks and experiments for a request.
+    ///
+    /// Returns the decision for a faulted request, or `None` to pass the
+    /// request through untouched.
+    pub(crate) async fn evaluate(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, Vec<String>>,
+    ) -> Option<Decision> {
+        self.evaluate_with_version(method, path, headers, None, None)
+            .await
+    }
+
+    /// Like [`evaluate`](Self::evaluate), for a request whose HTTP version
+    /// the proxy reported, so `targeting.http_versions` can apply, and
+    /// whose correlation id ties it to its later phases.
+    async fn evaluate_with_version(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, Vec<String>>,
+        http_version: Option<&str>,
+        correlation_id: Option<&str>,
+    ) -> Option<Decision> {
+        let state = self.state.load_full();
+
+        // Increment request counter
+        self.record_request(&state);
+
+        let mut headers = Self::flatten_headers(headers);
+        if let Some(version) = http_version.filter(|v| !v.is_empty()) {
+            headers.insert(HTTP_VERSION_HEADER.to_string(), version.to_string());
+        }
+        self.decide(
+            &state,
+            method,
+            path,
+            &headers,
+            correlation_id,
+            Phase::Request,
+        )
+        .await
+    }
+
+    /// Evaluate body-phase faults for a request whose body the proxy
+    /// buffered.
+    async fn evaluate_body(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, Vec<String>>,
+        correlation_id: Option<&str>,
+        body: &[u8],
+    ) -> Option<Decision> {
+        let state = self.state.load_full();
+        let headers = Self::flatten_headers(headers);
+        let phase = Phase::RequestBody(body);
+        self.decide(&state, method, path, &headers, correlation_id, phase)
+            .await
+    }
+
+    /// Evaluate response-phase faults for an upstream response to the
+    /// given request.
+    async fn evaluate_response(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, Vec<String>>,
+        correlation_id: Option<&str>,
+        response: UpstreamResponse<'_>,
+    ) -> Option<Decision> {
+        let state = self.state.load_full();
+        let headers = Self::flatten_headers(headers);
+        let phase = Phase::Response(response);
+        self.decide(&state, method, path, &headers, correlation_id, phase)
+            .await
+    }
+
+    /// Run the safety checks and matching experiments for one phase, with
+    /// the request's seeded generator when `settings.seed` is set.
+    ///
+    /// The request phase assigns the request its sequence number, kept
+    /// under `correlation_id` while body- or response-phase experiments
+    /// may draw from it later; the response phase is the last.
+    async fn decide(
+        &self,
+        state: &AgentState,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        correlation_id: Option<&str>,
+        phase: Phase<'_>,
+    ) -> Option<Decision> {
+        let correlation_id = correlation_id.filter(|id| !id.is_empty());
+        let rng = state.rng.as_ref().map(|rng| {
+            let sequence = match phase {
+                Phase::Request => {
+                    let later = state.compiled_experiments.iter().any(|exp| {
+                        let fault = &exp.experiment.fault;
+                        fault.is_body_phase() || fault.is_response_phase()
+                    });
+                    rng.start_request(correlation_id, later)
+                }
+                Phase::RequestBody(_) => rng.request_sequence(correlation_id, false),
+                Phase::Response(_) => rng.request_sequence(correlation_id, true),
+            };
+            rng.phase_rng(sequence, phase.index())
+        });
+        with_request_rng(
+            rng,
+            self.decide_unseeded(state, method, path, headers, phase),
+        )
+        .await
+    }
+
+    async fn decide_unseeded(
+        &self,
+        state: &AgentState,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        phase: Phase<'_>,
+    ) -> Option<Decision> {
+        // Count each skipped request once, in its request phase
+        let skip = |reason: &str| {
+            if matches!(phase, Phase::Request) {
+                self.registry.skips.record(reason);
+            }
+            None
+        };
+
+        // Run the shared checks, counting each experiment skipped for its
+        // schedule in the phase its fault applies to
+        let gates = Gates {
+            paused: self.is_suspended(),
+            draining: self.is_draining(),
+        };
+        let started = Instant::now();
+        let plan = state.plan_at(gates, method, path, headers, Utc::now(), |exp, reason| {
+            if phase.applies(&exp.experiment.fault) {
+                exp.skips.record(reason);
+            }
+        });
+        let (matching, forced) = match plan {
+            Plan::Spared(spared) => {
+                debug!(path = path, method = method, reason = %spared, "Request spared from chaos");
+                // Count each request once, in its request phase
+                if matches!(phase, Phase::Request) {
+                    match spared {
+                        Spared::Excluded(kind) => {
+                            if let Some(counter) = self.registry.exclusions.get(kind) {
+                                counter.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                        Spared::Bypassed => {
+                            self.registry.bypassed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        _ => {}
+                    }
+                }
+                return spared.skip_reason().and_then(|reason| skip(&reason));
+            }
+            Plan::Matching {
+                experiments,
+                forced,
+            } => (experiments, forced),
+        };
+        if state.config.settings.profile_matching {
+            self.registry
+                .match_eval
+                .record(started.elapsed().as_nanos().try_into().unwrap_or(u64::MAX));
+        }
+        for exp in &matching {
+            if phase.applies(&exp.experiment.fault) {
+                exp.matched.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        if matching.is_empty() {
+            debug!(path = path, method = method, "No matching experiments");
+            return None;
+        }
+
+        // A force header pins the named experiment and skips the roll
+        if let Some(exp) = forced {
+            let selection = Selection::Forced;
+            return match self
+                .run_experiment(state, exp, selection, path, headers, phase)
+                .await
+            {
+                ExperimentOutcome::Applied(result) => {
+                    self.record_recent(state, exp, method, path, &result, selection);
+                    Some(Self::fault_decision(state, result, exp, selection))
+                }
+                ExperimentOutcome::Failed => self.internal_error_decision(state),
+                ExperimentOutcome::Skipped | ExperimentOutcome::Suppressed => None,
+            };
+        }
+
+        // Spare new clients once the affected-client cap is reached; the
+        // client's place is held until the outcome is known
+        let now = self.now_secs();
+        let client = match &state.affected_clients {
+            Some(clients) => {
+                let key = headers.get(clients.key_header()).map(String::as_str);
+                match (key, clients.reserve_at(key, now)) {
+                    (Some(key), Some(reservation)) => Some((clients, key, reservation)),
+                    _ => {
+                        debug!(
+                            path = path,
+                            "Affected-client cap reached, sparing new client"
+                        );
+                        return skip("rate_limited");
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let (decision, applied) = self
+            .apply_first(state, &matching, method, path, headers, phase)
+            .await;
+        if let Some((clients, key, reservation)) = client {
+            clients.settle(key, reservation, applied);
+        }
+        decision
+    }
+
+    /// Apply the first matching experiment that passes its checks,
+    /// returning the decision and whether a fault was applied.
+    async fn apply_first(
+        &self,
+        state: &AgentState,
+        matching: &[&CompiledExperiment],
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        phase: Phase<'_>,
+    ) -> (Option<Decision>, bool) {
+        let now = self.now_secs();
+        for exp in matching.iter().copied() {
+            // Skip clients still cooling down from this experiment's last fault
+            let cooldown = exp
+                .cooldown
+                .as_deref()
+                .map(|cooldown| (cooldown, cooldown.client_key(headers)));
+            if let Some((cooldown, key)) = cooldown {
+                if cooldown.is_cooling_at(key, now) {
+                    debug!(experiment = %exp.id, "Client in cooldown, skipping experiment");
+                    if phase.applies(&exp.experiment.fault) {
+                        exp.skips.record("rate_limited");
+                    }
+                    continue;
+                }
+            }
+
+            // An armed trigger skips the roll; it is handed back if the
+            // fault is not applied
+            let selection = if phase.applies(&exp.experiment.fault) && exp.take_trigger() {
+                Selection::Triggered
+            } else {
+                Selection::Sampled
+            };
+            let outcome = self
+                .run_experiment(state, exp, selection, path, headers, phase)
+                .await;
+            if selection == Selection::Triggered
+                && !matches!(outcome, ExperimentOutcome::Applied(_))
+            {
+                exp.armed.fetch_add(1, Ordering::AcqRel);
+            }
+            match outcome {
+                ExperimentOutcome::Skipped => continue,
+                ExperimentOutcome::Suppressed => return (None, false),
+                ExperimentOutcome::Failed => return (self.internal_error_decision(state), false),
+                ExperimentOutcome::Applied(result) => {
+                    if let Some((cooldown, key)) = cooldown {
+                        cooldown.record_at(key, now);
+                    }
+                    self.record_recent(state, exp, method, path, &result, selection);
+                    return (
+                        Some(Self::fault_decision(state, result, exp, selection)),
+                        true,
+                    );
+                }
+            }
+        }
+
+        // No experiment was applied
+        (None, false)
+    }
+
+    /// Turn an applied fault into the decision returned to the proxy.
+    fn fault_decision(
+        state: &AgentState,
+        result: FaultResult,
+        exp: &CompiledExperiment,
+        selection: Selection,
+    ) -> Decision {
+        // A dry run only computed the delay, so there is nothing to announce
+        let result = match result {
+            FaultResult::Allow { .. } if state.config.settings.dry_run => {
+                FaultResult::Allow { delay: None }
+            }
+            FaultResult::Allow { delay: Some(d) } => {
+                debug!(
+                    experiment = %exp.id,
+                    delay_ms = d.as_millis(),
+                    "Fault applied with delay, allowing request"
+                );
+                result
+            }
+            result => result,
+        };
+        let blocked = matches!(result, FaultResult::Block { .. });
+
+        // Delay faults have already slept; the request continues
+        let decision =
+            result.into_decision(&exp.experiment.fault, &exp.id, exp.targeting.percentage());
+        let marker = match selection {
+            _ if state.config.settings.dry_run => return decision,
+            Selection::Sampled => return decision,
+            Selection::Forced => FORCED_HEADER,
+            Selection::Triggered => TRIGGERED_HEADER,
+        };
+        if blocked {
+            decision.with_block_header(marker, "true")
+        } else {
+            decision.add_response_header(marker, "true")
+        }
+    }
+
+    /// Try one matching experiment: percentage, budgets and concurrency
+    /// limit, then apply its fault.
+    ///
+    /// Forced and triggered injections skip the percentage roll and are
+    /// counted separately, outside experiment statistics and budgets. Only
+    /// faults for the current phase are tried.
+    async fn run_experiment(
+        &self,
+        state: &AgentState,
+        exp: &CompiledExperiment,
+        selection: Selection,
+        path: &str,
+        headers: &HashMap<String, String>,
+        phase: Phase<'_>,
+    ) -> ExperimentOutcome {
+        if !phase.applies(&exp.experiment.fault) {
+            return ExperimentOutcome::Skipped;
+        }
+
+        if exp.precheck.as_ref().is_some_and(|p| !p.is_steady()) {
+            debug!(experiment = %exp.id, "Steady state not confirmed, skipping experiment");
+            exp.skips.record("precheck");
+            return ExperimentOutcome::Skipped;
+        }
+
+        let sampled = selection == Selection::Sampled;
+        if sampled {
+            if exp.exhausted.load(Ordering::Relaxed) {
+                debug!(experiment = %exp.id, "Experiment injection budget exhausted");
+                exp.skips.record("budget");
+                return ExperimentOutcome::Skipped;
+            }
+
+            let intensity = state.roll_intensity(exp, path, self.run_time_left_at(Instant::now()));
+            let ceiling = state.config.safety.max_affected_percent;
+            if !exp.targeting.should_apply_scaled(intensity, ceiling) {
+                debug!(
+                    experiment = %exp.id,
+                    "Experiment matched but not selected by percentage"
+                );
+                exp.skips.record("percentage");
+                return ExperimentOutcome::Skipped;
+            }
+
+            // Check the global budget across all experiments
+            if !self.within_budget(state) {
+                debug!(
+                    experiment = %exp.id,
+                    "Injection suppressed by global budget"
+                );
+                exp.skips.record("budget");
+                return ExperimentOutcome::Suppressed;
+            }
+        }
+
+        // Faults that hold the request open need a concurrency slot; the
+        // permit is released once the fault has been applied
+        let fault = &exp.experiment.fault;
+        let settings = &state.config.settings;
+        let _permit = match &state.fault_slots {
+            Some(slots) if fault.holds_request() && !settings.dry_run => {
+                match slots.try_acquire() {
+                    Ok(permit) => Some(permit),
+                    Err(_) => {
+                        self.registry.faults_shed.fetch_add(1, Ordering::Relaxed);
+                        debug!(
+                            experiment = %exp.id,
+                            "Concurrent fault limit reached, shedding fault"
+                        );
+                        exp.skips.record("rate_limited");
+                        return ExperimentOutcome::Suppressed;
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        // In once mode, only the request that flips the flag injects. The
+        // flag is flipped before the budget is claimed, so a suppressed
+        // request never holds a claim, and handed back unless a fault comes
+        // of it
+        if settings.once && state.once_spent.swap(true, Ordering::Relaxed) {
+            exp.skips.record("budget");
+            return ExperimentOutcome::Suppressed;
+        }
+        let release_once = || {
+            if settings.once {
+                state.once_spent.store(false, Ordering::Relaxed);
+            }
+        };
+
+        // Claim a slot in the experiment's injection budget, kept apart for
+        // a dry run
+        let claim = if settings.dry_run {
+            Self::claim_dry_run_injection
+        } else {
+            Self::claim_injection
+        };
+        if sampled && !claim(self, exp) {
+            release_once();
+            exp.skips.record("budget");
+            return ExperimentOutcome::Skipped;
+        }
+
+        // Escalating latency is applied as a fixed latency at its current step
+        let escalated = exp.escalation.as_ref().map(|escalation| Fault::Latency {
+            fixed_ms: escalation.next_delay_ms_at(self.now_secs()),
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::Before,
+        });
+        let applied_fault = escalated.as_ref().unwrap_or(fault);
+
+        // A bug in a fault must never take the agent down with it
+        let applied = match phase {
+            Phase::RequestBody(body) => panic::catch_unwind(AssertUnwindSafe(|| {
+                apply_body_fault(
+                    fault,
+                    body,
+                    headers,
+                    &exp.id,
+                    settings.dry_run,
+                    settings.log_injections,
+                )
+            })),
+            Phase::Response(response) => {
+                AssertUnwindSafe(apply_response_fault(
+                    fault,
+                    response.status,
+                    response.body,
+                    &exp.id,
+                    settings.dry_run,
+                    settings.log_injections,
+                    state.config.safety.max_latency_ms,
+                ))
+                .catch_unwind()
+                .await
+            }
+            Phase::Request => {
+                AssertUnwindSafe(apply_fault(
+                    applied_fault,
+                    path,
+                    headers,
+                    &exp.id,
+                    settings.dry_run,
+                    settings.log_injections,
+                    state.config.safety.max_latency_ms,
+                ))
+                .catch_unwind()
+                .await
+            }
+        };
+        let result = match applied {
+            Ok(result) => result,
+            Err(_) => {
+                release_once();
+                self.registry.fault_panics.fetch_add(1, Ordering::Relaxed);
+                error!(
+                    experiment = %exp.id,
+                    fault = fault.type_name(),
+                    fail_mode = ?settings.fail_mode,
+                    "Fault panicked"
+                );
+                return ExperimentOutcome::Failed;
+            }
+        };
+
+        // A live fault that changed nothing, such as a corrupt fault that
+        // missed its roll, does not spend the single fault; in a dry run it
+        // stands for the fault that would have been applied
+        if matches!(result, FaultResult::Allow { delay: None }) && !settings.dry_run {
+            release_once();
+        }
+
+        let max_latency_ms = state.config.safety.max_latency_ms;
+        if let (Some(delays), Some(delay)) = (
+            &exp.delays,
+            result.injected_delay(applied_fault, max_latency_ms, settings.dry_run),
+        ) {
+            delays.record(delay.as_millis() as u64);
+        }
+        // A dry run is only counted as one, however the experiment was
+        // selected, and leaves the experiment's last injection alone
+        if !settings.dry_run {
+            exp.last_injection_ms
+                .store(unix_millis(), Ordering::Relaxed);
+        }
+        match selection {
+            Selection::Sampled => self.record_injection(state, exp),
+            _ if settings.dry_run => {
+                self.registry
+                    .dry_run_would_inject
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            Selection::Forced => {
+                self.registry
+                    .forced_injections
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            Selection::Triggered => {
+                self.registry
+                    .triggered_injections
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        ExperimentOutcome::Applied(result)
+    }
+
+    /// Count an internal error and decide the request per
+    /// `settings.fail_mode`: pass it through when failing open, fail it
+    /// with a 500 when failing closed.
+    fn internal_error_decision(&self, state: &AgentState) -> Option<Decision> {
+        self.registry
+            .internal_errors
+            .fetch_add(1, Ordering::Relaxed);
+        match state.config.settings.fail_mode {
+            FailMode::Open => None,
+            FailMode::Closed => Some(
+                Decision::block(500)
+                    .with_block_header("content-type", "text/plain; charset=utf-8")
+                    .with_block_header("x-chaos-internal-error", "true")
+                    .with_body("Chaos agent internal error")
+                    .with_tag("chaos-internal-error"),
+            ),
+        }
+    }
+
+    /// Record an injection for an experiment in the aggregate counters.
+    ///
+    /// A dry run is paced by the global budget like a live run, but counted
+    /// apart so dashboards never mistake it for real faults.
+    fn record_injection(&self, state: &AgentState, exp: &CompiledExperiment) {
+        let now = self.now_secs();
+        state.budget.record_injection(now);
+        if state.config.settings.dry_run {
+            self.registry
+                .dry_run_would_inject
+                .fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        if let Some(counter) = self
+            .registry
+            .fault_type_counts
+            .get(exp.experiment.fault.type_name())
+        {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        self.registry
+            .faults_injected
+            .fetch_add(1, Ordering::Relaxed);
+        self.registry.fault_rate.record(now, 1);
+    }
+
+    /// Get the number of requests excluded for a reason (`path`, `method`
+    /// or `header`).
+    pub fn get_exclusion_count(&self, reason: &str) -> u64 {
+        self.registry.exclusion_count(reason)
+    }
+
+    /// Get the number of requests passed through untouched for a reason in
+    /// [`REQUEST_SKIP_REASONS`], e.g. `paused` or `excluded_path`.
+    pub fn get_skip_count(&self, reason: &str) -> u64 {
+        self.registry.skips.get(reason)
+    }
+
+    /// Get injection count for a fault type.
+    pub fn get_fault_type_count(&self, fault_type: &str) -> u64 {
+        self.registry
+            .fault_type_counts
+            .get(fault_type)
+            .map(|c| c.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    /// Get injection count for an experiment.
+    pub fn get_injection_count(&self, experiment_id: &str) -> u64 {
+        self.state
+            .load()
+            .compiled_experiments
+            .iter()
+            .find(|exp| exp.id == experiment_id)
+            .map(|exp| exp.injections.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+}
+
+#[async_trait]
+impl Agent for ChaosAgent {
+    fn name(&self) -> &str {
+        "chaos"
+    }
+
+    async fn on_request(&self, request: &Request) -> Decision {
+        self.evaluate(request.method(), request.path(), request.headers())
+            .await
+            .unwrap_or_else(Decision::allow)
+    }
+
+    async fn on_request_body(&self, request: &Request) -> Decision {
+        let Some(body) = request.body() else {
+            return Decision::allow();
+        };
+        let correlation_id = Some(request.correlation_id());
+        self.evaluate_body(
+            request.method(),
+            request.path(),
+            request.headers(),
+            correlation_id,
+            body,
+        )
+        .await
+        .unwrap_or_else(Decision::allow)
+    }
+
+    async fn on_response(&self, request: &Request, response: &Response) -> Decision {
+        self.observe_response(response.status_code(), response.headers());
+
+        // Response-phase faults need the originating request, which only
+        // the v1 response event carries
+        let upstream = UpstreamResponse {
+            status: response.status_code(),
+            body: response.body(),
+        };
+        let decision = self
+            .evaluate_response(
+                request.method(),
+                request.path(),
+                request.headers(),
+                Some(request.correlation_id()),
+                upstream,
+            )
+            .await;
+        match decision {
+            Some(decision) => match response.header("content-type") {
+                Some(content_type) => decision.with_block_header("content-type", content_type),
+                None => decision,
+            },
+            None => Decision::allow(),
+        }
+    }
+
+    async fn on_configure(&self, config: serde_json::Value) -> Result<(), String> {
+        if config.is_null() {
+            return Ok(());
+        }
+        self.apply_pushed_config(config, None).map_err(|e| {
+            error!(error = %e, "Rejected pushed configuration");
+            e.to_string()
+        })
+    }
+}
+
+/// v2 Protocol implementation for ChaosAgent.
+#[async_trait]
+impl AgentHandlerV2 for ChaosAgent {
+    fn capabilities(&self) -> AgentCapabilities {
+        let mut capabilities = AgentCapabilities::new(
+            "zentinel-agent-chaos",
+            "Chaos Engineering Agent",
+            env!("CARGO_PKG_VERSION"),
+        )
+        .with_event(EventType::RequestHeaders);
+        if self.state.load().auto_abort.is_some() {
+            capabilities = capabilities.with_event(EventType::ResponseHeaders);
+        }
+        capabilities.with_features(AgentFeatures {
+            streaming_body: false,
+            websocket: false,
+            guardrails: false,
+            config_push: true,
+            metrics_export: true,
+            concurrent_requests: 100,
+            cancellation: true,
+            flow_control: false,
+            health_reporting: true,
+        })
+    }
+
+    async fn on_configure(&self, config: serde_json::Value, version: Option<String>) -> bool {
+        if config.is_null() {
+            return true;
+        }
+        match self.apply_pushed_config(config, version.clone()) {
+            Ok(()) => true,
+            Err(e) => {
+                error!(
+                    version = version.as_deref().unwrap_or("-"),
+                    error = %e,
+                    "Rejected pushed configuration"
+                );
+                false
+            }
+        }
+    }
+
+    async fn on_request_headers(&self, event: RequestHeadersEvent) -> AgentResponse {
+        self.evaluate_with_version(
+            &event.method,
+            &event.uri,
+            &event.headers,
+            Some(&event.metadata.protocol),
+            Some(&event.metadata.correlation_id),
+        )
+        .await
+        // Convert SDK Decision to AgentResponse using build()
+        .map(Decision::build)
+        .unwrap_or_else(AgentResponse::default_allow)
+    }
+
+    async fn on_response_headers(&self, event: ResponseHeadersEvent) -> AgentResponse {
+        self.observe_response(event.status, &event.headers);
+        AgentResponse::default_allow()
+    }
+
+    fn health_status(&self) -> HealthStatus {
+        let mut status = if self.is_degraded() {
+            HealthStatus::degraded(
+                "zentinel-agent-chaos",
+                vec!["fault-injection".to_string()],
+                1.0,
+            )
+        } else {
+            HealthStatus::healthy("zentinel-agent-chaos")
+        };
+        let mut details = Vec::new();
+        if self.is_suspended() {
+            match self.pause.remaining() {
+                Some(remaining) => {
+                    details.push(format!("paused, {}s left", remaining.as_secs_f64().ceil()))
+                }
+                None => details.push("paused".to_string()),
+            }
+        }
+        if let Some(version) = self.config_version() {
+            details.push(format!("config version {}", version));
+        }
+        if let Some(remaining) = self.remaining_run_time() {
+            details.push(format!("{}s of run time left", remaining.as_secs()));
+        }
+        if !details.is_empty() {
+            status.message = Some(details.join(", "));
+        }
+        status
+    }
+
+    fn metrics_report(&self) -> Option<MetricsReport> {
+        if self.state.load().config.settings.metrics_mode == MetricsMode::Cumulative {
+            return Some(self.control().collect_metrics());
+        }
+        // Hold the previous values while reading, so concurrent reports
+        // never count the same increments twice
+        let mut reported = self
+            .reported_counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let mut report = self.control().collect_metrics();
+        counter_deltas(&mut report.counters, &mut reported);
+        Some(report)
+    }
+
+    async fn on_shutdown(&self, reason: ShutdownReason, grace_period_ms: u64) {
+        info!(
+            reason = ?reason,
+            grace_period_ms = grace_period_ms,
+            "Chaos agent shutdown requested"
+        );
+        self.draining.drain_for_good();
+    }
+
+    async fn on_drain(&self, duration_ms: u64, reason: DrainReason) {
+        warn!(
+            reason = ?reason,
+            duration_ms = duration_ms,
+            "Chaos agent drain requested - stopping fault injection"
+        );
+        // With auto-resume the drain lifts once its duration and the
+        // configured delay have passed
+        let resume_after = self
+            .state
+            .load()
+            .config
+            .settings
+            .drain_auto_resume_after_ms
+            .map(|delay| Duration::from_millis(duration_ms.saturating_add(delay)));
+        if let Some(after) = resume_after {
+            info!(
+                resume_after_ms = after.as_millis() as u64,
+                "Drain will lift on its own"
+            );
+        }
+        self.draining.drain(resume_after);
+    }
+}
+
+// Safety: ChaosAgent is Send + Sync because all its fields are Send + Sync
+unsafe impl Send for ChaosAgent {}
+unsafe impl Sync for ChaosAgent {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        AutoAbortConfig, BypassConfig, BypassMode, ClientCooldownConfig, CorruptMode, DateSpec,
+        Fault, PathMatcher, PrecheckConfig, SafetyConfig, Settings, Targeting,
+    };
+    use zentinel_agent_protocol::{Decision as ProtocolDecision, HeaderOp, RequestMetadata};
+
+    fn create_test_config(experiments: Vec<Experiment>) -> Config {
+        Config {
+            settings: Settings {
+                enabled: true,
+                dry_run: false,
+                log_injections: false,
+                allow_force_header: false,
+                path_normalization: Default::default(),
+                recent_injections: 100,
+                intensity: 1.0,
+                profile_matching: false,
+                sni_header: "x-sni".to_string(),
+                alpn_header: "x-alpn".to_string(),
+                once: false,
+                fail_mode: FailMode::Open,
+                seed: None,
+                persist_runtime: false,
+                drain_auto_resume_after_ms: None,
+                metrics_mode: Default::default(),
+            },
+            safety: SafetyConfig {
+                max_affected_percent: 100,
+                schedule: vec![],
+                excluded_paths: vec!["/health".to_string()],
+                ..SafetyConfig::default()
+            },
+            admin: Default::default(),
+            experiments,
+            includes: Vec::new(),
+            experiments_dir: None,
+            files: Vec::new(),
+            targeting_templates: HashMap::new(),
+            templates: HashMap::new(),
+        }
+    }
+
+    fn create_latency_experiment(id: &str, path_prefix: &str, delay_ms: u64) -> Experiment {
+        Experiment {
+            id: id.to_string(),
+            enabled: true,
+            description: "Test latency".to_string(),
+            targeting: Targeting {
+                paths: vec![PathMatcher::Prefix {
+                    prefix: path_prefix.to_string(),
+                }],
+                methods: vec![],
+                headers: HashMap::new(),
+                headers_absent: Vec::new(),
+                percentage: 100,
+                every_n: None,
+                client_cooldown: None,
+                sni: None,
+                alpn: None,
+                http_versions: Vec::new(),
+                min_content_length: None,
+                max_content_length: None,
+                days: Vec::new(),
+            },
+            targeting_ref: None,
+            extends: None,
+            fault: Fault::Latency {
+                fixed_ms: delay_ms,
+                min_ms: 0,
+                max_ms: 0,
+                when: LatencyTiming::Before,
+            },
+            max_injections: None,
+            schedule: vec![],
+            precheck: None,
+            ramp_down_seconds: None,
+            source: None,
+        }
+    }
+
+    fn create_error_experiment(id: &str, path_prefix: &str, status: u16) -> Experiment {
+        Experiment {
+            id: id.to_string(),
+            enabled: true,
+            description: "Test error".to_string(),
+            targeting: Targeting {
+                paths: vec![PathMatcher::Prefix {
+                    prefix: path_prefix.to_string(),
+                }],
+                methods: vec![],
+                headers: HashMap::new(),
+                headers_absent: Vec::new(),
+                percentage: 100,
+                every_n: None,
+                client_cooldown: None,
+                sni: None,
+                alpn: None,
+                http_versions: Vec::new(),
+                min_content_length: None,
+                max_content_length: None,
+                days: Vec::new(),
+            },
+            targeting_ref: None,
+            extends: None,
+            fault: Fault::Error {
+                status,
+                message: Some("Test error".to_string()),
+                headers: HashMap::new(),
+                status_pool: Vec::new(),
+            },
+            max_injections: None,
+            schedule: vec![],
+            precheck: None,
+            ramp_down_seconds: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_agent_initialization() {
+        let config = create_test_config(vec![
+            create_latency_experiment("exp1", "/api/", 100),
+            create_error_experiment("exp2", "/test/", 500),
+        ]);
+
+        let agent = ChaosAgent::new(config);
+        assert_eq!(agent.state.load().compiled_experiments.len(), 2);
+    }
+
+    #[test]
+    fn test_flatten_headers() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "Content-Type".to_string(),
+            vec!["application/json".to_string()],
+        );
+        headers.insert(
+            "X-Test".to_string(),
+            vec!["value1".to_string(), "value2".to_string()],
+        );
+
+        let flat = ChaosAgent::flatten_headers(&headers);
+        assert_eq!(
+            flat.get("content-type"),
+            Some(&"application/json".to_string())
+        );
+        assert_eq!(flat.get("x-test"), Some(&"value1".to_string()));
+    }
+
+    #[test]
+    fn test_find_matching_experiments() {
+        let config = create_test_config(vec![
+            create_latency_experiment("api-latency", "/api/", 100),
+            create_error_experiment("test-error", "/test/", 500),
+        ]);
+
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+        let headers = HashMap::new();
+
+        // Should match api-latency
+        let matches = state.find_matching_experiments("GET", "/api/users", &headers);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, "api-latency");
+
+        // Should match test-error
+        let matches = state.find_matching_experiments("POST", "/test/data", &headers);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, "test-error");
+
+        // Should not match anything
+        let matches = state.find_matching_experiments("GET", "/other/path", &headers);
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_excluded_path() {
+        let config = create_test_config(vec![create_latency_experiment("all", "/", 100)]);
+
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+
+        // Health path should be excluded
+        assert!(is_excluded_path(
+            "/health",
+            &state.config.safety.excluded_paths
+        ));
+
+        // Other paths should not be excluded
+        assert!(!is_excluded_path(
+            "/api/test",
+            &state.config.safety.excluded_paths
+        ));
+    }
+
+    #[test]
+    fn test_draining_flag() {
+        let config = create_test_config(vec![]);
+        let agent = ChaosAgent::new(config);
+
+        assert!(!agent.is_draining());
+        agent.draining.drain(None);
+        assert!(agent.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_drain_auto_resumes() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.settings.drain_auto_resume_after_ms = Some(20);
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        agent.on_drain(30, DrainReason::ConfigReload).await;
+        assert!(agent.is_draining());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!agent.is_draining());
+        assert!(!agent.control().is_draining());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+
+        // Without the setting a drain lasts until lifted
+        let config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        let agent = ChaosAgent::new(config);
+        let control = agent.control();
+        agent.on_drain(1, DrainReason::Maintenance).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(agent.is_draining());
+        assert!(control.undrain().unwrap());
+        assert!(!control.undrain().unwrap());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+
+        // A shutdown cannot be undone
+        agent.on_shutdown(ShutdownReason::Graceful, 1000).await;
+        assert!(control.undrain().is_err());
+        assert!(agent.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_max_duration_drains() {
+        let config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        let agent = ChaosAgent::new(config).with_max_duration(Duration::from_secs(3600));
+        let started = agent.started;
+        assert!(!agent.run_time_elapsed_at(started + Duration::from_secs(3599)));
+        assert!(agent.run_time_elapsed_at(started + Duration::from_secs(3600)));
+        assert!(!agent.is_draining());
+
+        let remaining = agent.remaining_run_time().unwrap();
+        assert!(remaining > Duration::from_secs(3590), "{:?}", remaining);
+        let report = agent.metrics_report().unwrap();
+        assert!(report
+            .gauges
+            .iter()
+            .any(|g| g.name == "chaos_run_remaining_seconds" && g.value > 3590.0));
+        assert!(agent
+            .health_status()
+            .message
+            .unwrap()
+            .ends_with("s of run time left"));
+
+        // A short run drains on its own and stops injecting
+        let config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        let agent = ChaosAgent::new(config).with_max_duration(Duration::from_millis(20));
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(agent.is_draining());
+        assert!(agent.draining.is_draining());
+        assert_eq!(agent.remaining_run_time(), Some(Duration::ZERO));
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_none());
+        assert!(agent.control().undrain().is_err());
+
+        assert_eq!(
+            ChaosAgent::new(create_test_config(vec![])).remaining_run_time(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_seeded_agents_make_identical_decisions() {
+        async fn run(seed: u64) -> Vec<Option<u16>> {
+            let mut experiment = create_error_experiment("errors", "/api/", 500);
+            experiment.targeting.percentage = 50;
+            experiment.fault = Fault::Error {
+                status: 500,
+                message: None,
+                headers: HashMap::new(),
+                status_pool: vec![(500, 1), (502, 1), (503, 1)],
+            };
+            let mut config = create_test_config(vec![experiment]);
+            config.settings.seed = Some(seed);
+            let agent = ChaosAgent::new(config);
+
+            let mut statuses = Vec::new();
+            for i in 0..200 {
+                let decision = agent
+                    .evaluate("GET", &format!("/api/{}", i), &HashMap::new())
+                    .await;
+                statuses.push(decision.map(|d| match d.build().decision {
+                    ProtocolDecision::Block { status, .. } => status,
+                    other => panic!("unexpected decision {:?}", other),
+                }));
+            }
+            statuses
+        }
+
+        let first = run(42).await;
+        assert_eq!(first, run(42).await);
+        assert_ne!(first, run(43).await);
+
+        let faulted = first.iter().flatten().count();
+        assert!((60..=140).contains(&faulted), "{}", faulted);
+        assert!(first.contains(&Some(502)) && first.contains(&Some(503)));
+    }
+
+    #[test]
+    fn test_ramp_down_before_run_ends() {
+        let mut experiment = create_error_experiment("errors", "/api/", 503);
+        experiment.targeting.percentage = 40;
+        experiment.ramp_down_seconds = Some(600);
+        let agent = ChaosAgent::new(create_test_config(vec![experiment.clone()]))
+            .with_max_duration(Duration::from_secs(3600));
+        let at = |secs: u64| agent.started + Duration::from_secs(secs);
+
+        let rates: Vec<f64> = [0, 3000, 3150, 3300, 3450, 3600, 4000]
+            .iter()
+            .map(|secs| {
+                let scale = ramp_down_scale(&experiment, agent.run_time_left_at(at(*secs)));
+                agent.state.load().compiled_experiments[0]
+                    .targeting
+                    .scaled_rate(scale, 100)
+            })
+            .collect();
+        assert_eq!(rates, vec![0.4, 0.4, 0.3, 0.2, 0.1, 0.0, 0.0]);
+
+        // Without a run limit, or without a ramp, nothing changes
+        let unlimited = ChaosAgent::new(create_test_config(vec![]));
+        assert_eq!(
+            ramp_down_scale(&experiment, unlimited.run_time_left_at(at(3599))),
+            1.0
+        );
+        experiment.ramp_down_seconds = None;
+        assert_eq!(
+            ramp_down_scale(&experiment, agent.run_time_left_at(at(3599))),
+            1.0
+        );
+    }
+
+    fn overnight_schedule() -> Schedule {
+        Schedule {
+            days: vec![chrono::Weekday::Fri],
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+            timezone: chrono_tz::America::New_York,
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_schedule_status() {
+        let mut config = create_test_config(vec![]);
+        let agent = ChaosAgent::new(config.clone());
+        let status = agent
+            .state
+            .load()
+            .schedule_status_at(utc("2025-06-14T05:30:00Z"));
+        assert_eq!(
+            status,
+            ScheduleStatus {
+                active: true,
+                seconds_remaining: -1,
+                seconds_until_next: -1,
+            }
+        );
+
+        config.safety.schedule = vec![overnight_schedule()];
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+
+        // Sat 01:30 in New York, half an hour before Friday's window closes
+        let status = state.schedule_status_at(utc("2025-06-14T05:30:00Z"));
+        assert!(status.active);
+        assert_eq!(status.seconds_remaining, 1800);
+        assert_eq!(status.seconds_until_next, 6 * 86_400 + 20 * 3600 + 1800);
+
+        // Fri 21:00 in New York, an hour before the window opens
+        let status = state.schedule_status_at(utc("2025-06-14T01:00:00Z"));
+        assert!(!status.active);
+        assert_eq!(status.seconds_remaining, 0);
+        assert_eq!(status.seconds_until_next, 3600);
+    }
+
+    #[test]
+    fn test_overnight_schedule_before_midnight() {
+        let schedule = overnight_schedule();
+
+        // Fri 23:00 in New York is already Saturday in UTC
+        assert!(AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-14T03:00:00Z")
+        ));
+        // Fri 21:59 in New York, before the window opens
+        assert!(!AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-14T01:59:00Z")
+        ));
+        // Thu 23:00 in New York is not a scheduled start day
+        assert!(!AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-13T03:00:00Z")
+        ));
+    }
+
+    #[test]
+    fn test_overnight_schedule_after_midnight() {
+        let schedule = overnight_schedule();
+
+        // Sat 00:00 in New York, day handoff from Friday's window
+        assert!(AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-14T04:00:00Z")
+        ));
+        // Sat 01:30 in New York
+        assert!(AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-14T05:30:00Z")
+        ));
+        // Sat 02:30 in New York, after the window closes
+        assert!(!AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-14T06:30:00Z")
+        ));
+        // Fri 01:00 in New York belongs to Thursday's (unscheduled) window
+        assert!(!AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-13T05:00:00Z")
+        ));
+    }
+
+    #[test]
+    fn test_same_day_schedule() {
+        let schedule = Schedule {
+            days: vec![chrono::Weekday::Mon],
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            timezone: chrono_tz::UTC,
+        };
+
+        assert!(AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-16T12:00:00Z")
+        ));
+        assert!(!AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-16T18:00:00Z")
+        ));
+        assert!(!AgentState::check_schedule_at(
+            &schedule,
+            utc("2025-06-17T12:00:00Z")
+        ));
+    }
+
+    #[test]
+    fn test_blackout_date_uses_local_date() {
+        let mut config = create_test_config(vec![]);
+        config.safety.timezone = chrono_tz::America::Los_Angeles;
+        config.safety.blackout_dates = vec![DateSpec::Single(
+            chrono::NaiveDate::from_ymd_opt(2025, 11, 28).unwrap(),
+        )];
+        let agent = ChaosAgent::new(config);
+
+        // Nov 28 21:00 in Los Angeles, already Nov 29 in UTC
+        assert!(!agent
+            .state
+            .load()
+            .is_within_schedule_at(utc("2025-11-29T05:00:00Z")));
+        // Nov 27 21:00 in Los Angeles, already Nov 28 in UTC
+        assert!(agent
+            .state
+            .load()
+            .is_within_schedule_at(utc("2025-11-28T05:00:00Z")));
+    }
+
+    #[test]
+    fn test_blackout_date_uses_schedule_timezone() {
+        let mut config = create_test_config(vec![]);
+        config.safety.schedule = vec![Schedule {
+            days: vec![chrono::Weekday::Fri, chrono::Weekday::Sat],
+            start: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(23, 59, 0).unwrap(),
+            timezone: chrono_tz::Pacific::Auckland,
+        }];
+        config.safety.blackout_dates = vec![DateSpec::Range {
+            from: chrono::NaiveDate::from_ymd_opt(2025, 11, 29).unwrap(),
+            to: chrono::NaiveDate::from_ymd_opt(2025, 11, 30).unwrap(),
+        }];
+        let agent = ChaosAgent::new(config);
+
+        // Fri Nov 28 12:00 UTC is Sat Nov 29 01:00 in Auckland
+        assert!(!agent
+            .state
+            .load()
+            .is_within_schedule_at(utc("2025-11-28T12:00:00Z")));
+        // Fri Nov 28 09:00 UTC is Fri Nov 28 22:00 in Auckland
+        assert!(agent
+            .state
+            .load()
+            .is_within_schedule_at(utc("2025-11-28T09:00:00Z")));
+    }
+
+    #[test]
+    fn test_only_dates_restricts_activity() {
+        let mut config = create_test_config(vec![]);
+        config.safety.timezone = chrono_tz::Asia::Tokyo;
+        config.safety.only_dates = vec![DateSpec::Single(
+            chrono::NaiveDate::from_ymd_opt(2025, 10, 1).unwrap(),
+        )];
+        let agent = ChaosAgent::new(config);
+
+        // Sep 30 20:00 UTC is Oct 1 05:00 in Tokyo
+        assert!(agent
+            .state
+            .load()
+            .is_within_schedule_at(utc("2025-09-30T20:00:00Z")));
+        // Oct 1 20:00 UTC is Oct 2 05:00 in Tokyo
+        assert!(!agent
+            .state
+            .load()
+            .is_within_schedule_at(utc("2025-10-01T20:00:00Z")));
+    }
+
+    #[test]
+    fn test_fault_type_counts_sum_to_total() {
+        let config = create_test_config(vec![
+            create_latency_experiment("lat1", "/a/", 100),
+            create_latency_experiment("lat2", "/b/", 100),
+            create_error_experiment("err", "/c/", 500),
+        ]);
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+
+        for (i, exp) in state.compiled_experiments.iter().enumerate() {
+            for _ in 0..=i {
+                agent.record_injection(&state, exp);
+            }
+        }
+
+        assert_eq!(agent.get_fault_type_count("latency"), 3);
+        assert_eq!(agent.get_fault_type_count("error"), 3);
+        assert_eq!(agent.get_fault_type_count("timeout"), 0);
+        assert_eq!(
+            agent.get_fault_type_count("latency") + agent.get_fault_type_count("error"),
+            agent.total_faults_injected()
+        );
+    }
+
+    #[test]
+    fn test_max_affected_percent_clamps_experiments() {
+        let mut config = create_test_config(vec![
+            create_latency_experiment("full", "/api/", 100),
+            create_error_experiment("low", "/test/", 500),
+        ]);
+        config.safety.max_affected_percent = 10;
+        config.experiments[1].targeting.percentage = 5;
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+
+        assert_eq!(state.compiled_experiments[0].targeting.percentage(), 10);
+        assert_eq!(state.compiled_experiments[1].targeting.percentage(), 5);
+
+        let trials = 10_000;
+        let applied = (0..trials)
+            .filter(|_| state.compiled_experiments[0].targeting.should_apply())
+            .count();
+        let rate = applied as f64 / trials as f64;
+        assert!((0.08..=0.12).contains(&rate), "rate was {}", rate);
+    }
+
+    #[tokio::test]
+    async fn test_intensity_scales_percentages() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.settings.intensity = 0.5;
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        let trials = 2_000;
+        let mut applied = 0;
+        for _ in 0..trials {
+            if agent.evaluate("GET", "/api/x", &headers).await.is_some() {
+                applied += 1;
+            }
+        }
+        let rate = applied as f64 / trials as f64;
+        assert!((0.4..=0.6).contains(&rate), "rate was {}", rate);
+
+        // Scaling up never passes the safety ceiling
+        let state = agent.state.load();
+        let targeting = &state.compiled_experiments[0].targeting;
+        assert!(!(0..100).any(|_| targeting.should_apply_scaled(0.0, 100)));
+        assert!((0..100).all(|_| targeting.should_apply_scaled(3.0, 100)));
+        assert!(!(0..100).any(|_| targeting.should_apply_scaled(3.0, 0)));
+    }
+
+    #[test]
+    fn test_global_budget_suppresses_injections() {
+        let mut config = create_test_config(vec![create_latency_experiment("exp", "/", 100)]);
+        config.safety.max_affected_percent = 10;
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+
+        for _ in 0..10 {
+            agent.record_request(&state);
+        }
+        assert!(agent.within_budget(&state));
+        agent.record_injection(&state, &state.compiled_experiments[0]);
+
+        assert!(!agent.within_budget(&state));
+        assert_eq!(agent.total_budget_suppressed(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_max_injections_stops_after_limit() {
+        let mut config = create_test_config(vec![create_error_experiment("drill", "/api/", 503)]);
+        config.experiments[0].max_injections = Some(100);
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        for _ in 0..100 {
+            assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        }
+        // The 101st matching request passes through untouched
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        assert_eq!(agent.get_injection_count("drill"), 100);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_paths_scale_injection_rates() {
+        let mut config = create_test_config(vec![create_error_experiment("spread", "/", 503)]);
+        config.experiments[0].targeting.paths = [("/a", 50), ("/b", 30), ("/c", 20)]
+            .into_iter()
+            .map(|(prefix, weight)| PathMatcher::Weighted {
+                matcher: Box::new(PathMatcher::Prefix {
+                    prefix: prefix.to_string(),
+                }),
+                weight,
+            })
+            .collect();
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        let mut injected = Vec::new();
+        for path in ["/a/x", "/b/x", "/c/x"] {
+            let mut count = 0;
+            for _ in 0..4000 {
+                if agent.evaluate("GET", path, &headers).await.is_some() {
+                    count += 1;
+                }
+            }
+            injected.push(count);
+        }
+        // The heaviest path is always faulted; the others at 60% and 40%
+        assert_eq!(injected[0], 4000);
+        assert!((2100..=2700).contains(&injected[1]), "{:?}", injected);
+        assert!((1300..=1900).contains(&injected[2]), "{:?}", injected);
+    }
+
+    #[tokio::test]
+    async fn test_once_injects_a_single_fault() {
+        let mut config = create_test_config(vec![
+            create_error_experiment("first", "/api/", 503),
+            create_error_experiment("second", "/other/", 500),
+        ]);
+        config.settings.once = true;
+        let agent = ChaosAgent::new(config.clone());
+        let headers = HashMap::new();
+
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        // Later matching requests pass through, for any experiment
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        assert!(agent.evaluate("GET", "/other/x", &headers).await.is_none());
+        assert_eq!(agent.total_faults_injected(), 1);
+
+        // A reload re-arms the single fault
+        agent.control().reload(config).unwrap();
+        assert!(agent.evaluate("GET", "/other/x", &headers).await.is_some());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_once_is_spent_only_by_an_applied_fault() {
+        let mut dud = create_error_experiment("dud", "/api/", 503);
+        dud.fault = Fault::Corrupt {
+            probability: 0.0,
+            mode: CorruptMode::Garbage,
+            byte_flip_probability: 0.01,
+            size_bytes: None,
+        };
+        let mut config =
+            create_test_config(vec![dud, create_error_experiment("second", "/other/", 500)]);
+        config.settings.once = true;
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        // The corrupt fault never fires, so the single fault is still there
+        agent.evaluate("GET", "/api/x", &headers).await;
+        assert!(agent.evaluate("GET", "/other/x", &headers).await.is_some());
+        assert!(agent.evaluate("GET", "/other/x", &headers).await.is_none());
+
+        // A request suppressed by once holds no budget claim
+        assert_eq!(agent.get_injection_count("second"), 1);
+    }
+
+    #[test]
+    fn test_max_injections_never_exceeded_concurrently() {
+        let mut config = create_test_config(vec![create_latency_experiment("exp", "/", 100)]);
+        config.experiments[0].max_injections = Some(50);
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+        let exp = &state.compiled_experiments[0];
+
+        let claimed = std::sync::atomic::AtomicU64::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        if agent.claim_injection(exp) {
+                            claimed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(claimed.load(Ordering::Relaxed), 50);
+        assert_eq!(agent.get_injection_count("exp"), 50);
+        assert!(exp.exhausted.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_auto_abort_ignores_injected_responses() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.safety.auto_abort = Some(AutoAbortConfig {
+            error_rate_threshold: 0.5,
+            window_s: 60,
+            probe_url: None,
+            probe_interval_s: 10,
+            cooldown_s: 300,
+            min_requests: 10,
+        });
+        let agent = ChaosAgent::new(config);
+
+        let injected: HashMap<String, Vec<String>> =
+            [("x-chaos-injected".to_string(), vec!["true".to_string()])].into();
+        for _ in 0..20 {
+            agent.observe_response(503, &injected);
+        }
+        assert!(!agent.is_auto_aborted());
+
+        for _ in 0..20 {
+            agent.observe_response(503, &HashMap::new());
+        }
+        assert!(agent.is_auto_aborted());
+    }
+
+    #[test]
+    fn test_experiment_schedule_overrides_global() {
+        use chrono::Weekday::*;
+        let weekdays = vec![Mon, Tue, Wed, Thu, Fri];
+        let mut config = create_test_config(vec![
+            create_latency_experiment("business-latency", "/api/", 100),
+            create_error_experiment("nightly-errors", "/api/", 503),
+        ]);
+        config.safety.schedule = vec![Schedule {
+            days: weekdays.clone(),
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            timezone: chrono_tz::Tz::UTC,
+        }];
+        config.experiments[1].schedule = vec![Schedule {
+            days: weekdays,
+            start: NaiveTime::from_hms_opt(3, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+            timezone: chrono_tz::Tz::UTC,
+        }];
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+        let headers = HashMap::new();
+        let ids = |now: &str| -> Vec<String> {
+            state
+                .find_matching_experiments_at("GET", "/api/users", &headers, utc(now))
+                .iter()
+                .map(|exp| exp.id.clone())
+                .collect()
+        };
+
+        // Wednesday 03:30 is outside the global window but inside the
+        // experiment's own window
+        assert_eq!(ids("2025-01-15T03:30:00Z"), vec!["nightly-errors"]);
+        // Wednesday 12:00 only the global window applies
+        assert_eq!(ids("2025-01-15T12:00:00Z"), vec!["business-latency"]);
+        assert!(ids("2025-01-15T20:00:00Z").is_empty());
+    }
+
+    #[test]
+    fn test_targeting_days() {
+        let mut fridays = create_error_experiment("friday-errors", "/api/", 503);
+        fridays.targeting.days = vec![chrono::Weekday::Fri];
+        let mut nightly = create_latency_experiment("friday-nights", "/api/", 100);
+        nightly.targeting.days = vec![chrono::Weekday::Fri];
+        nightly.schedule = vec![serde_yaml::from_str(
+            "{ days: [mon, fri], start: '22:00', end: '23:00', timezone: America/New_York }",
+        )
+        .unwrap()];
+        let mut config = create_test_config(vec![fridays, nightly]);
+        config.safety.timezone = chrono_tz::Tz::America__New_York;
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+        let headers = HashMap::new();
+        let ids = |now: &str| -> Vec<String> {
+            state
+                .find_matching_experiments_at("GET", "/api/users", &headers, utc(now))
+                .iter()
+                .map(|exp| exp.id.clone())
+                .collect()
+        };
+
+        // Monday 2025-01-13 and Friday 2025-01-17, noon in New York
+        assert!(ids("2025-01-13T17:00:00Z").is_empty());
+        assert_eq!(ids("2025-01-17T17:00:00Z"), vec!["friday-errors"]);
+        // Saturday 02:00 UTC is still Friday in New York
+        assert_eq!(ids("2025-01-18T02:00:00Z"), vec!["friday-errors"]);
+        // Both the days and the schedule must allow a request
+        assert_eq!(
+            ids("2025-01-18T03:30:00Z"),
+            vec!["friday-errors", "friday-nights"]
+        );
+        assert!(ids("2025-01-14T03:30:00Z").is_empty());
+    }
+
+    #[test]
+    fn test_kill_switch_file_suppresses_injections() {
+        let dir = std::env::temp_dir().join(format!("chaos-agent-kill-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chaos-disable");
+        std::fs::write(&path, "").unwrap();
+
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.safety.kill_switch_file = Some(path.clone());
+        let agent = ChaosAgent::new(config);
+        assert!(agent.is_kill_switch_active());
+
+        let report = agent.metrics_report().unwrap();
+        let gauge = report
+            .gauges
+            .iter()
+            .find(|g| g.name == "chaos_kill_switch_active")
+            .unwrap();
+        assert_eq!(gauge.value, 1.0);
+
+        // Without a control file configured the switch never engages
+        let agent = ChaosAgent::new(create_test_config(vec![]));
+        assert!(!agent.is_kill_switch_active());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_fault_limit_sheds_excess() {
+        let mut config = create_test_config(vec![create_latency_experiment("slow", "/api/", 200)]);
+        config.safety.max_concurrent_faults = Some(1);
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load_full();
+        let exp = &state.compiled_experiments[0];
+        let headers = HashMap::new();
+
+        let (first, second) = tokio::join!(
+            agent.run_experiment(
+                &state,
+                exp,
+                Selection::Sampled,
+                "/api/x",
+                &headers,
+                Phase::Request
+            ),
+            agent.run_experiment(
+                &state,
+                exp,
+                Selection::Sampled,
+                "/api/x",
+                &headers,
+                Phase::Request
+            )
+        );
+        assert!(matches!(
+            first,
+            ExperimentOutcome::Applied(FaultResult::Allow { delay: Some(_) })
+        ));
+        assert!(matches!(second, ExperimentOutcome::Suppressed));
+        assert_eq!(agent.total_faults_shed(), 1);
+        let report = agent.metrics_report().unwrap();
+        let skips = report
+            .counters
+            .iter()
+            .find(|c| c.name == "chaos_delay_capacity_skips_total")
+            .unwrap();
+        assert_eq!(skips.value, 1);
+        assert_eq!(agent.total_faults_injected(), 1);
+        assert_eq!(state.fault_slot_usage(), Some((1, 0)));
+
+        let permit = state.fault_slots.as_ref().unwrap().try_acquire().unwrap();
+        assert_eq!(state.fault_slot_usage(), Some((1, 1)));
+        drop(permit);
+
+        // The permit is released once the first fault completes
+        assert!(matches!(
+            agent
+                .run_experiment(
+                    &state,
+                    exp,
+                    Selection::Sampled,
+                    "/api/x",
+                    &headers,
+                    Phase::Request
+                )
+                .await,
+            ExperimentOutcome::Applied(_)
+        ));
+    }
+
+    #[test]
+    fn test_control_toggle_suspended() {
+        let agent = ChaosAgent::new(create_test_config(vec![]));
+        let control = agent.control();
+
+        assert!(control.toggle_suspended());
+        assert!(agent.is_suspended());
+        assert!(!control.toggle_suspended());
+        assert!(!agent.is_suspended());
+    }
+
+    #[test]
+    fn test_bounded_pause_reported_in_health_and_metrics() {
+        let agent = ChaosAgent::new(create_test_config(vec![]));
+        let control = agent.control();
+        let paused_gauge = |agent: &ChaosAgent| {
+            let report = agent.metrics_report().unwrap();
+            let gauge = report.gauges.iter().find(|g| g.name == "chaos_paused");
+            gauge.unwrap().value
+        };
+        assert_eq!(paused_gauge(&agent), 0.0);
+
+        assert!(control.pause_for(Duration::from_secs(1800)));
+        assert!(agent.is_degraded());
+        let message = agent.health_status().message.unwrap();
+        assert!(
+            message == "paused, 1800s left" || message == "paused, 1799s left",
+            "{}",
+            message
+        );
+        assert!((1799.0..=1800.0).contains(&paused_gauge(&agent)));
+
+        control.pause();
+        assert_eq!(agent.health_status().message.as_deref(), Some("paused"));
+        assert_eq!(paused_gauge(&agent), f64::INFINITY);
+        control.resume();
+        assert!(agent.health_status().message.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recent_injections_capped_in_order() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.settings.recent_injections = 3;
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        for i in 0..5 {
+            let path = format!("/api/{}", i);
+            assert!(agent.evaluate("GET", &path, &headers).await.is_some());
+        }
+        // Requests that no experiment faults are not recorded
+        agent.evaluate("GET", "/other", &headers).await;
+
+        let recent = agent.recent_injections();
+        let paths: Vec<&str> = recent.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["/api/2", "/api/3", "/api/4"]);
+        assert_eq!(recent[0].experiment_id, "errors");
+        assert_eq!(recent[0].fault, "error");
+        assert_eq!(recent[0].status, Some(503));
+        assert!(!recent[0].forced);
+        assert!(recent[0].timestamp_ms <= recent[2].timestamp_ms);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_injections() {
+        let agent = ChaosAgent::new(create_test_config(vec![create_error_experiment(
+            "errors", "/api/", 503,
+        )]));
+        let control = agent.control();
+        let headers = HashMap::new();
+
+        assert!(control.pause());
+        assert!(!control.pause());
+        assert!(agent.is_degraded());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+
+        assert!(control.resume());
+        assert!(!control.resume());
+        assert!(!agent.is_degraded());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+
+        // A drain is not undone by resuming
+        agent.draining.drain(None);
+        control.resume();
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+    }
+
+    #[test]
+    fn test_control_reset_injection_counts() {
+        let mut config = create_test_config(vec![create_latency_experiment("exp", "/", 100)]);
+        config.experiments[0].max_injections = Some(1);
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+        let exp = &state.compiled_experiments[0];
+
+        assert!(agent.claim_injection(exp));
+        assert!(exp.exhausted.load(Ordering::Relaxed));
+
+        agent.control().reset_injection_counts();
+        assert_eq!(agent.get_injection_count("exp"), 0);
+        assert!(!exp.exhausted.load(Ordering::Relaxed));
+        assert!(agent.claim_injection(exp));
+    }
+
+    #[test]
+    fn test_control_reload_carries_unchanged_counters() {
+        let config = create_test_config(vec![
+            create_latency_experiment("kept", "/a/", 100),
+            create_latency_experiment("changed", "/b/", 100),
+        ]);
+        let agent = ChaosAgent::new(config.clone());
+        {
+            let state = agent.state.load();
+            for exp in &state.compiled_experiments {
+                agent.claim_injection(exp);
+            }
+        }
+
+        let mut reloaded = config;
+        reloaded.experiments[1].targeting.percentage = 50;
+        reloaded
+            .experiments
+            .push(create_error_experiment("added", "/c/", 503));
+        agent.control().reload(reloaded).unwrap();
+
+        assert_eq!(agent.state.load().compiled_experiments.len(), 3);
+        assert_eq!(agent.get_injection_count("kept"), 1);
+        assert_eq!(agent.get_injection_count("changed"), 0);
+        assert_eq!(agent.get_injection_count("added"), 0);
+    }
+
+    #[test]
+    fn test_control_reload_rejects_invalid_config() {
+        let agent = ChaosAgent::new(create_test_config(vec![create_latency_experiment(
+            "exp", "/", 100,
+        )]));
+
+        let mut invalid = create_test_config(vec![]);
+        invalid.safety.max_affected_percent = 150;
+        assert!(agent.control().reload(invalid.clone()).is_err());
+        assert_eq!(agent.state.load().compiled_experiments.len(), 1);
+
+        assert!(agent.control().reload_with(|| Ok(invalid)).is_err());
+        assert!(agent
+            .control()
+            .reload_with(|| Err(anyhow::anyhow!("unreadable")))
+            .is_err());
+        assert_eq!(agent.total_reload_errors(), 2);
+        assert_eq!(agent.state.load().compiled_experiments.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_on_configure_applies_pushed_config() {
+        let agent = ChaosAgent::new(create_test_config(vec![]));
+        assert_eq!(agent.experiments_enabled(), 0);
+
+        let pushed = serde_json::json!({
+            "safety": {"max_affected_percent": 100},
+            "experiments": [{
+                "id": "pushed",
+                "targeting": {"paths": [{"prefix": "/api/"}]},
+                "fault": {"type": "error", "status": 503}
+            }]
+        });
+        assert!(AgentHandlerV2::on_configure(&agent, pushed, Some("v7".to_string())).await);
+        assert_eq!(agent.experiments_enabled(), 1);
+        assert_eq!(agent.config_version().as_deref(), Some("v7"));
+        assert_eq!(
+            agent.health_status().message.as_deref(),
+            Some("config version v7")
+        );
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+
+        // Invalid pushes are rejected and the applied config kept
+        let invalid = serde_json::json!({
+            "experiments": [{
+                "id": "bad",
+                "targeting": {},
+                "fault": {"type": "error", "status": 9999}
+            }]
+        });
+        assert!(!AgentHandlerV2::on_configure(&agent, invalid, Some("v8".to_string())).await);
+        let err = Agent::on_configure(&agent, serde_json::json!({"experiments": 1}))
+            .await
+            .unwrap_err();
+        assert!(err.contains("Invalid configuration"), "{}", err);
+        assert_eq!(agent.config_version().as_deref(), Some("v7"));
+        assert_eq!(agent.total_reload_errors(), 2);
+
+        // A file reload replaces the pushed configuration
+        agent.control().reload(create_test_config(vec![])).unwrap();
+        assert_eq!(agent.config_version(), None);
+    }
+
+    #[tokio::test]
+    async fn test_bypass_counts_valid_tokens_only() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.safety.bypass = Some(BypassConfig {
+            header: "x-chaos-bypass".to_string(),
+            secret: "s3cret".to_string(),
+            mode: BypassMode::Hmac,
+        });
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load();
+
+        let token = crate::bypass::sign(b"s3cret", "/api/users");
+        let valid = HashMap::from([("x-chaos-bypass".to_string(), token)]);
+        let invalid = HashMap::from([("x-chaos-bypass".to_string(), "nope".to_string())]);
+        let none = HashMap::new();
+
+        let decide =
+            |headers, phase| agent.decide(&state, "GET", "/api/users", headers, None, phase);
+        assert!(decide(&valid, Phase::Request).await.is_none());
+        assert!(decide(&invalid, Phase::Request).await.is_some());
+        assert!(decide(&none, Phase::Request).await.is_some());
+        assert_eq!(agent.total_bypassed(), 1);
+
+        // Later phases of the same request are not counted again
+        assert!(decide(&valid, Phase::RequestBody(b"{}")).await.is_none());
+        assert_eq!(agent.total_bypassed(), 1);
+    }
+
+    fn force_headers(id: &str) -> HashMap<String, Vec<String>> {
+        HashMap::from([("X-Chaos-Force".to_string(), vec![id.to_string()])])
+    }
+
+    #[test]
+    fn test_precheck_pauses_and_resumes_injection() {
+        // Built outside a runtime so no background probe races the test
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let status = Arc::new(std::sync::atomic::AtomicU16::new(200));
+        let url = runtime.block_on(crate::probe::tests::mock_server(Arc::clone(&status)));
+
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.experiments[0].precheck = Some(PrecheckConfig {
+            url,
+            expect_status: 200,
+            interval_seconds: 10,
+        });
+        let agent = ChaosAgent::new(config);
+        let state = agent.state.load_full();
+        let precheck = state.compiled_experiments[0].precheck.clone().unwrap();
+
+        runtime.block_on(async {
+            // Nothing is injected before steady state is confirmed
+            let decision = agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+            assert!(decision.is_none());
+
+            precheck.probe_once().await;
+            let decision = agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+            assert!(decision.is_some());
+
+            status.store(500, Ordering::SeqCst);
+            precheck.probe_once().await;
+            let decision = agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+            assert!(decision.is_none());
+
+            status.store(200, Ordering::SeqCst);
+            precheck.probe_once().await;
+            let decision = agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+            assert!(decision.is_some());
+        });
+    }
+
+    #[tokio::test]
+    async fn test_return_fixture_experiments() {
+        let dir = std::env::temp_dir().join(format!("chaos-agent-fixture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("maintenance.html");
+        std::fs::write(&file, "<h1>Down for maintenance</h1>").unwrap();
+        let fixture = |file: std::path::PathBuf| Fault::ReturnFixture {
+            status: 503,
+            file,
+            content_type: None,
+            body: None,
+        };
+
+        let mut served = create_error_experiment("served", "/api/", 503);
+        served.fault = fixture(file);
+        let mut missing = create_error_experiment("missing", "/other/", 503);
+        missing.fault = fixture(dir.join("gone.html"));
+        let mut disabled = create_error_experiment("disabled", "/off/", 503);
+        disabled.enabled = false;
+        disabled.fault = fixture(dir.join("gone.html"));
+        let agent = ChaosAgent::new(create_test_config(vec![served, missing, disabled]));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Served from memory, after the file is gone
+        let decision = agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+        match decision.unwrap().build().decision {
+            ProtocolDecision::Block {
+                status,
+                body,
+                headers,
+            } => {
+                assert_eq!(status, 503);
+                assert_eq!(body.as_deref(), Some("<h1>Down for maintenance</h1>"));
+                assert_eq!(headers.unwrap_or_default()["content-type"], "text/html");
+            }
+            other => panic!("unexpected decision {:?}", other),
+        }
+
+        // An unreadable fixture leaves the experiment out
+        let control = agent.control();
+        assert!(control.set_experiment_enabled("missing", true).is_err());
+        // A disabled one can only be enabled in the configuration
+        let err = control
+            .set_experiment_enabled("disabled", true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("has a fixture that was never read"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_methods_and_headers() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/", 503)]);
+        config.safety.excluded_methods = vec!["options".to_string(), "HEAD".to_string()];
+        config.safety.excluded_headers = HashMap::from([
+            ("X-Internal-Probe".to_string(), Some("true".to_string())),
+            ("x-synthetic".to_string(), None),
+        ]);
+        let agent = ChaosAgent::new(config);
+        let header =
+            |name: &str, value: &str| HashMap::from([(name.to_string(), vec![value.to_string()])]);
+
+        assert!(agent
+            .evaluate("OPTIONS", "/api", &HashMap::new())
+            .await
+            .is_none());
+        assert!(agent
+            .evaluate("head", "/api", &HashMap::new())
+            .await
+            .is_none());
+        assert_eq!(agent.get_exclusion_count("method"), 2);
+
+        let probe = header("x-internal-probe", "true");
+        assert!(agent.evaluate("GET", "/api", &probe).await.is_none());
+        let synthetic = header("X-Synthetic", "anything");
+        assert!(agent.evaluate("GET", "/api", &synthetic).await.is_none());
+        assert_eq!(agent.get_exclusion_count("header"), 2);
+
+        // A different value does not match
+        let other = header("x-internal-probe", "false");
+        assert!(agent.evaluate("GET", "/api", &other).await.is_some());
+
+        assert!(agent
+            .evaluate("GET", "/health", &HashMap::new())
+            .await
+            .is_none());
+        assert_eq!(agent.get_exclusion_count("path"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_gauges() {
+        let config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        let agent = ChaosAgent::new(config);
+        for _ in 0..50 {
+            agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+            agent.evaluate("GET", "/other", &HashMap::new()).await;
+        }
+
+        let report = agent.metrics_report().unwrap();
+        let gauge = |name: &str| {
+            report
+                .gauges
+                .iter()
+                .find(|g| g.name == name)
+                .map(|g| g.value)
+                .unwrap()
+        };
+        // 50 faults and 100 requests over a 10 second window
+        assert!((4.0..=5.0).contains(&gauge("chaos_faults_per_second")));
+        assert!((9.0..=10.0).contains(&gauge("chaos_requests_per_second")));
+    }
+
+    #[tokio::test]
+    async fn test_experiment_state_and_last_injection_gauges() {
+        let mut capped = create_error_experiment("capped", "/api/", 503);
+        capped.max_injections = Some(1);
+        let mut off = create_error_experiment("off", "/other/", 503);
+        off.enabled = false;
+        let agent = ChaosAgent::new(create_test_config(vec![capped, off]));
+        let gauge = |agent: &ChaosAgent, name: &str, experiment: &str, state: Option<&str>| {
+            agent
+                .metrics_report()
+                .unwrap()
+                .gauges
+                .into_iter()
+                .find(|g| {
+                    g.name == name
+                        && g.labels["experiment"] == experiment
+                        && g.labels.get("state").map(String::as_str) == state
+                })
+                .unwrap()
+                .value
+        };
+        let timestamp = "chaos_experiment_last_injection_timestamp_seconds";
+        let state = "chaos_experiment_state";
+
+        assert_eq!(gauge(&agent, timestamp, "capped", None), 0.0);
+        assert_eq!(gauge(&agent, state, "capped", Some("enabled")), 1.0);
+        assert_eq!(gauge(&agent, state, "capped", Some("exhausted")), 0.0);
+        assert_eq!(gauge(&agent, state, "off", Some("disabled")), 1.0);
+        assert_eq!(gauge(&agent, state, "off", Some("enabled")), 0.0);
+
+        let before = unix_millis() as f64 / 1000.0;
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+        let injected_at = gauge(&agent, timestamp, "capped", None);
+        assert!(
+            injected_at >= before.floor(),
+            "{} < {}",
+            injected_at,
+            before
+        );
+        assert!(injected_at <= unix_millis() as f64 / 1000.0);
+        assert_eq!(gauge(&agent, state, "capped", Some("exhausted")), 1.0);
+        assert_eq!(gauge(&agent, state, "capped", Some("enabled")), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_counts_would_be_injections_apart() {
+        let mut capped = create_error_experiment("capped", "/api/", 503);
+        capped.max_injections = Some(3);
+        let live = create_test_config(vec![capped]);
+        let mut dry = live.clone();
+        dry.settings.dry_run = true;
+        let counter = |agent: &ChaosAgent, name: &str, experiment: Option<&str>| {
+            agent
+                .metrics_report()
+                .unwrap()
+                .counters
+                .into_iter()
+                .find(|c| {
+                    c.name == name && c.labels.get("experiment").map(String::as_str) == experiment
+                })
+                .unwrap()
+                .value
+        };
+        let dry_run_active = |agent: &ChaosAgent| {
+            let report = agent.metrics_report().unwrap();
+            report
+                .gauges
+                .iter()
+                .find(|g| g.name == "chaos_dry_run_active")
+                .unwrap()
+                .value
+        };
+        let would_inject = "chaos_dry_run_would_inject_total";
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::{Fault, PathMatcher, SafetyConfig, Settings, Targeting};
+        // The same experiment, live and dry
+        let live_agent = ChaosAgent::new(live.clone());
+        let dry_agent = ChaosAgent::new(dry);
+        for agent in [&live_agent, &dry_agent] {
+            for _ in 0..5 {
+                agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+            }
+        }
 
-    fn create_test_config(experiments: Vec<Experiment>) -> Config {
-        Config {
-            settings: Settings {
-                enabled: true,
-                dry_run: false,
-                log_injections: false,
-            },
-            safety: SafetyConfig {
-                max_affected_percent: 100,
-                schedule: vec![],
-                excluded_paths: vec!["/health".to_string()],
-            },
-            experiments,
+        assert_eq!(live_agent.total_faults_injected(), 3);
+        assert_eq!(live_agent.get_fault_type_count("error"), 3);
+        assert_eq!(live_agent.get_injection_count("capped"), 3);
+        assert_eq!(counter(&live_agent, would_inject, None), 0);
+        assert_eq!(counter(&live_agent, would_inject, Some("capped")), 0);
+        assert_eq!(dry_run_active(&live_agent), 0.0);
+
+        // max_injections still caps a dry run, without touching live counts
+        assert_eq!(dry_agent.total_faults_injected(), 0);
+        assert_eq!(dry_agent.get_fault_type_count("error"), 0);
+        assert_eq!(dry_agent.get_injection_count("capped"), 0);
+        assert_eq!(dry_agent.total_dry_run_would_inject(), 3);
+        assert_eq!(counter(&dry_agent, would_inject, None), 3);
+        assert_eq!(counter(&dry_agent, would_inject, Some("capped")), 3);
+        assert_eq!(counter(&dry_agent, "chaos_faults_injected_total", None), 0);
+        assert_eq!(dry_run_active(&dry_agent), 1.0);
+
+        // Going live afterwards starts from a full budget
+        dry_agent.control().reload(live).unwrap();
+        for _ in 0..5 {
+            dry_agent.evaluate("GET", "/api/x", &HashMap::new()).await;
         }
+        assert_eq!(dry_agent.total_faults_injected(), 3);
+        assert_eq!(dry_agent.total_dry_run_would_inject(), 3);
+        assert_eq!(dry_run_active(&dry_agent), 0.0);
     }
 
-    fn create_latency_experiment(id: &str, path_prefix: &str, delay_ms: u64) -> Experiment {
-        Experiment {
-            id: id.to_string(),
-            enabled: true,
-            description: "Test latency".to_string(),
-            targeting: Targeting {
-                paths: vec![PathMatcher::Prefix {
-                    prefix: path_prefix.to_string(),
-                }],
-                methods: vec![],
-                headers: HashMap::new(),
-                percentage: 100,
-            },
-            fault: Fault::Latency {
-                fixed_ms: delay_ms,
-                min_ms: 0,
-                max_ms: 0,
-            },
+    #[tokio::test]
+    async fn test_delta_metrics_sum_to_cumulative() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.settings.metrics_mode = MetricsMode::Delta;
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+        let counter = |report: &MetricsReport, name: &str| {
+            report
+                .counters
+                .iter()
+                .find(|c| c.name == name && c.labels.is_empty())
+                .unwrap()
+                .value
+        };
+        let experiment_injections = |report: &MetricsReport| {
+            report
+                .counters
+                .iter()
+                .find(|c| c.name == "chaos_experiment_injections_total")
+                .unwrap()
+                .value
+        };
+
+        for _ in 0..3 {
+            agent.evaluate("GET", "/api/x", &headers).await;
         }
+        let first = agent.metrics_report().unwrap();
+        for path in ["/api/x", "/api/y", "/other"] {
+            agent.evaluate("GET", path, &headers).await;
+        }
+        let second = agent.metrics_report().unwrap();
+
+        assert_eq!(counter(&first, "chaos_requests_total"), 3);
+        assert_eq!(counter(&second, "chaos_requests_total"), 3);
+        assert_eq!(
+            counter(&first, "chaos_requests_total") + counter(&second, "chaos_requests_total"),
+            agent.total_requests()
+        );
+        assert_eq!(
+            experiment_injections(&first) + experiment_injections(&second),
+            agent.total_faults_injected()
+        );
+        assert_eq!(experiment_injections(&second), 2);
+
+        // Nothing happened since the last report
+        let third = agent.metrics_report().unwrap();
+        assert!(third.counters.iter().all(|c| c.value == 0));
+        // Gauges are not affected
+        assert!(third
+            .gauges
+            .iter()
+            .any(|g| g.name == "chaos_experiments_enabled" && g.value == 1.0));
     }
 
-    fn create_error_experiment(id: &str, path_prefix: &str, status: u16) -> Experiment {
-        Experiment {
-            id: id.to_string(),
-            enabled: true,
-            description: "Test error".to_string(),
-            targeting: Targeting {
-                paths: vec![PathMatcher::Prefix {
-                    prefix: path_prefix.to_string(),
-                }],
-                methods: vec![],
-                headers: HashMap::new(),
-                percentage: 100,
-            },
-            fault: Fault::Error {
-                status,
-                message: Some("Test error".to_string()),
-                headers: HashMap::new(),
-            },
-        }
+    #[tokio::test]
+    async fn test_matched_and_skip_counters() {
+        let mut capped = create_error_experiment("capped", "/api/", 503);
+        capped.max_injections = Some(1);
+        let mut rolls = create_error_experiment("rolls", "/api/", 500);
+        rolls.targeting.percentage = 0;
+        let mut off = create_error_experiment("off", "/api/", 500);
+        off.enabled = false;
+        let mut never = create_error_experiment("never", "/api/", 500);
+        never.schedule =
+            vec![serde_yaml::from_str("{ days: [], start: '00:00', end: '23:59' }").unwrap()];
+        let agent = ChaosAgent::new(create_test_config(vec![capped, rolls, off, never]));
+        let headers = HashMap::new();
+
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        assert!(agent.evaluate("GET", "/health", &headers).await.is_none());
+        agent.control().pause();
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        agent.control().resume();
+        agent.draining.drain(None);
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+
+        assert_eq!(agent.get_skip_count("excluded_path"), 1);
+        assert_eq!(agent.get_skip_count("paused"), 1);
+        assert_eq!(agent.get_skip_count("draining"), 1);
+        assert_eq!(agent.get_skip_count("disabled"), 0);
+
+        let report = agent.metrics_report().unwrap();
+        let value = |name: &str, experiment: &str, reason: Option<&str>| {
+            report
+                .counters
+                .iter()
+                .find(|c| {
+                    c.name == name
+                        && c.labels["experiment"] == experiment
+                        && c.labels.get("reason").map(String::as_str) == reason
+                })
+                .map(|c| c.value)
+                .unwrap()
+        };
+        let matched = "chaos_experiment_matched_total";
+        let skipped = "chaos_experiment_skipped_total";
+        assert_eq!(value(matched, "capped", None), 2);
+        assert_eq!(value(skipped, "capped", Some("budget")), 1);
+        // Matched on both requests, but only tried once capped stopped winning
+        assert_eq!(value(matched, "rolls", None), 2);
+        assert_eq!(value(skipped, "rolls", Some("percentage")), 1);
+        assert_eq!(value(matched, "off", None), 0);
+        assert_eq!(value(skipped, "off", Some("disabled")), 2);
+        assert_eq!(value(skipped, "never", Some("outside_schedule")), 2);
+        assert!(report
+            .counters
+            .iter()
+            .any(|c| c.name == "chaos_skipped_total"
+                && c.labels["reason"] == "paused"
+                && c.value == 1));
     }
 
-    #[test]
-    fn test_agent_initialization() {
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_injected_delay_histogram_under_concurrency() {
+        let mut timeout = create_error_experiment("slow-timeout", "/slow/", 504);
+        timeout.fault = Fault::Timeout {
+            duration_ms: 60,
+            status: None,
+            return_success: false,
+        };
         let config = create_test_config(vec![
-            create_latency_experiment("exp1", "/api/", 100),
-            create_error_experiment("exp2", "/test/", 500),
+            create_latency_experiment("api-latency", "/api/", 20),
+            timeout,
+            create_error_experiment("errors", "/errors/", 503),
         ]);
+        let agent = Arc::new(ChaosAgent::new(config));
 
-        let agent = ChaosAgent::new(config);
-        assert_eq!(agent.compiled_experiments.len(), 2);
-    }
+        let tasks: Vec<_> = (0..32)
+            .map(|_| {
+                let agent = Arc::clone(&agent);
+                tokio::spawn(async move { agent.evaluate("GET", "/api/x", &HashMap::new()).await })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().is_some());
+        }
+        agent.evaluate("GET", "/slow/x", &HashMap::new()).await;
+        agent.evaluate("GET", "/errors/x", &HashMap::new()).await;
 
-    #[test]
-    fn test_flatten_headers() {
-        let mut headers = HashMap::new();
-        headers.insert(
-            "Content-Type".to_string(),
-            vec!["application/json".to_string()],
-        );
-        headers.insert(
-            "X-Test".to_string(),
-            vec!["value1".to_string(), "value2".to_string()],
+        let report = agent.metrics_report().unwrap();
+        let value = |name: &str, experiment: &str, le: Option<&str>| {
+            report
+                .counters
+                .iter()
+                .find(|c| {
+                    c.name == name
+                        && c.labels.get("experiment").map(String::as_str) == Some(experiment)
+                        && c.labels.get("le").map(String::as_str) == le
+                })
+                .map(|c| c.value)
+        };
+        let bucket = "chaos_injected_delay_ms_bucket";
+        assert_eq!(value(bucket, "api-latency", Some("10")), Some(0));
+        assert_eq!(value(bucket, "api-latency", Some("50")), Some(32));
+        assert_eq!(value(bucket, "api-latency", Some("+Inf")), Some(32));
+        assert_eq!(
+            value("chaos_injected_delay_ms_sum", "api-latency", None),
+            Some(640)
         );
-
-        let flat = ChaosAgent::flatten_headers(&headers);
         assert_eq!(
-            flat.get("content-type"),
-            Some(&"application/json".to_string())
+            value("chaos_injected_delay_ms_count", "api-latency", None),
+            Some(32)
         );
-        assert_eq!(flat.get("x-test"), Some(&"value1".to_string()));
+
+        assert_eq!(value(bucket, "slow-timeout", Some("50")), Some(0));
+        assert_eq!(value(bucket, "slow-timeout", Some("100")), Some(1));
+        let labels = &report
+            .counters
+            .iter()
+            .find(|c| c.name == bucket && c.labels["experiment"] == "slow-timeout")
+            .unwrap()
+            .labels;
+        assert_eq!(labels["fault_type"], "timeout");
+
+        // Faults that do not delay have no histogram
+        assert_eq!(value("chaos_injected_delay_ms_count", "errors", None), None);
     }
 
-    #[test]
-    fn test_find_matching_experiments() {
-        let config = create_test_config(vec![
-            create_latency_experiment("api-latency", "/api/", 100),
-            create_error_experiment("test-error", "/test/", 500),
-        ]);
+    #[tokio::test]
+    async fn test_match_eval_histogram_only_when_profiling() {
+        let has_histogram = |agent: &ChaosAgent| {
+            agent
+                .metrics_report()
+                .unwrap()
+                .counters
+                .iter()
+                .any(|c| c.name.starts_with("chaos_match_eval_nanos"))
+        };
+
+        let config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        let agent = ChaosAgent::new(config.clone());
+        agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+        assert!(!has_histogram(&agent));
+
+        let mut config = config;
+        config.settings.profile_matching = true;
+        let agent = ChaosAgent::new(config);
+        agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+        agent.evaluate("GET", "/other", &HashMap::new()).await;
+        assert!(has_histogram(&agent));
+
+        let report = agent.metrics_report().unwrap();
+        let count = report
+            .counters
+            .iter()
+            .find(|c| c.name == "chaos_match_eval_nanos_count")
+            .unwrap();
+        assert_eq!(count.value, 2);
+        let inf = report
+            .counters
+            .iter()
+            .find(|c| {
+                c.name == "chaos_match_eval_nanos_bucket"
+                    && c.labels.get("le").map(String::as_str) == Some("+Inf")
+            })
+            .unwrap();
+        assert_eq!(inf.value, 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_cooldown_skips_recent_clients() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.experiments[0].targeting.client_cooldown = Some(ClientCooldownConfig {
+            seconds: 300,
+            key_header: "x-user-id".to_string(),
+            max_clients: 100,
+        });
+        let agent = ChaosAgent::new(config);
+        let user = |id: &str| HashMap::from([("X-User-Id".to_string(), vec![id.to_string()])]);
+
+        assert!(agent
+            .evaluate("GET", "/api/x", &user("alice"))
+            .await
+            .is_some());
+        assert!(agent
+            .evaluate("GET", "/api/x", &user("alice"))
+            .await
+            .is_none());
+        assert!(agent
+            .evaluate("GET", "/api/x", &user("bob"))
+            .await
+            .is_some());
 
+        // Clients without the key header are not tracked
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_affected_client_cap() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.safety.max_affected_clients = Some(crate::config::MaxAffectedClients {
+            count: 3,
+            window_s: 3600,
+            key_header: "X-User-Id".to_string(),
+        });
+        let agent = ChaosAgent::new(config);
+        let user = |id: usize| HashMap::from([("x-user-id".to_string(), vec![format!("u{}", id)])]);
+
+        let mut faulted = 0;
+        for id in 0..50 {
+            if agent.evaluate("GET", "/api/x", &user(id)).await.is_some() {
+                faulted += 1;
+            }
+        }
+        assert_eq!(faulted, 3);
+
+        // Affected clients keep receiving faults, anonymous requests are spared
+        assert!(agent.evaluate("GET", "/api/x", &user(1)).await.is_some());
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_none());
+
+        let report = agent.metrics_report().unwrap();
+        let gauge = report
+            .gauges
+            .iter()
+            .find(|g| g.name == "chaos_affected_clients")
+            .unwrap();
+        assert_eq!(gauge.value, 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_bitrot_applies_in_response_phase() {
+        let mut config = create_test_config(vec![create_error_experiment("bitrot", "/api/", 500)]);
+        config.experiments[0].fault = Fault::Corrupt {
+            probability: 1.0,
+            mode: CorruptMode::Bitrot,
+            byte_flip_probability: 1.0,
+            size_bytes: None,
+        };
         let agent = ChaosAgent::new(config);
         let headers = HashMap::new();
 
-        // Should match api-latency
-        let matches = agent.find_matching_experiments("GET", "/api/users", &headers);
-        assert_eq!(matches.len(), 1);
-        assert_eq!(matches[0].id, "api-latency");
+        // Nothing to corrupt before the upstream has answered
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
 
-        // Should match test-error
-        let matches = agent.find_matching_experiments("POST", "/test/data", &headers);
-        assert_eq!(matches.len(), 1);
-        assert_eq!(matches[0].id, "test-error");
+        let upstream = UpstreamResponse {
+            status: 200,
+            body: Some(b"{\"ok\":true}"),
+        };
+        let decision = agent
+            .evaluate_response("GET", "/api/x", &headers, None, upstream)
+            .await;
+        let tags = decision.unwrap().build().audit.tags;
+        assert!(tags.contains(&"chaos-fault:corrupt".to_string()));
+        assert_eq!(agent.get_injection_count("bitrot"), 1);
+    }
 
-        // Should not match anything
-        let matches = agent.find_matching_experiments("GET", "/other/path", &headers);
-        assert!(matches.is_empty());
+    #[tokio::test]
+    async fn test_latency_after_delays_only_the_response() {
+        let mut experiment = create_latency_experiment("slow-egress", "/api/", 150);
+        experiment.fault = Fault::Latency {
+            fixed_ms: 150,
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::After,
+        };
+        let agent = ChaosAgent::new(create_test_config(vec![experiment]));
+        let headers = HashMap::new();
+
+        let started = Instant::now();
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(agent.get_injection_count("slow-egress"), 0);
+
+        let upstream = UpstreamResponse {
+            status: 200,
+            body: None,
+        };
+        let started = Instant::now();
+        let response = agent
+            .evaluate_response("GET", "/api/x", &headers, None, upstream)
+            .await
+            .unwrap()
+            .build();
+        assert!(started.elapsed() >= Duration::from_millis(150));
+        assert!(matches!(response.decision, ProtocolDecision::Allow));
+        assert!(response.response_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-delay-ms".to_string(),
+            value: "150".to_string(),
+        }));
+        assert!(response.request_headers.is_empty());
+        assert_eq!(agent.get_injection_count("slow-egress"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bad_regex_never_matches_all() {
+        let mut config = create_test_config(vec![
+            create_error_experiment("bad-regex", "/", 500),
+            create_error_experiment("api", "/api/", 503),
+        ]);
+        // Bypasses Config::validate, as a hot reload or test could
+        config.experiments[0].targeting.paths = vec![PathMatcher::Regex {
+            regex: "^/checkout/(".to_string(),
+        }];
+        let agent = ChaosAgent::new(config);
+        assert_eq!(agent.experiment_compile_errors(), 1);
+
+        let headers = HashMap::new();
+        assert!(agent
+            .evaluate("GET", "/checkout/1", &headers)
+            .await
+            .is_none());
+        assert!(agent.evaluate("GET", "/other", &headers).await.is_none());
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        assert_eq!(agent.get_injection_count("bad-regex"), 0);
+
+        let report = agent.metrics_report().unwrap();
+        let invalid: Vec<_> = report
+            .gauges
+            .iter()
+            .filter(|g| g.name == "chaos_experiment_invalid_matchers")
+            .map(|g| (g.labels["experiment"].as_str(), g.value))
+            .collect();
+        assert_eq!(invalid, vec![("bad-regex", 1.0)]);
+    }
+
+    #[tokio::test]
+    async fn test_pushed_config_with_invalid_regex_is_rejected() {
+        let agent = ChaosAgent::new(create_test_config(vec![create_error_experiment(
+            "api", "/api/", 503,
+        )]));
+        let pushed = serde_json::json!({
+            "experiments": [{
+                "id": "checkout",
+                "targeting": {"paths": [{"regex": "^/checkout/("}]},
+                "fault": {"type": "error", "status": 500}
+            }]
+        });
+        let err = agent.apply_pushed_config(pushed, None).unwrap_err();
+        assert!(err.to_string().contains("Invalid regex pattern"), "{}", err);
+        assert_eq!(agent.total_reload_errors(), 1);
+
+        // The working configuration stays active
+        assert!(agent.control().experiment("checkout").is_none());
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+        assert_eq!(agent.experiment_compile_errors(), 0);
     }
 
     #[test]
-    fn test_excluded_path() {
-        let config = create_test_config(vec![create_latency_experiment("all", "/", 100)]);
+    fn test_pushed_config_keeps_command_line_overrides() {
+        let mut config = create_test_config(vec![create_error_experiment("api", "/api/", 503)]);
+        config.settings.dry_run = true;
+        let agent = ChaosAgent::new(config).with_config_hook(|config| {
+            config.settings.dry_run = true;
+            Ok(())
+        });
+        let pushed = serde_json::json!({
+            "settings": {"dry_run": false},
+            "experiments": [{
+                "id": "checkout",
+                "targeting": {"paths": [{"prefix": "/checkout/"}]},
+                "fault": {"type": "error", "status": 500}
+            }]
+        });
+        agent.apply_pushed_config(pushed, None).unwrap();
+        assert!(agent.state.load().config.settings.dry_run);
+        assert!(agent.control().experiment("checkout").is_some());
+
+        // A failing hook rejects the push
+        let agent = ChaosAgent::new(create_test_config(vec![]))
+            .with_config_hook(|_| Err(anyhow!("too risky")));
+        let err = agent
+            .apply_pushed_config(serde_json::json!({}), None)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid configuration: too risky");
+        assert_eq!(agent.total_reload_errors(), 1);
+    }
 
+    #[tokio::test]
+    async fn test_fault_panic_allows_request() {
+        let config = create_test_config(vec![create_error_experiment(
+            crate::faults::PANIC_EXPERIMENT_ID,
+            "/api/",
+            500,
+        )]);
         let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
 
-        // Health path should be excluded
-        assert!(is_excluded_path(
-            "/health",
-            &agent.config.safety.excluded_paths
+        for i in 1..=FAULT_PANIC_DEGRADE_THRESHOLD {
+            assert!(!agent.is_degraded());
+            assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+            assert_eq!(agent.total_fault_panics(), i);
+        }
+        assert!(agent.is_degraded());
+        assert_eq!(agent.total_faults_injected(), 0);
+        assert_eq!(agent.total_internal_errors(), FAULT_PANIC_DEGRADE_THRESHOLD);
+    }
+
+    #[tokio::test]
+    async fn test_fail_mode_on_internal_error() {
+        let mut config = create_test_config(vec![create_error_experiment(
+            crate::faults::PANIC_EXPERIMENT_ID,
+            "/api/",
+            503,
+        )]);
+        assert_eq!(config.settings.fail_mode, FailMode::Open);
+        let agent = ChaosAgent::new(config.clone());
+        let response = agent.evaluate_request("GET", "/api/x", &[]).await;
+        assert!(matches!(response.decision, ProtocolDecision::Allow));
+        assert_eq!(agent.total_internal_errors(), 1);
+
+        config.settings.fail_mode = FailMode::Closed;
+        let agent = ChaosAgent::new(config);
+        let response = agent.evaluate_request("GET", "/api/x", &[]).await;
+        assert!(matches!(
+            response.decision,
+            ProtocolDecision::Block { status: 500, .. }
         ));
+        assert!(response
+            .audit
+            .tags
+            .contains(&"chaos-internal-error".to_string()));
+        assert_eq!(agent.total_internal_errors(), 1);
+        assert_eq!(agent.total_faults_injected(), 0);
+    }
 
-        // Other paths should not be excluded
-        assert!(!is_excluded_path(
-            "/api/test",
-            &agent.config.safety.excluded_paths
+    #[tokio::test]
+    async fn test_http_version_targeting_from_event() {
+        let mut experiment = create_error_experiment("h1-errors", "/api/", 503);
+        experiment.targeting.http_versions = vec!["HTTP/1.1".to_string()];
+        let agent = ChaosAgent::new(create_test_config(vec![experiment]));
+
+        let event = |protocol: &str| RequestHeadersEvent {
+            metadata: RequestMetadata {
+                correlation_id: "c-1".to_string(),
+                request_id: "r-1".to_string(),
+                client_ip: "10.0.0.1".to_string(),
+                client_port: 40000,
+                server_name: None,
+                protocol: protocol.to_string(),
+                tls_version: None,
+                tls_cipher: None,
+                route_id: None,
+                upstream_id: None,
+                timestamp: "2024-01-03T12:00:00Z".to_string(),
+                traceparent: None,
+            },
+            method: "GET".to_string(),
+            uri: "/api/x".to_string(),
+            headers: HashMap::new(),
+        };
+
+        let response = agent.on_request_headers(event("HTTP/1.1")).await;
+        assert!(matches!(
+            response.decision,
+            ProtocolDecision::Block { status: 503, .. }
+        ));
+        let response = agent.on_request_headers(event("HTTP/2")).await;
+        assert!(matches!(response.decision, ProtocolDecision::Allow));
+
+        // Without a reported version the experiment matches any request
+        let response = agent.on_request_headers(event("")).await;
+        assert!(matches!(
+            response.decision,
+            ProtocolDecision::Block { status: 503, .. }
         ));
     }
 
-    #[test]
-    fn test_draining_flag() {
-        let config = create_test_config(vec![]);
+    #[tokio::test]
+    async fn test_echo_returns_request_body() {
+        let mut experiment = create_error_experiment("echo", "/api/", 503);
+        experiment.fault = Fault::Echo {
+            status: 202,
+            content_type: None,
+        };
+        let agent = ChaosAgent::new(create_test_config(vec![experiment]));
+        let headers = HashMap::from([(
+            "Content-Type".to_string(),
+            vec!["application/json".to_string()],
+        )]);
+
+        // Nothing happens until the body arrives
+        assert!(agent.evaluate("POST", "/api/x", &headers).await.is_none());
+
+        let body = br#"{"order":42}"#;
+        let response = agent
+            .evaluate_body("POST", "/api/x", &headers, None, body)
+            .await
+            .map(Decision::build)
+            .unwrap();
+        let ProtocolDecision::Block {
+            status,
+            body: Some(echoed),
+            headers: Some(block_headers),
+        } = response.decision
+        else {
+            panic!("unexpected decision {:?}", response.decision);
+        };
+        assert_eq!(status, 202);
+        assert_eq!(echoed, r#"{"order":42}"#);
+        assert_eq!(block_headers["content-type"], "application/json");
+        assert!(response
+            .audit
+            .tags
+            .contains(&"chaos-fault:echo".to_string()));
+        assert_eq!(agent.total_faults_injected(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_latency_decision_carries_tag_and_headers() {
+        let config = create_test_config(vec![create_latency_experiment("slow", "/api/", 1)]);
         let agent = ChaosAgent::new(config);
 
-        assert!(!agent.is_draining());
-        agent.draining.store(true, Ordering::SeqCst);
-        assert!(agent.is_draining());
+        // The same decision backs v1 on_request and v2 on_request_headers
+        let response = agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .map(Decision::build)
+            .unwrap();
+        assert!(response.audit.tags.contains(&"chaos:slow".to_string()));
+        assert!(response
+            .audit
+            .tags
+            .contains(&"chaos-fault:latency".to_string()));
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-injected".to_string(),
+            value: "latency".to_string(),
+        }));
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-delay-ms".to_string(),
+            value: "1".to_string(),
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_escalating_latency_grows_to_cap() {
+        let mut config = create_test_config(vec![create_latency_experiment("degrade", "/api/", 1)]);
+        config.experiments[0].fault = Fault::EscalatingLatency {
+            base_ms: 1,
+            factor: 2.0,
+            max_ms: 5,
+            reset_after_seconds: 60,
+        };
+        let agent = ChaosAgent::new(config);
+
+        let mut delays = Vec::new();
+        for _ in 0..5 {
+            let response = agent
+                .evaluate("GET", "/api/x", &HashMap::new())
+                .await
+                .map(Decision::build)
+                .unwrap();
+            assert!(response.request_headers.contains(&HeaderOp::Set {
+                name: "x-chaos-injected".to_string(),
+                value: "escalating_latency".to_string(),
+            }));
+            delays.extend(response.request_headers.iter().filter_map(|op| match op {
+                HeaderOp::Set { name, value } if name == "x-chaos-delay-ms" => Some(value.clone()),
+                _ => None,
+            }));
+        }
+        assert_eq!(delays, vec!["1", "2", "4", "5", "5"]);
+        assert_eq!(agent.total_faults_injected(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_delay_is_not_announced() {
+        let mut config = create_test_config(vec![create_latency_experiment("slow", "/api/", 100)]);
+        config.settings.dry_run = true;
+        let agent = ChaosAgent::new(config);
+
+        let response = agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .map(Decision::build)
+            .unwrap();
+        assert!(!response.request_headers.iter().any(|op| matches!(
+            op,
+            HeaderOp::Set { name, .. } if name.starts_with("x-chaos-")
+        )));
+        assert_eq!(agent.total_dry_run_would_inject(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_forced_and_triggered_are_only_logged() {
+        let mut config = create_test_config(vec![
+            create_error_experiment("forced", "/api/", 503),
+            create_error_experiment("armed", "/api/", 500),
+        ]);
+        config.settings.dry_run = true;
+        config.settings.allow_force_header = true;
+        config.experiments[0].targeting.percentage = 0;
+        config.experiments[1].targeting.percentage = 0;
+        let agent = ChaosAgent::new(config);
+
+        let forced = agent
+            .evaluate("GET", "/api/x", &force_headers("forced"))
+            .await
+            .map(Decision::build);
+        agent.control().trigger_experiment("armed", 1).unwrap();
+        let triggered = agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .map(Decision::build);
+        for response in [forced, triggered].into_iter().flatten() {
+            assert!(matches!(response.decision, ProtocolDecision::Allow));
+            assert!(response.response_headers.is_empty());
+        }
+
+        assert_eq!(agent.total_forced_injections(), 0);
+        assert_eq!(agent.total_triggered_injections(), 0);
+        assert_eq!(agent.total_dry_run_would_inject(), 2);
+        let recent = agent.recent_injections();
+        assert_eq!(recent.len(), 2);
+        assert!(recent.iter().all(|r| r.dry_run));
+        for id in ["forced", "armed"] {
+            assert!(agent
+                .control()
+                .experiment(id)
+                .unwrap()
+                .last_injection
+                .is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutate_response_headers_on_response() {
+        let mut config = create_test_config(vec![create_error_experiment("headers", "/api/", 500)]);
+        config.experiments[0].fault = Fault::MutateResponseHeaders {
+            set: HashMap::from([("x-chaos-test".to_string(), "1".to_string())]),
+            remove: vec!["cache-control".to_string()],
+        };
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+
+        // Header mutation does not need the response body
+        let upstream = UpstreamResponse {
+            status: 200,
+            body: None,
+        };
+        let response = agent
+            .evaluate_response("GET", "/api/x", &headers, None, upstream)
+            .await
+            .unwrap()
+            .build();
+        assert!(response.response_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-test".to_string(),
+            value: "1".to_string(),
+        }));
+        assert!(response.response_headers.contains(&HeaderOp::Remove {
+            name: "cache-control".to_string(),
+        }));
+        assert_eq!(agent.get_injection_count("headers"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_path_normalization_applies_to_exclusion() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/", 503)]);
+        config.settings.path_normalization.case_insensitive = true;
+        config.settings.path_normalization.strip_trailing_slash = true;
+        let agent = ChaosAgent::new(config);
+
+        let decision = agent.evaluate("GET", "/Health/", &HashMap::new()).await;
+        assert!(decision.is_none());
+        let decision = agent.evaluate("GET", "/api/x", &HashMap::new()).await;
+        assert!(decision.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_force_header_fires_experiment() {
+        let mut config = create_test_config(vec![
+            create_error_experiment("always", "/api/", 500),
+            create_error_experiment("payment-errors", "/api/", 503),
+        ]);
+        config.settings.allow_force_header = true;
+        config.experiments[1].targeting.percentage = 0;
+        let agent = ChaosAgent::new(config);
+
+        let decision = agent
+            .evaluate("GET", "/api/pay", &force_headers("payment-errors"))
+            .await;
+        let tags = decision.unwrap().build().audit.tags;
+        assert!(tags.contains(&"chaos:payment-errors".to_string()));
+
+        // Forced injections stay out of experiment statistics
+        assert_eq!(agent.total_forced_injections(), 1);
+        assert_eq!(agent.total_faults_injected(), 0);
+        assert_eq!(agent.get_injection_count("payment-errors"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_force_header_ignored_when_unknown_or_disabled() {
+        let mut config = create_test_config(vec![create_error_experiment("errors", "/api/", 503)]);
+        config.experiments[0].targeting.percentage = 0;
+        let agent = ChaosAgent::new(config.clone());
+
+        // Disabled by default
+        let decision = agent
+            .evaluate("GET", "/api/x", &force_headers("errors"))
+            .await;
+        assert!(decision.is_none());
+
+        config.settings.allow_force_header = true;
+        let agent = ChaosAgent::new(config);
+
+        // Unknown id, or an experiment whose targeting does not match
+        assert!(agent
+            .evaluate("GET", "/api/x", &force_headers("missing"))
+            .await
+            .is_none());
+        assert!(agent
+            .evaluate("GET", "/other", &force_headers("errors"))
+            .await
+            .is_none());
+        assert_eq!(agent.total_forced_injections(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_every_n_faults_every_nth_matching_request() {
+        let mut config = create_test_config(vec![create_error_experiment("third", "/api/", 503)]);
+        config.experiments[0].targeting.percentage = 0;
+        config.experiments[0].targeting.every_n = Some(3);
+        let agent = ChaosAgent::new(config);
+        let headers = HashMap::new();
+
+        // Non-matching requests do not advance the count
+        assert!(agent.evaluate("GET", "/other", &headers).await.is_none());
+        let mut faulted = Vec::new();
+        for n in 1..=9 {
+            if agent.evaluate("GET", "/api/x", &headers).await.is_some() {
+                faulted.push(n);
+            }
+        }
+        assert_eq!(faulted, vec![3, 6, 9]);
+        let status = agent.control().experiment("third").unwrap();
+        assert!((status.effective_percentage - 100.0 / 3.0).abs() < 1e-9);
+
+        // The spacing is set in the configuration, not by percentage
+        assert!(agent
+            .control()
+            .set_experiment_percentage("third", 50)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_triggered_experiment_fires_armed_count() {
+        let mut config = create_test_config(vec![
+            create_error_experiment("rare", "/api/", 503),
+            create_error_experiment("off", "/api/", 500),
+        ]);
+        config.experiments[0].targeting.percentage = 0;
+        config.experiments[1].enabled = false;
+        let agent = ChaosAgent::new(config.clone());
+        let control = agent.control();
+        let headers = HashMap::new();
+
+        assert!(control.trigger_experiment("rare", 0).is_err());
+        assert!(control.trigger_experiment("missing", 1).is_err());
+        assert_eq!(control.trigger_experiment("rare", 2).unwrap(), 0);
+        assert_eq!(control.experiment("rare").unwrap().armed, 2);
+
+        // Armed triggers survive a reload of an unchanged experiment
+        control.reload(config).unwrap();
+
+        for _ in 0..2 {
+            let response = agent
+                .evaluate("GET", "/api/x", &headers)
+                .await
+                .unwrap()
+                .build();
+            let ProtocolDecision::Block {
+                status: 503,
+                headers: Some(block_headers),
+                ..
+            } = response.decision
+            else {
+                panic!("unexpected decision {:?}", response.decision);
+            };
+            assert_eq!(block_headers[TRIGGERED_HEADER], "true");
+        }
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        assert_eq!(control.experiment("rare").unwrap().armed, 0);
+
+        // Triggered injections stay out of experiment statistics
+        assert_eq!(agent.total_triggered_injections(), 2);
+        assert_eq!(agent.total_faults_injected(), 0);
+        assert_eq!(agent.get_injection_count("rare"), 0);
+        let recent = agent.recent_injections();
+        assert!(recent.iter().all(|r| r.triggered && !r.forced));
+
+        // A disabled experiment stays armed until it can fire
+        control.trigger_experiment("off", 1).unwrap();
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_none());
+        let status = control.experiment("off").unwrap();
+        assert_eq!((status.enabled, status.armed), (false, 1));
+        control.set_experiment_enabled("off", true).unwrap();
+        assert!(agent.evaluate("GET", "/api/x", &headers).await.is_some());
+        assert_eq!(agent.total_triggered_injections(), 3);
     }
 }
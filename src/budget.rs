@@ -0,0 +1,109 @@
+//! Global injection budget across overlapping experiments.
+//!
+//! Each experiment's percentage is already capped by
+//! `safety.max_affected_percent`, but several overlapping experiments can
+//! still affect more traffic combined. The budget tracks the share of all
+//! requests that received a fault over a sliding window and refuses further
+//! injections once the share reaches the limit.
+
+use crate::window::SlidingWindow;
+
+/// Sliding window length used for the injection budget.
+pub const BUDGET_WINDOW_SECS: u64 = 10;
+
+/// Sliding-window budget for the fraction of requests receiving faults.
+pub struct InjectionBudget {
+    max_percent: u8,
+    requests: SlidingWindow,
+    injections: SlidingWindow,
+}
+
+impl InjectionBudget {
+    /// Create a budget allowing at most `max_percent` of requests to be
+    /// faulted within a window of `window_secs` seconds.
+    pub fn new(max_percent: u8, window_secs: u64) -> Self {
+        Self {
+            max_percent,
+            requests: SlidingWindow::new(window_secs),
+            injections: SlidingWindow::new(window_secs),
+        }
+    }
+
+    /// Record a request seen at second `now`.
+    pub fn record_request(&self, now: u64) {
+        self.requests.record(now, 1);
+    }
+
+    /// Record a fault injected at second `now`.
+    pub fn record_injection(&self, now: u64) {
+        self.injections.record(now, 1);
+    }
+
+    /// Check whether one more injection at second `now` stays within budget.
+    pub fn allows_injection(&self, now: u64) -> bool {
+        if self.max_percent >= 100 {
+            return true;
+        }
+        let requests = self.requests.sum(now);
+        let injections = self.injections.sum(now);
+        (injections + 1) * 100 <= requests * self.max_percent as u64
+    }
+
+    /// Check whether injections in the window at second `now` have used up
+    /// the budget, so the next one would be refused.
+    pub fn is_exhausted(&self, now: u64) -> bool {
+        self.injections.sum(now) > 0 && !self.allows_injection(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drive `per_second` requests for `seconds` seconds where every request
+    /// wants a fault, returning the share that got one.
+    fn drive(budget: &InjectionBudget, seconds: u64, per_second: u64) -> f64 {
+        let mut injected = 0;
+        for now in 0..seconds {
+            for _ in 0..per_second {
+                budget.record_request(now);
+                if budget.allows_injection(now) {
+                    budget.record_injection(now);
+                    injected += 1;
+                }
+            }
+        }
+        injected as f64 / (seconds * per_second) as f64
+    }
+
+    #[test]
+    fn test_budget_holds_ceiling() {
+        let budget = InjectionBudget::new(30, BUDGET_WINDOW_SECS);
+        let share = drive(&budget, 60, 200);
+        assert!((0.29..=0.31).contains(&share), "share was {}", share);
+    }
+
+    #[test]
+    fn test_budget_resumes_as_window_rolls() {
+        let budget = InjectionBudget::new(10, 2);
+        for _ in 0..10 {
+            budget.record_request(0);
+        }
+        budget.record_injection(0);
+        assert!(!budget.allows_injection(0));
+
+        // Once second 0 leaves the window, injections resume
+        budget.record_request(2);
+        budget.record_request(3);
+        for _ in 0..10 {
+            budget.record_request(3);
+        }
+        assert!(budget.allows_injection(3));
+    }
+
+    #[test]
+    fn test_full_budget_always_allows() {
+        let budget = InjectionBudget::new(100, BUDGET_WINDOW_SECS);
+        assert!(budget.allows_injection(0));
+    }
+}
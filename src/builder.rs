@@ -0,0 +1,380 @@
+//! Fluent builders for constructing experiments in code.
+//!
+//! Useful when embedding the agent in a test harness instead of loading
+//! YAML. Builders run the same validation as configuration files and
+//! return an error instead of an invalid experiment.
+//!
+//! ```
+//! use zentinel_agent_chaos::builder::ExperimentBuilder;
+//!
+//! let experiment = ExperimentBuilder::new("slow-api")
+//!     .path_prefix("/api/")
+//!     .method("GET")
+//!     .percentage(10)
+//!     .latency_fixed(500)
+//!     .build()?;
+//!
+//! assert_eq!(experiment.targeting.percentage, 10);
+//! assert_eq!(experiment.fault.to_string(), "latency 500ms");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::config::{
+    ClientCooldownConfig, CorruptMode, Experiment, Fault, LatencyTiming, PathMatcher,
+    PrecheckConfig, ResetMode, Schedule, Targeting,
+};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// Builder for [`Targeting`] rules.
+///
+/// Starts out matching every request at 100%.
+///
+/// ```
+/// use zentinel_agent_chaos::builder::TargetingBuilder;
+///
+/// let targeting = TargetingBuilder::new()
+///     .path_exact("/api/checkout")
+///     .method("POST")
+///     .header("x-chaos-enabled", "true")
+///     .percentage(50)
+///     .build()?;
+///
+/// assert_eq!(targeting.methods, vec!["POST"]);
+///
+/// // Out-of-range values are rejected
+/// assert!(TargetingBuilder::new().percentage(150).build().is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TargetingBuilder {
+    targeting: Targeting,
+}
+
+impl Default for TargetingBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TargetingBuilder {
+    /// Create a builder matching all requests.
+    pub fn new() -> Self {
+        Self {
+            targeting: Targeting {
+                percentage: 100,
+                ..Targeting::default()
+            },
+        }
+    }
+
+    /// Match an exact path.
+    pub fn path_exact(mut self, path: impl Into<String>) -> Self {
+        self.targeting
+            .paths
+            .push(PathMatcher::Exact { exact: path.into() });
+        self
+    }
+
+    /// Match a path prefix.
+    pub fn path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.targeting.paths.push(PathMatcher::Prefix {
+            prefix: prefix.into(),
+        });
+        self
+    }
+
+    /// Match paths against a regex.
+    pub fn path_regex(mut self, regex: impl Into<String>) -> Self {
+        self.targeting.paths.push(PathMatcher::Regex {
+            regex: regex.into(),
+        });
+        self
+    }
+
+    /// Match paths against a segment glob such as `/api/*/users`.
+    pub fn path_glob(mut self, glob: impl Into<String>) -> Self {
+        self.targeting
+            .paths
+            .push(PathMatcher::Glob { glob: glob.into() });
+        self
+    }
+
+    /// Match an HTTP method.
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.targeting.methods.push(method.into());
+        self
+    }
+
+    /// Require a header with a specific value.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.targeting.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Require a header to be absent.
+    pub fn header_absent(mut self, name: impl Into<String>) -> Self {
+        self.targeting.headers_absent.push(name.into());
+        self
+    }
+
+    /// Set the percentage of matching requests to affect.
+    pub fn percentage(mut self, percentage: u8) -> Self {
+        self.targeting.percentage = percentage;
+        self
+    }
+
+    /// Skip clients that received a fault recently.
+    pub fn client_cooldown(mut self, cooldown: ClientCooldownConfig) -> Self {
+        self.targeting.client_cooldown = Some(cooldown);
+        self
+    }
+
+    /// Validate and return the targeting rules.
+    pub fn build(self) -> Result<Targeting> {
+        self.targeting.validate()?;
+        Ok(self.targeting)
+    }
+}
+
+/// Builder for an [`Experiment`].
+///
+/// Targeting methods such as [`path_prefix`](Self::path_prefix) add to the
+/// experiment's rules; [`targeting`](Self::targeting) replaces them. A fault
+/// must be set before building.
+///
+/// ```
+/// use zentinel_agent_chaos::builder::{ExperimentBuilder, TargetingBuilder};
+///
+/// let experiment = ExperimentBuilder::new("checkout-errors")
+///     .description("Checkout returns 503 for QA")
+///     .targeting(TargetingBuilder::new().path_exact("/checkout").build()?)
+///     .error(503)
+///     .max_injections(100)
+///     .build()?;
+/// assert_eq!(experiment.max_injections, Some(100));
+///
+/// // Missing faults and invalid fault settings are rejected
+/// assert!(ExperimentBuilder::new("no-fault").build().is_err());
+/// assert!(ExperimentBuilder::new("bad").error(9999).build().is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExperimentBuilder {
+    id: String,
+    enabled: bool,
+    description: String,
+    targeting: TargetingBuilder,
+    fault: Option<Fault>,
+    max_injections: Option<u64>,
+    schedule: Vec<Schedule>,
+    precheck: Option<PrecheckConfig>,
+    ramp_down_seconds: Option<u64>,
+}
+
+impl ExperimentBuilder {
+    /// Create a builder for an enabled experiment matching all requests.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            enabled: true,
+            description: String::new(),
+            targeting: TargetingBuilder::new(),
+            fault: None,
+            max_injections: None,
+            schedule: Vec::new(),
+            precheck: None,
+            ramp_down_seconds: None,
+        }
+    }
+
+    /// Set whether the experiment is enabled.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set the human-readable description.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Replace the targeting rules.
+    pub fn targeting(mut self, targeting: Targeting) -> Self {
+        self.targeting = TargetingBuilder { targeting };
+        self
+    }
+
+    /// Match an exact path.
+    pub fn path_exact(mut self, path: impl Into<String>) -> Self {
+        self.targeting = self.targeting.path_exact(path);
+        self
+    }
+
+    /// Match a path prefix.
+    pub fn path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.targeting = self.targeting.path_prefix(prefix);
+        self
+    }
+
+    /// Match paths against a regex.
+    pub fn path_regex(mut self, regex: impl Into<String>) -> Self {
+        self.targeting = self.targeting.path_regex(regex);
+        self
+    }
+
+    /// Match paths against a segment glob such as `/api/*/users`.
+    pub fn path_glob(mut self, glob: impl Into<String>) -> Self {
+        self.targeting = self.targeting.path_glob(glob);
+        self
+    }
+
+    /// Match an HTTP method.
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.targeting = self.targeting.method(method);
+        self
+    }
+
+    /// Require a header with a specific value.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.targeting = self.targeting.header(name, value);
+        self
+    }
+
+    /// Set the percentage of matching requests to affect.
+    pub fn percentage(mut self, percentage: u8) -> Self {
+        self.targeting = self.targeting.percentage(percentage);
+        self
+    }
+
+    /// Set the fault to inject.
+    pub fn fault(mut self, fault: Fault) -> Self {
+        self.fault = Some(fault);
+        self
+    }
+
+    /// Add a fixed delay.
+    pub fn latency_fixed(self, ms: u64) -> Self {
+        self.fault(Fault::Latency {
+            fixed_ms: ms,
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::Before,
+        })
+    }
+
+    /// Add a delay drawn uniformly from `min_ms..=max_ms`.
+    pub fn latency_range(self, min_ms: u64, max_ms: u64) -> Self {
+        self.fault(Fault::Latency {
+            fixed_ms: 0,
+            min_ms,
+            max_ms,
+            when: LatencyTiming::Before,
+        })
+    }
+
+    /// Add a normally distributed delay, capped at `max_ms`.
+    pub fn jitter(self, mean_ms: u64, stddev_ms: u64, max_ms: u64) -> Self {
+        self.fault(Fault::Jitter {
+            mean_ms,
+            stddev_ms,
+            max_ms,
+        })
+    }
+
+    /// Return an error status.
+    pub fn error(self, status: u16) -> Self {
+        self.fault(Fault::Error {
+            status,
+            message: None,
+            headers: HashMap::new(),
+            status_pool: Vec::new(),
+        })
+    }
+
+    /// Hold the request, then return 504.
+    pub fn timeout(self, duration_ms: u64) -> Self {
+        self.fault(Fault::Timeout {
+            duration_ms,
+            status: None,
+            return_success: false,
+        })
+    }
+
+    /// Throttle bandwidth.
+    pub fn throttle(self, bytes_per_second: u64) -> Self {
+        self.fault(Fault::Throttle { bytes_per_second })
+    }
+
+    /// Replace the response body with garbage.
+    pub fn corrupt(self, probability: f64) -> Self {
+        self.fault(Fault::Corrupt {
+            probability,
+            mode: CorruptMode::Garbage,
+            byte_flip_probability: 0.01,
+            size_bytes: None,
+        })
+    }
+
+    /// Simulate a connection reset with a 502.
+    pub fn reset(self) -> Self {
+        self.fault(Fault::Reset {
+            mode: ResetMode::BadGateway,
+            status: None,
+        })
+    }
+
+    /// Ask the proxy to replay the request `count` more times.
+    pub fn duplicate(self, count: u32) -> Self {
+        self.fault(Fault::Duplicate { count })
+    }
+
+    /// Stop injecting after this many faults.
+    pub fn max_injections(mut self, max: u64) -> Self {
+        self.max_injections = Some(max);
+        self
+    }
+
+    /// Add a window when the experiment is active.
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule.push(schedule);
+        self
+    }
+
+    /// Require a steady-state probe to pass before injecting.
+    pub fn precheck(mut self, precheck: PrecheckConfig) -> Self {
+        self.precheck = Some(precheck);
+        self
+    }
+
+    /// Taper the percentage to 0 over the last `seconds` of a time-limited
+    /// run.
+    pub fn ramp_down_seconds(mut self, seconds: u64) -> Self {
+        self.ramp_down_seconds = Some(seconds);
+        self
+    }
+
+    /// Validate and return the experiment.
+    pub fn build(self) -> Result<Experiment> {
+        let fault = self
+            .fault
+            .ok_or_else(|| anyhow!("experiments[{}].fault: no fault set", self.id))?;
+        let experiment = Experiment {
+            id: self.id,
+            enabled: self.enabled,
+            description: self.description,
+            targeting: self.targeting.targeting,
+            targeting_ref: None,
+            extends: None,
+            fault,
+            max_injections: self.max_injections,
+            schedule: self.schedule,
+            precheck: self.precheck,
+            ramp_down_seconds: self.ramp_down_seconds,
+            source: None,
+        };
+        experiment.validate()?;
+        Ok(experiment)
+    }
+}
@@ -0,0 +1,130 @@
+//! Signed bypass header for exempting requests from chaos.
+//!
+//! On-call engineers debugging through the proxy can send a header that
+//! skips fault injection. The value is either an HMAC-SHA256 of the request
+//! path keyed with the configured secret (hex-encoded), or the secret
+//! itself in static mode. Invalid tokens are ignored, never rejected.
+
+use crate::config::{BypassConfig, BypassMode};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt::Write;
+use subtle::ConstantTimeEq;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Compiled bypass verifier.
+pub struct BypassVerifier {
+    /// Header name, lowercased to match flattened request headers.
+    header: String,
+    secret: Vec<u8>,
+    mode: BypassMode,
+}
+
+impl BypassVerifier {
+    /// Create a verifier from configuration.
+    pub fn new(config: &BypassConfig) -> Self {
+        Self {
+            header: config.header.to_lowercase(),
+            secret: config.secret.as_bytes().to_vec(),
+            mode: config.mode,
+        }
+    }
+
+    /// Check whether the request carries a valid bypass token.
+    ///
+    /// `headers` must have lowercase names. The query string is not part
+    /// of the signed path.
+    pub fn is_bypassed(&self, path: &str, headers: &HashMap<String, String>) -> bool {
+        let Some(token) = headers.get(&self.header) else {
+            return false;
+        };
+        let token = token.trim();
+        match self.mode {
+            BypassMode::Static => constant_time_eq(token.as_bytes(), &self.secret),
+            BypassMode::Hmac => {
+                let path = path.split('?').next().unwrap_or(path);
+                constant_time_eq(token.as_bytes(), sign(&self.secret, path).as_bytes())
+            }
+        }
+    }
+}
+
+/// Compute the hex-encoded HMAC-SHA256 bypass token for a path.
+pub fn sign(secret: &[u8], path: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(path.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// Compare two byte strings without leaking the position of the first
+/// difference through timing.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verifier(mode: BypassMode) -> BypassVerifier {
+        BypassVerifier::new(&BypassConfig {
+            header: "X-Chaos-Bypass".to_string(),
+            secret: "s3cret".to_string(),
+            mode,
+        })
+    }
+
+    fn headers(value: &str) -> HashMap<String, String> {
+        HashMap::from([("x-chaos-bypass".to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn test_sign_known_vector() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign(b"Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_hmac_token() {
+        let verifier = verifier(BypassMode::Hmac);
+        let token = sign(b"s3cret", "/api/users");
+
+        // Valid for the signed path, with or without a query string
+        assert!(verifier.is_bypassed("/api/users", &headers(&token)));
+        assert!(verifier.is_bypassed("/api/users?debug=1", &headers(&token)));
+        // Not transferable to another path
+        assert!(!verifier.is_bypassed("/api/orders", &headers(&token)));
+        // Invalid and missing tokens
+        assert!(!verifier.is_bypassed("/api/users", &headers("deadbeef")));
+        assert!(!verifier.is_bypassed("/api/users", &headers("s3cret")));
+        assert!(!verifier.is_bypassed("/api/users", &HashMap::new()));
+    }
+
+    #[test]
+    fn test_static_token() {
+        let verifier = verifier(BypassMode::Static);
+        assert!(verifier.is_bypassed("/any", &headers("s3cret")));
+        assert!(!verifier.is_bypassed("/any", &headers("s3cre")));
+        assert!(!verifier.is_bypassed("/any", &headers("")));
+        assert!(!verifier.is_bypassed("/any", &HashMap::new()));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+        assert!(!constant_time_eq(b"", b"a"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
@@ -0,0 +1,211 @@
+//! Blast-radius limit by unique clients.
+//!
+//! Caps how many distinct clients (identified by a request header) receive
+//! faults per window. Once the cap is reached, clients already affected keep
+//! receiving faults so their experience stays consistent, while new clients
+//! are spared until the window resets. The set is bounded by the cap, so
+//! memory stays proportional to `count`.
+//!
+//! A request reserves its client's place before any fault is rolled, so
+//! concurrent requests from new clients cannot overshoot the cap, and hands
+//! it back when no fault was applied.
+
+use crate::config::MaxAffectedClients;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// Distinct clients affected within the current window.
+pub struct AffectedClients {
+    config: MaxAffectedClients,
+    window: Mutex<ClientWindow>,
+}
+
+struct ClientWindow {
+    /// Second (since agent start) at which the window opened.
+    started: u64,
+    clients: HashMap<String, Slot>,
+}
+
+/// A client's place within the cap.
+struct Slot {
+    /// Requests holding a reservation that has not been settled yet.
+    pending: u32,
+    /// Whether the client received a fault in this window.
+    affected: bool,
+}
+
+/// Place held by a client for one request, see
+/// [`AffectedClients::reserve_at`].
+#[must_use]
+pub struct Reservation {
+    /// Window the place was reserved in.
+    window: u64,
+}
+
+impl AffectedClients {
+    /// Create a tracker from configuration.
+    pub fn new(mut config: MaxAffectedClients) -> Self {
+        config.key_header = config.key_header.to_lowercase();
+        Self {
+            config,
+            window: Mutex::new(ClientWindow {
+                started: 0,
+                clients: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Header identifying the client, lowercased.
+    pub fn key_header(&self) -> &str {
+        &self.config.key_header
+    }
+
+    /// Reserve a place for a client that may receive a fault at second
+    /// `now`, or return `None` if the client must be spared.
+    ///
+    /// Clients already holding a place always get one; new clients only
+    /// while the cap is not reached. Requests without a client key are
+    /// always spared. The reservation must be [settled](Self::settle).
+    pub fn reserve_at(&self, key: Option<&str>, now: u64) -> Option<Reservation> {
+        let key = key?;
+        let mut window = self.current(now);
+        let full = window.clients.len() >= self.config.count;
+        let started = window.started;
+        match window.clients.get_mut(key) {
+            Some(slot) => slot.pending += 1,
+            None if full => return None,
+            None => {
+                window.clients.insert(
+                    key.to_string(),
+                    Slot {
+                        pending: 1,
+                        affected: false,
+                    },
+                );
+            }
+        }
+        Some(Reservation { window: started })
+    }
+
+    /// Settle a reservation of `key`: the client keeps its place if it
+    /// received a fault, and otherwise hands it back unless it was already
+    /// affected or another request still holds it.
+    pub fn settle(&self, key: &str, reservation: Reservation, affected: bool) {
+        let mut window = self.lock();
+        // A window that reset since has forgotten the reservation
+        if window.started != reservation.window {
+            return;
+        }
+        let Some(slot) = window.clients.get_mut(key) else {
+            return;
+        };
+        slot.pending = slot.pending.saturating_sub(1);
+        slot.affected |= affected;
+        if slot.pending == 0 && !slot.affected {
+            window.clients.remove(key);
+        }
+    }
+
+    /// Number of distinct clients affected, or holding a place, in the
+    /// window at second `now`.
+    pub fn len_at(&self, now: u64) -> usize {
+        self.current(now).clients.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ClientWindow> {
+        self.window.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock the window, resetting it first if it has expired.
+    fn current(&self, now: u64) -> MutexGuard<'_, ClientWindow> {
+        let mut window = self.lock();
+        if now >= window.started + self.config.window_s {
+            window.started = now;
+            window.clients.clear();
+        }
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(count: usize) -> AffectedClients {
+        AffectedClients::new(MaxAffectedClients {
+            count,
+            window_s: 3600,
+            key_header: "x-user-id".to_string(),
+        })
+    }
+
+    fn allows_at(clients: &AffectedClients, key: &str, now: u64, affected: bool) -> bool {
+        match clients.reserve_at(Some(key), now) {
+            Some(reservation) => {
+                clients.settle(key, reservation, affected);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn test_caps_distinct_clients() {
+        let clients = tracker(200);
+        let mut affected = 0;
+        for i in 0..10_000 {
+            let key = format!("user-{}", i % 1000);
+            if allows_at(&clients, &key, 10, true) {
+                affected += 1;
+            }
+        }
+        assert_eq!(clients.len_at(10), 200);
+        // The first 200 users keep receiving faults on every visit
+        assert_eq!(affected, 200 * 10);
+
+        assert!(allows_at(&clients, "user-0", 20, true));
+        assert!(!allows_at(&clients, "user-999", 20, true));
+    }
+
+    #[test]
+    fn test_reservations_cannot_overshoot() {
+        let clients = tracker(2);
+        // Concurrent new clients holding places fill the cap
+        let a = clients.reserve_at(Some("a"), 0).unwrap();
+        let b = clients.reserve_at(Some("b"), 0).unwrap();
+        assert!(clients.reserve_at(Some("c"), 0).is_none());
+
+        // A client that received no fault hands its place back
+        clients.settle("a", a, false);
+        clients.settle("b", b, true);
+        assert_eq!(clients.len_at(0), 1);
+        let c = clients.reserve_at(Some("c"), 0).unwrap();
+        clients.settle("c", c, true);
+        assert!(clients.reserve_at(Some("a"), 0).is_none());
+
+        // A place stays while another request from the client holds it
+        let clients = tracker(1);
+        let first = clients.reserve_at(Some("a"), 0).unwrap();
+        let second = clients.reserve_at(Some("a"), 0).unwrap();
+        clients.settle("a", first, false);
+        assert!(clients.reserve_at(Some("b"), 0).is_none());
+        clients.settle("a", second, false);
+        assert_eq!(clients.len_at(0), 0);
+    }
+
+    #[test]
+    fn test_window_resets() {
+        let clients = tracker(1);
+        assert!(allows_at(&clients, "a", 0, true));
+        assert!(!allows_at(&clients, "b", 3599, true));
+        assert_eq!(clients.len_at(3600), 0);
+        assert!(allows_at(&clients, "b", 3600, true));
+    }
+
+    #[test]
+    fn test_missing_key_is_spared() {
+        let clients = tracker(10);
+        assert!(clients.reserve_at(None, 0).is_none());
+        assert_eq!(clients.len_at(0), 0);
+    }
+}
@@ -1,69 +1,743 @@
 //! Configuration types for the Chaos Engineering agent.
 
-use anyhow::{anyhow, Result};
-use chrono::{NaiveTime, Weekday};
-use serde::{Deserialize, Deserializer, Serialize};
+use crate::interpolate::interpolate_env;
+use crate::targeting::{CompiledTargeting, PathGlob};
+use anyhow::{anyhow, bail, Result};
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Main configuration for the Chaos agent.
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
 pub struct Config {
     /// Global settings.
     pub settings: Settings,
     /// Safety limits.
     pub safety: SafetyConfig,
+    /// Admin API access, for `--admin-address`.
+    #[serde(skip_serializing_if = "AdminConfig::is_default")]
+    pub admin: AdminConfig,
     /// Fault experiments.
     #[serde(default)]
     pub experiments: Vec<Experiment>,
+    /// Files whose experiments are merged into this configuration, relative
+    /// to the including file's directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<PathBuf>,
+    /// Directory whose configuration files each contribute experiments,
+    /// relative to the main file's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiments_dir: Option<PathBuf>,
+    /// Every file read by [`Config::from_file`], the main file first, for
+    /// watching them.
+    #[serde(skip)]
+    pub files: Vec<PathBuf>,
+    /// Named targeting rules that experiments reference with
+    /// `targeting_ref`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub targeting_templates: HashMap<String, Targeting>,
+    /// Named partial experiments that experiments build on with `extends`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    pub templates: HashMap<String, serde_yaml::Value>,
+}
+
+/// An included experiment file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IncludeFile {
+    includes: Vec<PathBuf>,
+    experiments: Vec<Experiment>,
+}
+
+/// A configuration problem and the field it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Field path, e.g. `experiments[payment-errors].fault.status`.
+    pub path: String,
+    /// What is wrong with the field.
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: impl Into<String>, message: impl fmt::Display) -> Self {
+        Self {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Report collected validation errors, listing all of them.
+fn into_result(errors: Vec<ConfigError>) -> Result<()> {
+    let mut iter = errors.into_iter();
+    let Some(first) = iter.next() else {
+        return Ok(());
+    };
+    let rest: Vec<ConfigError> = iter.collect();
+    if rest.is_empty() {
+        return Err(first.into());
+    }
+    let lines: Vec<String> = std::iter::once(first)
+        .chain(rest)
+        .map(|e| format!("  {}", e))
+        .collect();
+    Err(anyhow!(
+        "{} configuration errors:\n{}",
+        lines.len(),
+        lines.join("\n")
+    ))
+}
+
+/// Configuration file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Pick the format from a file's extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        match extension.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            _ => Err(anyhow!(
+                "Unsupported config file extension '{}' for {} (expected .yaml, .yml, .json or .toml)",
+                extension,
+                path.display()
+            )),
+        }
+    }
 }
 
 impl Config {
-    /// Load configuration from a YAML file.
+    /// Load configuration from a YAML, JSON or TOML file, chosen by
+    /// extension, resolving includes.
     pub fn from_file(path: &Path) -> Result<Self> {
+        let format = ConfigFormat::from_path(path)?;
         let content = std::fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&content)?;
+        let content =
+            interpolate_env(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        let mut config: Config = parse_document(&content, format, None)?;
+        for exp in &mut config.experiments {
+            exp.source = Some(path.to_path_buf());
+        }
+
+        let mut stack = vec![canonical(path)?];
+        let mut files = vec![path.to_path_buf()];
+        let includes = config.includes.clone();
+        let templates = Templates {
+            targeting: serde_yaml::to_value(&config.targeting_templates)?,
+            experiments: serde_yaml::to_value(&config.templates)?,
+        };
+        config.experiments.extend(load_includes(
+            path, &includes, &templates, &mut stack, &mut files,
+        )?);
+
+        if let Some(dir) = config.experiments_dir_path(path) {
+            config.experiments.extend(load_experiments_dir(
+                &dir, &templates, &mut stack, &mut files,
+            )?);
+        }
+        config.files = files;
+
+        config.load_fixtures()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Resolve `experiments_dir` against the directory of the main file at
+    /// `config_path`.
+    pub fn experiments_dir_path(&self, config_path: &Path) -> Option<PathBuf> {
+        let dir = self.experiments_dir.as_ref()?;
+        let base = config_path.parent().unwrap_or_else(|| Path::new(""));
+        Some(base.join(dir))
+    }
+
+    /// Parse and validate a configuration held in memory.
+    ///
+    /// Includes and `experiments_dir` need a base directory and are only
+    /// supported by [`Config::from_file`].
+    pub fn from_str(content: &str, format: ConfigFormat) -> Result<Self> {
+        let mut config: Config = parse_document(content, format, None)?;
+        config.refuse_file_references()?;
+        config.load_fixtures()?;
         config.validate()?;
         Ok(config)
     }
 
-    /// Validate the configuration.
+    /// Deserialize a configuration from an already parsed document, such as
+    /// one pushed by the proxy, resolving `extends` and `targeting_ref`.
+    /// Return-fixture faults are refused, so a push cannot read files off
+    /// the agent's host, and so are includes and `experiments_dir`, which
+    /// would otherwise be dropped without their experiments.
+    ///
+    /// Unlike [`Config::from_file`] this does not validate.
+    pub fn from_value(mut value: serde_yaml::Value) -> Result<Self> {
+        let templates = Templates::of(&value);
+        resolve_references(&mut value, &templates);
+        let config: Config = serde_yaml::from_value(value)?;
+        config.refuse_file_references()?;
+        config
+            .experiments
+            .iter()
+            .try_for_each(Experiment::refuse_fixture)?;
+        Ok(config)
+    }
+
+    /// Refuse includes and `experiments_dir`, which need the base directory
+    /// only [`Config::from_file`] has.
+    fn refuse_file_references(&self) -> Result<()> {
+        if !self.includes.is_empty() || self.experiments_dir.is_some() {
+            return Err(anyhow!(
+                "includes and experiments_dir are only supported when loading from a file"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Deserialize a single experiment, such as one added at runtime,
+    /// resolving `extends` and `targeting_ref` against this configuration's
+    /// templates.
+    ///
+    /// Like [`Config::from_value`] this does not validate, and refuses
+    /// return-fixture faults.
+    pub fn experiment_from_value(&self, mut value: serde_yaml::Value) -> Result<Experiment> {
+        let templates = Templates {
+            targeting: serde_yaml::to_value(&self.targeting_templates)?,
+            experiments: serde_yaml::to_value(&self.templates)?,
+        };
+        apply_extends(&mut value, &templates.experiments);
+        apply_targeting_ref(&mut value, &templates.targeting);
+        let experiment: Experiment = serde_yaml::from_value(value)?;
+        experiment.refuse_fixture()?;
+        Ok(experiment)
+    }
+
+    /// Read the files of return-fixture faults, see
+    /// [`Experiment::load_fixture`].
+    pub fn load_fixtures(&mut self) -> Result<()> {
+        self.experiments
+            .iter_mut()
+            .try_for_each(Experiment::load_fixture)
+    }
+
+    /// Validate the configuration, reporting every error found.
     pub fn validate(&self) -> Result<()> {
+        into_result(self.errors())
+    }
+
+    /// Collect problems that do not make the configuration invalid but
+    /// almost certainly are mistakes, such as an enabled experiment that can
+    /// never inject.
+    pub fn warnings(&self) -> Vec<ConfigError> {
+        let mut warnings = Vec::new();
+        for (i, exp) in self.experiments.iter().enumerate() {
+            if !exp.enabled {
+                continue;
+            }
+            let path = if exp.id.is_empty() {
+                format!("experiments[{}]", i)
+            } else {
+                format!("experiments[{}]", exp.id)
+            };
+            if exp.targeting.percentage == 0 && exp.targeting.every_n.is_none() {
+                warnings.push(ConfigError::new(
+                    format!("{}.targeting.percentage", path),
+                    "is 0, experiment matches but never injects",
+                ));
+            }
+            for (j, matcher) in exp.targeting.paths.iter().enumerate() {
+                if let PathMatcher::Regex { regex } = matcher {
+                    if regex_misses_leading_slash(regex) {
+                        warnings.push(ConfigError::new(
+                            format!("{}.targeting.paths[{}]", path, j),
+                            format!(
+                                "regex '{}' is anchored without a leading '/', request paths never match",
+                                regex
+                            ),
+                        ));
+                    }
+                }
+            }
+            // Catch-all experiments (no paths) overlap every excluded path
+            // by design, so only explicit matchers are reported
+            if let Ok(compiled) = CompiledTargeting::with_settings(&exp.targeting, &self.settings) {
+                let overlaps = compiled.path_excluded_overlaps(&self.safety.excluded_paths);
+                for (j, (matcher, excluded)) in exp.targeting.paths.iter().zip(overlaps).enumerate()
+                {
+                    if !excluded.is_empty() {
+                        warnings.push(ConfigError::new(
+                            format!("{}.targeting.paths[{}]", path, j),
+                            format!(
+                                "{} \"{}\" overlaps excluded paths, which are never faulted: {}",
+                                matcher.key(),
+                                matcher.value(),
+                                excluded.join(", ")
+                            ),
+                        ));
+                    }
+                }
+            }
+            // Timeouts are meant to hold requests for a long time
+            let latency_ms = match exp.fault {
+                Fault::Latency { .. } | Fault::Jitter { .. } | Fault::EscalatingLatency { .. } => {
+                    exp.fault.max_delay_ms()
+                }
+                _ => None,
+            };
+            if let Some(delay_ms) = latency_ms {
+                if delay_ms > LONG_DELAY_WARNING_MS {
+                    warnings.push(ConfigError::new(
+                        format!("{}.fault", path),
+                        format!(
+                            "delays requests up to {}ms, more than {}s",
+                            delay_ms,
+                            LONG_DELAY_WARNING_MS / 1000
+                        ),
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Validate, also failing on [`warnings`](Self::warnings).
+    pub fn validate_strict(&self) -> Result<()> {
+        let mut errors = self.errors();
+        errors.extend(self.warnings());
+        into_result(errors)
+    }
+
+    /// Collect all validation errors in the configuration.
+    pub fn errors(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        for (field, header) in [
+            ("settings.sni_header", &self.settings.sni_header),
+            ("settings.alpn_header", &self.settings.alpn_header),
+        ] {
+            if header.is_empty() {
+                errors.push(ConfigError::new(field, "cannot be empty"));
+            }
+        }
+
+        let intensity = self.settings.intensity;
+        if !intensity.is_finite() || intensity < 0.0 {
+            errors.push(ConfigError::new(
+                "settings.intensity",
+                format!("must be >= 0, got {}", intensity),
+            ));
+        }
+
         // Validate safety config
         if self.safety.max_affected_percent > 100 {
-            return Err(anyhow!(
-                "max_affected_percent must be between 0 and 100, got {}",
-                self.safety.max_affected_percent
+            errors.push(ConfigError::new(
+                "safety.max_affected_percent",
+                format!(
+                    "must be between 0 and 100, got {}",
+                    self.safety.max_affected_percent
+                ),
             ));
         }
 
         // Validate schedules
-        for schedule in &self.safety.schedule {
-            if schedule.start >= schedule.end {
-                return Err(anyhow!(
-                    "Schedule start time ({}) must be before end time ({})",
-                    schedule.start,
-                    schedule.end
+        for (i, schedule) in self.safety.schedule.iter().enumerate() {
+            if let Err(e) = schedule.validate() {
+                errors.push(ConfigError::new(format!("safety.schedule[{}]", i), e));
+            }
+        }
+
+        if let Some(Err(e)) = self.safety.bypass.as_ref().map(|b| b.validate()) {
+            errors.push(ConfigError::new("safety.bypass", e));
+        }
+
+        if self
+            .admin
+            .auth_token
+            .as_ref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            errors.push(ConfigError::new("admin.auth_token", "cannot be empty"));
+        }
+
+        if self.safety.max_concurrent_faults == Some(0) {
+            errors.push(ConfigError::new(
+                "safety.max_concurrent_faults",
+                "must be > 0",
+            ));
+        }
+
+        if let Some(Err(e)) = self.safety.auto_abort.as_ref().map(|a| a.validate()) {
+            errors.push(ConfigError::new("safety.auto_abort", e));
+        }
+
+        if let Some(Err(e)) = self
+            .safety
+            .max_affected_clients
+            .as_ref()
+            .map(|c| c.validate())
+        {
+            errors.push(ConfigError::new("safety.max_affected_clients", e));
+        }
+
+        // Validate date ranges
+        for (field, specs) in [
+            ("blackout_dates", &self.safety.blackout_dates),
+            ("only_dates", &self.safety.only_dates),
+        ] {
+            for (i, spec) in specs.iter().enumerate() {
+                if let Err(e) = spec.validate() {
+                    errors.push(ConfigError::new(format!("safety.{}[{}]", field, i), e));
+                }
+            }
+        }
+
+        let mut templates: Vec<_> = self.targeting_templates.iter().collect();
+        templates.sort_by_key(|(name, _)| name.as_str());
+        for (name, targeting) in templates {
+            errors.extend(targeting.errors(&format!("targeting_templates[{}]", name)));
+        }
+
+        let mut templates: Vec<_> = self.templates.iter().collect();
+        templates.sort_by_key(|(name, _)| name.as_str());
+        for (name, template) in templates {
+            if !template.is_mapping() {
+                errors.push(ConfigError::new(
+                    format!("templates[{}]", name),
+                    "must be a mapping of experiment fields",
+                ));
+            } else if template.get("extends").is_some() {
+                errors.push(ConfigError::new(
+                    format!("templates[{}].extends", name),
+                    "templates cannot extend other templates",
                 ));
             }
         }
 
         // Validate experiments
         let mut ids = std::collections::HashSet::new();
-        for exp in &self.experiments {
+        for (i, exp) in self.experiments.iter().enumerate() {
+            let path = if exp.id.is_empty() {
+                format!("experiments[{}]", i)
+            } else {
+                format!("experiments[{}]", exp.id)
+            };
             if !ids.insert(&exp.id) {
-                return Err(anyhow!("Duplicate experiment id: {}", exp.id));
+                let first = self.experiments.iter().find(|e| e.id == exp.id);
+                let message = match (first.and_then(|e| e.source.as_ref()), &exp.source) {
+                    (Some(a), Some(b)) => format!(
+                        "Duplicate experiment id: {} (in {} and {})",
+                        exp.id,
+                        a.display(),
+                        b.display()
+                    ),
+                    _ => format!("Duplicate experiment id: {}", exp.id),
+                };
+                errors.push(ConfigError::new(&path, message));
+                continue;
+            }
+            errors.extend(exp.errors(&path));
+            let max_percent = self.safety.max_affected_percent;
+            if let Some(n) = exp.targeting.every_n.filter(|n| *n > 0) {
+                if n.saturating_mul(u64::from(max_percent)) < 100 {
+                    errors.push(ConfigError::new(
+                        format!("{}.targeting.every_n", path),
+                        format!(
+                            "faults 1 in {} requests, more than safety.max_affected_percent ({}%) allows",
+                            n, max_percent
+                        ),
+                    ));
+                }
+            }
+            if let Some(name) = &exp.extends {
+                if !self.templates.contains_key(name) {
+                    errors.push(ConfigError::new(
+                        format!("{}.extends", path),
+                        format!("unknown template '{}'", name),
+                    ));
+                }
+            }
+            if let Some(name) = &exp.targeting_ref {
+                if !self.targeting_templates.contains_key(name) {
+                    errors.push(ConfigError::new(
+                        format!("{}.targeting_ref", path),
+                        format!("unknown targeting template '{}'", name),
+                    ));
+                }
+            }
+            if let Some(delay_ms) = exp.fault.max_delay_ms() {
+                if delay_ms > self.safety.max_latency_ms {
+                    errors.push(ConfigError::new(
+                        format!("{}.fault", path),
+                        format!(
+                            "delays up to {}ms, above max_latency_ms ({}ms)",
+                            delay_ms, self.safety.max_latency_ms
+                        ),
+                    ));
+                }
             }
-            exp.validate()?;
         }
 
-        Ok(())
+        errors
+    }
+}
+
+fn canonical(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))
+}
+
+/// Load experiments from `includes` (relative to `parent`), recursively.
+///
+/// `stack` holds the files currently being loaded, to detect cycles, and
+/// every file read is added to `files`.
+fn load_includes(
+    parent: &Path,
+    includes: &[PathBuf],
+    templates: &Templates,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Vec<Experiment>> {
+    let base = parent.parent().unwrap_or_else(|| Path::new(""));
+    let mut experiments = Vec::new();
+    for include in includes {
+        experiments.extend(load_experiment_file(
+            &base.join(include),
+            templates,
+            stack,
+            files,
+        )?);
+    }
+    Ok(experiments)
+}
+
+/// Load experiments from every configuration file in `dir`, in file name
+/// order. Files with other extensions are ignored.
+fn load_experiments_dir(
+    dir: &Path,
+    templates: &Templates,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Vec<Experiment>> {
+    let mut paths = experiment_files(dir)?;
+    paths.sort();
+    let mut experiments = Vec::new();
+    for path in paths {
+        experiments.extend(load_experiment_file(&path, templates, stack, files)?);
+    }
+    Ok(experiments)
+}
+
+/// List the configuration files directly inside `dir`.
+pub fn experiment_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("Cannot read experiments_dir {}: {}", dir.display(), e))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() && ConfigFormat::from_path(&path).is_ok() {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Load one included experiment file and, recursively, its includes.
+fn load_experiment_file(
+    path: &Path,
+    templates: &Templates,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Vec<Experiment>> {
+    let canonical = canonical(path)?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(anyhow!("Include cycle: {}", chain.join(" -> ")));
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    files.push(path.to_path_buf());
+    let content = interpolate_env(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    let file: IncludeFile = ConfigFormat::from_path(path)
+        .and_then(|format| parse_document(&content, format, Some(templates)))
+        .map_err(|e| anyhow!("Invalid include {}: {}", path.display(), e))?;
+    let mut experiments: Vec<Experiment> = file
+        .experiments
+        .into_iter()
+        .map(|mut exp| {
+            exp.source = Some(path.to_path_buf());
+            exp
+        })
+        .collect();
+
+    stack.push(canonical);
+    experiments.extend(load_includes(
+        path,
+        &file.includes,
+        templates,
+        stack,
+        files,
+    )?);
+    stack.pop();
+    Ok(experiments)
+}
+
+/// Latency and jitter faults delaying longer than this are reported by
+/// [`Config::warnings`].
+const LONG_DELAY_WARNING_MS: u64 = 10_000;
+
+/// Best-effort check for a regex anchored at the start to a literal
+/// character other than `/`, e.g. `^api/`, which no request path matches.
+fn regex_misses_leading_slash(regex: &str) -> bool {
+    regex
+        .strip_prefix('^')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Templates experiments can reference, taken from the main file.
+#[derive(Debug, Default)]
+struct Templates {
+    /// `targeting_templates`, for `targeting_ref`.
+    targeting: serde_yaml::Value,
+    /// `templates`, for `extends`.
+    experiments: serde_yaml::Value,
+}
+
+impl Templates {
+    /// Take the templates defined in a parsed document.
+    fn of(document: &serde_yaml::Value) -> Self {
+        Self {
+            targeting: document
+                .get("targeting_templates")
+                .cloned()
+                .unwrap_or_default(),
+            experiments: document.get("templates").cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Parse a configuration document, expanding `extends` and
+/// `targeting_ref` against `templates`, or the document's own templates
+/// when `None`.
+///
+/// Documents without references are parsed directly so parse errors keep
+/// their line numbers.
+fn parse_document<T: DeserializeOwned>(
+    content: &str,
+    format: ConfigFormat,
+    templates: Option<&Templates>,
+) -> Result<T> {
+    let direct = !content.contains("targeting_ref") && !content.contains("extends");
+    let mut value: serde_yaml::Value = match format {
+        ConfigFormat::Yaml if direct => return Ok(serde_yaml::from_str(content)?),
+        ConfigFormat::Json if direct => return Ok(serde_json::from_str(content)?),
+        ConfigFormat::Toml if direct => return Ok(toml::from_str(content)?),
+        ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+        ConfigFormat::Json => serde_json::from_str(content)?,
+        ConfigFormat::Toml => toml::from_str(content)?,
+    };
+    match templates {
+        Some(templates) => resolve_references(&mut value, templates),
+        None => {
+            let templates = Templates::of(&value);
+            resolve_references(&mut value, &templates);
+        }
+    }
+    Ok(serde_yaml::from_value(value)?)
+}
+
+/// Expand each experiment's `extends` and then its `targeting_ref`.
+///
+/// Unknown references are left for validation to report.
+fn resolve_references(document: &mut serde_yaml::Value, templates: &Templates) {
+    let Some(serde_yaml::Value::Sequence(experiments)) = document.get_mut("experiments") else {
+        return;
+    };
+    for experiment in experiments {
+        apply_extends(experiment, &templates.experiments);
+        apply_targeting_ref(experiment, &templates.targeting);
+    }
+}
+
+/// Deep-merge the experiment over the template it extends, so the
+/// experiment's own values win.
+fn apply_extends(experiment: &mut serde_yaml::Value, templates: &serde_yaml::Value) {
+    let Some(name) = experiment.get("extends").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let Some(template @ serde_yaml::Value::Mapping(_)) = templates.get(name) else {
+        return;
+    };
+    let mut merged = template.clone();
+    deep_merge(&mut merged, experiment);
+    *experiment = merged;
+}
+
+/// Merge `overlay` into `base`, recursing into mappings present in both.
+fn deep_merge(base: &mut serde_yaml::Value, overlay: &serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// Replace the experiment's targeting with its referenced template, with
+/// the experiment's inline targeting fields taking precedence.
+///
+/// Unknown references get the inline targeting alone.
+fn apply_targeting_ref(experiment: &mut serde_yaml::Value, templates: &serde_yaml::Value) {
+    let Some(name) = experiment.get("targeting_ref").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let mut targeting = match templates.get(name) {
+        Some(serde_yaml::Value::Mapping(template)) => template.clone(),
+        _ => serde_yaml::Mapping::new(),
+    };
+    if let Some(serde_yaml::Value::Mapping(inline)) = experiment.get("targeting") {
+        for (key, value) in inline {
+            targeting.insert(key.clone(), value.clone());
+        }
     }
+    experiment["targeting"] = serde_yaml::Value::Mapping(targeting);
 }
 
 /// Global settings.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
 pub struct Settings {
     /// Global kill switch.
@@ -72,6 +746,76 @@ pub struct Settings {
     pub dry_run: bool,
     /// Log when faults are injected.
     pub log_injections: bool,
+    /// Honor the `x-chaos-force` header naming an experiment to fire.
+    pub allow_force_header: bool,
+    /// How request paths are normalized before exclusion and targeting.
+    pub path_normalization: PathNormalization,
+    /// Number of recent injections kept for inspection.
+    pub recent_injections: usize,
+    /// Multiplier applied to every experiment's percentage, e.g. `0.5` to
+    /// run everything at half intensity.
+    pub intensity: f64,
+    /// Time experiment matching per request and report it as the
+    /// `chaos_match_eval_nanos` histogram.
+    pub profile_matching: bool,
+    /// Header in which the proxy forwards the TLS server name, matched by
+    /// `targeting.sni`.
+    pub sni_header: String,
+    /// Header in which the proxy forwards the negotiated ALPN protocol,
+    /// matched by `targeting.alpn`.
+    pub alpn_header: String,
+    /// Inject a single fault, then pass every request through until the
+    /// process restarts or the configuration is reloaded.
+    pub once: bool,
+    /// What to do with a request when the agent fails internally, e.g. a
+    /// fault panics.
+    pub fail_mode: FailMode,
+    /// Seed for every random decision, making runs reproducible: the same
+    /// seed and request sequence fault the same requests the same way.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Keep experiments added through the admin API across configuration
+    /// reloads instead of dropping them.
+    pub persist_runtime: bool,
+    /// Lift a drain requested by the proxy on its own, this many
+    /// milliseconds after the drain's duration has passed. Unset, a drain
+    /// lasts until lifted through the admin API.
+    #[serde(
+        default,
+        alias = "drain_auto_resume_after",
+        deserialize_with = "deserialize_optional_duration_ms",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(schema_with = "crate::schema::optional_duration_ms")]
+    pub drain_auto_resume_after_ms: Option<u64>,
+    /// Whether metrics reports carry running totals or counts since the
+    /// previous report.
+    pub metrics_mode: MetricsMode,
+}
+
+/// How counters are reported to the proxy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsMode {
+    /// Running totals since the agent started.
+    #[default]
+    Cumulative,
+    /// Counts since the previous report, for collectors that expect
+    /// deltas. Gauges are reported as they are.
+    Delta,
+}
+
+/// Handling of requests hit by an internal error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailMode {
+    /// Allow the request, so a chaos bug never blocks real traffic.
+    #[default]
+    #[serde(alias = "fail_open")]
+    Open,
+    /// Fail the request with a 500, surfacing the error.
+    #[serde(alias = "fail_closed")]
+    Closed,
 }
 
 impl Default for Settings {
@@ -80,22 +824,101 @@ impl Default for Settings {
             enabled: true,
             dry_run: false,
             log_injections: true,
+            allow_force_header: false,
+            path_normalization: PathNormalization::default(),
+            recent_injections: 100,
+            intensity: 1.0,
+            profile_matching: false,
+            sni_header: "x-sni".to_string(),
+            alpn_header: "x-alpn".to_string(),
+            once: false,
+            fail_mode: FailMode::Open,
+            seed: None,
+            persist_runtime: false,
+            drain_auto_resume_after_ms: None,
+            metrics_mode: MetricsMode::Cumulative,
         }
     }
 }
 
+/// Path normalization applied before exclusion and targeting.
+///
+/// The default matches paths exactly as they arrive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct PathNormalization {
+    /// Compare paths and path matchers case-insensitively.
+    pub case_insensitive: bool,
+    /// Treat `/api/users/` the same as `/api/users`.
+    pub strip_trailing_slash: bool,
+}
+
 /// Safety configuration.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
 pub struct SafetyConfig {
     /// Maximum percentage of traffic that can be affected.
     pub max_affected_percent: u8,
     /// Schedule windows when chaos is active.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_schedules")]
     pub schedule: Vec<Schedule>,
     /// Paths that are never affected by chaos.
     #[serde(default)]
     pub excluded_paths: Vec<String>,
+    /// HTTP methods that are never affected by chaos (case-insensitive).
+    #[serde(default)]
+    pub excluded_methods: Vec<String>,
+    /// Headers whose presence exempts a request from chaos. A value must
+    /// match exactly; a null value matches any value.
+    #[serde(default)]
+    pub excluded_headers: HashMap<String, Option<String>>,
+    /// Calendar dates on which chaos is suspended.
+    #[serde(default)]
+    pub blackout_dates: Vec<DateSpec>,
+    /// If non-empty, chaos is only active on these calendar dates.
+    #[serde(default)]
+    pub only_dates: Vec<DateSpec>,
+    /// Header that lets authorized requests skip chaos.
+    #[serde(default)]
+    pub bypass: Option<BypassConfig>,
+    /// Maximum number of latency/timeout faults holding requests at once.
+    #[serde(default, alias = "max_concurrent_delays")]
+    pub max_concurrent_faults: Option<usize>,
+    /// Ceiling on any injected delay, in milliseconds.
+    #[serde(
+        default = "default_max_latency_ms",
+        alias = "max_latency",
+        deserialize_with = "deserialize_duration_ms"
+    )]
+    #[schemars(schema_with = "crate::schema::duration_ms")]
+    pub max_latency_ms: u64,
+    /// Cap on distinct clients receiving faults per window.
+    #[serde(default)]
+    pub max_affected_clients: Option<MaxAffectedClients>,
+    /// Stop injecting automatically when the upstream error rate rises.
+    #[serde(default)]
+    pub auto_abort: Option<AutoAbortConfig>,
+    /// Suppress all injections while this file exists.
+    #[serde(default)]
+    pub kill_switch_file: Option<PathBuf>,
+    /// Timezone for date rules when no schedule window applies.
+    #[serde(
+        default = "default_timezone",
+        deserialize_with = "deserialize_timezone",
+        serialize_with = "serialize_timezone"
+    )]
+    #[schemars(schema_with = "crate::schema::timezone")]
+    pub timezone: Tz,
+}
+
+impl SafetyConfig {
+    /// Check whether chaos may run on the given local date.
+    pub fn is_date_allowed(&self, date: NaiveDate) -> bool {
+        if self.blackout_dates.iter().any(|spec| spec.contains(date)) {
+            return false;
+        }
+        self.only_dates.is_empty() || self.only_dates.iter().any(|spec| spec.contains(date))
+    }
 }
 
 impl Default for SafetyConfig {
@@ -108,396 +931,3302 @@ impl Default for SafetyConfig {
                 "/ready".to_string(),
                 "/metrics".to_string(),
             ],
+            excluded_methods: Vec::new(),
+            excluded_headers: HashMap::new(),
+            blackout_dates: Vec::new(),
+            only_dates: Vec::new(),
+            bypass: None,
+            max_concurrent_faults: None,
+            max_latency_ms: default_max_latency_ms(),
+            max_affected_clients: None,
+            auto_abort: None,
+            kill_switch_file: None,
+            timezone: default_timezone(),
         }
     }
 }
 
-/// Schedule window when chaos is active.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Schedule {
-    /// Days of the week.
-    #[serde(deserialize_with = "deserialize_weekdays")]
-    pub days: Vec<Weekday>,
-    /// Start time (HH:MM format).
-    #[serde(deserialize_with = "deserialize_time")]
-    pub start: NaiveTime,
-    /// End time (HH:MM format).
-    #[serde(deserialize_with = "deserialize_time")]
-    pub end: NaiveTime,
-    /// Timezone (e.g., "UTC", "America/New_York").
-    #[serde(default = "default_timezone")]
-    pub timezone: String,
+fn default_max_latency_ms() -> u64 {
+    60_000
 }
 
-fn default_timezone() -> String {
-    "UTC".to_string()
+/// Blast-radius limit by unique clients.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct MaxAffectedClients {
+    /// Maximum distinct clients receiving faults per window.
+    pub count: usize,
+    /// Window length in seconds.
+    #[serde(default = "default_client_window_s")]
+    pub window_s: u64,
+    /// Header identifying the client.
+    #[serde(default = "default_client_key_header")]
+    pub key_header: String,
 }
 
-fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: String = Deserialize::deserialize(deserializer)?;
-    NaiveTime::parse_from_str(&s, "%H:%M").map_err(serde::de::Error::custom)
+fn default_client_window_s() -> u64 {
+    3600
 }
 
-fn deserialize_weekdays<'de, D>(deserializer: D) -> Result<Vec<Weekday>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let days: Vec<String> = Deserialize::deserialize(deserializer)?;
-    days.into_iter()
-        .map(|s| {
-            parse_weekday(&s)
-                .ok_or_else(|| serde::de::Error::custom(format!("Invalid weekday: {}", s)))
-        })
-        .collect()
+fn default_client_key_header() -> String {
+    "x-user-id".to_string()
 }
 
-fn parse_weekday(s: &str) -> Option<Weekday> {
-    match s.to_lowercase().as_str() {
-        "mon" | "monday" => Some(Weekday::Mon),
-        "tue" | "tuesday" => Some(Weekday::Tue),
-        "wed" | "wednesday" => Some(Weekday::Wed),
-        "thu" | "thursday" => Some(Weekday::Thu),
-        "fri" | "friday" => Some(Weekday::Fri),
-        "sat" | "saturday" => Some(Weekday::Sat),
-        "sun" | "sunday" => Some(Weekday::Sun),
-        _ => None,
+impl MaxAffectedClients {
+    /// Validate the client cap configuration.
+    pub fn validate(&self) -> Result<()> {
+        if self.count == 0 {
+            return Err(anyhow!("max_affected_clients count must be > 0"));
+        }
+        if self.window_s == 0 {
+            return Err(anyhow!("max_affected_clients window_s must be > 0"));
+        }
+        if self.key_header.is_empty() {
+            return Err(anyhow!("max_affected_clients key_header cannot be empty"));
+        }
+        Ok(())
     }
 }
 
-/// A fault experiment.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct Experiment {
-    /// Unique identifier for the experiment.
-    pub id: String,
-    /// Whether the experiment is enabled.
-    #[serde(default = "default_true")]
-    pub enabled: bool,
-    /// Human-readable description.
+/// Admin API access control.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// Token required as `Authorization: Bearer <token>`. Without one the
+    /// admin API refuses every change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+    /// Serve read-only endpoints without the token.
+    pub allow_unauthenticated_reads: bool,
+}
+
+impl AdminConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Bypass header configuration.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct BypassConfig {
+    /// Header carrying the bypass token.
+    #[serde(default = "default_bypass_header")]
+    pub header: String,
+    /// Shared secret: the HMAC key, or the token itself in static mode.
+    pub secret: String,
+    /// How the header value is verified.
     #[serde(default)]
-    pub description: String,
-    /// Targeting rules.
-    pub targeting: Targeting,
-    /// Fault to inject.
-    pub fault: Fault,
+    pub mode: BypassMode,
 }
 
-fn default_true() -> bool {
-    true
+fn default_bypass_header() -> String {
+    "x-chaos-bypass".to_string()
 }
 
-impl Experiment {
-    /// Validate the experiment configuration.
+impl BypassConfig {
+    /// Validate the bypass configuration.
     pub fn validate(&self) -> Result<()> {
-        if self.id.is_empty() {
-            return Err(anyhow!("Experiment id cannot be empty"));
+        if self.header.is_empty() {
+            return Err(anyhow!("bypass header cannot be empty"));
+        }
+        if self.secret.is_empty() {
+            return Err(anyhow!("bypass secret cannot be empty"));
         }
-
-        self.targeting.validate()?;
-        self.fault.validate()?;
-
         Ok(())
     }
 }
 
-/// Targeting rules for an experiment.
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
-#[serde(default)]
-pub struct Targeting {
-    /// Path matchers.
-    #[serde(default)]
-    pub paths: Vec<PathMatcher>,
-    /// HTTP methods to match.
-    #[serde(default)]
-    pub methods: Vec<String>,
-    /// Headers that must be present with specific values.
+/// Bypass token verification mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BypassMode {
+    /// Header value is the hex HMAC-SHA256 of the request path.
+    #[default]
+    Hmac,
+    /// Header value is the secret itself.
+    Static,
+}
+
+/// Automatic abort on upstream degradation.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct AutoAbortConfig {
+    /// Share of upstream responses with a 5xx status (0.0-1.0) that trips
+    /// the abort.
+    pub error_rate_threshold: f64,
+    /// Sliding window over which the error rate is computed, in seconds.
+    #[serde(default = "default_abort_window_s")]
+    pub window_s: u64,
+    /// Optional `http://` URL polled in the background as an extra signal.
     #[serde(default)]
-    pub headers: HashMap<String, String>,
-    /// Percentage of matching requests to affect (0-100).
-    #[serde(default = "default_percentage")]
-    pub percentage: u8,
+    pub probe_url: Option<String>,
+    /// Interval between probes, in seconds.
+    #[serde(default = "default_probe_interval_s")]
+    pub probe_interval_s: u64,
+    /// How long chaos stays aborted before resuming, in seconds.
+    #[serde(default = "default_abort_cooldown_s")]
+    pub cooldown_s: u64,
+    /// Minimum samples in the window before the rate is trusted.
+    #[serde(default = "default_abort_min_requests")]
+    pub min_requests: u64,
 }
 
-fn default_percentage() -> u8 {
-    100
+fn default_abort_window_s() -> u64 {
+    60
 }
 
-impl Targeting {
-    /// Validate the targeting configuration.
+fn default_probe_interval_s() -> u64 {
+    10
+}
+
+fn default_abort_cooldown_s() -> u64 {
+    300
+}
+
+fn default_abort_min_requests() -> u64 {
+    20
+}
+
+impl AutoAbortConfig {
+    /// Validate the auto-abort configuration.
     pub fn validate(&self) -> Result<()> {
-        if self.percentage > 100 {
+        if self.error_rate_threshold <= 0.0 || self.error_rate_threshold > 1.0 {
             return Err(anyhow!(
-                "Targeting percentage must be between 0 and 100, got {}",
-                self.percentage
+                "auto_abort error_rate_threshold must be in (0.0, 1.0], got {}",
+                self.error_rate_threshold
             ));
         }
-
-        for path in &self.paths {
-            path.validate()?;
+        if self.window_s == 0 {
+            return Err(anyhow!("auto_abort window_s must be > 0"));
+        }
+        if self.probe_interval_s == 0 {
+            return Err(anyhow!("auto_abort probe_interval_s must be > 0"));
+        }
+        if let Some(url) = &self.probe_url {
+            crate::probe::ProbeUrl::parse(url).map_err(|e| anyhow!("auto_abort {}", e))?;
         }
-
         Ok(())
     }
 }
 
-/// Path matching rule.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// A single calendar date or an inclusive date range.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
-pub enum PathMatcher {
-    /// Exact path match.
-    Exact { exact: String },
-    /// Path prefix match.
-    Prefix { prefix: String },
-    /// Regex pattern match.
-    Regex { regex: String },
+pub enum DateSpec {
+    /// A single date (YYYY-MM-DD).
+    Single(NaiveDate),
+    /// An inclusive range of dates.
+    Range { from: NaiveDate, to: NaiveDate },
 }
 
-impl PathMatcher {
-    /// Validate the path matcher.
+impl DateSpec {
+    /// Validate the date specification.
     pub fn validate(&self) -> Result<()> {
-        if let PathMatcher::Regex { regex: pattern } = self {
-            regex::Regex::new(pattern)
-                .map_err(|e| anyhow!("Invalid regex pattern '{}': {}", pattern, e))?;
+        if let DateSpec::Range { from, to } = self {
+            if from > to {
+                return Err(anyhow!(
+                    "Date range start ({}) must not be after end ({})",
+                    from,
+                    to
+                ));
+            }
         }
         Ok(())
     }
 
-    /// Get the path value for matching.
-    pub fn value(&self) -> &str {
+    /// Check whether the date falls within this specification.
+    pub fn contains(&self, date: NaiveDate) -> bool {
         match self {
-            PathMatcher::Exact { exact } => exact,
-            PathMatcher::Prefix { prefix } => prefix,
-            PathMatcher::Regex { regex } => regex,
+            DateSpec::Single(d) => *d == date,
+            DateSpec::Range { from, to } => *from <= date && date <= *to,
         }
     }
 }
 
-/// Fault types that can be injected.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum Fault {
-    /// Add latency before proxying.
-    Latency {
-        /// Fixed delay in milliseconds.
-        #[serde(default)]
-        fixed_ms: u64,
-        /// Minimum delay for random range.
-        #[serde(default)]
-        min_ms: u64,
-        /// Maximum delay for random range.
-        #[serde(default)]
-        max_ms: u64,
-    },
-    /// Return an HTTP error immediately.
-    Error {
-        /// HTTP status code.
-        status: u16,
-        /// Error message body.
-        #[serde(default)]
-        message: Option<String>,
-        /// Additional headers.
-        #[serde(default)]
-        headers: HashMap<String, String>,
-    },
-    /// Simulate an upstream timeout.
-    Timeout {
-        /// Duration to wait before returning 504.
-        duration_ms: u64,
-    },
-    /// Throttle response bandwidth.
-    Throttle {
-        /// Bytes per second.
-        bytes_per_second: u64,
-    },
-    /// Inject garbage into response.
-    Corrupt {
-        /// Probability of corruption (0.0-1.0).
-        probability: f64,
-    },
-    /// Simulate connection reset.
-    Reset,
+/// Schedule window when chaos is active.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct Schedule {
+    /// Days of the week.
+    #[serde(
+        deserialize_with = "deserialize_weekdays",
+        serialize_with = "serialize_weekdays"
+    )]
+    #[schemars(schema_with = "crate::schema::weekdays")]
+    pub days: Vec<Weekday>,
+    /// Start time (HH:MM format).
+    #[serde(
+        deserialize_with = "deserialize_time",
+        serialize_with = "serialize_time"
+    )]
+    #[schemars(schema_with = "crate::schema::time")]
+    pub start: NaiveTime,
+    /// End time (HH:MM format). An end before `start` wraps past midnight.
+    #[serde(
+        deserialize_with = "deserialize_time",
+        serialize_with = "serialize_time"
+    )]
+    #[schemars(schema_with = "crate::schema::time")]
+    pub end: NaiveTime,
+    /// Timezone (e.g., "UTC", "America/New_York").
+    #[serde(
+        default = "default_timezone",
+        deserialize_with = "deserialize_timezone",
+        serialize_with = "serialize_timezone"
+    )]
+    #[schemars(schema_with = "crate::schema::timezone")]
+    pub timezone: Tz,
 }
 
-impl Fault {
-    /// Validate the fault configuration.
+impl Schedule {
+    /// Validate the schedule window.
     pub fn validate(&self) -> Result<()> {
-        match self {
-            Fault::Latency {
-                fixed_ms,
-                min_ms,
-                max_ms,
-            } => {
-                if *fixed_ms == 0 && *min_ms == 0 && *max_ms == 0 {
-                    return Err(anyhow!(
-                        "Latency fault must specify either fixed_ms or min_ms/max_ms"
-                    ));
-                }
-                if *fixed_ms == 0 && *max_ms < *min_ms {
-                    return Err(anyhow!(
-                        "Latency max_ms ({}) must be >= min_ms ({})",
-                        max_ms,
-                        min_ms
-                    ));
-                }
-            }
-            Fault::Error { status, .. } => {
-                if *status < 100 || *status > 599 {
-                    return Err(anyhow!("Invalid HTTP status code: {}", status));
-                }
-            }
-            Fault::Timeout { duration_ms } => {
-                if *duration_ms == 0 {
-                    return Err(anyhow!("Timeout duration_ms must be > 0"));
-                }
+        // start > end is an overnight window that wraps midnight
+        if self.start == self.end {
+            return Err(anyhow!(
+                "Schedule start time ({}) must differ from end time ({})",
+                self.start,
+                self.end
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Schedule as written in config, before timezone resolution.
+#[derive(Deserialize)]
+struct RawSchedule {
+    #[serde(deserialize_with = "deserialize_weekdays")]
+    days: Vec<Weekday>,
+    #[serde(deserialize_with = "deserialize_time")]
+    start: NaiveTime,
+    #[serde(deserialize_with = "deserialize_time")]
+    end: NaiveTime,
+    #[serde(default = "default_timezone_name")]
+    timezone: String,
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+fn default_timezone_name() -> String {
+    "UTC".to_string()
+}
+
+/// Parse an IANA timezone name, suggesting the canonical spelling for
+/// case-variant typos.
+fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse().map_err(|_| {
+        match chrono_tz::TZ_VARIANTS
+            .iter()
+            .find(|tz| tz.name().eq_ignore_ascii_case(name))
+        {
+            Some(tz) => format!(
+                "unknown timezone '{}' (did you mean '{}'?)",
+                name,
+                tz.name()
+            ),
+            None => format!("unknown timezone '{}'", name),
+        }
+    })
+}
+
+fn deserialize_timezone<'de, D>(deserializer: D) -> Result<Tz, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    parse_timezone(&s).map_err(serde::de::Error::custom)
+}
+
+fn serialize_timezone<S>(tz: &Tz, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(tz.name())
+}
+
+/// Deserialize schedule windows, reporting every invalid timezone at once.
+fn deserialize_schedules<'de, D>(deserializer: D) -> Result<Vec<Schedule>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw: Vec<RawSchedule> = Deserialize::deserialize(deserializer)?;
+    let mut errors = Vec::new();
+    let mut schedules = Vec::with_capacity(raw.len());
+
+    for window in raw {
+        match parse_timezone(&window.timezone) {
+            Ok(timezone) => schedules.push(Schedule {
+                days: window.days,
+                start: window.start,
+                end: window.end,
+                timezone,
+            }),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(serde::de::Error::custom(format!(
+            "invalid schedule timezones: {}",
+            errors.join("; ")
+        )));
+    }
+    Ok(schedules)
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M").map_err(serde::de::Error::custom)
+}
+
+fn serialize_time<S>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&time.format("%H:%M"))
+}
+
+fn deserialize_weekdays<'de, D>(deserializer: D) -> Result<Vec<Weekday>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let days: Vec<String> = Deserialize::deserialize(deserializer)?;
+    days.into_iter()
+        .map(|s| {
+            parse_weekday(&s)
+                .ok_or_else(|| serde::de::Error::custom(format!("Invalid weekday: {}", s)))
+        })
+        .collect()
+}
+
+fn serialize_weekdays<S>(days: &[Weekday], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(days.iter().map(|day| day.to_string().to_lowercase()))
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    match s.to_lowercase().as_str() {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Parse a duration such as `500ms`, `30s`, `2.5s`, `5m` or `1h` into
+/// milliseconds. A bare number is taken as milliseconds.
+pub fn parse_duration_ms(s: &str) -> Result<u64, String> {
+    let trimmed = s.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let scale = match unit.trim() {
+        "" | "ms" => 1.0,
+        "s" => 1_000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        _ => return Err(format!("invalid duration '{}': unknown unit", s)),
+    };
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}': expected e.g. 500ms or 2.5s", s))?;
+    let ms = (value * scale).round();
+    if !ms.is_finite() || ms > u64::MAX as f64 {
+        return Err(format!("invalid duration '{}': too large", s));
+    }
+    Ok(ms as u64)
+}
+
+/// Deserialize milliseconds from an integer or a duration string.
+fn deserialize_duration_ms<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DurationMs {
+        Millis(u64),
+        Text(String),
+    }
+
+    match DurationMs::deserialize(deserializer)? {
+        DurationMs::Millis(ms) => Ok(ms),
+        DurationMs::Text(s) => parse_duration_ms(&s).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Deserialize optional milliseconds, see [`deserialize_duration_ms`].
+fn deserialize_optional_duration_ms<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Millis(#[serde(deserialize_with = "deserialize_duration_ms")] u64);
+
+    Ok(Option::<Millis>::deserialize(deserializer)?.map(|Millis(ms)| ms))
+}
+
+/// Deserialize a weighted status pool from either a `{status: weight}` map
+/// or a list of `[status, weight]` pairs.
+fn deserialize_status_pool<'de, D>(deserializer: D) -> Result<Vec<(u16, u32)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StatusPool {
+        Map(std::collections::BTreeMap<u16, u32>),
+        List(Vec<(u16, u32)>),
+    }
+
+    Ok(match StatusPool::deserialize(deserializer)? {
+        StatusPool::Map(map) => map.into_iter().collect(),
+        StatusPool::List(list) => list,
+    })
+}
+
+/// A fault experiment.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct Experiment {
+    /// Unique identifier for the experiment.
+    pub id: String,
+    /// Whether the experiment is enabled.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Human-readable description.
+    #[serde(default)]
+    pub description: String,
+    /// Targeting rules. With `targeting_ref`, only the fields that override
+    /// the template.
+    pub targeting: Targeting,
+    /// Name of a `templates` entry this experiment is merged over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Name of a `targeting_templates` entry to start the targeting from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub targeting_ref: Option<String>,
+    /// Fault to inject.
+    pub fault: Fault,
+    /// Stop injecting permanently after this many faults.
+    #[serde(default)]
+    pub max_injections: Option<u64>,
+    /// Windows when this experiment is active, overriding the global
+    /// schedule. Empty means the global schedule applies.
+    #[serde(default, deserialize_with = "deserialize_schedules")]
+    pub schedule: Vec<Schedule>,
+    /// Steady-state probe that must pass before this experiment injects.
+    #[serde(default)]
+    pub precheck: Option<PrecheckConfig>,
+    /// Taper the percentage linearly to 0 over the last this many seconds
+    /// of a time-limited run (`--max-duration`), to observe recovery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ramp_down_seconds: Option<u64>,
+    /// File the experiment was loaded from, for error messages.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Experiment {
+    /// Read the file of a return-fixture fault, relative to the file the
+    /// experiment was loaded from, or to the working directory.
+    ///
+    /// Like a precheck, the fixture of a disabled experiment is left unread,
+    /// so the experiment can only be enabled in the configuration.
+    pub fn load_fixture(&mut self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let base = self
+            .source
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new(""));
+        self.fault
+            .load_fixture(base)
+            .map_err(|e| ConfigError::new(format!("experiments[{}].fault.file", self.id), e).into())
+    }
+
+    /// Reject a return-fixture fault in an experiment that did not come
+    /// from a configuration file.
+    fn refuse_fixture(&self) -> Result<()> {
+        if matches!(self.fault, Fault::ReturnFixture { .. }) {
+            return Err(ConfigError::new(
+                format!("experiments[{}].fault.file", self.id),
+                "fixtures can only be read from a configuration file",
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Validate the experiment configuration.
+    pub fn validate(&self) -> Result<()> {
+        into_result(self.errors(&format!("experiments[{}]", self.id)))
+    }
+
+    /// Collect validation errors, with field paths under `path`.
+    pub fn errors(&self, path: &str) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.id.is_empty() {
+            errors.push(ConfigError::new(format!("{}.id", path), "cannot be empty"));
+        }
+
+        if self.max_injections == Some(0) {
+            errors.push(ConfigError::new(
+                format!("{}.max_injections", path),
+                "must be > 0",
+            ));
+        }
+
+        if self.ramp_down_seconds == Some(0) {
+            errors.push(ConfigError::new(
+                format!("{}.ramp_down_seconds", path),
+                "must be > 0",
+            ));
+        }
+
+        for (i, schedule) in self.schedule.iter().enumerate() {
+            if let Err(e) = schedule.validate() {
+                errors.push(ConfigError::new(format!("{}.schedule[{}]", path, i), e));
             }
-            Fault::Throttle { bytes_per_second } => {
-                if *bytes_per_second == 0 {
-                    return Err(anyhow!("Throttle bytes_per_second must be > 0"));
-                }
+        }
+
+        if let Some(Err(e)) = self.precheck.as_ref().map(|p| p.validate()) {
+            errors.push(ConfigError::new(format!("{}.precheck", path), e));
+        }
+
+        errors.extend(self.targeting.errors(&format!("{}.targeting", path)));
+        errors.extend(self.fault.errors(&format!("{}.fault", path)));
+
+        errors
+    }
+}
+
+/// Steady-state probe gating an experiment.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct PrecheckConfig {
+    /// `http://` URL polled in the background.
+    pub url: String,
+    /// Status the probe must return for the experiment to inject.
+    #[serde(default = "default_expect_status")]
+    pub expect_status: u16,
+    /// Interval between probes, in seconds.
+    #[serde(default = "default_probe_interval_s")]
+    pub interval_seconds: u64,
+}
+
+fn default_expect_status() -> u16 {
+    200
+}
+
+impl PrecheckConfig {
+    /// Validate the precheck configuration.
+    pub fn validate(&self) -> Result<()> {
+        if !(100..=599).contains(&self.expect_status) {
+            return Err(anyhow!(
+                "precheck expect_status must be 100-599, got {}",
+                self.expect_status
+            ));
+        }
+        if self.interval_seconds == 0 {
+            return Err(anyhow!("precheck interval_seconds must be > 0"));
+        }
+        crate::probe::ProbeUrl::parse(&self.url).map_err(|e| anyhow!("precheck {}", e))?;
+        Ok(())
+    }
+}
+
+/// Targeting rules for an experiment.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize, Default)]
+#[serde(default)]
+pub struct Targeting {
+    /// Path matchers.
+    #[serde(default)]
+    pub paths: Vec<PathMatcher>,
+    /// HTTP methods to match.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Headers that must be present with specific values.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Headers that must not be present.
+    #[serde(default)]
+    pub headers_absent: Vec<String>,
+    /// Percentage of matching requests to affect (0-100).
+    #[serde(default = "default_percentage")]
+    pub percentage: u8,
+    /// Fault exactly every Nth matching request instead of sampling by
+    /// `percentage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_n: Option<u64>,
+    /// Skip clients that received a fault from this experiment recently.
+    #[serde(default)]
+    pub client_cooldown: Option<ClientCooldownConfig>,
+    /// TLS server name to match, read from `settings.sni_header`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    /// Negotiated ALPN protocol to match (e.g. `h2`), read from
+    /// `settings.alpn_header`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpn: Option<String>,
+    /// HTTP versions to match, e.g. `["HTTP/1.1"]`. Requests whose version
+    /// the proxy does not report match any version.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub http_versions: Vec<String>,
+    /// Smallest `Content-Length` to match, inclusive. Requests without a
+    /// `Content-Length` never match a size bound.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_content_length: Option<u64>,
+    /// Largest `Content-Length` to match, inclusive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_content_length: Option<u64>,
+    /// Days of the week to fault requests on, in `safety.timezone`, for a
+    /// coarse constraint without a schedule window. Both this and the
+    /// experiment's schedule must allow a request.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_weekdays",
+        serialize_with = "serialize_weekdays",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(schema_with = "crate::schema::weekdays")]
+    pub days: Vec<Weekday>,
+}
+
+/// HTTP versions accepted by `targeting.http_versions`.
+pub const HTTP_VERSIONS: &[&str] = &["HTTP/1.0", "HTTP/1.1", "HTTP/2", "HTTP/3"];
+
+/// Canonical form of an HTTP version, e.g. `HTTP/2` for `http/2.0`.
+pub fn normalize_http_version(version: &str) -> Option<&'static str> {
+    let version = version.trim().to_ascii_uppercase();
+    let version = match version.as_str() {
+        "HTTP/2.0" => "HTTP/2",
+        "HTTP/3.0" => "HTTP/3",
+        other => other,
+    };
+    HTTP_VERSIONS.iter().copied().find(|v| *v == version)
+}
+
+/// Per-client cooldown between injections from one experiment.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct ClientCooldownConfig {
+    /// Seconds a client is skipped after receiving a fault.
+    #[serde(default = "default_cooldown_seconds")]
+    pub seconds: u64,
+    /// Header identifying the client.
+    #[serde(default = "default_client_key_header")]
+    pub key_header: String,
+    /// Maximum clients tracked at once.
+    #[serde(default = "default_cooldown_max_clients")]
+    pub max_clients: usize,
+}
+
+fn default_cooldown_seconds() -> u64 {
+    300
+}
+
+fn default_cooldown_max_clients() -> usize {
+    100_000
+}
+
+impl ClientCooldownConfig {
+    /// Validate the cooldown configuration.
+    pub fn validate(&self) -> Result<()> {
+        if self.seconds == 0 {
+            return Err(anyhow!("client_cooldown seconds must be > 0"));
+        }
+        if self.key_header.is_empty() {
+            return Err(anyhow!("client_cooldown key_header cannot be empty"));
+        }
+        if self.max_clients == 0 {
+            return Err(anyhow!("client_cooldown max_clients must be > 0"));
+        }
+        Ok(())
+    }
+}
+
+fn default_percentage() -> u8 {
+    100
+}
+
+impl Targeting {
+    /// Validate the targeting configuration.
+    pub fn validate(&self) -> Result<()> {
+        into_result(self.errors("targeting"))
+    }
+
+    /// Collect validation errors, with field paths under `path`.
+    pub fn errors(&self, path: &str) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.percentage > 100 {
+            errors.push(ConfigError::new(
+                format!("{}.percentage", path),
+                format!("must be between 0 and 100, got {}", self.percentage),
+            ));
+        }
+
+        if self.every_n == Some(0) {
+            errors.push(ConfigError::new(
+                format!("{}.every_n", path),
+                "must be at least 1",
+            ));
+        }
+
+        for (i, matcher) in self.paths.iter().enumerate() {
+            if let Err(e) = matcher.validate() {
+                errors.push(ConfigError::new(format!("{}.paths[{}]", path, i), e));
             }
-            Fault::Corrupt { probability } => {
-                if *probability < 0.0 || *probability > 1.0 {
-                    return Err(anyhow!(
-                        "Corrupt probability must be between 0.0 and 1.0, got {}",
-                        probability
-                    ));
+        }
+
+        let weighted = self.paths.iter().filter(|m| m.weight().is_some()).count();
+        if weighted > 0 && weighted < self.paths.len() {
+            errors.push(ConfigError::new(
+                format!("{}.paths", path),
+                "set a weight on every path or on none",
+            ));
+        }
+
+        for name in &self.headers_absent {
+            if self.headers.keys().any(|h| h.eq_ignore_ascii_case(name)) {
+                errors.push(ConfigError::new(
+                    format!("{}.headers_absent", path),
+                    format!("header '{}' cannot be both required and absent", name),
+                ));
+            }
+        }
+
+        if let Some(Err(e)) = self.client_cooldown.as_ref().map(|c| c.validate()) {
+            errors.push(ConfigError::new(format!("{}.client_cooldown", path), e));
+        }
+
+        for version in &self.http_versions {
+            if normalize_http_version(version).is_none() {
+                errors.push(ConfigError::new(
+                    format!("{}.http_versions", path),
+                    format!(
+                        "unknown HTTP version '{}' (expected one of: {})",
+                        version,
+                        HTTP_VERSIONS.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_content_length, self.max_content_length) {
+            if min > max {
+                errors.push(ConfigError::new(
+                    format!("{}.min_content_length", path),
+                    format!("{} is greater than max_content_length {}", min, max),
+                ));
+            }
+        }
+
+        errors
+    }
+}
+
+/// Path matching rule.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum PathMatcher {
+    /// Another matcher with a relative weight, e.g.
+    /// `{ prefix: "/api/a", weight: 50 }`. Listed first so the weight is
+    /// not silently dropped by the plain forms.
+    #[serde(serialize_with = "serialize_weighted_path")]
+    #[schemars(schema_with = "crate::schema::weighted_path")]
+    Weighted {
+        /// The matcher being weighted.
+        #[serde(flatten)]
+        matcher: Box<PathMatcher>,
+        /// Share of the experiment's percentage, relative to the heaviest
+        /// path of the experiment.
+        weight: u32,
+    },
+    /// Exact path match.
+    Exact { exact: String },
+    /// Path prefix match.
+    Prefix { prefix: String },
+    /// Regex pattern match.
+    Regex { regex: String },
+    /// Segment glob: `*` matches one segment, `**` any number.
+    Glob { glob: String },
+}
+
+impl PathMatcher {
+    /// Validate the path matcher.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            PathMatcher::Regex { regex: pattern } => {
+                regex::Regex::new(pattern)
+                    .map_err(|e| anyhow!("Invalid regex pattern '{}': {}", pattern, e))?;
+            }
+            PathMatcher::Glob { glob } => {
+                PathGlob::parse(glob, false)?;
+            }
+            PathMatcher::Weighted { matcher, weight } => {
+                if *weight == 0 {
+                    return Err(anyhow!("weight must be > 0"));
+                }
+                if matches!(**matcher, PathMatcher::Weighted { .. }) {
+                    return Err(anyhow!("weighted matchers cannot be nested"));
                 }
+                matcher.validate()?;
             }
-            Fault::Reset => {}
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Config key of the matcher form, e.g. `prefix`.
+    fn key(&self) -> &'static str {
+        match self {
+            PathMatcher::Exact { .. } => "exact",
+            PathMatcher::Prefix { .. } => "prefix",
+            PathMatcher::Regex { .. } => "regex",
+            PathMatcher::Glob { .. } => "glob",
+            PathMatcher::Weighted { matcher, .. } => matcher.key(),
+        }
+    }
+
+    /// Weight of the matcher, if it has one.
+    pub fn weight(&self) -> Option<u32> {
+        match self {
+            PathMatcher::Weighted { weight, .. } => Some(*weight),
+            _ => None,
+        }
+    }
+
+    /// Get the path value for matching.
+    pub fn value(&self) -> &str {
+        match self {
+            PathMatcher::Exact { exact } => exact,
+            PathMatcher::Prefix { prefix } => prefix,
+            PathMatcher::Regex { regex } => regex,
+            PathMatcher::Glob { glob } => glob,
+            PathMatcher::Weighted { matcher, .. } => matcher.value(),
+        }
+    }
+}
+
+/// Serialize a weighted matcher flat, as `{ prefix: "/api/", weight: 50 }`.
+///
+/// A derived flatten would recurse into `PathMatcher` without bound.
+fn serialize_weighted_path<S>(
+    matcher: &PathMatcher,
+    weight: &u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    use serde::ser::SerializeMap;
+
+    let mut map = serializer.serialize_map(Some(2))?;
+    map.serialize_entry(matcher.key(), matcher.value())?;
+    map.serialize_entry("weight", weight)?;
+    map.end()
+}
+
+/// Status returned by a timeout fault unless configured otherwise.
+pub const DEFAULT_TIMEOUT_STATUS: u16 = 504;
+
+/// Status returned by a reset fault unless configured otherwise.
+pub const DEFAULT_RESET_STATUS: u16 = 502;
+
+/// Fault types that can be injected.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Fault {
+    /// Add latency before proxying.
+    Latency {
+        /// Fixed delay in milliseconds.
+        #[serde(default, alias = "fixed", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        fixed_ms: u64,
+        /// Minimum delay for random range.
+        #[serde(default, alias = "min", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        min_ms: u64,
+        /// Maximum delay for random range.
+        #[serde(default, alias = "max", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        max_ms: u64,
+        /// Delay the request before proxying, or the response once the
+        /// upstream has answered.
+        #[serde(default, skip_serializing_if = "LatencyTiming::is_default")]
+        when: LatencyTiming,
+    },
+    /// Add latency drawn from a normal distribution.
+    Jitter {
+        /// Mean delay in milliseconds.
+        #[serde(alias = "mean", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        mean_ms: u64,
+        /// Standard deviation in milliseconds.
+        #[serde(alias = "stddev", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        stddev_ms: u64,
+        /// Upper bound on any single delay.
+        #[serde(alias = "max", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        max_ms: u64,
+    },
+    /// Add latency that grows with each injection, like a degrading
+    /// dependency.
+    EscalatingLatency {
+        /// Delay of the first injection.
+        #[serde(alias = "base", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        base_ms: u64,
+        /// Multiplier applied to the delay on each injection (>= 1.0).
+        factor: f64,
+        /// Upper bound on the delay.
+        #[serde(alias = "max", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        max_ms: u64,
+        /// Seconds without an injection after which the delay drops back to
+        /// `base_ms`.
+        reset_after_seconds: u64,
+    },
+    /// Return an HTTP error immediately.
+    Error {
+        /// HTTP status code.
+        status: u16,
+        /// Error message body.
+        #[serde(default)]
+        message: Option<String>,
+        /// Additional headers.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// Weighted statuses as (status, weight); overrides `status` when set.
+        #[serde(default, deserialize_with = "deserialize_status_pool")]
+        #[schemars(schema_with = "crate::schema::status_pool")]
+        status_pool: Vec<(u16, u32)>,
+    },
+    /// Simulate an upstream timeout.
+    Timeout {
+        /// Duration to wait before responding.
+        #[serde(alias = "duration", deserialize_with = "deserialize_duration_ms")]
+        #[schemars(schema_with = "crate::schema::duration_ms")]
+        duration_ms: u64,
+        /// Status returned after the wait (default 504).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+        /// Let the request through after the wait instead of failing it.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        return_success: bool,
+    },
+    /// Throttle response bandwidth.
+    Throttle {
+        /// Bytes per second.
+        bytes_per_second: u64,
+    },
+    /// Inject garbage into response.
+    Corrupt {
+        /// Probability of corruption (0.0-1.0).
+        probability: f64,
+        /// Whether to replace the response or flip bytes of the real body.
+        #[serde(default)]
+        mode: CorruptMode,
+        /// Probability of flipping each byte in `bitrot` mode (0.0-1.0).
+        #[serde(default = "default_byte_flip_probability")]
+        byte_flip_probability: f64,
+        /// Size of the garbage body in `garbage` mode, at most
+        /// [`MAX_CORRUPT_SIZE_BYTES`]. Unset, 50-500 bytes are picked at
+        /// random.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size_bytes: Option<usize>,
+    },
+    /// Simulate connection reset.
+    Reset {
+        /// How the reset is signaled to the proxy.
+        #[serde(default, skip_serializing_if = "ResetMode::is_default")]
+        mode: ResetMode,
+        /// Status of the response, or of the fallback response when the
+        /// proxy does not act on the reset signal (default 502).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+    },
+    /// Ask the proxy to replay the request upstream.
+    Duplicate {
+        /// Additional copies to send (1-10).
+        count: u32,
+    },
+    /// Add or strip headers on the upstream response.
+    MutateResponseHeaders {
+        /// Headers to set on the response.
+        #[serde(default)]
+        set: HashMap<String, String>,
+        /// Headers to remove from the response.
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// Return the status named in a request header.
+    ///
+    /// Lets clients choose the injected status, so it should normally be
+    /// gated behind a header-targeted experiment.
+    StatusFromHeader {
+        /// Request header carrying the status.
+        header: String,
+        /// Status when the header is missing or not a valid status.
+        default_status: u16,
+    },
+    /// Short-circuit with the request body sent back to the client.
+    ///
+    /// Needs the buffered request body, so the proxy must send body events
+    /// to the agent.
+    Echo {
+        /// HTTP status code.
+        status: u16,
+        /// Content type of the echo; defaults to the request's.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_type: Option<String>,
+    },
+    /// Ask the proxy to send the request upstream under a different path,
+    /// like a routing or rewrite bug.
+    RewritePath {
+        /// Path sent upstream; the original query string is kept.
+        to: String,
+    },
+    /// Short-circuit with a canned response read from a file, e.g. a stale
+    /// or malformed payload the client must cope with.
+    ///
+    /// The file is read once when the configuration is loaded, relative to
+    /// the file defining the experiment, and served from memory.
+    ReturnFixture {
+        /// HTTP status code.
+        status: u16,
+        /// UTF-8 file holding the response body, at most
+        /// [`MAX_FIXTURE_BYTES`].
+        file: PathBuf,
+        /// Content type of the response; guessed from the file extension
+        /// when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_type: Option<String>,
+        /// Contents of `file`, see [`Fault::load_fixture`].
+        #[serde(skip)]
+        body: Option<Arc<str>>,
+    },
+}
+
+/// Upper bound on `Fault::Duplicate` copies.
+pub const MAX_DUPLICATE_COUNT: u32 = 10;
+
+/// Upper bound on the garbage body of `Fault::Corrupt`, so a typo cannot
+/// make the agent allocate gigabytes per request.
+pub const MAX_CORRUPT_SIZE_BYTES: usize = 1024 * 1024;
+
+/// Upper bound on the file of `Fault::ReturnFixture`, which is held in
+/// memory for the lifetime of the configuration.
+pub const MAX_FIXTURE_BYTES: u64 = 1024 * 1024;
+
+/// How a corrupt fault damages the response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorruptMode {
+    /// Replace the whole response with random bytes.
+    #[default]
+    Garbage,
+    /// Flip a fraction of the bytes of the real upstream body.
+    Bitrot,
+}
+
+/// When a latency fault delays the request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyTiming {
+    /// Before the request is proxied.
+    #[default]
+    Before,
+    /// After the upstream has answered, before the response reaches the
+    /// client.
+    After,
+}
+
+impl LatencyTiming {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How a reset fault is signaled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetMode {
+    /// Answer with a 502, as if the upstream connection was reset.
+    #[default]
+    BadGateway,
+    /// Ask the proxy to close the client connection with
+    /// `x-chaos-reset: close`.
+    Close,
+    /// Ask the proxy to abort the client connection with a TCP RST with
+    /// `x-chaos-reset: rst`.
+    Rst,
+}
+
+impl ResetMode {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Value of the `x-chaos-reset` header, for modes the proxy acts on.
+    pub fn signal(&self) -> Option<&'static str> {
+        match self {
+            ResetMode::BadGateway => None,
+            ResetMode::Close => Some("close"),
+            ResetMode::Rst => Some("rst"),
+        }
+    }
+}
+
+fn default_byte_flip_probability() -> f64 {
+    0.01
+}
+
+impl Fault {
+    /// Names of all fault variants, as returned by [`Fault::type_name`].
+    pub const TYPE_NAMES: &'static [&'static str] = &[
+        "latency",
+        "jitter",
+        "escalating_latency",
+        "error",
+        "timeout",
+        "throttle",
+        "corrupt",
+        "reset",
+        "duplicate",
+        "mutate_response_headers",
+        "status_from_header",
+        "echo",
+        "rewrite_path",
+        "return_fixture",
+    ];
+
+    /// Name of the fault variant, as used in config and metric labels.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Fault::Latency { .. } => "latency",
+            Fault::Jitter { .. } => "jitter",
+            Fault::EscalatingLatency { .. } => "escalating_latency",
+            Fault::Error { .. } => "error",
+            Fault::Timeout { .. } => "timeout",
+            Fault::Throttle { .. } => "throttle",
+            Fault::Corrupt { .. } => "corrupt",
+            Fault::Reset { .. } => "reset",
+            Fault::Duplicate { .. } => "duplicate",
+            Fault::MutateResponseHeaders { .. } => "mutate_response_headers",
+            Fault::StatusFromHeader { .. } => "status_from_header",
+            Fault::Echo { .. } => "echo",
+            Fault::RewritePath { .. } => "rewrite_path",
+            Fault::ReturnFixture { .. } => "return_fixture",
+        }
+    }
+
+    /// Whether applying the fault holds the request open inside the agent.
+    pub fn holds_request(&self) -> bool {
+        matches!(
+            self,
+            Fault::Latency { .. }
+                | Fault::Jitter { .. }
+                | Fault::EscalatingLatency { .. }
+                | Fault::Timeout { .. }
+        )
+    }
+
+    /// Longest delay the fault can inject, for faults that delay requests.
+    pub fn max_delay_ms(&self) -> Option<u64> {
+        match self {
+            Fault::Latency {
+                fixed_ms,
+                min_ms,
+                max_ms,
+                ..
+            } => Some(if *fixed_ms > 0 {
+                *fixed_ms
+            } else {
+                (*min_ms).max(*max_ms)
+            }),
+            Fault::Jitter { max_ms, .. } | Fault::EscalatingLatency { max_ms, .. } => Some(*max_ms),
+            Fault::Timeout { duration_ms, .. } => Some(*duration_ms),
+            _ => None,
+        }
+    }
+
+    /// Whether the fault is applied to the upstream response rather than
+    /// the request.
+    pub fn is_response_phase(&self) -> bool {
+        matches!(
+            self,
+            Fault::Latency {
+                when: LatencyTiming::After,
+                ..
+            } | Fault::Corrupt {
+                mode: CorruptMode::Bitrot,
+                ..
+            } | Fault::MutateResponseHeaders { .. }
+        )
+    }
+
+    /// Whether the fault is applied once the request body has been
+    /// buffered rather than on the request headers.
+    pub fn is_body_phase(&self) -> bool {
+        matches!(self, Fault::Echo { .. })
+    }
+
+    /// Read the file of a return-fixture fault, resolving a relative path
+    /// against `base`. Other faults, and fixtures already read, are left
+    /// untouched.
+    pub fn load_fixture(&mut self, base: &Path) -> Result<()> {
+        let Fault::ReturnFixture { file, body, .. } = self else {
+            return Ok(());
+        };
+        if body.is_some() {
+            return Ok(());
+        }
+        let path = base.join(file);
+        let cannot_read =
+            |e: std::io::Error| anyhow!("cannot read fixture {}: {}", path.display(), e);
+        // Devices and pipes report no size, and may never end
+        let metadata = std::fs::metadata(&path).map_err(cannot_read)?;
+        if !metadata.is_file() {
+            bail!("fixture {} is not a regular file", path.display());
+        }
+        if metadata.len() > MAX_FIXTURE_BYTES {
+            bail!(
+                "fixture {} is {} bytes, more than the limit of {}",
+                path.display(),
+                metadata.len(),
+                MAX_FIXTURE_BYTES
+            );
+        }
+        // The file may have grown since it was checked
+        let mut bytes = Vec::new();
+        std::fs::File::open(&path)
+            .and_then(|file| file.take(MAX_FIXTURE_BYTES + 1).read_to_end(&mut bytes))
+            .map_err(cannot_read)?;
+        if bytes.len() as u64 > MAX_FIXTURE_BYTES {
+            bail!(
+                "fixture {} is more than the limit of {} bytes",
+                path.display(),
+                MAX_FIXTURE_BYTES
+            );
+        }
+        let text = String::from_utf8(bytes)
+            .map_err(|_| anyhow!("fixture {} is not valid UTF-8", path.display()))?;
+        *body = Some(Arc::from(text));
+        Ok(())
+    }
+
+    /// Validate the fault configuration.
+    pub fn validate(&self) -> Result<()> {
+        into_result(self.errors("fault"))
+    }
+
+    /// Collect validation errors, with field paths under `path`.
+    pub fn errors(&self, path: &str) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let mut error = |field: &str, message: String| {
+            let path = if field.is_empty() {
+                path.to_string()
+            } else {
+                format!("{}.{}", path, field)
+            };
+            errors.push(ConfigError::new(path, message));
+        };
+
+        match self {
+            Fault::Latency {
+                fixed_ms,
+                min_ms,
+                max_ms,
+                ..
+            } => {
+                if *fixed_ms == 0 && *min_ms == 0 && *max_ms == 0 {
+                    error(
+                        "",
+                        "latency fault must specify either fixed_ms or min_ms/max_ms".into(),
+                    );
+                } else if *fixed_ms == 0 && *max_ms < *min_ms {
+                    error(
+                        "max_ms",
+                        format!("must be >= min_ms ({}), got {}", min_ms, max_ms),
+                    );
+                }
+            }
+            Fault::Jitter {
+                mean_ms,
+                stddev_ms,
+                max_ms,
+            } => {
+                if *stddev_ms == 0 {
+                    error("stddev_ms", "must be > 0".into());
+                }
+                if *max_ms < *mean_ms {
+                    error(
+                        "max_ms",
+                        format!("must be >= mean_ms ({}), got {}", mean_ms, max_ms),
+                    );
+                }
+            }
+            Fault::EscalatingLatency {
+                base_ms,
+                factor,
+                max_ms,
+                reset_after_seconds,
+            } => {
+                if *base_ms == 0 {
+                    error("base_ms", "must be > 0".into());
+                }
+                if *factor < 1.0 {
+                    error("factor", format!("must be >= 1.0, got {}", factor));
+                }
+                if *max_ms < *base_ms {
+                    error(
+                        "max_ms",
+                        format!("must be >= base_ms ({}), got {}", base_ms, max_ms),
+                    );
+                }
+                if *reset_after_seconds == 0 {
+                    error("reset_after_seconds", "must be > 0".into());
+                }
+            }
+            Fault::Error {
+                status,
+                status_pool,
+                ..
+            } => {
+                if *status < 100 || *status > 599 {
+                    error("status", format!("invalid code {}", status));
+                }
+                for (pooled, _) in status_pool {
+                    if *pooled < 100 || *pooled > 599 {
+                        error("status_pool", format!("invalid code {}", pooled));
+                    }
+                }
+                if !status_pool.is_empty()
+                    && status_pool.iter().map(|(_, w)| *w as u64).sum::<u64>() == 0
+                {
+                    error("status_pool", "total weight must be > 0".into());
+                }
+            }
+            Fault::Timeout {
+                duration_ms,
+                status,
+                return_success,
+            } => {
+                if *duration_ms == 0 {
+                    error("duration_ms", "must be > 0".into());
+                }
+                if let Some(status) = status {
+                    if *status < 100 || *status > 599 {
+                        error("status", format!("invalid code {}", status));
+                    } else if *return_success {
+                        error("status", "cannot be set with return_success".into());
+                    }
+                }
+            }
+            Fault::Throttle { bytes_per_second } => {
+                if *bytes_per_second == 0 {
+                    error("bytes_per_second", "must be > 0".into());
+                }
+            }
+            Fault::Corrupt {
+                probability,
+                mode,
+                byte_flip_probability,
+                size_bytes,
+            } => {
+                if *probability < 0.0 || *probability > 1.0 {
+                    error(
+                        "probability",
+                        format!("must be between 0.0 and 1.0, got {}", probability),
+                    );
+                }
+                if *byte_flip_probability < 0.0 || *byte_flip_probability > 1.0 {
+                    error(
+                        "byte_flip_probability",
+                        format!("must be between 0.0 and 1.0, got {}", byte_flip_probability),
+                    );
+                }
+                match size_bytes {
+                    Some(_) if *mode == CorruptMode::Bitrot => {
+                        error("size_bytes", "only applies to garbage mode".into());
+                    }
+                    Some(size) if *size == 0 || *size > MAX_CORRUPT_SIZE_BYTES => error(
+                        "size_bytes",
+                        format!(
+                            "must be between 1 and {}, got {}",
+                            MAX_CORRUPT_SIZE_BYTES, size
+                        ),
+                    ),
+                    _ => {}
+                }
+            }
+            Fault::Reset {
+                status: Some(status),
+                ..
+            } => {
+                if *status < 100 || *status > 599 {
+                    error("status", format!("invalid code {}", status));
+                }
+            }
+            Fault::Reset { status: None, .. } => {}
+            Fault::Duplicate { count } => {
+                if *count == 0 || *count > MAX_DUPLICATE_COUNT {
+                    error(
+                        "count",
+                        format!(
+                            "must be between 1 and {}, got {}",
+                            MAX_DUPLICATE_COUNT, count
+                        ),
+                    );
+                }
+            }
+            Fault::MutateResponseHeaders { set, remove } => {
+                if set.is_empty() && remove.is_empty() {
+                    error("", "must set or remove at least one header".into());
+                }
+            }
+            Fault::StatusFromHeader {
+                header,
+                default_status,
+            } => {
+                if header.is_empty() {
+                    error("header", "cannot be empty".into());
+                }
+                if *default_status < 100 || *default_status > 599 {
+                    error("default_status", format!("invalid code {}", default_status));
+                }
+            }
+            Fault::Echo {
+                status,
+                content_type,
+            } => {
+                if *status < 100 || *status > 599 {
+                    error("status", format!("invalid code {}", status));
+                }
+                if content_type.as_ref().is_some_and(|c| c.trim().is_empty()) {
+                    error("content_type", "cannot be empty".into());
+                }
+            }
+            Fault::RewritePath { to } => {
+                if to.is_empty() {
+                    error("to", "cannot be empty".into());
+                } else if !to.starts_with('/') {
+                    error("to", format!("must start with '/', got '{}'", to));
+                } else if to.contains(|c: char| c.is_whitespace() || c.is_control()) {
+                    error("to", format!("must not contain whitespace, got '{}'", to));
+                }
+            }
+            Fault::ReturnFixture {
+                status,
+                file,
+                content_type,
+                ..
+            } => {
+                if *status < 100 || *status > 599 {
+                    error("status", format!("invalid code {}", status));
+                }
+                if file.as_os_str().is_empty() {
+                    error("file", "cannot be empty".into());
+                }
+                if content_type.as_ref().is_some_and(|c| c.trim().is_empty()) {
+                    error("content_type", "cannot be empty".into());
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Latency {
+                fixed_ms,
+                min_ms,
+                max_ms,
+                when,
+            } => {
+                if *fixed_ms > 0 {
+                    write!(f, "latency {}ms", fixed_ms)?;
+                } else {
+                    write!(f, "latency {}-{}ms", min_ms, max_ms)?;
+                }
+                match when {
+                    LatencyTiming::Before => Ok(()),
+                    LatencyTiming::After => write!(f, " after the response"),
+                }
+            }
+            Fault::Jitter {
+                mean_ms,
+                stddev_ms,
+                max_ms,
+            } => write!(
+                f,
+                "jitter {}ms ± {}ms (max {}ms)",
+                mean_ms, stddev_ms, max_ms
+            ),
+            Fault::EscalatingLatency {
+                base_ms,
+                factor,
+                max_ms,
+                reset_after_seconds,
+            } => write!(
+                f,
+                "escalating latency {}ms x{} (max {}ms, reset after {}s)",
+                base_ms, factor, max_ms, reset_after_seconds
+            ),
+            Fault::Error {
+                status,
+                status_pool,
+                ..
+            } => {
+                if status_pool.is_empty() {
+                    write!(f, "error {}", status)
+                } else {
+                    let pool: Vec<String> = status_pool
+                        .iter()
+                        .map(|(status, weight)| format!("{}x{}", status, weight))
+                        .collect();
+                    write!(f, "error from pool [{}]", pool.join(", "))
+                }
+            }
+            Fault::Timeout {
+                duration_ms,
+                return_success: true,
+                ..
+            } => write!(f, "timeout after {}ms, then allow", duration_ms),
+            Fault::Timeout {
+                duration_ms,
+                status,
+                ..
+            } => write!(
+                f,
+                "timeout after {}ms ({})",
+                duration_ms,
+                status.unwrap_or(DEFAULT_TIMEOUT_STATUS)
+            ),
+            Fault::Throttle { bytes_per_second } => {
+                write!(f, "throttle {} bytes/s", bytes_per_second)
+            }
+            Fault::Corrupt {
+                probability,
+                mode: CorruptMode::Garbage,
+                size_bytes,
+                ..
+            } => {
+                write!(f, "corrupt with probability {}", probability)?;
+                match size_bytes {
+                    Some(size) => write!(f, " ({} bytes)", size),
+                    None => Ok(()),
+                }
+            }
+            Fault::Corrupt {
+                probability,
+                mode: CorruptMode::Bitrot,
+                byte_flip_probability,
+                ..
+            } => write!(
+                f,
+                "bitrot with probability {} (byte flip probability {})",
+                probability, byte_flip_probability
+            ),
+            Fault::Reset { mode, status } => {
+                let status = status.unwrap_or(DEFAULT_RESET_STATUS);
+                match mode {
+                    ResetMode::BadGateway => write!(f, "connection reset ({})", status),
+                    ResetMode::Close => write!(f, "connection close (fallback {})", status),
+                    ResetMode::Rst => write!(f, "connection reset via RST (fallback {})", status),
+                }
+            }
+            Fault::Duplicate { count } => write!(f, "duplicate x{}", count),
+            Fault::MutateResponseHeaders { set, remove } => {
+                let mut set: Vec<&str> = set.keys().map(String::as_str).collect();
+                set.sort();
+                write!(
+                    f,
+                    "mutate response headers (set [{}], remove [{}])",
+                    set.join(", "),
+                    remove.join(", ")
+                )
+            }
+            Fault::StatusFromHeader {
+                header,
+                default_status,
+            } => write!(f, "status from {} (default {})", header, default_status),
+            Fault::Echo {
+                status,
+                content_type: Some(content_type),
+            } => write!(f, "echo request body ({}, {})", status, content_type),
+            Fault::Echo { status, .. } => write!(f, "echo request body ({})", status),
+            Fault::RewritePath { to } => write!(f, "rewrite path to {}", to),
+            Fault::ReturnFixture { status, file, .. } => {
+                write!(f, "return fixture {} ({})", file.display(), status)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert!(config.settings.enabled);
+        assert!(!config.settings.dry_run);
+        assert!(config.experiments.is_empty());
+    }
+
+    #[test]
+    fn test_parse_minimal_config() {
+        let yaml = r#"
+settings:
+  enabled: true
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.settings.enabled);
+    }
+
+    #[test]
+    fn test_parse_latency_experiment() {
+        let yaml = r#"
+experiments:
+  - id: "test-latency"
+    targeting:
+      paths:
+        - prefix: "/api/"
+      percentage: 10
+    fault:
+      type: latency
+      fixed_ms: 500
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.experiments.len(), 1);
+        assert_eq!(config.experiments[0].id, "test-latency");
+        assert!(matches!(
+            config.experiments[0].fault,
+            Fault::Latency { fixed_ms: 500, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_experiment() {
+        let yaml = r#"
+experiments:
+  - id: "test-error"
+    targeting:
+      percentage: 5
+    fault:
+      type: error
+      status: 503
+      message: "Service Unavailable"
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(matches!(
+            config.experiments[0].fault,
+            Fault::Error { status: 503, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let yaml = r#"
+safety:
+  schedule:
+    - days: [mon, tue, wed]
+      start: "09:00"
+      end: "17:00"
+      timezone: "UTC"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.safety.schedule.len(), 1);
+        assert_eq!(config.safety.schedule[0].days.len(), 3);
+    }
+
+    #[test]
+    fn test_validation_fails_for_duplicate_ids() {
+        let yaml = r#"
+experiments:
+  - id: "test"
+    targeting:
+      percentage: 10
+    fault:
+      type: latency
+      fixed_ms: 100
+  - id: "test"
+    targeting:
+      percentage: 10
+    fault:
+      type: latency
+      fixed_ms: 200
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_fails_for_invalid_percentage() {
+        let yaml = r#"
+experiments:
+  - id: "test"
+    targeting:
+      percentage: 150
+    fault:
+      type: latency
+      fixed_ms: 100
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_fails_for_invalid_regex() {
+        let yaml = r#"
+experiments:
+  - id: "test"
+    targeting:
+      paths:
+        - regex: "[invalid"
+    fault:
+      type: latency
+      fixed_ms: 100
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_overnight_schedule_is_valid() {
+        let yaml = r#"
+safety:
+  schedule:
+    - days: [fri]
+      start: "22:00"
+      end: "02:00"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validation_fails_for_empty_schedule_window() {
+        let yaml = r#"
+safety:
+  schedule:
+    - days: [fri]
+      start: "22:00"
+      end: "22:00"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_blackout_dates() {
+        let yaml = r#"
+safety:
+  blackout_dates:
+    - "2025-11-28"
+    - from: "2025-12-20"
+      to: "2026-01-02"
+  only_dates: ["2025-10-01"]
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let safety = &config.safety;
+        assert_eq!(safety.blackout_dates.len(), 2);
+        assert!(safety.blackout_dates[1].contains(date("2025-12-31")));
+        assert!(!safety.blackout_dates[1].contains(date("2026-01-03")));
+        assert!(safety.is_date_allowed(date("2025-10-01")));
+        assert!(!safety.is_date_allowed(date("2025-10-02")));
+    }
+
+    #[test]
+    fn test_parse_fails_for_malformed_date() {
+        let yaml = r#"
+safety:
+  blackout_dates: ["2025-13-01"]
+experiments: []
+"#;
+        assert!(serde_yaml::from_str::<Config>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_validation_fails_for_reversed_date_range() {
+        let yaml = r#"
+safety:
+  blackout_dates:
+    - from: "2026-01-02"
+      to: "2025-12-20"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_valid_timezone() {
+        let yaml = r#"
+safety:
+  timezone: "Europe/Berlin"
+  schedule:
+    - days: [mon]
+      start: "09:00"
+      end: "17:00"
+      timezone: "America/New_York"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.safety.timezone, chrono_tz::Europe::Berlin);
+        assert_eq!(
+            config.safety.schedule[0].timezone,
+            chrono_tz::America::New_York
+        );
+    }
+
+    #[test]
+    fn test_parse_fails_listing_all_invalid_timezones() {
+        let yaml = r#"
+safety:
+  schedule:
+    - days: [mon]
+      start: "09:00"
+      end: "17:00"
+      timezone: "Mars/Olympus_Mons"
+    - days: [tue]
+      start: "09:00"
+      end: "17:00"
+      timezone: "Moon/Tranquility"
+experiments: []
+"#;
+        let err = serde_yaml::from_str::<Config>(yaml)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Mars/Olympus_Mons"), "{}", err);
+        assert!(err.contains("Moon/Tranquility"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_fails_for_case_variant_timezone() {
+        let yaml = r#"
+safety:
+  schedule:
+    - days: [mon]
+      start: "09:00"
+      end: "17:00"
+      timezone: "America/New_york"
+experiments: []
+"#;
+        let err = serde_yaml::from_str::<Config>(yaml)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("did you mean 'America/New_York'"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_max_injections() {
+        let yaml = r#"
+experiments:
+  - id: "one-shot"
+    targeting:
+      percentage: 10
+    fault:
+      type: reset
+    max_injections: 500
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.experiments[0].max_injections, Some(500));
+        assert!(config.validate().is_ok());
+
+        let mut config = config;
+        config.experiments[0].max_injections = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_ramp_down_seconds() {
+        let yaml = r#"
+experiments:
+  - id: "tapering"
+    targeting: { percentage: 10 }
+    fault: { type: reset }
+    ramp_down_seconds: 300
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.experiments[0].ramp_down_seconds, Some(300));
+        assert!(config.validate().is_ok());
+
+        config.experiments[0].ramp_down_seconds = Some(0);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "experiments[tapering].ramp_down_seconds: must be > 0"
+        );
+    }
+
+    #[test]
+    fn test_parse_status_pool() {
+        let yaml = r#"
+experiments:
+  - id: "map-pool"
+    targeting:
+      percentage: 5
+    fault:
+      type: error
+      status: 500
+      status_pool: {500: 3, 502: 1, 503: 1}
+  - id: "list-pool"
+    targeting:
+      percentage: 5
+    fault:
+      type: error
+      status: 500
+      status_pool: [[500, 3], [502, 1]]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        assert!(matches!(
+            &config.experiments[0].fault,
+            Fault::Error { status_pool, .. } if status_pool == &vec![(500, 3), (502, 1), (503, 1)]
+        ));
+        assert!(matches!(
+            &config.experiments[1].fault,
+            Fault::Error { status_pool, .. } if status_pool == &vec![(500, 3), (502, 1)]
+        ));
+    }
+
+    #[test]
+    fn test_validation_fails_for_invalid_status_pool() {
+        let invalid_status = Fault::Error {
+            status: 500,
+            message: None,
+            headers: HashMap::new(),
+            status_pool: vec![(500, 1), (9999, 1)],
+        };
+        assert!(invalid_status.validate().is_err());
+
+        let zero_weight = Fault::Error {
+            status: 500,
+            message: None,
+            headers: HashMap::new(),
+            status_pool: vec![(500, 0), (502, 0)],
+        };
+        assert!(zero_weight.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_auto_abort() {
+        let yaml = r#"
+safety:
+  auto_abort:
+    error_rate_threshold: 0.05
+    probe_url: "http://upstream:8080/health"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let auto_abort = config.safety.auto_abort.unwrap();
+        assert_eq!(auto_abort.window_s, 60);
+        assert_eq!(auto_abort.cooldown_s, 300);
+    }
+
+    #[test]
+    fn test_validation_fails_for_invalid_auto_abort() {
+        let yaml = r#"
+safety:
+  auto_abort:
+    error_rate_threshold: 1.5
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+
+        let yaml = r#"
+safety:
+  auto_abort:
+    error_rate_threshold: 0.1
+    probe_url: "https://upstream/health"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_experiment_schedule() {
+        let yaml = r#"
+experiments:
+  - id: "nightly-errors"
+    schedule:
+      - days: [mon, tue, wed, thu, fri]
+        start: "03:00"
+        end: "04:00"
+    targeting:
+      percentage: 10
+    fault:
+      type: error
+      status: 503
+  - id: "bad-window"
+    schedule:
+      - days: [mon]
+        start: "03:00"
+        end: "03:00"
+    targeting:
+      percentage: 10
+    fault:
+      type: error
+      status: 503
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.experiments[0].schedule.len(), 1);
+        assert_eq!(config.experiments[0].schedule[0].timezone, Tz::UTC);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_precheck() {
+        let yaml = r#"
+experiments:
+  - id: "checked"
+    precheck:
+      url: "http://upstream:8080/health"
+    targeting:
+      percentage: 10
+    fault:
+      type: error
+      status: 503
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let precheck = config.experiments[0].precheck.as_ref().unwrap();
+        assert_eq!(precheck.expect_status, 200);
+        assert_eq!(precheck.interval_seconds, 10);
+        assert!(config.validate().is_ok());
+
+        config.experiments[0].precheck.as_mut().unwrap().url = "upstream/health".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_corrupt_bitrot_validation() {
+        let fault: Fault = serde_yaml::from_str(
+            r#"
+type: corrupt
+probability: 0.5
+mode: bitrot
+byte_flip_probability: 0.001
+"#,
+        )
+        .unwrap();
+        assert!(fault.is_response_phase());
+        assert!(fault.validate().is_ok());
+
+        let fault: Fault = serde_yaml::from_str("type: corrupt\nprobability: 0.5").unwrap();
+        assert!(!fault.is_response_phase());
+
+        let fault: Fault = serde_yaml::from_str(
+            "type: corrupt\nprobability: 0.5\nmode: bitrot\nbyte_flip_probability: 1.5",
+        )
+        .unwrap();
+        assert!(fault.validate().is_err());
+    }
+
+    #[test]
+    fn test_corrupt_size_bytes_validation() {
+        let fault: Fault =
+            serde_yaml::from_str("type: corrupt\nprobability: 1.0\nsize_bytes: 65536").unwrap();
+        assert!(fault.validate().is_ok());
+        assert_eq!(
+            fault.to_string(),
+            "corrupt with probability 1 (65536 bytes)"
+        );
+
+        for yaml in [
+            "type: corrupt\nprobability: 1.0\nsize_bytes: 0",
+            "type: corrupt\nprobability: 1.0\nsize_bytes: 1048577",
+            "type: corrupt\nprobability: 1.0\nmode: bitrot\nsize_bytes: 100",
+        ] {
+            let fault: Fault = serde_yaml::from_str(yaml).unwrap();
+            assert!(fault.validate().is_err(), "{}", yaml);
+        }
+        let fault: Fault =
+            serde_yaml::from_str("type: corrupt\nprobability: 1.0\nsize_bytes: 4294967296")
+                .unwrap();
+        let err = fault.validate().unwrap_err().to_string();
+        assert!(err.contains("must be between 1 and 1048576"), "{}", err);
+    }
+
+    #[test]
+    fn test_return_fixture_loads_relative_to_config() {
+        let experiment = |file: &str, status: u16| {
+            format!(
+                "experiments:\n  - id: stale\n    targeting: {{}}\n    fault: {{ type: return_fixture, status: {}, file: {} }}\n",
+                status, file
+            )
+        };
+        let big = "x".repeat(MAX_FIXTURE_BYTES as usize + 1);
+        let dir = write_files(
+            "fixture",
+            &[
+                ("chaos.yaml", &experiment("fixtures/stale.json", 200)),
+                ("fixtures/stale.json", "{\"stale\": true}"),
+                ("missing.yaml", &experiment("fixtures/gone.json", 200)),
+                ("big.yaml", &experiment("big.json", 200)),
+                ("big.json", &big),
+                ("status.yaml", &experiment("fixtures/stale.json", 99)),
+                ("dir.yaml", &experiment("fixtures", 200)),
+            ],
+        );
+
+        let config = Config::from_file(&dir.join("chaos.yaml")).unwrap();
+        let fault = &config.experiments[0].fault;
+        assert!(matches!(
+            fault,
+            Fault::ReturnFixture { body: Some(body), .. } if &**body == "{\"stale\": true}"
+        ));
+        assert_eq!(
+            fault.to_string(),
+            "return fixture fixtures/stale.json (200)"
+        );
+
+        let err = Config::from_file(&dir.join("missing.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("experiments[stale].fault.file: cannot read fixture"),
+            "{}",
+            err
+        );
+        let err = Config::from_file(&dir.join("big.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("1048577 bytes, more than the limit of 1048576"),
+            "{}",
+            err
+        );
+        let err = Config::from_file(&dir.join("status.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "experiments[stale].fault.status: invalid code 99");
+        let err = Config::from_file(&dir.join("dir.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("is not a regular file"), "{}", err);
+
+        // Pushed and runtime experiments cannot name files on the host
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(&experiment("/etc/passwd", 200)).unwrap();
+        let err = Config::from_value(value.clone()).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "experiments[stale].fault.file: fixtures can only be read from a configuration file"
+        );
+        let err = config
+            .experiment_from_value(value["experiments"][0].clone())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("can only be read from a configuration file"),
+            "{}",
+            err
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validation_errors_name_experiment_and_field() {
+        let yaml = r#"
+experiments:
+  - id: "payment-errors"
+    targeting:
+      percentage: 10
+    fault:
+      type: error
+      status: 9999
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "experiments[payment-errors].fault.status: invalid code 9999"
+        );
+    }
+
+    #[test]
+    fn test_every_n_above_safety_ceiling_rejected() {
+        let yaml = r#"
+safety:
+  max_affected_percent: 10
+experiments:
+  - id: "dense"
+    targeting:
+      every_n: 5
+    fault:
+      type: error
+      status: 503
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "experiments[dense].targeting.every_n: faults 1 in 5 requests, more than safety.max_affected_percent (10%) allows"
+        );
+
+        let yaml = yaml.replace("every_n: 5", "every_n: 10");
+        let config: Config = serde_yaml::from_str(&yaml).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_zero_percentage_warning() {
+        let yaml = r#"
+experiments:
+  - id: "never"
+    targeting:
+      percentage: 0
+      paths:
+        - regex: "^api/.*"
+    fault:
+      type: error
+      status: 503
+  - id: "parked"
+    enabled: false
+    targeting:
+      percentage: 0
+    fault:
+      type: error
+      status: 503
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+
+        let warnings: Vec<String> = config.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "experiments[never].targeting.percentage: is 0, experiment matches but never injects",
+                "experiments[never].targeting.paths[0]: regex '^api/.*' is anchored without a leading '/', request paths never match",
+            ]
+        );
+
+        let err = config.validate_strict().unwrap_err().to_string();
+        assert!(err.starts_with("2 configuration errors:"), "{}", err);
+    }
+
+    #[test]
+    fn test_excluded_path_and_long_latency_warnings() {
+        let yaml = r#"
+safety:
+  excluded_paths: ["/health", "/metrics"]
+experiments:
+  - id: "probe-latency"
+    targeting:
+      paths:
+        - prefix: "/health"
+        - exact: "/api/users"
+    fault:
+      type: latency
+      fixed_ms: 15s
+  - id: "catch-all"
+    targeting: {}
+    fault:
+      type: timeout
+      duration_ms: 30s
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let warnings: Vec<String> = config.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "experiments[probe-latency].targeting.paths[0]: prefix \"/health\" overlaps excluded paths, which are never faulted: /health",
+                "experiments[probe-latency].fault: delays requests up to 15000ms, more than 10s",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validation_reports_all_errors() {
+        let yaml = r#"
+safety:
+  max_affected_percent: 150
+experiments:
+  - id: "slow"
+    targeting:
+      percentage: 101
+    fault:
+      type: timeout
+      duration_ms: 0
+  - id: "broken"
+    targeting: {}
+    fault:
+      type: corrupt
+      probability: 2.0
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let paths: Vec<String> = config.errors().into_iter().map(|e| e.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "safety.max_affected_percent",
+                "experiments[slow].targeting.percentage",
+                "experiments[slow].fault.duration_ms",
+                "experiments[broken].fault.probability",
+            ]
+        );
+
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.starts_with("4 configuration errors:"), "{}", err);
+        assert!(
+            err.contains("experiments[broken].fault.probability: must be between 0.0 and 1.0"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_headers_absent_validation() {
+        let targeting: Targeting =
+            serde_yaml::from_str("headers_absent: [\"x-api-version\"]").unwrap();
+        assert_eq!(targeting.headers_absent, vec!["x-api-version"]);
+        assert!(targeting.validate().is_ok());
+
+        let targeting: Targeting = serde_yaml::from_str(
+            "headers:\n  X-Api-Version: \"2\"\nheaders_absent: [\"x-api-version\"]",
+        )
+        .unwrap();
+        assert!(targeting.validate().is_err());
+    }
+
+    #[test]
+    fn test_status_from_header_validation() {
+        let fault: Fault = serde_yaml::from_str(
+            "type: status_from_header\nheader: x-chaos-force-status\ndefault_status: 503",
+        )
+        .unwrap();
+        assert!(fault.validate().is_ok());
+
+        let fault: Fault = serde_yaml::from_str(
+            "type: status_from_header\nheader: x-chaos-force-status\ndefault_status: 42",
+        )
+        .unwrap();
+        assert!(fault.validate().is_err());
+    }
+
+    #[test]
+    fn test_echo_validation() {
+        let fault: Fault = serde_yaml::from_str("type: echo\nstatus: 200").unwrap();
+        assert!(fault.validate().is_ok());
+        assert!(fault.is_body_phase());
+        assert!(!fault.is_response_phase());
+        assert_eq!(fault.to_string(), "echo request body (200)");
+
+        let fault: Fault = serde_yaml::from_str("type: echo\nstatus: 42").unwrap();
+        assert_eq!(
+            fault.validate().unwrap_err().to_string(),
+            "fault.status: invalid code 42"
+        );
+        let fault: Fault =
+            serde_yaml::from_str("type: echo\nstatus: 200\ncontent_type: ' '").unwrap();
+        assert!(fault.validate().is_err());
+    }
+
+    #[test]
+    fn test_rewrite_path_validation() {
+        let fault: Fault = serde_yaml::from_str("type: rewrite_path\nto: /v0/orders").unwrap();
+        assert!(fault.validate().is_ok());
+        assert_eq!(fault.to_string(), "rewrite path to /v0/orders");
+
+        for (to, message) in [
+            ("''", "fault.to: cannot be empty"),
+            (
+                "v0/orders",
+                "fault.to: must start with '/', got 'v0/orders'",
+            ),
+            (
+                "'/v0/ orders'",
+                "fault.to: must not contain whitespace, got '/v0/ orders'",
+            ),
+        ] {
+            let fault: Fault =
+                serde_yaml::from_str(&format!("type: rewrite_path\nto: {}", to)).unwrap();
+            assert_eq!(fault.validate().unwrap_err().to_string(), message);
         }
-        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_latency_timing() {
+        let fault: Fault = serde_yaml::from_str("type: latency\nfixed: 200ms").unwrap();
+        assert!(!fault.is_response_phase());
+        assert_eq!(
+            serde_json::to_value(&fault).unwrap(),
+            serde_json::json!({"type": "latency", "fixed_ms": 200, "min_ms": 0, "max_ms": 0})
+        );
+
+        let fault: Fault =
+            serde_yaml::from_str("type: latency\nfixed: 200ms\nwhen: after").unwrap();
+        assert!(fault.validate().is_ok());
+        assert!(fault.is_response_phase());
+        assert_eq!(fault.to_string(), "latency 200ms after the response");
+
+        let err =
+            serde_yaml::from_str::<Fault>("type: latency\nfixed: 200ms\nwhen: during").unwrap_err();
+        assert!(
+            err.to_string().contains("unknown variant `during`"),
+            "{}",
+            err
+        );
+    }
 
     #[test]
-    fn test_default_config() {
-        let config = Config::default();
-        assert!(config.settings.enabled);
-        assert!(!config.settings.dry_run);
-        assert!(config.experiments.is_empty());
+    fn test_reset_modes() {
+        // A bare reset keeps the original 502 behavior and serialization
+        let fault: Fault = serde_yaml::from_str("type: reset").unwrap();
+        assert_eq!(
+            fault,
+            Fault::Reset {
+                mode: ResetMode::BadGateway,
+                status: None
+            }
+        );
+        assert_eq!(fault.to_string(), "connection reset (502)");
+        assert_eq!(
+            serde_json::to_value(&fault).unwrap(),
+            serde_json::json!({"type": "reset"})
+        );
+
+        let fault: Fault = serde_yaml::from_str(
+            "type: reset
+mode: rst
+status: 503",
+        )
+        .unwrap();
+        assert!(fault.validate().is_ok());
+        assert_eq!(fault.to_string(), "connection reset via RST (fallback 503)");
+        let fault: Fault = serde_yaml::from_str(
+            "type: reset
+mode: close",
+        )
+        .unwrap();
+        assert_eq!(fault.to_string(), "connection close (fallback 502)");
+
+        let fault: Fault = serde_yaml::from_str(
+            "type: reset
+status: 700",
+        )
+        .unwrap();
+        assert_eq!(
+            fault.validate().unwrap_err().to_string(),
+            "fault.status: invalid code 700"
+        );
+        assert!(serde_yaml::from_str::<Fault>(
+            "type: reset
+mode: fin"
+        )
+        .is_err());
     }
 
     #[test]
-    fn test_parse_minimal_config() {
-        let yaml = r#"
-settings:
-  enabled: true
-experiments: []
-"#;
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
-        assert!(config.settings.enabled);
+    fn test_timeout_status_validation() {
+        let fault: Fault =
+            serde_yaml::from_str("type: timeout\nduration: 5s\nstatus: 503").unwrap();
+        assert!(fault.validate().is_ok());
+        assert_eq!(fault.to_string(), "timeout after 5000ms (503)");
+
+        let fault: Fault =
+            serde_yaml::from_str("type: timeout\nduration: 5s\nstatus: 9999").unwrap();
+        assert!(fault.validate().is_err());
+
+        let fault: Fault =
+            serde_yaml::from_str("type: timeout\nduration: 5s\nstatus: 503\nreturn_success: true")
+                .unwrap();
+        assert!(fault.validate().is_err());
     }
 
     #[test]
-    fn test_parse_latency_experiment() {
+    fn test_mutate_response_headers_validation() {
+        let fault: Fault = serde_yaml::from_str(
+            r#"
+type: mutate_response_headers
+set:
+  x-frame-options: "ALLOWALL"
+remove: ["cache-control"]
+"#,
+        )
+        .unwrap();
+        assert!(fault.is_response_phase());
+        assert!(fault.validate().is_ok());
+
+        let fault: Fault = serde_yaml::from_str("type: mutate_response_headers").unwrap();
+        assert!(fault.validate().is_err());
+    }
+
+    #[test]
+    fn test_validation_fails_above_max_latency() {
         let yaml = r#"
+safety:
+  max_latency_ms: 10000
 experiments:
-  - id: "test-latency"
+  - id: "typo"
     targeting:
-      paths:
-        - prefix: "/api/"
       percentage: 10
     fault:
       type: latency
-      fixed_ms: 500
+      fixed_ms: 500000
 "#;
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
-        assert_eq!(config.experiments.len(), 1);
-        assert_eq!(config.experiments[0].id, "test-latency");
-        assert!(matches!(
-            config.experiments[0].fault,
-            Fault::Latency { fixed_ms: 500, .. }
-        ));
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_err());
+
+        config.safety.max_latency_ms = 600_000;
+        assert!(config.validate().is_ok());
+
+        assert_eq!(SafetyConfig::default().max_latency_ms, 60_000);
     }
 
     #[test]
-    fn test_parse_error_experiment() {
-        let yaml = r#"
+    fn test_parse_max_concurrent_delays_alias() {
+        let config: Config =
+            serde_yaml::from_str("safety:\n  max_concurrent_delays: 25\n").unwrap();
+        assert_eq!(config.safety.max_concurrent_faults, Some(25));
+    }
+
+    /// Write `files` into a fresh temporary directory, returning its path.
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chaos-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (file, content) in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    const TEAM_FILE: &str = r#"
 experiments:
-  - id: "test-error"
+  - id: "payments-errors"
     targeting:
       percentage: 5
     fault:
       type: error
       status: 503
-      message: "Service Unavailable"
 "#;
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
-        assert!(matches!(
-            config.experiments[0].fault,
-            Fault::Error { status: 503, .. }
-        ));
-    }
 
     #[test]
-    fn test_parse_schedule() {
-        let yaml = r#"
-safety:
-  schedule:
-    - days: [mon, tue, wed]
-      start: "09:00"
-      end: "17:00"
-      timezone: "UTC"
-experiments: []
-"#;
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
-        assert_eq!(config.safety.schedule.len(), 1);
-        assert_eq!(config.safety.schedule[0].days.len(), 3);
+    fn test_includes_merge_experiments() {
+        let dir = write_files(
+            "includes",
+            &[
+                (
+                    "chaos.yaml",
+                    "includes: [teams/payments.yaml, teams/search.yaml]\nexperiments: []\n",
+                ),
+                ("teams/payments.yaml", TEAM_FILE),
+                (
+                    "teams/search.yaml",
+                    "experiments:\n  - id: search-latency\n    targeting:\n      percentage: 5\n    fault:\n      type: latency\n      fixed_ms: 100\n",
+                ),
+            ],
+        );
+
+        let config = Config::from_file(&dir.join("chaos.yaml")).unwrap();
+        let ids: Vec<&str> = config.experiments.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["payments-errors", "search-latency"]);
+        assert_eq!(
+            config.experiments[0].source,
+            Some(dir.join("teams/payments.yaml"))
+        );
+        let _ = std::fs::remove_dir_all(dir);
     }
 
     #[test]
-    fn test_validation_fails_for_duplicate_ids() {
-        let yaml = r#"
+    fn test_targeting_templates_merge_inline_overrides() {
+        let dir = write_files(
+            "templates",
+            &[
+                (
+                    "chaos.yaml",
+                    r#"
+includes: [team.yaml]
+targeting_templates:
+  checkout-canary:
+    paths:
+      - prefix: "/checkout/"
+    headers:
+      x-canary: "true"
+    percentage: 10
 experiments:
-  - id: "test"
+  - id: "checkout-latency"
+    targeting_ref: checkout-canary
     targeting:
-      percentage: 10
+      percentage: 50
     fault:
       type: latency
-      fixed_ms: 100
-  - id: "test"
+      fixed_ms: 200
+"#,
+                ),
+                (
+                    "team.yaml",
+                    "experiments:\n  - id: checkout-errors\n    targeting_ref: checkout-canary\n    fault:\n      type: error\n      status: 503\n",
+                ),
+            ],
+        );
+
+        let config = Config::from_file(&dir.join("chaos.yaml")).unwrap();
+        let latency = &config.experiments[0].targeting;
+        assert_eq!(latency.percentage, 50);
+        assert_eq!(latency.headers.get("x-canary"), Some(&"true".to_string()));
+        assert_eq!(
+            latency.paths,
+            vec![PathMatcher::Prefix {
+                prefix: "/checkout/".to_string()
+            }]
+        );
+        // Included files use the main file's templates
+        assert_eq!(config.experiments[1].targeting.percentage, 10);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_extends_deep_merges_template() {
+        let yaml = r#"
+templates:
+  payments-error:
+    description: "Payments chaos, owned by team-payments"
     targeting:
-      percentage: 10
+      paths:
+        - prefix: "/api/payments/"
+      headers:
+        x-canary: "true"
+      percentage: 5
     fault:
-      type: latency
-      fixed_ms: 200
+      type: error
+      status: 503
+      message: "Chaos: payments unavailable"
+experiments:
+  - id: "refunds-errors"
+    extends: payments-error
+    targeting:
+      paths:
+        - prefix: "/api/refunds/"
+    fault:
+      status: 500
 "#;
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
-        assert!(config.validate().is_err());
+        let config = Config::from_str(yaml, ConfigFormat::Yaml).unwrap();
+        let exp = &config.experiments[0];
+        assert_eq!(exp.description, "Payments chaos, owned by team-payments");
+        assert_eq!(
+            exp.targeting.paths,
+            vec![PathMatcher::Prefix {
+                prefix: "/api/refunds/".to_string()
+            }]
+        );
+        assert_eq!(exp.targeting.percentage, 5);
+        assert_eq!(
+            exp.targeting.headers.get("x-canary"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(
+            exp.fault,
+            Fault::Error {
+                status: 500,
+                message: Some("Chaos: payments unavailable".to_string()),
+                headers: HashMap::new(),
+                status_pool: Vec::new(),
+            }
+        );
     }
 
     #[test]
-    fn test_validation_fails_for_invalid_percentage() {
+    fn test_extends_rejects_unknown_and_chained_templates() {
         let yaml = r#"
+templates:
+  base:
+    targeting: {}
+  derived:
+    extends: base
 experiments:
-  - id: "test"
+  - id: "errors"
+    extends: missing
+    targeting: {}
+    fault:
+      type: error
+      status: 503
+"#;
+        let err = Config::from_str(yaml, ConfigFormat::Yaml)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("templates[derived].extends: templates cannot extend other templates"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("experiments[errors].extends: unknown template 'missing'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_targeting_ref_unknown_template() {
+        let yaml = r#"
+experiments:
+  - id: "checkout-errors"
+    targeting_ref: checkout-canry
+    fault:
+      type: error
+      status: 503
+"#;
+        let config = Config::from_value(serde_yaml::from_str(yaml).unwrap()).unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "experiments[checkout-errors].targeting_ref: unknown targeting template 'checkout-canry'"
+        );
+    }
+
+    #[test]
+    fn test_in_memory_config_refuses_file_references() {
+        for yaml in ["includes: [team.yaml]\n", "experiments_dir: chaos.d\n"] {
+            let err = Config::from_value(serde_yaml::from_str(yaml).unwrap())
+                .unwrap_err()
+                .to_string();
+            assert_eq!(
+                err,
+                "includes and experiments_dir are only supported when loading from a file"
+            );
+            assert!(Config::from_str(yaml, ConfigFormat::Yaml).is_err());
+        }
+    }
+
+    const YAML_FIXTURE: &str = r#"
+settings:
+  dry_run: true
+safety:
+  max_affected_percent: 50
+  max_latency: "10s"
+  excluded_paths: ["/health"]
+  schedule:
+    - days: [mon, fri]
+      start: "09:00"
+      end: "17:00"
+      timezone: "Europe/Berlin"
+experiments:
+  - id: "api-latency"
     targeting:
-      percentage: 150
+      paths:
+        - prefix: "/api/"
+      percentage: 10
     fault:
       type: latency
-      fixed_ms: 100
+      fixed: "500ms"
 "#;
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
-        assert!(config.validate().is_err());
+
+    const JSON_FIXTURE: &str = r#"{
+  "settings": {"dry_run": true},
+  "safety": {
+    "max_affected_percent": 50,
+    "max_latency": "10s",
+    "excluded_paths": ["/health"],
+    "schedule": [
+      {"days": ["mon", "fri"], "start": "09:00", "end": "17:00", "timezone": "Europe/Berlin"}
+    ]
+  },
+  "experiments": [
+    {
+      "id": "api-latency",
+      "targeting": {"paths": [{"prefix": "/api/"}], "percentage": 10},
+      "fault": {"type": "latency", "fixed": "500ms"}
+    }
+  ]
+}"#;
+
+    const TOML_FIXTURE: &str = r#"
+[settings]
+dry_run = true
+
+[safety]
+max_affected_percent = 50
+max_latency = "10s"
+excluded_paths = ["/health"]
+
+[[safety.schedule]]
+days = ["mon", "fri"]
+start = "09:00"
+end = "17:00"
+timezone = "Europe/Berlin"
+
+[[experiments]]
+id = "api-latency"
+
+[experiments.targeting]
+paths = [{ prefix = "/api/" }]
+percentage = 10
+
+[experiments.fault]
+type = "latency"
+fixed = "500ms"
+"#;
+
+    #[test]
+    fn test_config_formats_parse_equal() {
+        let yaml = Config::from_str(YAML_FIXTURE, ConfigFormat::Yaml).unwrap();
+        let json = Config::from_str(JSON_FIXTURE, ConfigFormat::Json).unwrap();
+        let toml = Config::from_str(TOML_FIXTURE, ConfigFormat::Toml).unwrap();
+
+        assert_eq!(yaml.safety.max_latency_ms, 10_000);
+        assert_eq!(
+            yaml.safety.schedule[0].days,
+            vec![Weekday::Mon, Weekday::Fri]
+        );
+        let expected = serde_json::to_value(&yaml).unwrap();
+        assert_eq!(serde_json::to_value(&json).unwrap(), expected);
+        assert_eq!(serde_json::to_value(&toml).unwrap(), expected);
+
+        // Files are dispatched on extension
+        let dir = write_files(
+            "formats",
+            &[("chaos.json", JSON_FIXTURE), ("chaos.toml", TOML_FIXTURE)],
+        );
+        for name in ["chaos.json", "chaos.toml"] {
+            let config = Config::from_file(&dir.join(name)).unwrap();
+            assert_eq!(serde_json::to_value(&config).unwrap(), expected, "{}", name);
+        }
+        let _ = std::fs::remove_dir_all(dir);
     }
 
     #[test]
-    fn test_validation_fails_for_invalid_regex() {
+    fn test_unknown_config_extension() {
+        let err = Config::from_file(Path::new("/etc/zentinel/chaos.ini"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'ini'"), "{}", err);
+        assert!(err.contains("chaos.ini"), "{}", err);
+        assert!(err.contains(".yaml, .yml, .json or .toml"), "{}", err);
+    }
+
+    #[test]
+    fn test_experiments_dir_merges_files_in_order() {
+        let dir = write_files(
+            "experiments-dir",
+            &[
+                ("chaos.yaml", "experiments_dir: chaos.d\n"),
+                (
+                    "chaos.d/b-search.yaml",
+                    "experiments:\n  - id: search-latency\n    targeting: {}\n    fault:\n      type: latency\n      fixed_ms: 100\n",
+                ),
+                ("chaos.d/a-payments.yaml", TEAM_FILE),
+                ("chaos.d/README.md", "not configuration"),
+            ],
+        );
+
+        let config = Config::from_file(&dir.join("chaos.yaml")).unwrap();
+        let ids: Vec<&str> = config.experiments.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["payments-errors", "search-latency"]);
+        assert_eq!(
+            config.experiments[1].source,
+            Some(dir.join("chaos.d/b-search.yaml"))
+        );
+
+        // A duplicate id names both files
+        std::fs::write(dir.join("chaos.d/c-copy.yaml"), TEAM_FILE).unwrap();
+        let err = Config::from_file(&dir.join("chaos.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("a-payments.yaml") && err.contains("c-copy.yaml"),
+            "{}",
+            err
+        );
+
+        // An invalid file is named in the error
+        std::fs::write(dir.join("chaos.d/c-copy.yaml"), "experiments: [").unwrap();
+        let err = Config::from_file(&dir.join("chaos.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("c-copy.yaml"), "{}", err);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_includes_reject_duplicate_ids_across_files() {
+        let dir = write_files(
+            "includes-dup",
+            &[
+                ("chaos.yaml", "includes: [a.yaml, b.yaml]\n"),
+                ("a.yaml", TEAM_FILE),
+                ("b.yaml", TEAM_FILE),
+            ],
+        );
+
+        let err = Config::from_file(&dir.join("chaos.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Duplicate experiment id: payments-errors"));
+        assert!(err.contains("a.yaml") && err.contains("b.yaml"), "{}", err);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_includes_reject_cycles() {
+        let dir = write_files(
+            "includes-cycle",
+            &[
+                ("chaos.yaml", "includes: [a.yaml]\n"),
+                ("a.yaml", "includes: [chaos.yaml]\n"),
+            ],
+        );
+
+        let err = Config::from_file(&dir.join("chaos.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Include cycle"), "{}", err);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_duration_ms() {
+        assert_eq!(parse_duration_ms("500ms"), Ok(500));
+        assert_eq!(parse_duration_ms("30s"), Ok(30_000));
+        assert_eq!(parse_duration_ms("2.5s"), Ok(2_500));
+        assert_eq!(parse_duration_ms("1m"), Ok(60_000));
+        assert_eq!(parse_duration_ms("1h"), Ok(3_600_000));
+        assert_eq!(parse_duration_ms("250"), Ok(250));
+        assert!(parse_duration_ms("5 sec").is_err());
+        assert!(parse_duration_ms("s").is_err());
+    }
+
+    #[test]
+    fn test_duration_strings_round_trip() {
+        let yaml = r#"
+type: latency
+min: "1s"
+max: "2.5s"
+"#;
+        let fault: Fault = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            fault,
+            Fault::Latency {
+                fixed_ms: 0,
+                min_ms: 1000,
+                max_ms: 2500,
+                when: LatencyTiming::Before,
+            }
+        );
+        let reparsed: Fault =
+            serde_yaml::from_str(&serde_yaml::to_string(&fault).unwrap()).unwrap();
+        assert_eq!(reparsed, fault);
+
+        // Millisecond integers keep working
+        let fault: Fault = serde_yaml::from_str("type: timeout\nduration_ms: 30000\n").unwrap();
+        assert_eq!(
+            fault,
+            Fault::Timeout {
+                duration_ms: 30000,
+                status: None,
+                return_success: false,
+            }
+        );
+        let fault: Fault = serde_yaml::from_str("type: timeout\nduration: 30s\n").unwrap();
+        assert_eq!(
+            fault,
+            Fault::Timeout {
+                duration_ms: 30000,
+                status: None,
+                return_success: false,
+            }
+        );
+
+        let settings: Settings = serde_yaml::from_str("drain_auto_resume_after: 5s\n").unwrap();
+        assert_eq!(settings.drain_auto_resume_after_ms, Some(5000));
+        let settings: Settings = serde_yaml::from_str("drain_auto_resume_after_ms: 250\n").unwrap();
+        assert_eq!(settings.drain_auto_resume_after_ms, Some(250));
+        let settings: Settings = serde_yaml::from_str("dry_run: true\n").unwrap();
+        assert_eq!(settings.drain_auto_resume_after_ms, None);
+
+        let err = serde_yaml::from_str::<Fault>("type: latency\nfixed: 500sm\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("invalid duration '500sm'"), "{}", err);
+    }
+
+    #[test]
+    fn test_schedule_serialization_round_trips() {
+        let yaml = "days: [mon, sat]\nstart: \"22:30\"\nend: \"06:00\"\ntimezone: Europe/Berlin\n";
+        let schedule: Schedule = serde_yaml::from_str(yaml).unwrap();
+        let serialized = serde_yaml::to_string(&schedule).unwrap();
+        assert!(serialized.contains("- mon"), "{}", serialized);
+        assert!(serialized.contains("22:30"), "{}", serialized);
+        let reparsed: Schedule = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(reparsed, schedule);
+    }
+
+    #[test]
+    fn test_config_serialization_round_trips() {
         let yaml = r#"
+safety:
+  schedule:
+    - days: [mon, tue, wed, thu, fri]
+      start: "09:00"
+      end: "17:30"
+      timezone: America/New_York
 experiments:
-  - id: "test"
+  - id: "latency"
     targeting:
       paths:
-        - regex: "[invalid"
+        - exact: "/api/users"
+        - prefix: "/api/"
+        - regex: "^/api/v[0-9]+/.*"
+        - glob: "/api/*/orders/**"
+      methods: [GET]
+      headers:
+        x-chaos: "true"
+      percentage: 10
     fault:
       type: latency
-      fixed_ms: 100
+      min_ms: 100
+      max_ms: 500ms
+    schedule:
+      - days: [sat, sun]
+        start: "22:00"
+        end: "06:00"
+        timezone: Europe/Berlin
+  - id: "jitter"
+    targeting: { percentage: 5 }
+    fault: { type: jitter, mean_ms: 100, stddev_ms: 30, max_ms: 1s }
+  - id: "escalating"
+    targeting: { percentage: 5 }
+    fault: { type: escalating_latency, base_ms: 100, factor: 2.0, max_ms: 2s, reset_after_seconds: 60 }
+  - id: "error"
+    targeting: { percentage: 5 }
+    fault:
+      type: error
+      status: 503
+      message: "Unavailable"
+      headers:
+        retry-after: "1"
+  - id: "timeout"
+    targeting: { percentage: 5 }
+    fault: { type: timeout, duration_ms: 30s, status: 503 }
+  - id: "throttle"
+    targeting: { percentage: 5 }
+    fault: { type: throttle, bytes_per_second: 1024 }
+  - id: "corrupt"
+    targeting: { percentage: 5 }
+    fault: { type: corrupt, probability: 0.5 }
+  - id: "reset"
+    targeting: { percentage: 5 }
+    fault: { type: reset, mode: rst, status: 503 }
+  - id: "duplicate"
+    targeting: { percentage: 5 }
+    fault: { type: duplicate, count: 2 }
+  - id: "headers"
+    targeting: { percentage: 5 }
+    fault:
+      type: mutate_response_headers
+      set:
+        x-chaos: "1"
+      remove: [cache-control]
+  - id: "status"
+    targeting: { percentage: 5 }
+    fault: { type: status_from_header, header: x-chaos-status, default_status: 500 }
+  - id: "echo"
+    targeting: { percentage: 5 }
+    fault: { type: echo, status: 202, content_type: application/json }
+  - id: "rewrite"
+    targeting: { percentage: 5 }
+    fault: { type: rewrite_path, to: /api/v0/orders }
+  - id: "fixture"
+    targeting: { percentage: 5 }
+    fault: { type: return_fixture, status: 200, file: fixtures/stale.json }
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.validate().unwrap();
+        let types: Vec<&str> = config
+            .experiments
+            .iter()
+            .map(|e| e.fault.type_name())
+            .collect();
+        assert_eq!(types, Fault::TYPE_NAMES);
+
+        let expected = serde_json::to_value(&config).unwrap();
+        let serialized = serde_yaml::to_string(&config).unwrap();
+        let reparsed: Config =
+            serde_yaml::from_str(&serialized).unwrap_or_else(|e| panic!("{}\n{}", e, serialized));
+        assert_eq!(serde_json::to_value(&reparsed).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_weighted_paths() {
+        let yaml = r#"
+id: "spread"
+targeting:
+  paths:
+    - prefix: "/api/a"
+      weight: 50
+    - { exact: "/api/b", weight: 30 }
+    - { glob: "/api/*/c", weight: 20 }
+fault:
+  type: error
+  status: 503
+"#;
+        let experiment: Experiment = serde_yaml::from_str(yaml).unwrap();
+        experiment.validate().unwrap();
+        assert_eq!(
+            experiment.targeting.paths[0],
+            PathMatcher::Weighted {
+                matcher: Box::new(PathMatcher::Prefix {
+                    prefix: "/api/a".to_string()
+                }),
+                weight: 50,
+            }
+        );
+
+        let serialized = serde_yaml::to_string(&experiment).unwrap();
+        assert!(serialized.contains("weight: 30"), "{}", serialized);
+        let reparsed: Experiment = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(reparsed, experiment);
+
+        let mut mixed = experiment.clone();
+        mixed.targeting.paths.push(PathMatcher::Exact {
+            exact: "/api/d".to_string(),
+        });
+        let err = mixed.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("set a weight on every path or on none"),
+            "{}",
+            err
+        );
+
+        let mut zero = experiment;
+        zero.targeting.paths[0] = PathMatcher::Weighted {
+            matcher: Box::new(PathMatcher::Prefix {
+                prefix: "/api/a".to_string(),
+            }),
+            weight: 0,
+        };
+        let err = zero.validate().unwrap_err().to_string();
+        assert!(err.contains("paths[0]: weight must be > 0"), "{}", err);
+    }
+
+    #[test]
+    fn test_jitter_validation() {
+        let fault: Fault = serde_yaml::from_str(
+            r#"
+type: jitter
+mean_ms: 100
+stddev_ms: 30
+max_ms: 1000
+"#,
+        )
+        .unwrap();
+        assert!(fault.validate().is_ok());
+        assert_eq!(fault.to_string(), "jitter 100ms ± 30ms (max 1000ms)");
+
+        let zero_stddev = Fault::Jitter {
+            mean_ms: 100,
+            stddev_ms: 0,
+            max_ms: 1000,
+        };
+        assert!(zero_stddev.validate().is_err());
+
+        let max_below_mean = Fault::Jitter {
+            mean_ms: 100,
+            stddev_ms: 30,
+            max_ms: 50,
+        };
+        assert!(max_below_mean.validate().is_err());
+    }
+
+    #[test]
+    fn test_escalating_latency_validation() {
+        let fault: Fault = serde_yaml::from_str(
+            r#"
+type: escalating_latency
+base_ms: 100
+factor: 2
+max_ms: 2s
+reset_after_seconds: 30
+"#,
+        )
+        .unwrap();
+        assert!(fault.validate().is_ok());
+        assert_eq!(
+            fault.to_string(),
+            "escalating latency 100ms x2 (max 2000ms, reset after 30s)"
+        );
+        assert_eq!(fault.max_delay_ms(), Some(2000));
+
+        let shrinking = Fault::EscalatingLatency {
+            base_ms: 100,
+            factor: 0.5,
+            max_ms: 2000,
+            reset_after_seconds: 30,
+        };
+        let err = shrinking.validate().unwrap_err().to_string();
+        assert!(err.contains("factor: must be >= 1.0, got 0.5"), "{}", err);
+
+        let max_below_base = Fault::EscalatingLatency {
+            base_ms: 100,
+            factor: 2.0,
+            max_ms: 50,
+            reset_after_seconds: 30,
+        };
+        let err = max_below_base.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("max_ms: must be >= base_ms (100), got 50"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_bypass() {
+        let yaml = r#"
+safety:
+  bypass:
+    secret: "s3cret"
+experiments: []
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let bypass = config.safety.bypass.unwrap();
+        assert_eq!(bypass.header, "x-chaos-bypass");
+        assert_eq!(bypass.mode, BypassMode::Hmac);
+
+        let yaml = r#"
+safety:
+  bypass:
+    secret: ""
+    mode: static
+experiments: []
 "#;
         let config: Config = serde_yaml::from_str(yaml).unwrap();
         assert!(config.validate().is_err());
@@ -0,0 +1,147 @@
+//! Per-client cooldown between injections.
+//!
+//! After a client receives a fault from an experiment, that experiment
+//! skips the client until the cooldown elapses. Last-injection times are
+//! kept in a TTL map that is swept of expired entries periodically and
+//! never grows beyond `max_clients`.
+
+use crate::config::ClientCooldownConfig;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Client key to last injection time for one experiment.
+pub struct ClientCooldown {
+    config: ClientCooldownConfig,
+    entries: Mutex<CooldownEntries>,
+}
+
+struct CooldownEntries {
+    /// Second (since agent start) of each client's last injection.
+    last_injection: HashMap<String, u64>,
+    /// Second of the last sweep for expired entries.
+    last_sweep: u64,
+}
+
+impl ClientCooldown {
+    /// Create a cooldown tracker from configuration.
+    pub fn new(mut config: ClientCooldownConfig) -> Self {
+        config.key_header = config.key_header.to_lowercase();
+        Self {
+            config,
+            entries: Mutex::new(CooldownEntries {
+                last_injection: HashMap::new(),
+                last_sweep: 0,
+            }),
+        }
+    }
+
+    /// Client key for a request. `headers` must have lowercased names.
+    pub fn client_key<'a>(&self, headers: &'a HashMap<String, String>) -> Option<&'a str> {
+        headers.get(&self.config.key_header).map(String::as_str)
+    }
+
+    /// Check whether a client is still cooling down at second `now`.
+    ///
+    /// Clients without a key are never cooling down.
+    pub fn is_cooling_at(&self, key: Option<&str>, now: u64) -> bool {
+        let Some(key) = key else {
+            return false;
+        };
+        let entries = self.lock();
+        entries
+            .last_injection
+            .get(key)
+            .is_some_and(|last| now < last + self.config.seconds)
+    }
+
+    /// Record an injection for a client at second `now`.
+    pub fn record_at(&self, key: Option<&str>, now: u64) {
+        let Some(key) = key else {
+            return;
+        };
+        let mut entries = self.lock();
+        let full = entries.last_injection.len() >= self.config.max_clients;
+        if full || now >= entries.last_sweep + self.config.seconds {
+            let seconds = self.config.seconds;
+            entries
+                .last_injection
+                .retain(|_, last| now < *last + seconds);
+            entries.last_sweep = now;
+        }
+        // Still full of live entries: leave this client untracked rather
+        // than grow without bound
+        if entries.last_injection.len() < self.config.max_clients
+            || entries.last_injection.contains_key(key)
+        {
+            entries.last_injection.insert(key.to_string(), now);
+        }
+    }
+
+    /// Number of clients currently tracked.
+    pub fn len(&self) -> usize {
+        self.lock().last_injection.len()
+    }
+
+    /// Whether no clients are tracked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CooldownEntries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cooldown(seconds: u64, max_clients: usize) -> ClientCooldown {
+        ClientCooldown::new(ClientCooldownConfig {
+            seconds,
+            key_header: "x-user-id".to_string(),
+            max_clients,
+        })
+    }
+
+    #[test]
+    fn test_cooldown_expires() {
+        let cooldown = cooldown(300, 100);
+        assert!(!cooldown.is_cooling_at(Some("alice"), 0));
+
+        cooldown.record_at(Some("alice"), 10);
+        assert!(cooldown.is_cooling_at(Some("alice"), 10));
+        assert!(cooldown.is_cooling_at(Some("alice"), 309));
+        assert!(!cooldown.is_cooling_at(Some("alice"), 310));
+        assert!(!cooldown.is_cooling_at(Some("bob"), 10));
+
+        // Clients without a key are not tracked
+        cooldown.record_at(None, 10);
+        assert!(!cooldown.is_cooling_at(None, 10));
+        assert_eq!(cooldown.len(), 1);
+    }
+
+    #[test]
+    fn test_expired_entries_are_evicted() {
+        let cooldown = cooldown(60, 1000);
+        for i in 0..500 {
+            cooldown.record_at(Some(&format!("user-{}", i)), 0);
+        }
+        assert_eq!(cooldown.len(), 500);
+
+        // The next record after the cooldown sweeps the expired entries
+        cooldown.record_at(Some("late"), 60);
+        assert_eq!(cooldown.len(), 1);
+    }
+
+    #[test]
+    fn test_memory_is_bounded() {
+        let cooldown = cooldown(300, 10);
+        for i in 0..100 {
+            cooldown.record_at(Some(&format!("user-{}", i)), 0);
+        }
+        assert_eq!(cooldown.len(), 10);
+        assert!(cooldown.is_cooling_at(Some("user-0"), 1));
+        assert!(!cooldown.is_cooling_at(Some("user-99"), 1));
+    }
+}
@@ -0,0 +1,138 @@
+//! A flag that may lower itself once a deadline has passed.
+//!
+//! Shared by the runtime pause and the drain state. The flag and its
+//! deadline live in one atomic, so a flag raised again while an old
+//! deadline expires is never cleared by that expiry. Expiry is evaluated
+//! lazily whenever the flag is checked.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// `until_ms` while the flag is down.
+const DOWN: u64 = 0;
+
+/// `until_ms` while the flag is raised until lowered.
+const UNTIL_LOWERED: u64 = u64::MAX;
+
+/// Flag raised for a duration or until lowered.
+pub struct DeadlineFlag {
+    /// [`DOWN`], [`UNTIL_LOWERED`], or the deadline in milliseconds since
+    /// `epoch`.
+    until_ms: AtomicU64,
+    epoch: Instant,
+    /// Logged once when a bounded flag expires.
+    expiry_message: &'static str,
+}
+
+impl DeadlineFlag {
+    /// Create a lowered flag, logging `expiry_message` when a bounded flag
+    /// expires.
+    pub fn new(expiry_message: &'static str) -> Self {
+        Self {
+            until_ms: AtomicU64::new(DOWN),
+            epoch: Instant::now(),
+            expiry_message,
+        }
+    }
+
+    /// Raise the flag, for `duration` or until lowered, returning whether
+    /// it was down. Raising again replaces the duration; a duration too
+    /// long to represent saturates.
+    pub fn raise(&self, duration: Option<Duration>) -> bool {
+        let was_raised = self.is_raised();
+        let until = duration.map_or(UNTIL_LOWERED, |d| {
+            let ms = u64::try_from(d.as_millis()).unwrap_or(u64::MAX);
+            self.elapsed_ms()
+                .saturating_add(ms)
+                .clamp(DOWN + 1, UNTIL_LOWERED - 1)
+        });
+        self.until_ms.store(until, Ordering::SeqCst);
+        !was_raised
+    }
+
+    /// Lower the flag, returning whether it was raised.
+    pub fn lower(&self) -> bool {
+        let was_raised = self.is_raised();
+        self.until_ms.store(DOWN, Ordering::SeqCst);
+        was_raised
+    }
+
+    /// Check whether the flag is raised, lowering it if its deadline has
+    /// passed.
+    pub fn is_raised(&self) -> bool {
+        self.is_raised_at(self.elapsed_ms())
+    }
+
+    /// Time left before a bounded flag lowers itself, `None` when down or
+    /// raised until lowered.
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = self.elapsed_ms();
+        if !self.is_raised_at(now) {
+            return None;
+        }
+        match self.until_ms.load(Ordering::SeqCst) {
+            DOWN | UNTIL_LOWERED => None,
+            until => Some(Duration::from_millis(until.saturating_sub(now))),
+        }
+    }
+
+    pub(crate) fn is_raised_at(&self, now_ms: u64) -> bool {
+        let until = self.until_ms.load(Ordering::SeqCst);
+        match until {
+            DOWN => return false,
+            UNTIL_LOWERED => return true,
+            until if now_ms < until => return true,
+            _ => {}
+        }
+        // Only the caller that clears this deadline logs; a flag raised
+        // again since is left alone
+        if self
+            .until_ms
+            .compare_exchange(until, DOWN, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            info!("{}", self.expiry_message);
+        }
+        self.until_ms.load(Ordering::SeqCst) != DOWN
+    }
+
+    pub(crate) fn elapsed_ms(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_flag_expires() {
+        let flag = DeadlineFlag::new("expired");
+        assert!(flag.raise(Some(Duration::from_secs(60))));
+        assert!(!flag.raise(Some(Duration::from_secs(60))));
+        assert!(flag.remaining().unwrap() > Duration::from_secs(59));
+
+        let now = flag.elapsed_ms();
+        assert!(flag.is_raised_at(now + 59_000));
+        assert!(!flag.is_raised_at(now + 60_001));
+        assert!(!flag.is_raised());
+        assert_eq!(flag.remaining(), None);
+
+        // A bounded flag can be made open-ended
+        flag.raise(Some(Duration::from_millis(1)));
+        flag.raise(None);
+        assert!(flag.is_raised_at(u64::MAX));
+        assert_eq!(flag.remaining(), None);
+        assert!(flag.lower());
+        assert!(!flag.lower());
+    }
+
+    #[test]
+    fn test_huge_duration_saturates() {
+        let flag = DeadlineFlag::new("expired");
+        flag.raise(Some(Duration::from_secs(u64::MAX)));
+        assert!(flag.is_raised_at(u64::MAX - 2));
+        assert!(flag.remaining().is_some());
+    }
+}
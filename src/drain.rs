@@ -0,0 +1,94 @@
+//! Drain state: no new faults while the proxy moves traffic away.
+//!
+//! A drain requested by the proxy lasts until it is lifted through the
+//! admin API, or, with `settings.drain_auto_resume_after_ms`, until its
+//! deadline passes. Expiry is evaluated lazily whenever the state is
+//! checked, like a bounded pause. A shutdown, or the end of a
+//! `--max-duration` run, drains for good.
+
+use crate::deadline::DeadlineFlag;
+use anyhow::{bail, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Drain state shared by the agent and its control handles.
+pub struct DrainState {
+    flag: DeadlineFlag,
+    /// Set by a shutdown, which cannot be lifted.
+    terminal: AtomicBool,
+}
+
+impl Default for DrainState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrainState {
+    pub fn new() -> Self {
+        Self {
+            flag: DeadlineFlag::new("Drain period over - resuming fault injection"),
+            terminal: AtomicBool::new(false),
+        }
+    }
+
+    /// Start draining, for `duration` or until lifted, returning whether
+    /// the agent was running. A terminal drain stays terminal.
+    pub fn drain(&self, duration: Option<Duration>) -> bool {
+        let duration = duration.filter(|_| !self.terminal.load(Ordering::SeqCst));
+        self.flag.raise(duration)
+    }
+
+    /// Drain for good, returning whether the agent was running.
+    pub fn drain_for_good(&self) -> bool {
+        self.terminal.store(true, Ordering::SeqCst);
+        self.drain(None)
+    }
+
+    /// Lift a drain, returning whether the agent was draining. A terminal
+    /// drain is refused.
+    pub fn undrain(&self) -> Result<bool> {
+        if self.terminal.load(Ordering::SeqCst) {
+            bail!("the agent is shutting down, the drain cannot be lifted");
+        }
+        Ok(self.flag.lower())
+    }
+
+    /// Check whether the agent is draining, lifting an expired bounded
+    /// drain.
+    pub fn is_draining(&self) -> bool {
+        self.flag.is_raised()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_drain_expires() {
+        let drain = DrainState::new();
+        assert!(drain.drain(Some(Duration::from_secs(60))));
+        assert!(!drain.drain(Some(Duration::from_secs(60))));
+
+        let now = drain.flag.elapsed_ms();
+        assert!(drain.flag.is_raised_at(now + 59_000));
+        assert!(!drain.flag.is_raised_at(now + 60_001));
+        assert!(!drain.is_draining());
+
+        assert!(drain.drain(None));
+        assert!(drain.flag.is_raised_at(u64::MAX));
+        assert!(drain.undrain().unwrap());
+        assert!(!drain.undrain().unwrap());
+    }
+
+    #[test]
+    fn test_terminal_drain_cannot_be_lifted() {
+        let drain = DrainState::new();
+        assert!(drain.drain_for_good());
+        assert!(drain.undrain().is_err());
+        // A later bounded drain does not make it expire
+        drain.drain(Some(Duration::from_millis(1)));
+        assert!(drain.flag.is_raised_at(u64::MAX));
+    }
+}
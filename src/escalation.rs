@@ -0,0 +1,76 @@
+//! Escalating latency state.
+//!
+//! An escalating latency experiment delays its first injection by
+//! `base_ms` and each following one by `factor` times the previous delay,
+//! up to `max_ms`, like a dependency degrading under retries. A quiet
+//! period of `reset_after_seconds` without injections starts over at
+//! `base_ms`.
+
+use std::sync::Mutex;
+
+/// Current delay of one escalating latency experiment.
+pub struct Escalation {
+    base_ms: u64,
+    factor: f64,
+    max_ms: u64,
+    reset_after_seconds: u64,
+    state: Mutex<Option<Step>>,
+}
+
+#[derive(Clone, Copy)]
+struct Step {
+    /// Delay of the last injection.
+    delay_ms: u64,
+    /// Second (since agent start) of the last injection.
+    at: u64,
+}
+
+impl Escalation {
+    /// Create a tracker for an escalating latency fault.
+    pub fn new(base_ms: u64, factor: f64, max_ms: u64, reset_after_seconds: u64) -> Self {
+        Self {
+            base_ms,
+            factor,
+            max_ms,
+            reset_after_seconds,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Delay for an injection at second `now`, advancing the escalation.
+    pub fn next_delay_ms_at(&self, now: u64) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let delay_ms = match *state {
+            Some(step) if now < step.at + self.reset_after_seconds => {
+                ((step.delay_ms as f64 * self.factor).round() as u64).min(self.max_ms)
+            }
+            _ => self.base_ms,
+        };
+        *state = Some(Step { delay_ms, at: now });
+        delay_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalates_to_cap_and_resets() {
+        let escalation = Escalation::new(100, 2.0, 1000, 60);
+        let delays: Vec<u64> = (0..6).map(|i| escalation.next_delay_ms_at(i)).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+
+        // Still escalated just before the quiet period ends
+        assert_eq!(escalation.next_delay_ms_at(64), 1000);
+        assert_eq!(escalation.next_delay_ms_at(124), 100);
+        assert_eq!(escalation.next_delay_ms_at(125), 200);
+    }
+
+    #[test]
+    fn test_fractional_factor_rounds() {
+        let escalation = Escalation::new(100, 1.5, 500, 60);
+        let delays: Vec<u64> = (0..5).map(|i| escalation.next_delay_ms_at(i)).collect();
+        assert_eq!(delays, vec![100, 150, 225, 338, 500]);
+    }
+}
@@ -0,0 +1,373 @@
+//! Example configuration printed by `--print-config`.
+//!
+//! Built from the real configuration types, so the printed YAML always
+//! matches the schema and is checked to load. Durations are printed as
+//! strings under their short aliases, and fields left at an empty default
+//! are omitted.
+
+use crate::builder::{ExperimentBuilder, TargetingBuilder};
+use crate::config::{Config, CorruptMode, Fault, SafetyConfig, Schedule, Settings};
+use crate::schema::ALIASES;
+use anyhow::Result;
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Comments printed before top-level sections of the example.
+const SECTION_COMMENTS: &[(&str, &str)] = &[
+    ("settings:", "# Global settings"),
+    ("safety:", "# Safety limits"),
+    (
+        "experiments:",
+        "# Fault experiments, one of each fault type. Only the first few are enabled.",
+    ),
+];
+
+/// Comments printed after the first occurrence of a field, as (section,
+/// field, comment).
+const FIELD_COMMENTS: &[(&str, &str, &str)] = &[
+    ("settings", "enabled", "Global kill switch"),
+    ("settings", "dry_run", "Log faults without applying"),
+    ("settings", "log_injections", "Log when faults are injected"),
+    (
+        "settings",
+        "intensity",
+        "Scales every experiment's percentage",
+    ),
+    (
+        "safety",
+        "max_affected_percent",
+        "Never affect more than 50% of traffic",
+    ),
+    ("safety", "schedule", "Only active during these windows"),
+    ("safety", "excluded_paths", "Never inject faults here"),
+    ("safety", "max_latency", "Ceiling on any injected delay"),
+    (
+        "experiments",
+        "percentage",
+        "Affect this share of matching requests",
+    ),
+    (
+        "experiments",
+        "fixed",
+        "Fixed delay; or a random range with min and max",
+    ),
+    (
+        "experiments",
+        "duration",
+        "Hold the request this long, then time out",
+    ),
+];
+
+/// Zero-based column trailing field comments are aligned to.
+const COMMENT_COLUMN: usize = 35;
+
+/// Build a representative configuration: defaults plus an office-hours
+/// schedule and one experiment per fault type.
+pub fn example_config() -> Result<Config> {
+    let office_hours = Schedule {
+        days: vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ],
+        start: NaiveTime::from_hms_opt(9, 0, 0).expect("valid time"),
+        end: NaiveTime::from_hms_opt(17, 0, 0).expect("valid time"),
+        timezone: Tz::UTC,
+    };
+
+    let experiments = vec![
+        ExperimentBuilder::new("api-latency")
+            .description("Add latency to API calls")
+            .path_prefix("/api/")
+            .method("GET")
+            .method("POST")
+            .percentage(10)
+            .latency_fixed(500)
+            .build()?,
+        ExperimentBuilder::new("payment-errors")
+            .description("Inject 500 errors into payment service")
+            .path_exact("/api/payments")
+            .percentage(5)
+            .fault(Fault::Error {
+                status: 500,
+                message: Some("Chaos: Internal Server Error".to_string()),
+                headers: HashMap::from([("x-chaos-injected".to_string(), "true".to_string())]),
+                status_pool: Vec::new(),
+            })
+            .build()?,
+        ExperimentBuilder::new("header-triggered-latency")
+            .description("Add latency when the x-chaos-latency header is present")
+            .header("x-chaos-latency", "true")
+            .latency_range(1000, 3000)
+            .build()?,
+        ExperimentBuilder::new("upstream-timeout")
+            .enabled(false)
+            .description("Simulate upstream timeouts")
+            .path_regex("^/api/external/.*")
+            .percentage(2)
+            .timeout(30_000)
+            .build()?,
+        ExperimentBuilder::new("search-jitter")
+            .enabled(false)
+            .description("Long-tail latency on search")
+            .path_prefix("/api/search/")
+            .percentage(10)
+            .jitter(120, 40, 2000)
+            .build()?,
+        ExperimentBuilder::new("degrading-inventory")
+            .enabled(false)
+            .description("Latency that doubles on each hit, like a struggling dependency")
+            .path_prefix("/api/inventory/")
+            .percentage(5)
+            .fault(Fault::EscalatingLatency {
+                base_ms: 100,
+                factor: 2.0,
+                max_ms: 3200,
+                reset_after_seconds: 60,
+            })
+            .build()?,
+        ExperimentBuilder::new("download-throttle")
+            .enabled(false)
+            .description("Slow down file downloads")
+            .path_glob("/files/**")
+            .percentage(5)
+            .throttle(10_240)
+            .build()?,
+        ExperimentBuilder::new("corrupt-responses")
+            .enabled(false)
+            .description("Flip bits in upstream response bodies")
+            .path_prefix("/api/")
+            .percentage(1)
+            .fault(Fault::Corrupt {
+                probability: 1.0,
+                mode: CorruptMode::Bitrot,
+                byte_flip_probability: 0.01,
+                size_bytes: None,
+            })
+            .build()?,
+        ExperimentBuilder::new("connection-reset")
+            .enabled(false)
+            .description("Simulate dropped connections")
+            .path_prefix("/api/")
+            .percentage(1)
+            .reset()
+            .build()?,
+        ExperimentBuilder::new("duplicate-orders")
+            .enabled(false)
+            .description("Replay order submissions to test idempotency")
+            .path_exact("/api/orders")
+            .method("POST")
+            .percentage(1)
+            .duplicate(1)
+            .build()?,
+        ExperimentBuilder::new("strip-cache-headers")
+            .enabled(false)
+            .description("Remove caching headers from responses")
+            .path_prefix("/static/")
+            .percentage(10)
+            .fault(Fault::MutateResponseHeaders {
+                set: HashMap::new(),
+                remove: vec!["cache-control".to_string()],
+            })
+            .build()?,
+        ExperimentBuilder::new("status-on-demand")
+            .enabled(false)
+            .description("Return the status named in a request header")
+            .targeting(
+                TargetingBuilder::new()
+                    .header("x-chaos-enabled", "true")
+                    .build()?,
+            )
+            .fault(Fault::StatusFromHeader {
+                header: "x-chaos-force-status".to_string(),
+                default_status: 503,
+            })
+            .build()?,
+        ExperimentBuilder::new("echo-contract")
+            .enabled(false)
+            .description("Send webhook payloads straight back to the client")
+            .path_prefix("/api/webhooks/")
+            .method("POST")
+            .percentage(1)
+            .fault(Fault::Echo {
+                status: 200,
+                content_type: None,
+            })
+            .build()?,
+        ExperimentBuilder::new("orders-misroute")
+            .enabled(false)
+            .description("Send order reads to the retired v1 endpoint")
+            .path_prefix("/api/v2/orders")
+            .method("GET")
+            .percentage(1)
+            .fault(Fault::RewritePath {
+                to: "/api/v1/orders".to_string(),
+            })
+            .build()?,
+        ExperimentBuilder::new("catalog-stale")
+            .enabled(false)
+            .description("Serve a stale catalog snapshot instead of the live one")
+            .path_prefix("/api/catalog")
+            .method("GET")
+            .percentage(1)
+            .fault(Fault::ReturnFixture {
+                status: 200,
+                file: "fixtures/catalog-stale.json".into(),
+                content_type: None,
+                body: None,
+            })
+            .build()?,
+    ];
+
+    let config = Config {
+        settings: Settings::default(),
+        safety: SafetyConfig {
+            schedule: vec![office_hours],
+            ..SafetyConfig::default()
+        },
+        experiments,
+        ..Config::default()
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+/// Render [`example_config`] as commented YAML.
+pub fn example_yaml() -> Result<String> {
+    let mut value = serde_yaml::to_value(example_config()?)?;
+    humanize(&mut value);
+    let yaml = serde_yaml::to_string(&value)?;
+    let mut out = String::from(
+        "# Chaos Engineering Agent Configuration\n\
+         # See https://zentinelproxy.io/agents/chaos/ for full documentation\n",
+    );
+    let mut section = "";
+    let mut commented = HashSet::new();
+    for line in yaml.lines() {
+        if let Some((key, comment)) = SECTION_COMMENTS.iter().find(|(key, _)| *key == line) {
+            section = key.trim_end_matches(':');
+            out.push('\n');
+            out.push_str(comment);
+            out.push('\n');
+        }
+        let field = line
+            .trim_start()
+            .trim_start_matches("- ")
+            .split(':')
+            .next()
+            .unwrap_or_default();
+        let comment = FIELD_COMMENTS
+            .iter()
+            .find(|(s, f, _)| *s == section && *f == field && commented.insert((s, f)));
+        match comment {
+            Some((_, _, comment)) => out.push_str(&format!(
+                "{:<width$}# {}\n",
+                line,
+                comment,
+                width = COMMENT_COLUMN
+            )),
+            None => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Print millisecond fields as duration strings under their aliases, e.g.
+/// `fixed_ms: 500` as `fixed: 500ms`, and drop fields left at a null, empty
+/// or zero-duration default.
+fn humanize(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            let mut humanized = Mapping::new();
+            for (key, mut value) in std::mem::take(map) {
+                let alias = key.as_str().and_then(|key| {
+                    ALIASES
+                        .iter()
+                        .find(|(field, _)| *field == key && field.ends_with("_ms"))
+                });
+                let (key, value) = match (alias, value.as_u64()) {
+                    (Some(_), Some(0)) => continue,
+                    (Some((_, alias)), Some(ms)) => {
+                        (Value::from(*alias), Value::from(format_duration(ms)))
+                    }
+                    _ => {
+                        humanize(&mut value);
+                        (key, value)
+                    }
+                };
+                if !is_empty(&value) {
+                    humanized.insert(key, value);
+                }
+            }
+            *map = humanized;
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(humanize),
+        _ => {}
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Sequence(items) => items.is_empty(),
+        Value::Mapping(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// Format milliseconds in the largest unit that divides them exactly.
+fn format_duration(ms: u64) -> String {
+    const UNITS: &[(u64, &str)] = &[(3_600_000, "h"), (60_000, "m"), (1_000, "s")];
+    match UNITS.iter().find(|(scale, _)| ms.is_multiple_of(*scale)) {
+        Some((scale, unit)) => format!("{}{}", ms / scale, unit),
+        None => format!("{}ms", ms),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_parses_and_validates() {
+        let yaml = example_yaml().unwrap();
+        let config: Config = serde_yaml::from_str(&yaml).unwrap();
+        config.validate().unwrap();
+
+        let types: Vec<&str> = config
+            .experiments
+            .iter()
+            .map(|e| e.fault.type_name())
+            .collect();
+        for name in Fault::TYPE_NAMES {
+            assert!(types.contains(name), "no example for {}", name);
+        }
+        assert_eq!(config.safety.schedule.len(), 1);
+        assert!(yaml.contains("# Safety limits"), "{}", yaml);
+
+        // Durations print as strings, with a comment on first use
+        assert!(yaml.contains("max_latency: 1m"), "{}", yaml);
+        let fixed = yaml
+            .lines()
+            .find(|line| line.trim_start().starts_with("fixed: 500ms "))
+            .unwrap();
+        assert_eq!(
+            fixed.find("# Fixed delay"),
+            Some(COMMENT_COLUMN),
+            "{}",
+            fixed
+        );
+        assert!(yaml.contains("duration: 30s"), "{}", yaml);
+        assert!(!yaml.contains("_ms:"), "{}", yaml);
+        assert_eq!(yaml.matches("# Affect this share").count(), 1);
+        assert_eq!(format_duration(1500), "1500ms");
+        assert_eq!(format_duration(7_200_000), "2h");
+    }
+}
@@ -0,0 +1,325 @@
+//! Human-readable explanation of the effective match plan.
+//!
+//! Used by `--validate --explain` to show how each experiment will be
+//! matched and which fault it injects. Output is deterministic so it can be
+//! diffed in CI.
+
+use crate::config::{Config, PathMatcher, Schedule, Targeting};
+use crate::targeting::CompiledTargeting;
+use anyhow::Result;
+use chrono::Weekday;
+use std::fmt::Write;
+
+/// Render the effective match plan for a configuration.
+pub fn explain(config: &Config) -> String {
+    let compiled: Vec<Result<CompiledTargeting>> = config
+        .experiments
+        .iter()
+        .map(|exp| CompiledTargeting::with_settings(&exp.targeting, &config.settings))
+        .collect();
+
+    let enabled = config.experiments.iter().filter(|e| e.enabled).count();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} experiments ({} enabled), schedule: {}",
+        config.experiments.len(),
+        enabled,
+        describe_schedule(config)
+    );
+
+    for (index, exp) in config.experiments.iter().enumerate() {
+        let targeting = &exp.targeting;
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "experiment \"{}\" ({})",
+            exp.id,
+            if exp.enabled { "enabled" } else { "disabled" }
+        );
+        if !exp.description.is_empty() {
+            let _ = writeln!(out, "  description: {}", exp.description);
+        }
+        let _ = writeln!(out, "  paths: {}", describe_paths(&targeting.paths));
+        let _ = writeln!(out, "  methods: {}", describe_methods(&targeting.methods));
+        let _ = writeln!(out, "  headers: {}", describe_headers(targeting));
+        let max_percent = config.safety.max_affected_percent;
+        if let Some(n) = targeting.every_n {
+            let _ = writeln!(out, "  selection: every {} matching requests", n);
+        } else if targeting.percentage > max_percent {
+            let _ = writeln!(
+                out,
+                "  percentage: {}% (clamped to {}%)",
+                targeting.percentage, max_percent
+            );
+        } else {
+            let _ = writeln!(out, "  percentage: {}%", targeting.percentage);
+        }
+        if !exp.schedule.is_empty() {
+            let windows: Vec<String> = exp.schedule.iter().map(describe_window).collect();
+            let _ = writeln!(out, "  schedule: {}", windows.join("; "));
+        }
+        if !targeting.days.is_empty() {
+            let _ = writeln!(
+                out,
+                "  days: {} {}",
+                describe_days(&targeting.days),
+                config.safety.timezone.name()
+            );
+        }
+        if let Some(precheck) = &exp.precheck {
+            let _ = writeln!(
+                out,
+                "  precheck: {} (expect {}, every {}s)",
+                precheck.url, precheck.expect_status, precheck.interval_seconds
+            );
+        }
+        let _ = writeln!(out, "  fault: {}", exp.fault);
+        if let Some(max) = exp.max_injections {
+            let _ = writeln!(out, "  max injections: {}", max);
+        }
+
+        if !exp.enabled {
+            continue;
+        }
+
+        if targeting.percentage == 0 && targeting.every_n.is_none() {
+            let _ = writeln!(out, "  warning: percentage is 0, experiment never injects");
+        }
+
+        let current = match &compiled[index] {
+            Ok(current) => current,
+            Err(e) => {
+                let _ = writeln!(out, "  error: {}, experiment is disabled", e);
+                continue;
+            }
+        };
+
+        // An earlier experiment at 100% that matches everything this one
+        // matches always wins the apply loop. Experiments with their own
+        // schedules or days are only active part of the time, so they
+        // neither shadow nor get shadowed.
+        for (earlier, earlier_targeting) in
+            config.experiments[..index].iter().zip(&compiled[..index])
+        {
+            let Ok(earlier_targeting) = earlier_targeting else {
+                continue;
+            };
+            if earlier.enabled
+                && earlier.schedule.is_empty()
+                && exp.schedule.is_empty()
+                && earlier.targeting.days.is_empty()
+                && targeting.days.is_empty()
+                && match earlier.targeting.every_n {
+                    Some(n) => n == 1,
+                    None => earlier.targeting.percentage.min(max_percent) >= 100,
+                }
+                && earlier_targeting.covers(current)
+            {
+                let _ = writeln!(out, "  warning: shadowed by experiment \"{}\"", earlier.id);
+            }
+        }
+
+        let overlaps = current.excluded_overlaps(&config.safety.excluded_paths);
+        if !overlaps.is_empty() {
+            let _ = writeln!(
+                out,
+                "  warning: targeting overlaps excluded paths: {}",
+                overlaps.join(", ")
+            );
+        }
+    }
+
+    out
+}
+
+fn describe_schedule(config: &Config) -> String {
+    let safety = &config.safety;
+    let mut description = if safety.schedule.is_empty() {
+        "always".to_string()
+    } else {
+        safety
+            .schedule
+            .iter()
+            .map(describe_window)
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+    if !safety.blackout_dates.is_empty() {
+        let _ = write!(
+            description,
+            " (except {} blackout date rules)",
+            safety.blackout_dates.len()
+        );
+    }
+    if !safety.only_dates.is_empty() {
+        let _ = write!(
+            description,
+            " (only on {} date rules)",
+            safety.only_dates.len()
+        );
+    }
+    description
+}
+
+fn describe_window(schedule: &Schedule) -> String {
+    format!(
+        "{} {}-{} {}",
+        describe_days(&schedule.days),
+        schedule.start.format("%H:%M"),
+        schedule.end.format("%H:%M"),
+        schedule.timezone.name()
+    )
+}
+
+fn describe_days(days: &[Weekday]) -> String {
+    days.iter()
+        .map(|d| d.to_string().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn describe_paths(paths: &[PathMatcher]) -> String {
+    if paths.is_empty() {
+        return "any".to_string();
+    }
+    paths
+        .iter()
+        .map(describe_path)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_path(path: &PathMatcher) -> String {
+    match path {
+        PathMatcher::Exact { exact } => format!("exact \"{}\"", exact),
+        PathMatcher::Prefix { prefix } => format!("prefix \"{}\"", prefix),
+        PathMatcher::Regex { regex } => format!("regex \"{}\"", regex),
+        PathMatcher::Glob { glob } => format!("glob \"{}\"", glob),
+        PathMatcher::Weighted { matcher, weight } => {
+            format!("{} (weight {})", describe_path(matcher), weight)
+        }
+    }
+}
+
+fn describe_methods(methods: &[String]) -> String {
+    if methods.is_empty() {
+        return "any".to_string();
+    }
+    methods
+        .iter()
+        .map(|m| m.to_uppercase())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_headers(targeting: &Targeting) -> String {
+    if targeting.headers.is_empty()
+        && targeting.headers_absent.is_empty()
+        && targeting.sni.is_none()
+        && targeting.alpn.is_none()
+        && targeting.http_versions.is_empty()
+        && targeting.min_content_length.is_none()
+        && targeting.max_content_length.is_none()
+    {
+        return "any".to_string();
+    }
+    let mut headers: Vec<String> = targeting
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: \"{}\"", name.to_lowercase(), value))
+        .chain(
+            targeting
+                .headers_absent
+                .iter()
+                .map(|name| format!("{} absent", name.to_lowercase())),
+        )
+        .chain(targeting.sni.iter().map(|sni| format!("sni \"{}\"", sni)))
+        .chain(
+            targeting
+                .alpn
+                .iter()
+                .map(|alpn| format!("alpn \"{}\"", alpn)),
+        )
+        .chain(
+            targeting
+                .http_versions
+                .iter()
+                .map(|version| format!("http version {}", version)),
+        )
+        .chain(
+            targeting
+                .min_content_length
+                .map(|min| format!("content-length >= {}", min)),
+        )
+        .chain(
+            targeting
+                .max_content_length
+                .map(|max| format!("content-length <= {}", max)),
+        )
+        .collect();
+    headers.sort();
+    headers.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_two_experiments() {
+        let yaml = r#"
+safety:
+  max_affected_percent: 100
+  schedule:
+    - days: [mon, fri]
+      start: "22:00"
+      end: "02:00"
+      timezone: "UTC"
+  excluded_paths: ["/health"]
+experiments:
+  - id: "all-latency"
+    description: "Slow everything down"
+    targeting:
+      paths:
+        - prefix: "/"
+    fault:
+      type: latency
+      min_ms: 100
+      max_ms: 200
+  - id: "user-errors"
+    targeting:
+      paths:
+        - exact: "/api/users"
+      methods: ["get"]
+      headers:
+        X-Test: "yes"
+      percentage: 5
+    fault:
+      type: error
+      status: 503
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+        let expected = r#"2 experiments (2 enabled), schedule: mon,fri 22:00-02:00 UTC
+
+experiment "all-latency" (enabled)
+  description: Slow everything down
+  paths: prefix "/"
+  methods: any
+  headers: any
+  percentage: 100%
+  fault: latency 100-200ms
+  warning: targeting overlaps excluded paths: /health
+
+experiment "user-errors" (enabled)
+  paths: exact "/api/users"
+  methods: GET
+  headers: x-test: "yes"
+  percentage: 5%
+  fault: error 503
+  warning: shadowed by experiment "all-latency"
+"#;
+        assert_eq!(explain(&config), expected);
+    }
+}
@@ -1,10 +1,16 @@
 //! Fault injection implementations.
 
-use crate::config::Fault;
+use crate::config::{
+    CorruptMode, Fault, LatencyTiming, ResetMode, DEFAULT_RESET_STATUS, DEFAULT_TIMEOUT_STATUS,
+    MAX_CORRUPT_SIZE_BYTES,
+};
+use crate::rng::with_rng;
 use rand::Rng;
+use rand_distr::{Distribution, Normal};
 use std::collections::HashMap;
+use std::path::Path;
 use std::time::Duration;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use zentinel_agent_sdk::Decision;
 
 /// Result of applying a fault.
@@ -13,26 +19,112 @@ pub enum FaultResult {
     /// Request should be allowed after optional delay.
     Allow { delay: Option<Duration> },
     /// Request should be blocked with a response.
-    Block(Box<Decision>),
+    Block {
+        /// Status returned to the client.
+        status: u16,
+        decision: Box<Decision>,
+    },
+    /// Request should be allowed with headers for the proxy to act on.
+    Annotate(Box<Decision>),
 }
 
+impl FaultResult {
+    /// Status returned to the client, for faults that replace the response.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            FaultResult::Block { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Delay the agent injected by applying `fault`, for faults that hold
+    /// the request open. Nothing is reported for a dry run.
+    pub fn injected_delay(
+        &self,
+        fault: &Fault,
+        max_latency_ms: u64,
+        dry_run: bool,
+    ) -> Option<Duration> {
+        if dry_run || !fault.holds_request() {
+            return None;
+        }
+        match (self, fault) {
+            (FaultResult::Allow { delay }, _) => *delay,
+            (FaultResult::Block { .. }, Fault::Timeout { duration_ms, .. }) => {
+                Some(Duration::from_millis((*duration_ms).min(max_latency_ms)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Convert into a proxy decision. A delay is announced to the upstream,
+    /// or to the client for response-phase faults, so callers must drop the
+    /// delay of a dry run, which was never applied. Faults that do not hold
+    /// the request announce no delay. The decision is tagged
+    /// so access logs and routing rules can tell chaos traffic apart:
+    ///
+    /// - `chaos:{experiment_id}`
+    /// - `chaos-fault:{fault_type}`
+    /// - `chaos-pct:{percentage}`
+    pub fn into_decision(self, fault: &Fault, experiment_id: &str, percentage: u8) -> Decision {
+        let decision = match self {
+            // A throttle only estimates its delay and never sleeps
+            FaultResult::Allow { delay: Some(_) } if !fault.holds_request() => Decision::allow(),
+            // Let the upstream attribute the added latency to the fault
+            FaultResult::Allow { delay: Some(delay) } if fault.is_response_phase() => {
+                Decision::allow()
+                    .add_response_header("x-chaos-injected", fault.type_name())
+                    .add_response_header("x-chaos-delay-ms", delay.as_millis().to_string())
+            }
+            FaultResult::Allow { delay: Some(delay) } => Decision::allow()
+                .add_request_header("x-chaos-injected", fault.type_name())
+                .add_request_header("x-chaos-delay-ms", delay.as_millis().to_string()),
+            FaultResult::Allow { delay: None } => Decision::allow(),
+            FaultResult::Block { decision, .. } | FaultResult::Annotate(decision) => *decision,
+        };
+        decision
+            .with_tag(format!("chaos:{}", experiment_id))
+            .with_tag(format!("chaos-fault:{}", fault.type_name()))
+            .with_tag(format!("chaos-pct:{}", percentage))
+    }
+}
+
+/// Experiment id that makes [`apply_fault`] panic, for exercising panic
+/// containment in tests.
+#[cfg(test)]
+pub(crate) const PANIC_EXPERIMENT_ID: &str = "__panic__";
+
 /// Apply a fault to a request.
+///
+/// Delays are clamped to `max_latency_ms` as a second line of defense
+/// behind config validation. `path` is the request path, with any query
+/// string.
 pub async fn apply_fault(
     fault: &Fault,
+    path: &str,
+    request_headers: &HashMap<String, String>,
     experiment_id: &str,
     dry_run: bool,
     log_injections: bool,
+    max_latency_ms: u64,
 ) -> FaultResult {
+    #[cfg(test)]
+    if experiment_id == PANIC_EXPERIMENT_ID {
+        panic!("deliberate panic in fault application");
+    }
+
     match fault {
         Fault::Latency {
             fixed_ms,
             min_ms,
             max_ms,
+            when: LatencyTiming::Before,
         } => {
             apply_latency(
                 *fixed_ms,
                 *min_ms,
                 *max_ms,
+                max_latency_ms,
                 experiment_id,
                 dry_run,
                 log_injections,
@@ -43,32 +135,248 @@ pub async fn apply_fault(
             status,
             message,
             headers,
+            status_pool,
         } => apply_error(
             *status,
+            status_pool,
             message.as_deref(),
             headers,
             experiment_id,
             dry_run,
             log_injections,
         ),
-        Fault::Timeout { duration_ms } => {
-            apply_timeout(*duration_ms, experiment_id, dry_run, log_injections).await
+        Fault::Timeout {
+            duration_ms,
+            status,
+            return_success,
+        } => {
+            apply_timeout(
+                *duration_ms,
+                status.unwrap_or(DEFAULT_TIMEOUT_STATUS),
+                *return_success,
+                max_latency_ms,
+                experiment_id,
+                dry_run,
+                log_injections,
+            )
+            .await
+        }
+        Fault::Jitter {
+            mean_ms,
+            stddev_ms,
+            max_ms,
+        } => {
+            apply_jitter(
+                *mean_ms,
+                *stddev_ms,
+                *max_ms,
+                max_latency_ms,
+                experiment_id,
+                dry_run,
+                log_injections,
+            )
+            .await
+        }
+        // The agent passes escalating latency in as a fixed latency at the
+        // experiment's current step; on its own it applies the base delay
+        Fault::EscalatingLatency { base_ms, .. } => {
+            apply_latency(
+                *base_ms,
+                0,
+                0,
+                max_latency_ms,
+                experiment_id,
+                dry_run,
+                log_injections,
+            )
+            .await
         }
         Fault::Throttle { bytes_per_second } => {
             apply_throttle(*bytes_per_second, experiment_id, dry_run, log_injections)
         }
-        Fault::Corrupt { probability } => {
-            apply_corrupt(*probability, experiment_id, dry_run, log_injections)
+        Fault::Corrupt {
+            probability,
+            mode: CorruptMode::Garbage,
+            size_bytes,
+            ..
+        } => apply_corrupt(
+            *probability,
+            *size_bytes,
+            experiment_id,
+            dry_run,
+            log_injections,
+        ),
+        // Response latency and bitrot wait for the upstream response, see
+        // `apply_response_fault`
+        Fault::Latency {
+            when: LatencyTiming::After,
+            ..
+        }
+        | Fault::Corrupt {
+            mode: CorruptMode::Bitrot,
+            ..
+        } => FaultResult::Allow { delay: None },
+        Fault::Reset { mode, status } => apply_reset(
+            *mode,
+            status.unwrap_or(DEFAULT_RESET_STATUS),
+            experiment_id,
+            dry_run,
+            log_injections,
+        ),
+        Fault::Duplicate { count } => {
+            apply_duplicate(*count, experiment_id, dry_run, log_injections)
+        }
+        // Response headers only exist once the upstream has answered
+        Fault::MutateResponseHeaders { .. } => FaultResult::Allow { delay: None },
+        Fault::StatusFromHeader {
+            header,
+            default_status,
+        } => apply_status_from_header(
+            header,
+            *default_status,
+            request_headers,
+            experiment_id,
+            dry_run,
+            log_injections,
+        ),
+        // Echo needs the request body, see `apply_body_fault`
+        Fault::Echo { .. } => FaultResult::Allow { delay: None },
+        Fault::RewritePath { to } => {
+            apply_rewrite_path(to, path, experiment_id, dry_run, log_injections)
+        }
+        Fault::ReturnFixture {
+            status,
+            file,
+            content_type,
+            body,
+        } => {
+            let Some(body) = body else {
+                warn!(
+                    experiment = experiment_id,
+                    file = %file.display(),
+                    "Fixture was never loaded, not injecting"
+                );
+                return FaultResult::Allow { delay: None };
+            };
+            let content_type = content_type
+                .as_deref()
+                .unwrap_or_else(|| fixture_content_type(file));
+            apply_return_fixture(
+                *status,
+                content_type,
+                body,
+                experiment_id,
+                dry_run,
+                log_injections,
+            )
         }
-        Fault::Reset => apply_reset(experiment_id, dry_run, log_injections),
     }
 }
 
-/// Apply latency fault - add delay before proxying.
+/// Apply a fault to a buffered request body.
+///
+/// Only body-phase faults (see [`Fault::is_body_phase`]) act here;
+/// everything else passes the request through. `request_headers` must have
+/// lowercased names.
+pub fn apply_body_fault(
+    fault: &Fault,
+    body: &[u8],
+    request_headers: &HashMap<String, String>,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    match fault {
+        Fault::Echo {
+            status,
+            content_type,
+        } => {
+            let content_type = content_type
+                .as_deref()
+                .or(request_headers.get("content-type").map(String::as_str))
+                .unwrap_or("application/octet-stream");
+            apply_echo(
+                *status,
+                content_type,
+                body,
+                experiment_id,
+                dry_run,
+                log_injections,
+            )
+        }
+        _ => FaultResult::Allow { delay: None },
+    }
+}
+
+/// Apply a fault to an upstream response.
+///
+/// Only response-phase faults (see [`Fault::is_response_phase`]) act here;
+/// everything else passes the response through. Delays are clamped to
+/// `max_latency_ms` like in [`apply_fault`].
+pub async fn apply_response_fault(
+    fault: &Fault,
+    status: u16,
+    body: Option<&[u8]>,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+    max_latency_ms: u64,
+) -> FaultResult {
+    match fault {
+        Fault::Latency {
+            fixed_ms,
+            min_ms,
+            max_ms,
+            when: LatencyTiming::After,
+        } => {
+            apply_latency(
+                *fixed_ms,
+                *min_ms,
+                *max_ms,
+                max_latency_ms,
+                experiment_id,
+                dry_run,
+                log_injections,
+            )
+            .await
+        }
+        Fault::Corrupt {
+            probability,
+            mode: CorruptMode::Bitrot,
+            byte_flip_probability,
+            ..
+        } => match body {
+            Some(body) => apply_bitrot(
+                *probability,
+                *byte_flip_probability,
+                status,
+                body,
+                experiment_id,
+                dry_run,
+                log_injections,
+            ),
+            None => {
+                debug!(
+                    experiment = experiment_id,
+                    "Bitrot fault - no response body to corrupt"
+                );
+                FaultResult::Allow { delay: None }
+            }
+        },
+        Fault::MutateResponseHeaders { set, remove } => {
+            apply_mutate_response_headers(set, remove, experiment_id, dry_run, log_injections)
+        }
+        _ => FaultResult::Allow { delay: None },
+    }
+}
+
+/// Apply latency fault - add delay before proxying, or before answering
+/// the client for response latency.
 async fn apply_latency(
     fixed_ms: u64,
     min_ms: u64,
     max_ms: u64,
+    max_latency_ms: u64,
     experiment_id: &str,
     dry_run: bool,
     log_injections: bool,
@@ -76,11 +384,11 @@ async fn apply_latency(
     let delay_ms = if fixed_ms > 0 {
         fixed_ms
     } else if max_ms > min_ms {
-        let mut rng = rand::thread_rng();
-        rng.gen_range(min_ms..=max_ms)
+        with_rng(|rng| rng.gen_range(min_ms..=max_ms))
     } else {
         min_ms
     };
+    let delay_ms = clamp_delay_ms(delay_ms, max_latency_ms, experiment_id);
 
     let duration = Duration::from_millis(delay_ms);
 
@@ -102,15 +410,77 @@ async fn apply_latency(
     }
 }
 
+/// Apply jitter fault - add normally distributed delay before proxying.
+async fn apply_jitter(
+    mean_ms: u64,
+    stddev_ms: u64,
+    max_ms: u64,
+    max_latency_ms: u64,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    let delay_ms = draw_jitter_ms(mean_ms, stddev_ms, max_ms);
+    let delay_ms = clamp_delay_ms(delay_ms, max_latency_ms, experiment_id);
+    let duration = Duration::from_millis(delay_ms);
+
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            delay_ms = delay_ms,
+            dry_run = dry_run,
+            "Injecting jitter fault"
+        );
+    }
+
+    if !dry_run {
+        tokio::time::sleep(duration).await;
+    }
+
+    FaultResult::Allow {
+        delay: Some(duration),
+    }
+}
+
+/// Clamp a sampled delay to the safety ceiling, warning when it applies.
+fn clamp_delay_ms(delay_ms: u64, max_latency_ms: u64, experiment_id: &str) -> u64 {
+    if delay_ms <= max_latency_ms {
+        return delay_ms;
+    }
+    warn!(
+        experiment = experiment_id,
+        delay_ms = delay_ms,
+        max_latency_ms = max_latency_ms,
+        "Delay exceeds max_latency_ms, clamping"
+    );
+    max_latency_ms
+}
+
+/// Draw a delay from N(mean, stddev), clamped to `[0, max_ms]`.
+fn draw_jitter_ms(mean_ms: u64, stddev_ms: u64, max_ms: u64) -> u64 {
+    match Normal::new(mean_ms as f64, stddev_ms as f64) {
+        Ok(normal) => {
+            let sample: f64 = with_rng(|rng| normal.sample(rng));
+            sample.clamp(0.0, max_ms as f64).round() as u64
+        }
+        // Only reachable with a non-finite stddev, which validation rules out
+        Err(_) => mean_ms.min(max_ms),
+    }
+}
+
 /// Apply error fault - return HTTP error immediately.
 fn apply_error(
     status: u16,
+    status_pool: &[(u16, u32)],
     message: Option<&str>,
     headers: &HashMap<String, String>,
     experiment_id: &str,
     dry_run: bool,
     log_injections: bool,
 ) -> FaultResult {
+    let drawn = draw_status(status_pool);
+    let status = drawn.unwrap_or(status);
+
     if log_injections {
         info!(
             experiment = experiment_id,
@@ -130,27 +500,98 @@ fn apply_error(
         .with_block_header("content-type", "text/plain; charset=utf-8")
         .with_block_header("x-chaos-injected", "true")
         .with_block_header("x-chaos-experiment", experiment_id)
-        .with_body(body.to_string())
-        .with_tag(format!("chaos:{}", experiment_id));
+        .with_body(body.to_string());
+
+    if let Some(drawn) = drawn {
+        decision = decision.with_block_header("x-chaos-status-drawn", drawn.to_string());
+    }
 
     for (name, value) in headers {
         decision = decision.with_block_header(name, value);
     }
 
-    FaultResult::Block(Box::new(decision))
+    FaultResult::Block {
+        status,
+        decision: Box::new(decision),
+    }
+}
+
+/// Apply status-from-header fault - return the status the client asked for.
+///
+/// `request_headers` must have lowercased names.
+fn apply_status_from_header(
+    header: &str,
+    default_status: u16,
+    request_headers: &HashMap<String, String>,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    let status = requested_status(request_headers, header).unwrap_or_else(|| {
+        debug!(
+            experiment = experiment_id,
+            header = header,
+            default_status = default_status,
+            "Status header missing or invalid, using default status"
+        );
+        default_status
+    });
+
+    apply_error(
+        status,
+        &[],
+        None,
+        &HashMap::new(),
+        experiment_id,
+        dry_run,
+        log_injections,
+    )
+}
+
+/// Status named in a request header, if present and between 100 and 599.
+fn requested_status(request_headers: &HashMap<String, String>, header: &str) -> Option<u16> {
+    request_headers
+        .get(&header.to_lowercase())
+        .and_then(|value| value.trim().parse::<u16>().ok())
+        .filter(|status| (100..=599).contains(status))
 }
 
-/// Apply timeout fault - sleep then return 504 Gateway Timeout.
+/// Draw a status from a weighted pool, or `None` if the pool is empty.
+fn draw_status(pool: &[(u16, u32)]) -> Option<u16> {
+    let total: u64 = pool.iter().map(|(_, weight)| *weight as u64).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut pick = with_rng(|rng| rng.gen_range(0..total));
+    for (status, weight) in pool {
+        if pick < *weight as u64 {
+            return Some(*status);
+        }
+        pick -= *weight as u64;
+    }
+    None
+}
+
+/// Apply timeout fault - sleep then return `status`, or let the request
+/// through if `return_success`.
 async fn apply_timeout(
     duration_ms: u64,
+    status: u16,
+    return_success: bool,
+    max_latency_ms: u64,
     experiment_id: &str,
     dry_run: bool,
     log_injections: bool,
 ) -> FaultResult {
+    let duration_ms = clamp_delay_ms(duration_ms, max_latency_ms, experiment_id);
+
     if log_injections {
         info!(
             experiment = experiment_id,
             duration_ms = duration_ms,
+            status = status,
+            return_success = return_success,
             dry_run = dry_run,
             "Injecting timeout fault"
         );
@@ -161,17 +602,29 @@ async fn apply_timeout(
     }
 
     // Sleep for the specified duration
-    tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+    let delay = Duration::from_millis(duration_ms);
+    tokio::time::sleep(delay).await;
 
-    // Return 504 Gateway Timeout
-    let decision = Decision::block(504)
+    // A slow success: the request continues after the wait
+    if return_success {
+        return FaultResult::Allow { delay: Some(delay) };
+    }
+
+    let body = if status == DEFAULT_TIMEOUT_STATUS {
+        "Gateway Timeout (chaos fault)"
+    } else {
+        "Upstream timed out (chaos fault)"
+    };
+    let decision = Decision::block(status)
         .with_block_header("content-type", "text/plain; charset=utf-8")
         .with_block_header("x-chaos-injected", "true")
         .with_block_header("x-chaos-experiment", experiment_id)
-        .with_body("Gateway Timeout (chaos fault)".to_string())
-        .with_tag(format!("chaos:{}", experiment_id));
+        .with_body(body.to_string());
 
-    FaultResult::Block(Box::new(decision))
+    FaultResult::Block {
+        status,
+        decision: Box::new(decision),
+    }
 }
 
 /// Apply throttle fault - return metadata for slow response delivery.
@@ -218,12 +671,12 @@ fn apply_throttle(
 /// Apply corrupt fault - inject garbage into response.
 fn apply_corrupt(
     probability: f64,
+    size_bytes: Option<usize>,
     experiment_id: &str,
     dry_run: bool,
     log_injections: bool,
 ) -> FaultResult {
-    let mut rng = rand::thread_rng();
-    let should_corrupt = rng.gen::<f64>() < probability;
+    let should_corrupt = with_rng(|rng| rng.gen::<f64>()) < probability;
 
     if !should_corrupt {
         debug!(
@@ -238,6 +691,7 @@ fn apply_corrupt(
         info!(
             experiment = experiment_id,
             probability = probability,
+            status = 200,
             dry_run = dry_run,
             "Injecting corrupt fault"
         );
@@ -248,23 +702,208 @@ fn apply_corrupt(
     }
 
     // Generate garbage response
-    let garbage = generate_garbage();
+    let len = size_bytes.unwrap_or_else(|| with_rng(|rng| rng.gen_range(50..500)));
+    let garbage = generate_garbage(len.min(MAX_CORRUPT_SIZE_BYTES));
 
     let decision = Decision::block(200)
         .with_block_header("content-type", "application/octet-stream")
         .with_block_header("x-chaos-injected", "true")
         .with_block_header("x-chaos-experiment", experiment_id)
-        .with_body(garbage)
-        .with_tag(format!("chaos:{}", experiment_id));
+        .with_body(garbage);
+
+    FaultResult::Block {
+        status: 200,
+        decision: Box::new(decision),
+    }
+}
+
+/// Apply bitrot fault - flip a fraction of the upstream body's bytes.
+fn apply_bitrot(
+    probability: f64,
+    byte_flip_probability: f64,
+    status: u16,
+    body: &[u8],
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    let should_corrupt = with_rng(|rng| rng.gen::<f64>()) < probability;
+
+    if !should_corrupt {
+        debug!(
+            experiment = experiment_id,
+            probability = probability,
+            "Bitrot fault - not triggered this time"
+        );
+        return FaultResult::Allow { delay: None };
+    }
+
+    let mut corrupted = body.to_vec();
+    let flipped = flip_ascii_bytes(&mut corrupted, byte_flip_probability);
+    // The block body is text: flipping only ASCII bytes keeps a UTF-8 body
+    // valid, and a binary body cannot be delivered byte for byte
+    let Ok(corrupted) = String::from_utf8(corrupted) else {
+        debug!(
+            experiment = experiment_id,
+            "Bitrot fault - response body is not UTF-8, left intact"
+        );
+        return FaultResult::Allow { delay: None };
+    };
+
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            status = status,
+            bytes = body.len(),
+            flipped = flipped,
+            dry_run = dry_run,
+            "Injecting bitrot fault"
+        );
+    }
+
+    if dry_run {
+        return FaultResult::Allow { delay: None };
+    }
+
+    // Replace the response with the damaged body, keeping the status
+    let decision = Decision::block(status)
+        .with_block_header("x-chaos-injected", "true")
+        .with_block_header("x-chaos-experiment", experiment_id)
+        .with_body(corrupted);
+
+    FaultResult::Block {
+        status,
+        decision: Box::new(decision),
+    }
+}
+
+/// XOR each ASCII byte with a random non-zero 7-bit value with the given
+/// probability, returning the number of bytes changed. Other bytes are left
+/// alone, so a valid UTF-8 body stays valid.
+pub fn flip_ascii_bytes(body: &mut [u8], probability: f64) -> usize {
+    if probability <= 0.0 {
+        return 0;
+    }
+    with_rng(|rng| {
+        let mut flipped = 0;
+        for byte in body.iter_mut().filter(|byte| byte.is_ascii()) {
+            if rng.gen::<f64>() < probability {
+                *byte ^= rng.gen_range(1..=127u8);
+                flipped += 1;
+            }
+        }
+        flipped
+    })
+}
+
+/// Apply echo fault - answer with the request body.
+fn apply_echo(
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            status = status,
+            bytes = body.len(),
+            dry_run = dry_run,
+            "Injecting echo fault"
+        );
+    }
+
+    if dry_run {
+        return FaultResult::Allow { delay: None };
+    }
+
+    // The block body is text, so a binary body cannot be echoed byte for
+    // byte
+    let Ok(body) = std::str::from_utf8(body) else {
+        debug!(
+            experiment = experiment_id,
+            "Echo fault - request body is not UTF-8, passing through"
+        );
+        return FaultResult::Allow { delay: None };
+    };
+
+    let decision = Decision::block(status)
+        .with_block_header("content-type", content_type)
+        .with_block_header("x-chaos-injected", "true")
+        .with_block_header("x-chaos-experiment", experiment_id)
+        .with_body(body);
+
+    FaultResult::Block {
+        status,
+        decision: Box::new(decision),
+    }
+}
+
+/// Apply return-fixture fault - short-circuit with a canned response.
+fn apply_return_fixture(
+    status: u16,
+    content_type: &str,
+    body: &str,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            status = status,
+            bytes = body.len(),
+            dry_run = dry_run,
+            "Injecting return fixture fault"
+        );
+    }
+
+    if dry_run {
+        return FaultResult::Allow { delay: None };
+    }
+
+    let decision = Decision::block(status)
+        .with_block_header("content-type", content_type)
+        .with_block_header("x-chaos-injected", "true")
+        .with_block_header("x-chaos-experiment", experiment_id)
+        .with_body(body);
+
+    FaultResult::Block {
+        status,
+        decision: Box::new(decision),
+    }
+}
 
-    FaultResult::Block(Box::new(decision))
+/// Content type of a fixture without a configured one, by file extension.
+fn fixture_content_type(file: &Path) -> &'static str {
+    let extension = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => "application/json",
+        Some("html" | "htm") => "text/html",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain",
+        _ => "application/octet-stream",
+    }
 }
 
 /// Apply reset fault - simulate connection reset.
-fn apply_reset(experiment_id: &str, dry_run: bool, log_injections: bool) -> FaultResult {
+fn apply_reset(
+    mode: ResetMode,
+    status: u16,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
     if log_injections {
         info!(
             experiment = experiment_id,
+            mode = ?mode,
+            status = status,
             dry_run = dry_run,
             "Injecting connection reset fault"
         );
@@ -274,30 +913,140 @@ fn apply_reset(experiment_id: &str, dry_run: bool, log_injections: bool) -> Faul
         return FaultResult::Allow { delay: None };
     }
 
-    // We can't actually reset the connection at the agent level,
-    // so we return a 502 Bad Gateway to simulate upstream failure
-    let decision = Decision::block(502)
+    // The agent cannot touch the client connection itself: it either
+    // answers like a reset upstream would, or asks the proxy to close or
+    // abort the connection. A proxy that ignores `x-chaos-reset` still
+    // sends the error response.
+    let mut decision = Decision::block(status)
         .with_block_header("content-type", "text/plain; charset=utf-8")
         .with_block_header("x-chaos-injected", "true")
         .with_block_header("x-chaos-experiment", experiment_id)
-        .with_body("Connection reset (chaos fault)".to_string())
-        .with_tag(format!("chaos:{}", experiment_id));
+        .with_body("Connection reset (chaos fault)".to_string());
+    if let Some(signal) = mode.signal() {
+        decision = decision.with_block_header("x-chaos-reset", signal);
+    }
+    if mode == ResetMode::Close {
+        decision = decision.with_block_header("connection", "close");
+    }
+
+    FaultResult::Block {
+        status,
+        decision: Box::new(decision),
+    }
+}
+
+/// Apply response header mutation fault - set and strip response headers.
+fn apply_mutate_response_headers(
+    set: &HashMap<String, String>,
+    remove: &[String],
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            set = set.len(),
+            remove = remove.len(),
+            dry_run = dry_run,
+            "Injecting response header mutation fault"
+        );
+    }
+
+    if dry_run {
+        return FaultResult::Allow { delay: None };
+    }
+
+    let mut decision = Decision::allow();
+    for name in remove {
+        decision = decision.remove_response_header(name);
+    }
+    let mut set: Vec<(&String, &String)> = set.iter().collect();
+    set.sort();
+    for (name, value) in set {
+        decision = decision.add_response_header(name, value);
+    }
 
-    FaultResult::Block(Box::new(decision))
+    FaultResult::Annotate(Box::new(decision))
 }
 
-/// Generate random garbage data.
-fn generate_garbage() -> String {
-    let mut rng = rand::thread_rng();
-    let len = rng.gen_range(50..500);
-    (0..len)
-        .map(|_| rng.gen_range(0x20..0x7e) as u8 as char)
-        .collect()
+/// Apply duplicate fault - ask the proxy to replay the request.
+/// Note: The agent cannot send upstream traffic itself; the proxy must
+/// honor the `x-chaos-duplicate` header for this fault to have any effect.
+fn apply_duplicate(
+    count: u32,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            count = count,
+            dry_run = dry_run,
+            "Injecting duplicate fault"
+        );
+    }
+
+    if dry_run {
+        return FaultResult::Allow { delay: None };
+    }
+
+    let decision = Decision::allow()
+        .add_request_header("x-chaos-duplicate", count.to_string())
+        .add_request_header("x-chaos-experiment", experiment_id);
+
+    FaultResult::Annotate(Box::new(decision))
+}
+
+/// Apply rewrite path fault - ask the proxy to send the request upstream
+/// under another path, keeping the query string.
+fn apply_rewrite_path(
+    to: &str,
+    path: &str,
+    experiment_id: &str,
+    dry_run: bool,
+    log_injections: bool,
+) -> FaultResult {
+    let rewritten = match path.split_once('?') {
+        Some((_, query)) => format!("{}?{}", to, query),
+        None => to.to_string(),
+    };
+    if log_injections {
+        info!(
+            experiment = experiment_id,
+            original_path = path,
+            rewritten_path = %rewritten,
+            dry_run = dry_run,
+            "Injecting path rewrite fault"
+        );
+    }
+
+    if dry_run {
+        return FaultResult::Allow { delay: None };
+    }
+
+    let decision = Decision::allow()
+        .add_request_header("x-chaos-rewrite-path", rewritten)
+        .add_request_header("x-chaos-original-path", path)
+        .add_request_header("x-chaos-experiment", experiment_id);
+
+    FaultResult::Annotate(Box::new(decision))
+}
+
+/// Generate `len` bytes of printable ASCII.
+fn generate_garbage(len: usize) -> String {
+    with_rng(|rng| {
+        (0..len)
+            .map(|_| rng.gen_range(0x20..0x7e) as u8 as char)
+            .collect()
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use zentinel_agent_protocol::{Decision as ProtocolDecision, HeaderOp};
 
     #[tokio::test]
     async fn test_latency_fault_fixed() {
@@ -305,10 +1054,11 @@ mod tests {
             fixed_ms: 100,
             min_ms: 0,
             max_ms: 0,
+            when: LatencyTiming::Before,
         };
 
         let start = std::time::Instant::now();
-        let result = apply_fault(&fault, "test", false, false).await;
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
         let elapsed = start.elapsed();
 
         assert!(matches!(result, FaultResult::Allow { delay: Some(_) }));
@@ -321,10 +1071,11 @@ mod tests {
             fixed_ms: 1000,
             min_ms: 0,
             max_ms: 0,
+            when: LatencyTiming::Before,
         };
 
         let start = std::time::Instant::now();
-        let result = apply_fault(&fault, "test", true, false).await;
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", true, false, 60_000).await;
         let elapsed = start.elapsed();
 
         assert!(matches!(result, FaultResult::Allow { delay: Some(_) }));
@@ -332,16 +1083,208 @@ mod tests {
         assert!(elapsed < Duration::from_millis(100));
     }
 
+    #[test]
+    fn test_delay_decision_marks_request() {
+        let fault = Fault::Latency {
+            fixed_ms: 250,
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::Before,
+        };
+        let result = FaultResult::Allow {
+            delay: Some(Duration::from_millis(250)),
+        };
+
+        let response = result.into_decision(&fault, "slow", 100).build();
+        assert!(response.audit.tags.contains(&"chaos:slow".to_string()));
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-injected".to_string(),
+            value: "latency".to_string(),
+        }));
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-delay-ms".to_string(),
+            value: "250".to_string(),
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_throttle_decision_announces_no_delay() {
+        let fault = Fault::Throttle {
+            bytes_per_second: 1024,
+        };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "slow", false, false, 60_000).await;
+
+        let response = result.into_decision(&fault, "slow", 100).build();
+        assert!(response
+            .audit
+            .tags
+            .contains(&"chaos-fault:throttle".to_string()));
+        assert!(!response.request_headers.iter().any(|op| matches!(
+            op,
+            HeaderOp::Set { name, .. } if name == "x-chaos-delay-ms" || name == "x-chaos-injected"
+        )));
+    }
+
     #[tokio::test]
     async fn test_error_fault() {
         let fault = Fault::Error {
             status: 503,
             message: Some("Service Unavailable".to_string()),
             headers: HashMap::new(),
+            status_pool: Vec::new(),
         };
 
-        let result = apply_fault(&fault, "test", false, false).await;
-        assert!(matches!(result, FaultResult::Block(_)));
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Block { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_status_from_header() {
+        let fault = Fault::StatusFromHeader {
+            header: "X-Chaos-Force-Status".to_string(),
+            default_status: 500,
+        };
+        let status_for = |value: &str| {
+            let headers = HashMap::from([("x-chaos-force-status".to_string(), value.to_string())]);
+            requested_status(&headers, "X-Chaos-Force-Status")
+        };
+
+        assert_eq!(status_for("503"), Some(503));
+        assert_eq!(status_for(" 429 "), Some(429));
+        // Out of range or not a number: the default status applies
+        assert_eq!(status_for("999"), None);
+        assert_eq!(status_for("teapot"), None);
+        assert_eq!(
+            requested_status(&HashMap::new(), "x-chaos-force-status"),
+            None
+        );
+
+        // Missing headers still inject the default status
+        let result = apply_fault(&fault, "/", &HashMap::new(), "qa", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Block { .. }));
+        let result = apply_fault(&fault, "/", &HashMap::new(), "qa", true, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+    }
+
+    #[test]
+    fn test_echo_content_type() {
+        let content_type = |fault: &Fault, headers: &HashMap<String, String>| {
+            let result = apply_body_fault(fault, b"{}", headers, "echo", false, false);
+            let FaultResult::Block { decision, .. } = result else {
+                panic!("echo did not block");
+            };
+            match decision.build().decision {
+                ProtocolDecision::Block { headers, .. } => {
+                    headers.unwrap_or_default().remove("content-type")
+                }
+                other => panic!("unexpected decision {:?}", other),
+            }
+        };
+        let json = HashMap::from([("content-type".to_string(), "application/json".to_string())]);
+
+        let fault = Fault::Echo {
+            status: 200,
+            content_type: None,
+        };
+        assert_eq!(
+            content_type(&fault, &json).as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(
+            content_type(&fault, &HashMap::new()).as_deref(),
+            Some("application/octet-stream")
+        );
+
+        let fault = Fault::Echo {
+            status: 200,
+            content_type: Some("text/plain".to_string()),
+        };
+        assert_eq!(content_type(&fault, &json).as_deref(), Some("text/plain"));
+
+        // Other faults leave the body alone
+        let reset = Fault::Reset {
+            mode: ResetMode::BadGateway,
+            status: None,
+        };
+        let result = apply_body_fault(&reset, b"{}", &json, "echo", false, false);
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+    }
+
+    #[test]
+    fn test_echo_returns_body_unchanged() {
+        let fault = Fault::Echo {
+            status: 200,
+            content_type: None,
+        };
+        let body = "{\"name\": \"zoë\"}";
+        let result = apply_body_fault(
+            &fault,
+            body.as_bytes(),
+            &HashMap::new(),
+            "echo",
+            false,
+            false,
+        );
+        let FaultResult::Block { decision, .. } = result else {
+            panic!("echo did not block");
+        };
+        match decision.build().decision {
+            ProtocolDecision::Block { body: echoed, .. } => {
+                assert_eq!(echoed.as_deref(), Some(body))
+            }
+            other => panic!("unexpected decision {:?}", other),
+        }
+
+        // Binary bodies cannot be echoed as text and pass through
+        let result = apply_body_fault(
+            &fault,
+            b"\xff\xd8\xff",
+            &HashMap::new(),
+            "echo",
+            false,
+            false,
+        );
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+    }
+
+    #[tokio::test]
+    async fn test_return_fixture_serves_file() {
+        let dir = std::env::temp_dir().join(format!("chaos-fixture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stale.json"), "{\"items\": []}").unwrap();
+
+        let mut fault = Fault::ReturnFixture {
+            status: 200,
+            file: "stale.json".into(),
+            content_type: None,
+            body: None,
+        };
+        // Nothing is served before the file is read
+        let result = apply_fault(&fault, "/", &HashMap::new(), "qa", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+
+        fault.load_fixture(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let result = apply_fault(&fault, "/", &HashMap::new(), "qa", false, false, 60_000).await;
+        let FaultResult::Block { status, decision } = result else {
+            panic!("fixture did not block");
+        };
+        assert_eq!(status, 200);
+        match decision.build().decision {
+            ProtocolDecision::Block { headers, body, .. } => {
+                assert_eq!(body.as_deref(), Some("{\"items\": []}"));
+                let headers = headers.unwrap_or_default();
+                assert_eq!(headers["content-type"], "application/json");
+                assert_eq!(headers["x-chaos-experiment"], "qa");
+            }
+            other => panic!("unexpected decision {:?}", other),
+        }
+
+        assert_eq!(fixture_content_type(Path::new("page.HTML")), "text/html");
+        assert_eq!(
+            fixture_content_type(Path::new("payload")),
+            "application/octet-stream"
+        );
     }
 
     #[tokio::test]
@@ -350,58 +1293,423 @@ mod tests {
             status: 503,
             message: None,
             headers: HashMap::new(),
+            status_pool: Vec::new(),
         };
 
-        let result = apply_fault(&fault, "test", true, false).await;
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", true, false, 60_000).await;
         // Dry run should allow the request
         assert!(matches!(result, FaultResult::Allow { delay: None }));
     }
 
     #[tokio::test]
     async fn test_timeout_fault() {
-        let fault = Fault::Timeout { duration_ms: 50 };
+        let fault = Fault::Timeout {
+            duration_ms: 50,
+            status: None,
+            return_success: false,
+        };
 
         let start = std::time::Instant::now();
-        let result = apply_fault(&fault, "test", false, false).await;
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
         let elapsed = start.elapsed();
 
-        assert!(matches!(result, FaultResult::Block(_)));
+        assert_eq!(result.status(), Some(504));
         assert!(elapsed >= Duration::from_millis(50));
     }
 
+    #[tokio::test]
+    async fn test_timeout_fault_custom_status() {
+        let fault = Fault::Timeout {
+            duration_ms: 10,
+            status: Some(503),
+            return_success: false,
+        };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
+        assert_eq!(result.status(), Some(503));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_fault_allows_after_sleep() {
+        let fault = Fault::Timeout {
+            duration_ms: 20,
+            status: None,
+            return_success: true,
+        };
+
+        let start = std::time::Instant::now();
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
+
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(matches!(
+            result,
+            FaultResult::Allow { delay: Some(d) } if d == Duration::from_millis(20)
+        ));
+    }
+
     #[tokio::test]
     async fn test_corrupt_fault_zero_probability() {
-        let fault = Fault::Corrupt { probability: 0.0 };
+        let fault = Fault::Corrupt {
+            probability: 0.0,
+            mode: CorruptMode::Garbage,
+            byte_flip_probability: 0.01,
+            size_bytes: None,
+        };
 
         // Should never corrupt with 0 probability
         for _ in 0..10 {
-            let result = apply_fault(&fault, "test", false, false).await;
+            let result =
+                apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
             assert!(matches!(result, FaultResult::Allow { delay: None }));
         }
     }
 
     #[tokio::test]
     async fn test_corrupt_fault_full_probability() {
-        let fault = Fault::Corrupt { probability: 1.0 };
+        let fault = Fault::Corrupt {
+            probability: 1.0,
+            mode: CorruptMode::Garbage,
+            byte_flip_probability: 0.01,
+            size_bytes: None,
+        };
 
         // Should always corrupt with 1.0 probability
-        let result = apply_fault(&fault, "test", false, false).await;
-        assert!(matches!(result, FaultResult::Block(_)));
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Block { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_delay_clamped_to_max_latency() {
+        let fault = Fault::Latency {
+            fixed_ms: 500_000,
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::Before,
+        };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "typo", true, false, 50).await;
+        assert!(matches!(
+            result,
+            FaultResult::Allow { delay: Some(d) } if d == Duration::from_millis(50)
+        ));
+
+        let fault = Fault::Timeout {
+            duration_ms: 500_000,
+            status: None,
+            return_success: false,
+        };
+        let start = std::time::Instant::now();
+        let result = apply_fault(&fault, "/", &HashMap::new(), "typo", false, false, 20).await;
+        assert!(matches!(result, FaultResult::Block { .. }));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_fault_sets_count_header() {
+        let fault = Fault::Duplicate { count: 3 };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "dedup", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Annotate(_)));
+
+        let response = result.into_decision(&fault, "dedup", 10).build();
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-duplicate".to_string(),
+            value: "3".to_string(),
+        }));
+        assert!(response
+            .audit
+            .tags
+            .contains(&"chaos-fault:duplicate".to_string()));
+
+        let result = apply_fault(&fault, "/", &HashMap::new(), "dedup", true, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_path_keeps_query() {
+        let fault = Fault::RewritePath {
+            to: "/api/v0/orders".to_string(),
+        };
+        let path = "/api/v2/orders?page=2";
+        let result = apply_fault(
+            &fault,
+            path,
+            &HashMap::new(),
+            "misroute",
+            false,
+            false,
+            60_000,
+        )
+        .await;
+        let response = result.into_decision(&fault, "misroute", 5).build();
+        assert!(matches!(response.decision, ProtocolDecision::Allow));
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-rewrite-path".to_string(),
+            value: "/api/v0/orders?page=2".to_string(),
+        }));
+        assert!(response.request_headers.contains(&HeaderOp::Set {
+            name: "x-chaos-original-path".to_string(),
+            value: path.to_string(),
+        }));
+
+        let result = apply_fault(
+            &fault,
+            path,
+            &HashMap::new(),
+            "misroute",
+            true,
+            false,
+            60_000,
+        )
+        .await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+    }
+
+    #[tokio::test]
+    async fn test_mutate_response_headers() {
+        let fault = Fault::MutateResponseHeaders {
+            set: HashMap::from([("X-Frame-Options".to_string(), "bogus".to_string())]),
+            remove: vec!["cache-control".to_string()],
+        };
+
+        let result = apply_response_fault(&fault, 200, None, "headers", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Annotate(_)));
+        let response = result.into_decision(&fault, "headers", 100).build();
+        assert!(response.response_headers.contains(&HeaderOp::Set {
+            name: "X-Frame-Options".to_string(),
+            value: "bogus".to_string(),
+        }));
+        assert!(response.response_headers.contains(&HeaderOp::Remove {
+            name: "cache-control".to_string(),
+        }));
+        assert!(response.request_headers.is_empty());
+
+        // Nothing happens on the request path
+        let result = apply_response_fault(&fault, 200, None, "headers", true, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+    }
+
+    #[test]
+    fn test_flip_ascii_bytes() {
+        let original: Vec<u8> = (0..=255u8).collect();
+
+        let mut body = original.clone();
+        assert_eq!(flip_ascii_bytes(&mut body, 1.0), 128);
+        assert!(body[..128].iter().zip(&original).all(|(a, b)| a != b));
+        assert!(body[..128].is_ascii());
+        assert_eq!(body[128..], original[128..]);
+
+        let mut body = original.clone();
+        assert_eq!(flip_ascii_bytes(&mut body, 0.0), 0);
+        assert_eq!(body, original);
+    }
+
+    #[tokio::test]
+    async fn test_bitrot_only_applies_to_responses() {
+        let fault = Fault::Corrupt {
+            probability: 1.0,
+            mode: CorruptMode::Bitrot,
+            byte_flip_probability: 1.0,
+            size_bytes: None,
+        };
+
+        let original = "héllo wörld";
+        let body = Some(original.as_bytes());
+        let result = apply_response_fault(&fault, 201, body, "test", false, false, 60_000).await;
+        let FaultResult::Block { decision, .. } = result else {
+            panic!("bitrot did not block");
+        };
+        // Every ASCII byte is damaged, the rest of the text arrives intact
+        let ProtocolDecision::Block {
+            body: Some(delivered),
+            ..
+        } = decision.build().decision
+        else {
+            panic!("bitrot did not replace the body");
+        };
+        assert_eq!(delivered.len(), original.len());
+        assert_eq!(delivered.chars().filter(|c| !c.is_ascii()).count(), 2);
+        assert!(delivered
+            .bytes()
+            .zip(original.bytes())
+            .all(|(a, b)| !b.is_ascii() || a != b));
+
+        // Binary bodies cannot be sent as text and are left alone
+        let binary = Some(&b"\xff\xfe\x00\x01"[..]);
+        let result = apply_response_fault(&fault, 201, binary, "test", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+
+        let result = apply_response_fault(&fault, 201, body, "test", true, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+
+        let result = apply_response_fault(&fault, 201, None, "test", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
+
+        let reset = Fault::Reset {
+            mode: ResetMode::BadGateway,
+            status: None,
+        };
+        let result = apply_response_fault(&reset, 201, body, "test", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
     }
 
     #[tokio::test]
     async fn test_reset_fault() {
-        let fault = Fault::Reset;
+        let reset = |mode, status| async move {
+            let fault = Fault::Reset { mode, status };
+            let result =
+                apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
+            let FaultResult::Block { status, decision } = result else {
+                panic!("reset did not block");
+            };
+            match decision.build().decision {
+                ProtocolDecision::Block { headers, body, .. } => {
+                    let headers = headers.unwrap_or_default();
+                    assert_eq!(body.as_deref(), Some("Connection reset (chaos fault)"));
+                    assert_eq!(headers["x-chaos-injected"], "true");
+                    (status, headers)
+                }
+                other => panic!("unexpected decision {:?}", other),
+            }
+        };
+
+        let (status, headers) = reset(ResetMode::BadGateway, None).await;
+        assert_eq!(status, 502);
+        assert!(!headers.contains_key("x-chaos-reset"));
+        assert!(!headers.contains_key("connection"));
+
+        let (status, headers) = reset(ResetMode::Close, None).await;
+        assert_eq!(status, 502);
+        assert_eq!(headers["x-chaos-reset"], "close");
+        assert_eq!(headers["connection"], "close");
+
+        let (status, headers) = reset(ResetMode::Rst, Some(503)).await;
+        assert_eq!(status, 503);
+        assert_eq!(headers["x-chaos-reset"], "rst");
+        assert!(!headers.contains_key("connection"));
 
-        let result = apply_fault(&fault, "test", false, false).await;
-        assert!(matches!(result, FaultResult::Block(_)));
+        let fault = Fault::Reset {
+            mode: ResetMode::Rst,
+            status: None,
+        };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", true, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: None }));
     }
 
     #[test]
     fn test_generate_garbage() {
-        let garbage = generate_garbage();
-        assert!(!garbage.is_empty());
-        assert!(garbage.len() >= 50);
-        assert!(garbage.len() < 500);
+        let garbage = generate_garbage(64);
+        assert_eq!(garbage.len(), 64);
+        assert!(garbage.bytes().all(|b| (0x20..0x7e).contains(&b)));
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_fault_configured_size() {
+        let fault = Fault::Corrupt {
+            probability: 1.0,
+            mode: CorruptMode::Garbage,
+            byte_flip_probability: 0.01,
+            size_bytes: Some(MAX_CORRUPT_SIZE_BYTES),
+        };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", false, false, 60_000).await;
+        let FaultResult::Block { decision, .. } = result else {
+            panic!("expected a block");
+        };
+        match decision.build().decision {
+            ProtocolDecision::Block { body, .. } => {
+                assert_eq!(body.map(|body| body.len()), Some(MAX_CORRUPT_SIZE_BYTES))
+            }
+            other => panic!("unexpected decision {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_draw_status_matches_weights() {
+        let pool = vec![(500, 3), (502, 1), (503, 1)];
+        let trials = 50_000;
+        let mut counts: HashMap<u16, u32> = HashMap::new();
+        for _ in 0..trials {
+            *counts.entry(draw_status(&pool).unwrap()).or_default() += 1;
+        }
+
+        let share = |status| counts[&status] as f64 / trials as f64;
+        assert!((share(500) - 0.6).abs() < 0.02, "500 share {}", share(500));
+        assert!((share(502) - 0.2).abs() < 0.02, "502 share {}", share(502));
+        assert!((share(503) - 0.2).abs() < 0.02, "503 share {}", share(503));
+    }
+
+    #[test]
+    fn test_draw_status_empty_pool() {
+        assert_eq!(draw_status(&[]), None);
+    }
+
+    #[test]
+    fn test_jitter_mean() {
+        let draws = 20_000;
+        let total: u64 = (0..draws).map(|_| draw_jitter_ms(100, 20, 1000)).sum();
+        let mean = total as f64 / draws as f64;
+        assert!((mean - 100.0).abs() < 2.0, "mean was {}", mean);
+    }
+
+    #[test]
+    fn test_jitter_clamped() {
+        for _ in 0..1000 {
+            assert!(draw_jitter_ms(10, 100, 50) <= 50);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jitter_fault_dry_run() {
+        let fault = Fault::Jitter {
+            mean_ms: 100,
+            stddev_ms: 10,
+            max_ms: 200,
+        };
+        let result = apply_fault(&fault, "/", &HashMap::new(), "test", true, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Allow { delay: Some(_) }));
+    }
+
+    fn decision_tags(decision: Decision) -> Vec<String> {
+        decision.build().audit.tags
+    }
+
+    #[tokio::test]
+    async fn test_latency_decision_tags() {
+        let fault = Fault::Latency {
+            fixed_ms: 1,
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::Before,
+        };
+        let result = apply_fault(
+            &fault,
+            "/",
+            &HashMap::new(),
+            "api-latency",
+            false,
+            false,
+            60_000,
+        )
+        .await;
+        let tags = decision_tags(result.into_decision(&fault, "api-latency", 10));
+        assert_eq!(
+            tags,
+            vec!["chaos:api-latency", "chaos-fault:latency", "chaos-pct:10"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_decision_tags() {
+        let fault = Fault::Error {
+            status: 503,
+            message: None,
+            headers: HashMap::new(),
+            status_pool: Vec::new(),
+        };
+        let result =
+            apply_fault(&fault, "/", &HashMap::new(), "errors", false, false, 60_000).await;
+        assert!(matches!(result, FaultResult::Block { .. }));
+        let tags = decision_tags(result.into_decision(&fault, "errors", 5));
+        assert_eq!(
+            tags,
+            vec!["chaos:errors", "chaos-fault:error", "chaos-pct:5"]
+        );
     }
 }
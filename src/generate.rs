@@ -0,0 +1,368 @@
+//! Experiment scaffolds printed by `generate experiment`.
+//!
+//! Experiments are built from the configuration types and serialized, so a
+//! scaffold always matches the schema. Each fault type starts from a
+//! conservative template that the caller can adjust.
+
+use crate::builder::ExperimentBuilder;
+use crate::config::{
+    Config, ConfigFormat, CorruptMode, Experiment, Fault, LatencyTiming, ResetMode,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Options for a generated experiment.
+#[derive(Debug, Clone, Default)]
+pub struct ExperimentSpec {
+    pub id: String,
+    /// Fault type, one of [`Fault::TYPE_NAMES`].
+    pub fault_type: String,
+    pub description: Option<String>,
+    pub path_prefix: Option<String>,
+    pub methods: Vec<String>,
+    pub percentage: u8,
+    /// Status for error, timeout, reset, status-from-header, echo and
+    /// return-fixture faults.
+    pub status: Option<u16>,
+    /// Delay for latency, jitter, escalating latency and timeout faults.
+    pub delay_ms: Option<u64>,
+}
+
+/// Template fault for a fault type, with `status` and `delay_ms`
+/// overriding the template's status and delay where the fault has one.
+pub fn fault_template(
+    fault_type: &str,
+    status: Option<u16>,
+    delay_ms: Option<u64>,
+) -> Result<Fault> {
+    let fault = match fault_type {
+        "latency" => Fault::Latency {
+            fixed_ms: delay_ms.unwrap_or(500),
+            min_ms: 0,
+            max_ms: 0,
+            when: LatencyTiming::Before,
+        },
+        "jitter" => {
+            let mean_ms = delay_ms.unwrap_or(100);
+            Fault::Jitter {
+                mean_ms,
+                stddev_ms: (mean_ms / 3).max(1),
+                max_ms: mean_ms * 10,
+            }
+        }
+        "escalating_latency" => {
+            let base_ms = delay_ms.unwrap_or(100);
+            Fault::EscalatingLatency {
+                base_ms,
+                factor: 2.0,
+                max_ms: base_ms * 32,
+                reset_after_seconds: 60,
+            }
+        }
+        "error" => Fault::Error {
+            status: status.unwrap_or(500),
+            message: None,
+            headers: HashMap::new(),
+            status_pool: Vec::new(),
+        },
+        "timeout" => Fault::Timeout {
+            duration_ms: delay_ms.unwrap_or(30_000),
+            status,
+            return_success: false,
+        },
+        "throttle" => Fault::Throttle {
+            bytes_per_second: 10_240,
+        },
+        "corrupt" => Fault::Corrupt {
+            probability: 1.0,
+            mode: CorruptMode::Garbage,
+            byte_flip_probability: 0.01,
+            size_bytes: None,
+        },
+        "reset" => Fault::Reset {
+            mode: ResetMode::BadGateway,
+            status,
+        },
+        "duplicate" => Fault::Duplicate { count: 1 },
+        "mutate_response_headers" => Fault::MutateResponseHeaders {
+            set: HashMap::new(),
+            remove: vec!["cache-control".to_string()],
+        },
+        "status_from_header" => Fault::StatusFromHeader {
+            header: "x-chaos-force-status".to_string(),
+            default_status: status.unwrap_or(503),
+        },
+        "echo" => Fault::Echo {
+            status: status.unwrap_or(200),
+            content_type: None,
+        },
+        "rewrite_path" => Fault::RewritePath {
+            to: "/chaos-misrouted".to_string(),
+        },
+        "return_fixture" => Fault::ReturnFixture {
+            status: status.unwrap_or(200),
+            file: "fixtures/response.json".into(),
+            content_type: None,
+            body: None,
+        },
+        other => bail!(
+            "unknown fault type '{}' (expected one of: {})",
+            other,
+            Fault::TYPE_NAMES.join(", ")
+        ),
+    };
+    Ok(fault)
+}
+
+/// Build and validate an experiment from a spec.
+pub fn generate_experiment(spec: &ExperimentSpec) -> Result<Experiment> {
+    let fault = fault_template(&spec.fault_type, spec.status, spec.delay_ms)?;
+    let mut builder = ExperimentBuilder::new(&spec.id)
+        .percentage(spec.percentage)
+        .fault(fault);
+    if let Some(description) = &spec.description {
+        builder = builder.description(description);
+    }
+    if let Some(prefix) = &spec.path_prefix {
+        builder = builder.path_prefix(prefix);
+    }
+    for method in &spec.methods {
+        builder = builder.method(method.to_uppercase());
+    }
+    builder.build()
+}
+
+/// Render an experiment as a YAML list item, ready to paste under
+/// `experiments:`.
+///
+/// Unset options and empty lists are left out, as they would be in a
+/// hand-written file.
+pub fn experiment_yaml(experiment: &Experiment) -> Result<String> {
+    let mut value = serde_yaml::to_value([experiment])?;
+    prune(&mut value);
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+/// Drop null, empty string and empty collection values from mappings.
+fn prune(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            for (_, field) in map.iter_mut() {
+                prune(field);
+            }
+            map.retain(|_, field| !is_empty(field));
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(prune),
+        _ => {}
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::Sequence(items) => items.is_empty(),
+        Value::Mapping(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+/// Insert an experiment at the end of the `experiments` list of a YAML
+/// document, leaving every other line untouched.
+///
+/// A document without the list gets one appended.
+pub fn append_experiment_yaml(document: &str, experiment: &Experiment) -> Result<String> {
+    let item = experiment_yaml(experiment)?;
+    let lines: Vec<&str> = document.lines().collect();
+    let Some(start) = lines.iter().position(|line| is_experiments_key(line)) else {
+        let mut out = document.to_string();
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str("experiments:\n");
+        out.push_str(&item);
+        return Ok(out);
+    };
+
+    let value = lines[start]["experiments:".len()..].trim();
+    let value = value.split('#').next().unwrap_or_default().trim();
+    let mut head: Vec<String> = lines[..start].iter().map(|l| l.to_string()).collect();
+    let mut body_end = start + 1;
+    let indent = match value {
+        "" => {
+            // The list runs until the next top-level key
+            let mut indent = None;
+            for (i, line) in lines.iter().enumerate().skip(start + 1) {
+                let top_level = !line.starts_with([' ', '\t', '-', '#']) && !line.is_empty();
+                if top_level {
+                    break;
+                }
+                if !line.trim().is_empty() && !line.starts_with('#') {
+                    body_end = i + 1;
+                    if indent.is_none() && line.trim_start().starts_with('-') {
+                        indent = Some(line.len() - line.trim_start().len());
+                    }
+                }
+            }
+            head.extend(lines[start..body_end].iter().map(|l| l.to_string()));
+            indent.unwrap_or(0)
+        }
+        "[]" => {
+            head.push("experiments:".to_string());
+            0
+        }
+        _ => bail!("cannot append to a flow-style experiments list, rewrite it as a block list"),
+    };
+
+    let pad = " ".repeat(indent);
+    let mut out = head.join("\n");
+    out.push('\n');
+    for line in item.lines() {
+        out.push_str(&pad);
+        out.push_str(line);
+        out.push('\n');
+    }
+    for line in &lines[body_end..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn is_experiments_key(line: &str) -> bool {
+    line.strip_prefix("experiments:")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Append an experiment to a YAML configuration file.
+///
+/// The result is loaded and validated, includes and all, before it
+/// replaces the file.
+pub fn append_to_file(path: &Path, experiment: &Experiment) -> Result<()> {
+    if ConfigFormat::from_path(path)? != ConfigFormat::Yaml {
+        bail!("--append supports YAML configuration files only");
+    }
+    let document =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let updated = append_experiment_yaml(&document, experiment)?;
+
+    // Stage next to the original so relative includes resolve the same way
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("invalid configuration path {}", path.display()))?;
+    let staged = path.with_file_name(format!(".{}.generate.yaml", file_name));
+    std::fs::write(&staged, &updated)?;
+    if let Err(e) = Config::from_file(&staged) {
+        let _ = std::fs::remove_file(&staged);
+        return Err(e.context("appending the experiment would make the configuration invalid"));
+    }
+    std::fs::rename(&staged, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(fault_type: &str) -> ExperimentSpec {
+        ExperimentSpec {
+            id: "orders-latency".to_string(),
+            fault_type: fault_type.to_string(),
+            path_prefix: Some("/api/orders/".to_string()),
+            percentage: 5,
+            ..ExperimentSpec::default()
+        }
+    }
+
+    #[test]
+    fn test_every_fault_type_generates_valid_yaml() {
+        for fault_type in Fault::TYPE_NAMES {
+            let experiment = generate_experiment(&spec(fault_type)).unwrap();
+            assert_eq!(experiment.fault.type_name(), *fault_type);
+
+            let yaml = format!("experiments:\n{}", experiment_yaml(&experiment).unwrap());
+            let config: Config = serde_yaml::from_str(&yaml).unwrap();
+            config.validate().unwrap();
+            assert_eq!(config.experiments[0], experiment);
+        }
+
+        let err = generate_experiment(&spec("meteor")).unwrap_err();
+        assert!(err.to_string().starts_with("unknown fault type 'meteor'"));
+        let invalid = ExperimentSpec {
+            status: Some(999),
+            ..spec("error")
+        };
+        assert!(generate_experiment(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_append_preserves_document() {
+        let experiment = generate_experiment(&spec("latency")).unwrap();
+        let document = "\
+# Chaos config
+settings:
+  dry_run: true
+
+experiments:
+  # Existing experiments
+  - id: existing
+    targeting: { percentage: 1 }
+    fault: { type: reset }
+
+# Safety last
+safety:
+  max_affected_percent: 10
+";
+        let updated = append_experiment_yaml(document, &experiment).unwrap();
+        assert!(updated.starts_with(
+            "# Chaos config\nsettings:\n  dry_run: true\n\nexperiments:\n  # Existing experiments\n  - id: existing\n    targeting: { percentage: 1 }\n    fault: { type: reset }\n  - id: orders-latency\n"
+        ), "{}", updated);
+        assert!(updated.ends_with("\n# Safety last\nsafety:\n  max_affected_percent: 10\n"));
+
+        let config: Config = serde_yaml::from_str(&updated).unwrap();
+        let ids: Vec<&str> = config.experiments.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["existing", "orders-latency"]);
+        assert!(config.settings.dry_run);
+
+        // Empty and missing lists
+        for document in ["settings: {}\nexperiments: []\n", "settings: {}"] {
+            let updated = append_experiment_yaml(document, &experiment).unwrap();
+            let config: Config = serde_yaml::from_str(&updated).unwrap();
+            assert_eq!(config.experiments.len(), 1, "{}", updated);
+        }
+        assert!(append_experiment_yaml("experiments: [{ id: x }]\n", &experiment).is_err());
+    }
+
+    #[test]
+    fn test_append_to_file_validates() {
+        let dir = std::env::temp_dir().join(format!("chaos-generate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chaos.yaml");
+        std::fs::write(
+            &path,
+            "experiments:\n- id: orders-latency\n  targeting: {}\n  fault: { type: reset }\n",
+        )
+        .unwrap();
+
+        // A duplicate id leaves the file untouched
+        let experiment = generate_experiment(&spec("latency")).unwrap();
+        let before = std::fs::read_to_string(&path).unwrap();
+        assert!(append_to_file(&path, &experiment).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+
+        let experiment = generate_experiment(&ExperimentSpec {
+            id: "orders-errors".to_string(),
+            ..spec("error")
+        })
+        .unwrap();
+        append_to_file(&path, &experiment).unwrap();
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.experiments.len(), 2);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
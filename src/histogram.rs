@@ -0,0 +1,90 @@
+//! Lock-free fixed-bucket histograms.
+//!
+//! Buckets are chosen up front and counted with atomics, so recording is a
+//! couple of relaxed increments and never allocates. Reports follow the
+//! Prometheus convention of cumulative `le` buckets plus a sum and count.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Histogram over fixed upper bounds.
+pub struct Histogram {
+    /// Inclusive upper bound of each bucket, ascending.
+    bounds: &'static [u64],
+    /// Observations per bucket, with one extra for values above every bound.
+    counts: Vec<AtomicU64>,
+    sum: AtomicU64,
+}
+
+/// Point-in-time view of a [`Histogram`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// Upper bound and cumulative count for each bucket; the last entry has
+    /// no bound and equals `count`.
+    pub buckets: Vec<(Option<u64>, u64)>,
+    /// Sum of all observations.
+    pub sum: u64,
+    /// Number of observations.
+    pub count: u64,
+}
+
+impl Histogram {
+    /// Create a histogram with the given ascending upper bounds.
+    pub fn new(bounds: &'static [u64]) -> Self {
+        Self {
+            bounds,
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0),
+        }
+    }
+
+    /// Record one observation.
+    pub fn record(&self, value: u64) {
+        let index = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Read the current counts.
+    ///
+    /// Counts are read one by one, so a snapshot taken while observations
+    /// are recorded may be off by those observations.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (self.bounds.get(i).copied(), cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            sum: self.sum.load(Ordering::Relaxed),
+            count: cumulative,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cumulative_buckets() {
+        let histogram = Histogram::new(&[10, 100]);
+        histogram.record(5);
+        histogram.record(10);
+        histogram.record(50);
+        histogram.record(1000);
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(
+            snapshot.buckets,
+            vec![(Some(10), 2), (Some(100), 3), (None, 4)]
+        );
+        assert_eq!(snapshot.sum, 1065);
+        assert_eq!(snapshot.count, 4);
+    }
+}
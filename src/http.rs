@@ -0,0 +1,154 @@
+//! Minimal HTTP/1.1 listener shared by the admin API and the Prometheus
+//! endpoint.
+//!
+//! Speaks just enough HTTP over a plain TCP connection to avoid pulling in
+//! a web framework: one request per connection, answered and closed. The
+//! request head and body are bounded, and a client that is slow to send its
+//! request is dropped after [`READ_TIMEOUT`].
+
+use anyhow::{bail, Context, Result};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Largest request head accepted, request line and headers included.
+const MAX_HEAD_BYTES: u64 = 8 * 1024;
+
+/// Largest request body accepted.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Time a client has to send its request.
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request read by [`read_request`].
+#[derive(Debug, PartialEq)]
+pub(crate) struct Request {
+    pub(crate) method: String,
+    /// Path without the query string.
+    pub(crate) path: String,
+    /// Query string, without the `?`.
+    pub(crate) query: String,
+    /// Value of the `Authorization` header.
+    pub(crate) authorization: Option<String>,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Accept connections from a bound listener in a background task, handling
+/// each one in its own task. `name` labels failures in the logs.
+pub(crate) fn serve<H, F>(listener: TcpListener, name: &'static str, handle: H) -> JoinHandle<()>
+where
+    H: Fn(TcpStream, SocketAddr) -> F + Send + 'static,
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let connection = handle(stream, peer);
+                    tokio::spawn(async move {
+                        if let Err(e) = connection.await {
+                            debug!(peer = %peer, error = %e, "{} request failed", name);
+                        }
+                    });
+                }
+                Err(e) => {
+                    // Usually out of file descriptors; back off instead of spinning
+                    warn!(error = %e, "{} listener failed to accept a connection", name);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    })
+}
+
+/// Read the request line, headers and body.
+pub(crate) async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Request> {
+    let mut head = reader.take(MAX_HEAD_BYTES);
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("malformed request line");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        authorization: None,
+        body: Vec::new(),
+    };
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line).await? == 0 {
+            bail!(
+                "request head truncated or larger than {} bytes",
+                MAX_HEAD_BYTES
+            );
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().context("invalid content-length")?;
+            } else if name.eq_ignore_ascii_case("authorization") {
+                request.authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        bail!("request body larger than {} bytes", MAX_BODY_BYTES);
+    }
+    request.body = vec![0; content_length];
+    head.into_inner().read_exact(&mut request.body).await?;
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(raw: &str) -> Result<Request> {
+        read_request(&mut raw.as_bytes()).await
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let request = read(
+            "POST /experiments/x/trigger-once?count=2 HTTP/1.1\r\nAuthorization: Bearer t\r\nContent-Length: 2\r\n\r\n{}",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                method: "POST".to_string(),
+                path: "/experiments/x/trigger-once".to_string(),
+                query: "count=2".to_string(),
+                authorization: Some("Bearer t".to_string()),
+                body: b"{}".to_vec(),
+            }
+        );
+
+        assert!(read("GET /\r\n\r\n").await.is_err());
+        assert!(read("GET / HTTP/1.1\r\nHost: x\r\n").await.is_err());
+        let huge = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(9000));
+        assert!(read(&huge).await.is_err());
+        let body = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        assert!(read(&body).await.is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Environment variable interpolation for configuration files.
+//!
+//! Applied to the raw YAML before parsing:
+//!
+//! - `${VAR}` is replaced with the value of `VAR`; an unset variable is an
+//!   error naming the variable and line
+//! - `${VAR:-default}` falls back to `default` when `VAR` is unset or empty
+//! - `$${...}` is kept as a literal `${...}`
+//!
+//! Comments are copied untouched, so commented-out references to unset
+//! variables do not fail the load.
+
+use anyhow::{anyhow, Result};
+
+/// Interpolate `${VAR}` references using the process environment.
+pub fn interpolate_env(content: &str) -> Result<String> {
+    interpolate(content, |name| std::env::var(name).ok())
+}
+
+/// Interpolate `${VAR}` references using `lookup` to resolve variables.
+pub fn interpolate<F>(content: &str, lookup: F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(content.len());
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        interpolate_line(line, index + 1, &lookup, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn interpolate_line<F>(line: &str, line_number: usize, lookup: &F, out: &mut String) -> Result<()>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut quote: Option<char> = None;
+    let mut prev: Option<char> = None;
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            // A comment starts at a `#` outside quotes that begins the line
+            // or follows whitespace; copy the remainder as is
+            '#' if quote.is_none() && prev.is_none_or(char::is_whitespace) => {
+                out.push_str(rest);
+                return Ok(());
+            }
+            '"' | '\'' if quote == Some(c) => quote = None,
+            // Only a quote opening a scalar starts a quoted string, so an
+            // apostrophe in plain text does not hide a later comment
+            '"' | '\'' if quote.is_none() && prev.is_none_or(opens_scalar) => quote = Some(c),
+            '$' if rest.starts_with("$${") => {
+                out.push_str("${");
+                rest = &rest[3..];
+                prev = Some('{');
+                continue;
+            }
+            '$' if rest.starts_with("${") => {
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| anyhow!("Unterminated '${{' on line {}", line_number))?;
+                out.push_str(&resolve(&rest[2..end], line_number, lookup)?);
+                rest = &rest[end + 1..];
+                prev = Some('}');
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    Ok(())
+}
+
+fn opens_scalar(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ':' | '[' | '{' | ',' | '-')
+}
+
+/// Resolve the inside of a `${...}` reference.
+fn resolve<F>(reference: &str, line_number: usize, lookup: &F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let (name, default) = match reference.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (reference, None),
+    };
+
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(anyhow!(
+            "Invalid environment variable name '{}' on line {}",
+            name,
+            line_number
+        ));
+    }
+
+    match (lookup(name), default) {
+        (Some(value), Some(default)) if value.is_empty() => Ok(default.to_string()),
+        (Some(value), _) => Ok(value),
+        (None, Some(default)) => Ok(default.to_string()),
+        (None, None) => Err(anyhow!(
+            "Environment variable {} is not set (line {})",
+            name,
+            line_number
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PERCENT" => Some("25".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_variables_and_defaults() {
+        let yaml = "percentage: ${PERCENT}\ntimezone: \"${TZ_NAME:-UTC}\"\nempty: ${EMPTY:-x}\n";
+        assert_eq!(
+            interpolate(yaml, lookup).unwrap(),
+            "percentage: 25\ntimezone: \"UTC\"\nempty: x\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_variable_names_line() {
+        let yaml = "settings:\n  enabled: true\npercentage: ${CHAOS_PERCENT}\n";
+        let err = interpolate(yaml, lookup).unwrap_err().to_string();
+        assert!(err.contains("CHAOS_PERCENT"), "{}", err);
+        assert!(err.contains("line 3"), "{}", err);
+
+        let err = interpolate("x: ${PERCENT", lookup).unwrap_err().to_string();
+        assert!(err.contains("Unterminated"), "{}", err);
+    }
+
+    #[test]
+    fn test_escapes_and_comments() {
+        let yaml = "message: \"$${LITERAL}\"\n# percentage: ${UNSET}\npath: \"/a#${PERCENT}\" # ${UNSET}\nnote: don't # ${UNSET}\n";
+        assert_eq!(
+            interpolate(yaml, lookup).unwrap(),
+            "message: \"${LITERAL}\"\n# percentage: ${UNSET}\npath: \"/a#25\" # ${UNSET}\nnote: don't # ${UNSET}\n"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Kill switch driven by a control file.
+//!
+//! During an incident, creating the file is the fastest way to stop chaos:
+//! no config push or restart is needed. While the file exists every
+//! injection is suppressed, exactly as with `settings.enabled: false`;
+//! removing it re-enables chaos. The file is checked on the request path,
+//! at most once per [`CHECK_INTERVAL_MS`], so a busy agent does not stat it
+//! on every request.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Minimum time between two checks of the control file.
+pub const CHECK_INTERVAL_MS: u64 = 1000;
+
+/// Control file watcher with a cached result.
+pub struct KillSwitch {
+    path: PathBuf,
+    started: Instant,
+    active: AtomicBool,
+    /// Milliseconds since start at which the file is due for a check.
+    next_check_ms: AtomicU64,
+}
+
+impl KillSwitch {
+    /// Create a kill switch watching `path`. The file is checked on first
+    /// use.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            started: Instant::now(),
+            active: AtomicBool::new(false),
+            next_check_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Check whether the control file exists, using the cached answer if it
+    /// was checked recently.
+    pub fn is_active(&self) -> bool {
+        self.is_active_at(self.started.elapsed().as_millis() as u64)
+    }
+
+    /// Check whether the kill switch is active at `now_ms` milliseconds
+    /// since start.
+    pub fn is_active_at(&self, now_ms: u64) -> bool {
+        let due = self.next_check_ms.load(Ordering::Acquire);
+        // Only the caller that moves the deadline checks the file and logs
+        if now_ms >= due
+            && self
+                .next_check_ms
+                .compare_exchange(
+                    due,
+                    now_ms + CHECK_INTERVAL_MS,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        {
+            self.refresh();
+        }
+        self.active.load(Ordering::Acquire)
+    }
+
+    fn refresh(&self) {
+        let present = self.path.exists();
+        if self.active.swap(present, Ordering::AcqRel) == present {
+            return;
+        }
+        if present {
+            warn!(
+                path = %self.path.display(),
+                "Kill switch file present, suppressing all fault injection"
+            );
+        } else {
+            info!(
+                path = %self.path.display(),
+                "Kill switch file removed, resuming fault injection"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_switch_follows_file() {
+        let dir = std::env::temp_dir().join(format!("chaos-kill-switch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chaos-disable");
+        let _ = std::fs::remove_file(&path);
+
+        let switch = KillSwitch::new(&path);
+        assert!(!switch.is_active_at(0));
+
+        // The cached answer holds until the next check is due
+        std::fs::write(&path, "").unwrap();
+        assert!(!switch.is_active_at(CHECK_INTERVAL_MS - 1));
+        assert!(switch.is_active_at(CHECK_INTERVAL_MS));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(switch.is_active_at(CHECK_INTERVAL_MS + 1));
+        assert!(!switch.is_active_at(2 * CHECK_INTERVAL_MS));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -12,13 +12,49 @@
 //! - Schedule windows (only active during specified times)
 //! - Excluded paths (health checks always pass)
 //! - Maximum affected percentage
-//! - Global kill switch
+//! - Global kill switch, plus runtime suspension via `SIGUSR1`
 //! - Dry run mode
+//!
+//! The configuration can be reloaded without a restart via `SIGHUP` or, with
+//! `--watch-config`, whenever the file changes.
 
+pub mod abort;
+pub mod accesslog;
+pub mod admin;
 pub mod agent;
+pub mod budget;
+pub mod builder;
+pub mod bypass;
+pub mod clients;
 pub mod config;
+pub mod cooldown;
+pub mod deadline;
+pub mod drain;
+pub mod escalation;
+pub mod example;
+pub mod explain;
 pub mod faults;
+pub mod generate;
+pub mod histogram;
+pub mod http;
+pub mod interpolate;
+pub mod killswitch;
+pub mod lint;
+pub mod pause;
+pub mod precheck;
+pub mod probe;
+pub mod prometheus;
+pub mod registry;
+pub mod replay;
+pub mod rng;
+pub mod schema;
+#[cfg(unix)]
+pub mod signals;
+pub mod simulate;
+pub mod status;
 pub mod targeting;
+pub mod watch;
+pub mod window;
 
-pub use agent::ChaosAgent;
+pub use agent::{ChaosAgent, ChaosControl};
 pub use config::Config;
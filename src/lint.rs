@@ -0,0 +1,507 @@
+//! Heuristic checks for configurations, run by the `lint` subcommand.
+//!
+//! Validation rejects configurations the agent cannot run; the linter
+//! flags ones that run but probably do not do what was intended. Every
+//! finding carries a stable rule code so CI can track or silence rules.
+
+use crate::config::{Config, Experiment, Fault, PathMatcher};
+use serde::Serialize;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// Timeouts longer than this usually outlast the proxy's own upstream
+/// timeout, so the proxy gives up before the fault completes.
+pub const TYPICAL_PROXY_TIMEOUT_MS: u64 = 60_000;
+
+/// Default age after which a disabled experiment is reported as stale.
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth a look, never fails a lint run.
+    Info,
+    /// Probably a mistake.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A lint rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Stable code, e.g. `L001`.
+    pub code: &'static str,
+    /// Short name, e.g. `unanchored-regex`.
+    pub name: &'static str,
+    pub severity: Severity,
+}
+
+/// Regex path matcher without a leading `^`, matching anywhere in the path.
+pub const UNANCHORED_REGEX: Rule = Rule {
+    code: "L001",
+    name: "unanchored-regex",
+    severity: Severity::Warning,
+};
+
+/// Two experiments with identical targeting, likely a copy-paste.
+pub const DUPLICATE_TARGETING: Rule = Rule {
+    code: "L002",
+    name: "duplicate-targeting",
+    severity: Severity::Warning,
+};
+
+/// Disabled experiment in a file untouched for a long time.
+pub const STALE_DISABLED: Rule = Rule {
+    code: "L003",
+    name: "stale-disabled",
+    severity: Severity::Info,
+};
+
+/// Percentage above `safety.max_affected_percent`, silently clamped.
+pub const PERCENTAGE_CLAMPED: Rule = Rule {
+    code: "L004",
+    name: "percentage-clamped",
+    severity: Severity::Warning,
+};
+
+/// Header matcher name not written in lowercase.
+pub const HEADER_CASE: Rule = Rule {
+    code: "L005",
+    name: "header-case",
+    severity: Severity::Info,
+};
+
+/// Timeout fault longer than typical proxy timeouts.
+pub const LONG_TIMEOUT: Rule = Rule {
+    code: "L006",
+    name: "long-timeout",
+    severity: Severity::Warning,
+};
+
+/// Ramp-down in a run without `--max-duration`, which never starts.
+pub const RAMP_DOWN_WITHOUT_RUN_LIMIT: Rule = Rule {
+    code: "L007",
+    name: "ramp-down-without-run-limit",
+    severity: Severity::Warning,
+};
+
+/// Every rule, in code order.
+pub const RULES: &[Rule] = &[
+    UNANCHORED_REGEX,
+    DUPLICATE_TARGETING,
+    STALE_DISABLED,
+    PERCENTAGE_CLAMPED,
+    HEADER_CASE,
+    LONG_TIMEOUT,
+    RAMP_DOWN_WITHOUT_RUN_LIMIT,
+];
+
+/// A single lint finding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Rule code, e.g. `L001`.
+    pub code: &'static str,
+    /// Rule name, e.g. `unanchored-regex`.
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// Field path, e.g. `experiments[api-latency].targeting.paths[0]`.
+    pub location: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(rule: Rule, location: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: rule.code,
+            rule: rule.name,
+            severity: rule.severity,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {} ({})",
+            self.severity, self.code, self.location, self.message, self.rule
+        )
+    }
+}
+
+/// Lint settings.
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// Age after which a disabled experiment's file counts as stale.
+    pub stale_after: Duration,
+    /// Current time, for staleness.
+    pub now: SystemTime,
+    /// The agent's `--max-duration`, which ramp-downs count down to.
+    pub max_duration: Option<Duration>,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            stale_after: DEFAULT_STALE_AFTER,
+            now: SystemTime::now(),
+            max_duration: None,
+        }
+    }
+}
+
+/// Run every rule against a configuration, returning findings in
+/// experiment order.
+pub fn lint(config: &Config, options: &LintOptions) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (index, exp) in config.experiments.iter().enumerate() {
+        let location = format!("experiments[{}]", exp.id);
+        unanchored_regex(exp, &location, &mut findings);
+        duplicate_targeting(&config.experiments[..index], exp, &location, &mut findings);
+        stale_disabled(exp, options, &location, &mut findings);
+        percentage_clamped(config, exp, &location, &mut findings);
+        header_case(exp, &location, &mut findings);
+        long_timeout(exp, &location, &mut findings);
+        ramp_down_without_run_limit(exp, options, &location, &mut findings);
+    }
+    findings
+}
+
+fn unanchored_regex(exp: &Experiment, location: &str, findings: &mut Vec<Finding>) {
+    for (i, matcher) in exp.targeting.paths.iter().enumerate() {
+        let matcher = match matcher {
+            PathMatcher::Weighted { matcher, .. } => matcher,
+            matcher => matcher,
+        };
+        if let PathMatcher::Regex { regex } = matcher {
+            if !regex.starts_with('^') {
+                findings.push(Finding::new(
+                    UNANCHORED_REGEX,
+                    format!("{}.targeting.paths[{}]", location, i),
+                    format!(
+                        "regex '{}' is not anchored with '^' and matches anywhere in the path",
+                        regex
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn duplicate_targeting(
+    earlier: &[Experiment],
+    exp: &Experiment,
+    location: &str,
+    findings: &mut Vec<Finding>,
+) {
+    if let Some(original) = earlier.iter().find(|e| e.targeting == exp.targeting) {
+        findings.push(Finding::new(
+            DUPLICATE_TARGETING,
+            format!("{}.targeting", location),
+            format!("same targeting as experiment '{}'", original.id),
+        ));
+    }
+}
+
+fn stale_disabled(
+    exp: &Experiment,
+    options: &LintOptions,
+    location: &str,
+    findings: &mut Vec<Finding>,
+) {
+    if exp.enabled {
+        return;
+    }
+    let modified = exp
+        .source
+        .as_ref()
+        .and_then(|source| std::fs::metadata(source).ok())
+        .and_then(|metadata| metadata.modified().ok());
+    let Some(age) = modified.and_then(|modified| options.now.duration_since(modified).ok()) else {
+        return;
+    };
+    if age > options.stale_after {
+        findings.push(Finding::new(
+            STALE_DISABLED,
+            format!("{}.enabled", location),
+            format!(
+                "disabled in a file unchanged for {} days; remove it if it is no longer needed",
+                age.as_secs() / (24 * 3600)
+            ),
+        ));
+    }
+}
+
+fn percentage_clamped(
+    config: &Config,
+    exp: &Experiment,
+    location: &str,
+    findings: &mut Vec<Finding>,
+) {
+    let max = config.safety.max_affected_percent;
+    if exp.targeting.percentage > max && exp.targeting.every_n.is_none() {
+        findings.push(Finding::new(
+            PERCENTAGE_CLAMPED,
+            format!("{}.targeting.percentage", location),
+            format!(
+                "{}% is above safety.max_affected_percent and runs at {}%",
+                exp.targeting.percentage, max
+            ),
+        ));
+    }
+}
+
+fn header_case(exp: &Experiment, location: &str, findings: &mut Vec<Finding>) {
+    let targeting = &exp.targeting;
+    let mut names: Vec<(&str, &String)> = targeting
+        .headers
+        .keys()
+        .map(|name| ("headers", name))
+        .chain(
+            targeting
+                .headers_absent
+                .iter()
+                .map(|name| ("headers_absent", name)),
+        )
+        .collect();
+    names.sort();
+    for (field, name) in names {
+        if name.chars().any(|c| c.is_ascii_uppercase()) {
+            findings.push(Finding::new(
+                HEADER_CASE,
+                format!("{}.targeting.{}", location, field),
+                format!(
+                    "header '{}' matches case-insensitively; write it as '{}'",
+                    name,
+                    name.to_ascii_lowercase()
+                ),
+            ));
+        }
+    }
+}
+
+fn long_timeout(exp: &Experiment, location: &str, findings: &mut Vec<Finding>) {
+    if let Fault::Timeout { duration_ms, .. } = exp.fault {
+        if duration_ms > TYPICAL_PROXY_TIMEOUT_MS {
+            findings.push(Finding::new(
+                LONG_TIMEOUT,
+                format!("{}.fault.duration_ms", location),
+                format!(
+                    "{}ms is longer than typical proxy timeouts ({}s); the proxy may give up first",
+                    duration_ms,
+                    TYPICAL_PROXY_TIMEOUT_MS / 1000
+                ),
+            ));
+        }
+    }
+}
+
+fn ramp_down_without_run_limit(
+    exp: &Experiment,
+    options: &LintOptions,
+    location: &str,
+    findings: &mut Vec<Finding>,
+) {
+    if exp.ramp_down_seconds.is_some() && options.max_duration.is_none() {
+        findings.push(Finding::new(
+            RAMP_DOWN_WITHOUT_RUN_LIMIT,
+            format!("{}.ramp_down_seconds", location),
+            "only applies before a --max-duration run ends; without one the experiment never tapers off",
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn codes(config: &Config) -> Vec<(&'static str, String)> {
+        lint(config, &LintOptions::default())
+            .into_iter()
+            .map(|f| (f.code, f.location))
+            .collect()
+    }
+
+    #[test]
+    fn test_unanchored_regex() {
+        let config = config(
+            r#"
+experiments:
+  - id: "a"
+    targeting:
+      paths:
+        - regex: "/users/[0-9]+"
+        - regex: "^/orders/.*"
+      percentage: 5
+    fault: { type: reset }
+"#,
+        );
+        assert_eq!(
+            codes(&config),
+            vec![("L001", "experiments[a].targeting.paths[0]".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_targeting() {
+        let config = config(
+            r#"
+experiments:
+  - id: "a"
+    targeting: { paths: [{ prefix: "/api/" }], percentage: 5 }
+    fault: { type: reset }
+  - id: "b"
+    targeting: { paths: [{ prefix: "/api/" }], percentage: 5 }
+    fault: { type: error, status: 500 }
+  - id: "c"
+    targeting: { paths: [{ prefix: "/api/" }], percentage: 10 }
+    fault: { type: reset }
+"#,
+        );
+        let findings = lint(&config, &LintOptions::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].location, "experiments[b].targeting");
+        assert_eq!(findings[0].message, "same targeting as experiment 'a'");
+    }
+
+    #[test]
+    fn test_stale_disabled() {
+        let dir = std::env::temp_dir().join(format!("chaos-lint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chaos.yaml");
+        std::fs::write(
+            &path,
+            r#"
+experiments:
+  - id: "old"
+    enabled: false
+    targeting: { percentage: 5 }
+    fault: { type: reset }
+  - id: "live"
+    targeting: { percentage: 5, paths: [{ prefix: "/x/" }] }
+    fault: { type: reset }
+"#,
+        )
+        .unwrap();
+        let config = Config::from_file(&path).unwrap();
+
+        let fresh = LintOptions::default();
+        assert!(lint(&config, &fresh).is_empty());
+
+        let later = LintOptions {
+            now: SystemTime::now() + Duration::from_secs(90 * 24 * 3600),
+            ..LintOptions::default()
+        };
+        let findings = lint(&config, &later);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "L003");
+        assert_eq!(findings[0].location, "experiments[old].enabled");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_percentage_clamped() {
+        let config = config(
+            r#"
+safety: { max_affected_percent: 20 }
+experiments:
+  - id: "a"
+    targeting: { percentage: 50 }
+    fault: { type: reset }
+"#,
+        );
+        let findings = lint(&config, &LintOptions::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "L004");
+        assert_eq!(
+            findings[0].message,
+            "50% is above safety.max_affected_percent and runs at 20%"
+        );
+    }
+
+    #[test]
+    fn test_header_case() {
+        let config = config(
+            r#"
+experiments:
+  - id: "a"
+    targeting:
+      headers: { X-Chaos-Enabled: "true", x-team: "payments" }
+      headers_absent: [X-Api-Version]
+      percentage: 5
+    fault: { type: reset }
+"#,
+        );
+        assert_eq!(
+            codes(&config),
+            vec![
+                ("L005", "experiments[a].targeting.headers".to_string()),
+                (
+                    "L005",
+                    "experiments[a].targeting.headers_absent".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_timeout() {
+        let config = config(
+            r#"
+experiments:
+  - id: "a"
+    targeting: { percentage: 5, paths: [{ prefix: "/a/" }] }
+    fault: { type: timeout, duration_ms: 30s }
+  - id: "b"
+    targeting: { percentage: 5, paths: [{ prefix: "/b/" }] }
+    fault: { type: timeout, duration_ms: 5m }
+"#,
+        );
+        let findings = lint(&config, &LintOptions::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].to_string(),
+            "warning[L006] experiments[b].fault.duration_ms: 300000ms is longer than typical \
+             proxy timeouts (60s); the proxy may give up first (long-timeout)"
+        );
+    }
+
+    #[test]
+    fn test_ramp_down_without_run_limit() {
+        let config = config(
+            r#"
+experiments:
+  - id: "a"
+    ramp_down_seconds: 600
+    targeting: { percentage: 5 }
+    fault: { type: reset }
+"#,
+        );
+        assert_eq!(
+            codes(&config),
+            vec![("L007", "experiments[a].ramp_down_seconds".to_string())]
+        );
+
+        let limited = LintOptions {
+            max_duration: Some(Duration::from_secs(3600)),
+            ..LintOptions::default()
+        };
+        assert!(lint(&config, &limited).is_empty());
+    }
+}
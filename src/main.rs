@@ -1,11 +1,32 @@
 //! Chaos Engineering Agent CLI.
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::fs::File;
+use std::io::BufReader;
 use std::net::SocketAddr;
 use std::path::PathBuf;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
+use zentinel_agent_chaos::accesslog::{JsonFields, LogFormat, LogParser};
+use zentinel_agent_chaos::admin::spawn_admin_server;
+use zentinel_agent_chaos::config::parse_duration_ms;
+use zentinel_agent_chaos::example::example_yaml;
+use zentinel_agent_chaos::explain::explain;
+use zentinel_agent_chaos::generate::{
+    append_to_file, experiment_yaml, generate_experiment, ExperimentSpec,
+};
+use zentinel_agent_chaos::lint::{lint, LintOptions, Severity};
+use zentinel_agent_chaos::prometheus::spawn_metrics_server;
+use zentinel_agent_chaos::replay::{replay, ReplayOptions};
+use zentinel_agent_chaos::schema::config_schema;
+#[cfg(unix)]
+use zentinel_agent_chaos::signals;
+use zentinel_agent_chaos::simulate::{simulate, SimulatedRequest};
+use zentinel_agent_chaos::status::fetch_status;
+use zentinel_agent_chaos::watch::{spawn_config_watcher, WatchedFiles, WATCH_INTERVAL};
 use zentinel_agent_chaos::{ChaosAgent, Config};
 use zentinel_agent_sdk::v2::{AgentRunnerV2, TransportConfig};
 
@@ -16,8 +37,8 @@ use zentinel_agent_sdk::v2::{AgentRunnerV2, TransportConfig};
 )]
 #[command(version)]
 struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "chaos.yaml")]
+    /// Path to configuration file (.yaml, .yml, .json or .toml)
+    #[arg(short, long, default_value = "chaos.yaml", global = true)]
     config: PathBuf,
 
     /// Unix socket path
@@ -28,6 +49,16 @@ struct Args {
     #[arg(long, value_name = "ADDR")]
     grpc_address: Option<SocketAddr>,
 
+    /// Serve the admin API on this address, e.g. 127.0.0.1:9901 (off by
+    /// default); for `status`, the address of the running agent's admin API
+    #[arg(long, value_name = "ADDR", global = true)]
+    admin_address: Option<SocketAddr>,
+
+    /// Serve Prometheus metrics at /metrics on this address, e.g.
+    /// 0.0.0.0:9900 (off by default); may equal --admin-address
+    #[arg(long, value_name = "ADDR")]
+    metrics_address: Option<SocketAddr>,
+
     /// Log level (trace, debug, info, warn, error)
     #[arg(short = 'L', long, default_value = "info")]
     log_level: String,
@@ -36,96 +67,409 @@ struct Args {
     #[arg(long)]
     print_config: bool,
 
+    /// Print the JSON Schema for configuration files and exit
+    #[arg(long)]
+    print_schema: bool,
+
     /// Validate configuration and exit
     #[arg(long)]
     validate: bool,
 
+    /// With --validate, print the effective match plan for each experiment
+    #[arg(long, requires = "validate")]
+    explain: bool,
+
+    /// Treat configuration warnings, such as an enabled experiment at 0%,
+    /// as errors
+    #[arg(long)]
+    strict: bool,
+
     /// Run in dry-run mode (log faults without applying)
     #[arg(long)]
     dry_run: bool,
+
+    /// Inject a single fault, then pass everything through (re-armed on
+    /// reload)
+    #[arg(long)]
+    once: bool,
+
+    /// Seed every random decision so runs can be reproduced (overrides
+    /// settings.seed)
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Reload the configuration whenever the config file changes
+    #[arg(long)]
+    watch_config: bool,
+
+    /// Stop injecting faults after this long, e.g. 2h, then exit once
+    /// --drain-grace has passed
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    max_duration: Option<Duration>,
+
+    /// With --max-duration, how long to keep serving after injections stop
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    drain_grace: Duration,
+
+    /// Run only this experiment, disabling all others (repeatable)
+    #[arg(long = "only-experiment", value_name = "ID")]
+    only_experiments: Vec<String>,
+
+    /// Disable this experiment (repeatable)
+    #[arg(long = "disable-experiment", value_name = "ID")]
+    disabled_experiments: Vec<String>,
+
+    /// Multiply every experiment's percentage by this factor, e.g. 0.1 for
+    /// a cautious first run, by scaling `settings.intensity`
+    #[arg(long, value_name = "FACTOR", value_parser = parse_scale)]
+    percentage_scale: Option<f64>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn print_example_config() {
-    let example = r#"# Chaos Engineering Agent Configuration
-# See https://zentinelproxy.io/agents/chaos/ for full documentation
-
-settings:
-  enabled: true                    # Global kill switch
-  dry_run: false                   # Log faults without applying
-  log_injections: true             # Log when faults are injected
-
-# Safety limits
-safety:
-  max_affected_percent: 50         # Never affect more than 50% of traffic
-  schedule:                        # Only active during these windows
-    - days: [mon, tue, wed, thu, fri]
-      start: "09:00"
-      end: "17:00"
-      timezone: "UTC"
-  excluded_paths:                  # Never inject faults here
-    - "/health"
-    - "/ready"
-    - "/metrics"
-
-# Fault experiments
-experiments:
-  # Example: Add latency to API calls
-  - id: "api-latency"
-    enabled: true
-    description: "Add latency to API calls"
-    targeting:
-      paths:
-        - prefix: "/api/"
-      methods: ["GET", "POST"]
-      percentage: 10               # Affect 10% of matching requests
-    fault:
-      type: latency
-      fixed_ms: 500                # Fixed 500ms delay
-      # OR random range:
-      # min_ms: 100
-      # max_ms: 1000
-
-  # Example: Inject 500 errors
-  - id: "payment-errors"
-    enabled: true
-    description: "Inject 500 errors into payment service"
-    targeting:
-      paths:
-        - exact: "/api/payments"
-      percentage: 5
-    fault:
-      type: error
-      status: 500
-      message: "Chaos: Internal Server Error"
-      headers:
-        x-chaos-injected: "true"
-
-  # Example: Simulate upstream timeout
-  - id: "upstream-timeout"
-    enabled: false
-    description: "Simulate upstream timeouts"
-    targeting:
-      paths:
-        - regex: "^/api/external/.*"
-      percentage: 2
-    fault:
-      type: timeout
-      duration_ms: 30000           # 30 second timeout
-
-  # Example: Header-triggered latency (for testing)
-  - id: "header-triggered-latency"
-    enabled: true
-    description: "Add latency when X-Chaos-Latency header is present"
-    targeting:
-      headers:
-        x-chaos-latency: "true"
-      percentage: 100
-    fault:
-      type: latency
-      min_ms: 1000
-      max_ms: 3000
-"#;
-    println!("{}", example);
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check the configuration for likely mistakes and exit, non-zero if
+    /// any warnings were found
+    Lint {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Report disabled experiments in files unchanged for this many days
+        #[arg(long, default_value_t = 30)]
+        stale_days: u64,
+    },
+    /// Show which experiments would hit a request, without sending traffic
+    Simulate {
+        /// Request method
+        #[arg(long, default_value = "GET")]
+        method: String,
+
+        /// Request path
+        #[arg(long)]
+        path: String,
+
+        /// Request header as `name:value` (repeatable)
+        #[arg(long = "header", value_name = "NAME:VALUE", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+
+        /// Evaluate schedules at this RFC 3339 timestamp instead of now
+        #[arg(long, value_name = "TIMESTAMP")]
+        at: Option<DateTime<Utc>>,
+
+        /// Sample the percentage roll this many times and report the
+        /// injection rate
+        #[arg(long, default_value_t = 0)]
+        count: u64,
+
+        /// Simulate this long before a --max-duration run ends, applying
+        /// `ramp_down_seconds`
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        run_time_left: Option<Duration>,
+    },
+    /// Estimate the blast radius of the configuration from an access log
+    Replay {
+        /// Access log to replay
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Access log format
+        #[arg(long, value_enum, default_value_t = InputFormat::Combined)]
+        log_format: InputFormat,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Count expected injections instead of sampling percentages
+        #[arg(long)]
+        deterministic: bool,
+
+        /// Path segments per prefix in the breakdown
+        #[arg(long, default_value_t = 1)]
+        prefix_depth: usize,
+
+        /// JSON field holding the request method
+        #[arg(long, default_value = "method")]
+        method_field: String,
+
+        /// JSON field holding the request path
+        #[arg(long, default_value = "path")]
+        path_field: String,
+
+        /// JSON field holding an object of request headers
+        #[arg(long, default_value = "headers")]
+        headers_field: String,
+
+        /// JSON field holding the RFC 3339 request time
+        #[arg(long, default_value = "time")]
+        time_field: String,
+    },
+    /// Print a configuration scaffold
+    Generate {
+        #[command(subcommand)]
+        what: GenerateCommand,
+    },
+    /// Summarize a running agent through its admin API
+    Status {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Admin API bearer token (default: $CHAOS_ADMIN_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Refresh until interrupted
+        #[arg(long)]
+        watch: bool,
+
+        /// With --watch, how often to refresh
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "2s")]
+        interval: Duration,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GenerateCommand {
+    /// Print a valid experiment block, or append it to a config file
+    Experiment {
+        /// Experiment ID
+        #[arg(long)]
+        id: String,
+
+        /// Fault type, e.g. latency, error or timeout
+        #[arg(long = "type", value_name = "TYPE")]
+        fault_type: String,
+
+        /// Only target paths starting with this prefix
+        #[arg(long)]
+        path_prefix: Option<String>,
+
+        /// Only target this method (repeatable)
+        #[arg(long = "method")]
+        methods: Vec<String>,
+
+        /// Percentage of matching requests to fault
+        #[arg(long, default_value_t = 5)]
+        percentage: u8,
+
+        /// Experiment description
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Status for error, timeout, reset, status_from_header and echo faults
+        #[arg(long)]
+        status: Option<u16>,
+
+        /// Delay in milliseconds for latency, jitter, escalating_latency and
+        /// timeout faults
+        #[arg(long)]
+        delay_ms: Option<u64>,
+
+        /// Append the experiment to this YAML config file instead of
+        /// printing it
+        #[arg(long, value_name = "FILE")]
+        append: Option<PathBuf>,
+    },
+}
+
+/// Parse a duration argument such as `90s` or `2h`.
+fn parse_duration(arg: &str) -> Result<Duration, String> {
+    parse_duration_ms(arg).map(Duration::from_millis)
+}
+
+/// Parse a non-negative scale factor.
+fn parse_scale(arg: &str) -> Result<f64, String> {
+    match arg.parse::<f64>() {
+        Ok(scale) if scale.is_finite() && scale >= 0.0 => Ok(scale),
+        _ => Err(format!("expected a number >= 0, got '{}'", arg)),
+    }
+}
+
+/// Experiment overrides given on the command line, applied to every
+/// loaded configuration.
+#[derive(Debug, Clone, Default)]
+struct ExperimentOverrides {
+    only: Vec<String>,
+    disabled: Vec<String>,
+    percentage_scale: Option<f64>,
+}
+
+impl ExperimentOverrides {
+    fn from_args(args: &Args) -> Self {
+        Self {
+            only: args.only_experiments.clone(),
+            disabled: args.disabled_experiments.clone(),
+            percentage_scale: args.percentage_scale,
+        }
+    }
+
+    /// Apply the overrides, failing if they name an unknown experiment.
+    fn apply(&self, config: &mut Config) -> Result<()> {
+        for id in self.only.iter().chain(&self.disabled) {
+            if !config.experiments.iter().any(|exp| exp.id == *id) {
+                bail!("no experiment '{}' in the configuration", id);
+            }
+        }
+
+        if !self.only.is_empty() {
+            for exp in &mut config.experiments {
+                exp.enabled = self.only.contains(&exp.id);
+            }
+            info!(experiments = ?self.only, "Only running experiments named via --only-experiment");
+        }
+        if !self.disabled.is_empty() {
+            for exp in &mut config.experiments {
+                if self.disabled.contains(&exp.id) {
+                    exp.enabled = false;
+                }
+            }
+            info!(experiments = ?self.disabled, "Experiments disabled via --disable-experiment");
+        }
+        // Scaling the intensity keeps fractional rates, such as 0.5% of a
+        // configured 5%, that rounding percentages would lose
+        if let Some(scale) = self.percentage_scale {
+            config.settings.intensity *= scale;
+            info!(
+                scale,
+                intensity = config.settings.intensity,
+                "Experiment percentages scaled via --percentage-scale"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `name:value` header argument.
+fn parse_header(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg
+        .split_once(':')
+        .ok_or_else(|| format!("expected NAME:VALUE, got '{}'", arg))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum InputFormat {
+    Common,
+    Combined,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Lint the configuration, print findings and exit with status 1 if any
+/// of them is a warning.
+fn run_lint(args: &Args, format: OutputFormat, stale_days: u64) -> Result<()> {
+    let config = Config::from_file(&args.config)?;
+    let options = LintOptions {
+        stale_after: Duration::from_secs(stale_days * 24 * 3600),
+        max_duration: args.max_duration,
+        ..LintOptions::default()
+    };
+    let findings = lint(&config, &options);
+    match format {
+        OutputFormat::Text => {
+            for finding in &findings {
+                println!("{}", finding);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&findings)?),
+    }
+    if findings.iter().any(|f| f.severity >= Severity::Warning) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Print the status of a running agent, once or every `interval` with
+/// `watch`.
+async fn run_status(
+    args: &Args,
+    format: OutputFormat,
+    token: Option<String>,
+    watch: bool,
+    interval: Duration,
+) -> Result<()> {
+    let Some(addr) = args.admin_address else {
+        bail!("status needs the agent's --admin-address");
+    };
+    let token = token.or_else(|| std::env::var("CHAOS_ADMIN_TOKEN").ok());
+    loop {
+        let report = fetch_status(addr, token.as_deref()).await;
+        match (&report, watch) {
+            (Err(e), true) => eprintln!("{:#}", e),
+            (Err(_), false) => return report.map(|_| ()),
+            (Ok(report), _) => match format {
+                OutputFormat::Text if watch => {
+                    // Clear the screen so the summary redraws in place
+                    print!("\x1b[2J\x1b[H{}", report);
+                }
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json if watch => println!("{}", serde_json::to_string(report)?),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+            },
+        }
+        if !watch {
+            return Ok(());
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Log configuration warnings, or fail on them with `--strict`.
+fn check_warnings(config: &Config, strict: bool) -> Result<()> {
+    if strict {
+        return config.validate_strict();
+    }
+    for warning in config.warnings() {
+        warn!(path = %warning.path, "{}", warning.message);
+    }
+    Ok(())
+}
+
+/// Warn about ramp-downs that never start: they count down to the end of a
+/// `--max-duration` run.
+fn check_ramp_down(config: &Config, max_duration: Option<Duration>) {
+    if max_duration.is_some() {
+        return;
+    }
+    for exp in &config.experiments {
+        if exp.ramp_down_seconds.is_some() {
+            warn!(
+                path = %format!("experiments[{}].ramp_down_seconds", exp.id),
+                "ramp_down_seconds has no effect without --max-duration"
+            );
+        }
+    }
+}
+
+/// Reject faults this binary can never apply: it runs the v2 protocol,
+/// which does not send request bodies and whose response events it only
+/// observes, so echo and response-phase faults would never fire.
+fn check_protocol(config: &Config) -> Result<()> {
+    for exp in config.experiments.iter().filter(|exp| exp.enabled) {
+        if exp.fault.is_body_phase() {
+            bail!(
+                "experiments[{}].fault: echo needs request body events, which the v2 protocol does not send",
+                exp.id
+            );
+        }
+        if exp.fault.is_response_phase() {
+            bail!(
+                "experiments[{}].fault: {} runs on the response, which the v2 protocol handler cannot change",
+                exp.id,
+                exp.fault.type_name()
+            );
+        }
+    }
+    Ok(())
 }
 
 #[tokio::main]
@@ -134,10 +478,123 @@ async fn main() -> Result<()> {
 
     // Handle --print-config
     if args.print_config {
-        print_example_config();
+        print!("{}", example_yaml()?);
         return Ok(());
     }
 
+    // Handle --print-schema
+    if args.print_schema {
+        println!("{}", serde_json::to_string_pretty(&config_schema())?);
+        return Ok(());
+    }
+
+    match &args.command {
+        Some(Command::Lint { format, stale_days }) => {
+            return run_lint(&args, *format, *stale_days);
+        }
+        Some(Command::Simulate {
+            method,
+            path,
+            headers,
+            at,
+            count,
+            run_time_left,
+        }) => {
+            let config = Config::from_file(&args.config)?;
+            let request = SimulatedRequest {
+                method: method.clone(),
+                path: path.clone(),
+                headers: headers.iter().cloned().collect(),
+                at: at.unwrap_or_else(Utc::now),
+                run_time_left: *run_time_left,
+            };
+            print!("{}", simulate(&config, &request, *count));
+            return Ok(());
+        }
+        Some(Command::Replay {
+            input,
+            log_format,
+            format,
+            deterministic,
+            prefix_depth,
+            method_field,
+            path_field,
+            headers_field,
+            time_field,
+        }) => {
+            let config = Config::from_file(&args.config)?;
+            let log_format = match log_format {
+                InputFormat::Common => LogFormat::Common,
+                InputFormat::Combined => LogFormat::Combined,
+                InputFormat::Json => LogFormat::Json,
+            };
+            let parser = LogParser::new(
+                log_format,
+                JsonFields {
+                    method: method_field.clone(),
+                    path: path_field.clone(),
+                    headers: headers_field.clone(),
+                    time: time_field.clone(),
+                },
+            );
+            let options = ReplayOptions {
+                deterministic: *deterministic,
+                prefix_depth: *prefix_depth,
+                ..ReplayOptions::default()
+            };
+            let file =
+                File::open(input).with_context(|| format!("cannot open {}", input.display()))?;
+            let report = replay(&config, BufReader::new(file), &parser, &options)?;
+            match format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            }
+            return Ok(());
+        }
+        Some(Command::Generate {
+            what:
+                GenerateCommand::Experiment {
+                    id,
+                    fault_type,
+                    path_prefix,
+                    methods,
+                    percentage,
+                    description,
+                    status,
+                    delay_ms,
+                    append,
+                },
+        }) => {
+            let experiment = generate_experiment(&ExperimentSpec {
+                id: id.clone(),
+                fault_type: fault_type.clone(),
+                description: description.clone(),
+                path_prefix: path_prefix.clone(),
+                methods: methods.clone(),
+                percentage: *percentage,
+                status: *status,
+                delay_ms: *delay_ms,
+            })?;
+            match append {
+                Some(path) => {
+                    append_to_file(path, &experiment)?;
+                    eprintln!("Appended experiment '{}' to {}", id, path.display());
+                }
+                None => print!("{}", experiment_yaml(&experiment)?),
+            }
+            return Ok(());
+        }
+        Some(Command::Status {
+            format,
+            token,
+            watch,
+            interval,
+        }) => {
+            return run_status(&args, *format, token.clone(), *watch, *interval).await;
+        }
+        None => {}
+    }
+
     // Initialize logging
     let filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&args.log_level));
@@ -150,21 +607,114 @@ async fn main() -> Result<()> {
     // Load configuration
     info!(config = %args.config.display(), "Loading configuration");
     let mut config = Config::from_file(&args.config)?;
+    if !args.validate {
+        check_warnings(&config, args.strict)?;
+        check_ramp_down(&config, args.max_duration);
+    }
 
     // Override dry_run if specified on command line
     if args.dry_run {
         config.settings.dry_run = true;
         info!("Dry-run mode enabled via command line");
     }
+    if args.once {
+        config.settings.once = true;
+        info!("Single-fault mode enabled via command line");
+    }
+    if let Some(seed) = args.seed {
+        config.settings.seed = Some(seed);
+        info!(seed, "Random decisions seeded via command line");
+    }
+    let overrides = ExperimentOverrides::from_args(&args);
+    overrides.apply(&mut config)?;
+    check_protocol(&config)?;
 
     // Handle --validate
     if args.validate {
-        info!("Configuration is valid");
+        if args.explain {
+            print!("{}", explain(&config));
+        }
+        if args.strict {
+            config.validate_strict()?;
+        }
+        let warnings = config.warnings();
+        for warning in &warnings {
+            println!("warning: {}", warning);
+        }
+        info!(warnings = warnings.len(), "Configuration is valid");
         return Ok(());
     }
 
+    let watched = WatchedFiles::of(&config, &args.config);
+
+    // Reloads and proxy pushes keep the --dry-run, --once, --seed,
+    // --strict and experiment overrides
+    let prepare_config = {
+        let dry_run = args.dry_run;
+        let once = args.once;
+        let seed = args.seed;
+        let strict = args.strict;
+        let max_duration = args.max_duration;
+        move |config: &mut Config| -> Result<()> {
+            check_warnings(config, strict)?;
+            check_ramp_down(config, max_duration);
+            if dry_run {
+                config.settings.dry_run = true;
+            }
+            if once {
+                config.settings.once = true;
+            }
+            if seed.is_some() {
+                config.settings.seed = seed;
+            }
+            overrides.apply(config)?;
+            check_protocol(config)
+        }
+    };
+
     // Create agent
-    let agent = ChaosAgent::new(config);
+    let mut agent = ChaosAgent::new(config).with_config_hook(prepare_config.clone());
+    if let Some(max_duration) = args.max_duration {
+        info!(
+            max_duration_secs = max_duration.as_secs(),
+            "Fault injection stops after the maximum run time"
+        );
+        agent = agent.with_max_duration(max_duration);
+    }
+
+    let load_config = {
+        let config_path = args.config.clone();
+        move || {
+            let mut config = Config::from_file(&config_path)?;
+            prepare_config(&mut config)?;
+            Ok(config)
+        }
+    };
+
+    // SIGUSR1 suspends/resumes, SIGUSR2 resets counters, SIGHUP reloads
+    #[cfg(unix)]
+    signals::spawn_signal_handler(agent.control(), load_config.clone())?;
+
+    if args.watch_config {
+        info!(path = %args.config.display(), "Watching configuration file for changes");
+        spawn_config_watcher(
+            agent.control(),
+            args.config.clone(),
+            watched,
+            WATCH_INTERVAL,
+            load_config,
+        );
+    }
+
+    // The same address for both serves /metrics from the admin listener
+    let shared_metrics = args.admin_address.is_some() && args.admin_address == args.metrics_address;
+    if let Some(admin_address) = args.admin_address {
+        spawn_admin_server(admin_address, agent.control(), shared_metrics).await?;
+    }
+
+    if let Some(metrics_address) = args.metrics_address.filter(|_| !shared_metrics) {
+        spawn_metrics_server(metrics_address, agent.control()).await?;
+    }
 
     // Configure transport based on CLI options
     let transport = match args.grpc_address {
@@ -197,7 +747,165 @@ async fn main() -> Result<()> {
         } => runner.with_both(grpc_address, uds_path),
     };
 
-    runner.run().await?;
+    // A time-limited run drains by itself; exit once the grace period is over
+    match args.max_duration {
+        Some(max_duration) => {
+            tokio::select! {
+                result = runner.run() => result?,
+                _ = tokio::time::sleep(max_duration + args.drain_grace) => {
+                    info!("Maximum run time and drain grace period elapsed, exiting");
+                }
+            }
+        }
+        None => runner.run().await?,
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+experiments:
+  - id: "api-latency"
+    targeting: { percentage: 40 }
+    fault: { type: latency, fixed_ms: 100 }
+  - id: "payment-errors"
+    targeting: { percentage: 5 }
+    fault: { type: error, status: 503 }
+  - id: "disabled-reset"
+    enabled: false
+    targeting: { percentage: 100 }
+    fault: { type: reset }
+"#,
+        )
+        .unwrap()
+    }
+
+    fn enabled(config: &Config) -> Vec<&str> {
+        config
+            .experiments
+            .iter()
+            .filter(|exp| exp.enabled)
+            .map(|exp| exp.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_echo_rejected_under_v2() {
+        let mut config = config();
+        assert!(check_protocol(&config).is_ok());
+
+        let echo: zentinel_agent_chaos::config::Experiment = serde_yaml::from_str(
+            "id: webhooks\ntargeting: { percentage: 1 }\nfault: { type: echo, status: 200 }",
+        )
+        .unwrap();
+        config.experiments.push(echo);
+        assert_eq!(
+            check_protocol(&config).unwrap_err().to_string(),
+            "experiments[webhooks].fault: echo needs request body events, which the v2 protocol does not send"
+        );
+
+        // A disabled echo can stay in a shared configuration
+        config.experiments[3].enabled = false;
+        assert!(check_protocol(&config).is_ok());
+    }
+
+    #[test]
+    fn test_response_phase_rejected_under_v2() {
+        for (fault, name) in [
+            ("{ type: latency, fixed_ms: 100, when: after }", "latency"),
+            (
+                "{ type: corrupt, probability: 1.0, mode: bitrot }",
+                "corrupt",
+            ),
+            (
+                "{ type: mutate_response_headers, remove: [cache-control] }",
+                "mutate_response_headers",
+            ),
+        ] {
+            let mut config = config();
+            let experiment: zentinel_agent_chaos::config::Experiment = serde_yaml::from_str(
+                &format!("id: late\ntargeting: {{ percentage: 1 }}\nfault: {}", fault),
+            )
+            .unwrap();
+            config.experiments.push(experiment);
+            assert_eq!(
+                check_protocol(&config).unwrap_err().to_string(),
+                format!(
+                    "experiments[late].fault: {} runs on the response, which the v2 protocol handler cannot change",
+                    name
+                )
+            );
+            config.experiments[3].enabled = false;
+            assert!(check_protocol(&config).is_ok());
+        }
+
+        // Corruption that replaces the body is decided on the request
+        let mut config = config();
+        config.experiments.push(
+            serde_yaml::from_str(
+                "id: garbage\ntargeting: { percentage: 1 }\nfault: { type: corrupt, probability: 1.0 }",
+            )
+            .unwrap(),
+        );
+        assert!(check_protocol(&config).is_ok());
+    }
+
+    #[test]
+    fn test_experiment_overrides() {
+        let mut only = config();
+        ExperimentOverrides {
+            only: vec!["disabled-reset".to_string()],
+            ..ExperimentOverrides::default()
+        }
+        .apply(&mut only)
+        .unwrap();
+        assert_eq!(enabled(&only), vec!["disabled-reset"]);
+
+        let mut disabled = config();
+        ExperimentOverrides {
+            disabled: vec!["payment-errors".to_string()],
+            percentage_scale: Some(0.1),
+            ..ExperimentOverrides::default()
+        }
+        .apply(&mut disabled)
+        .unwrap();
+        assert_eq!(enabled(&disabled), vec!["api-latency"]);
+        // Percentages are kept and scaled through the intensity, so 5%
+        // becomes 0.5% rather than rounding to 1%
+        let percentages: Vec<u8> = disabled
+            .experiments
+            .iter()
+            .map(|exp| exp.targeting.percentage)
+            .collect();
+        assert_eq!(percentages, vec![40, 5, 100]);
+        assert!((disabled.settings.intensity - 0.1).abs() < 1e-9);
+
+        let mut scaled_up = config();
+        scaled_up.settings.intensity = 0.5;
+        ExperimentOverrides {
+            percentage_scale: Some(3.0),
+            ..ExperimentOverrides::default()
+        }
+        .apply(&mut scaled_up)
+        .unwrap();
+        assert_eq!(scaled_up.settings.intensity, 1.5);
+
+        let err = ExperimentOverrides {
+            only: vec!["missing".to_string()],
+            ..ExperimentOverrides::default()
+        }
+        .apply(&mut config())
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no experiment 'missing' in the configuration"
+        );
+        assert!(parse_scale("-1").is_err());
+    }
+}
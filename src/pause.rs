@@ -0,0 +1,98 @@
+//! Reversible runtime pause of all injections.
+//!
+//! Unlike a drain, which is one-way for shutdown, a pause can be lifted with
+//! a resume. A pause may be bounded: it then lifts itself once its duration
+//! has passed. Expiry is evaluated lazily whenever the pause is checked, so
+//! requests, health checks, metrics and the admin API all observe it.
+
+use crate::deadline::DeadlineFlag;
+use std::time::Duration;
+
+/// Pause state shared by the agent and its control handles.
+pub struct RuntimePause {
+    flag: DeadlineFlag,
+}
+
+impl Default for RuntimePause {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuntimePause {
+    pub fn new() -> Self {
+        Self {
+            flag: DeadlineFlag::new("Pause expired - resuming fault injection"),
+        }
+    }
+
+    /// Pause injections, for `duration` or until resumed, returning whether
+    /// injections were running. Pausing again replaces the duration.
+    pub fn pause(&self, duration: Option<Duration>) -> bool {
+        self.flag.raise(duration)
+    }
+
+    /// Resume injections, returning whether they were paused.
+    pub fn resume(&self) -> bool {
+        self.flag.lower()
+    }
+
+    /// Pause until resumed if running, resume if paused, returning whether
+    /// injections are now paused.
+    pub fn toggle(&self) -> bool {
+        if self.is_paused() {
+            self.resume();
+            false
+        } else {
+            self.pause(None);
+            true
+        }
+    }
+
+    /// Check whether injections are paused, lifting an expired bounded
+    /// pause.
+    pub fn is_paused(&self) -> bool {
+        self.flag.is_raised()
+    }
+
+    /// Time left of a bounded pause, `None` when running or paused until
+    /// resumed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.flag.remaining()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_ended_pause() {
+        let pause = RuntimePause::new();
+        assert!(!pause.is_paused());
+        assert!(pause.pause(None));
+        assert!(!pause.pause(None));
+        assert!(pause.is_paused());
+        assert_eq!(pause.remaining(), None);
+        assert!(pause.resume());
+        assert!(!pause.resume());
+
+        assert!(pause.toggle());
+        assert!(!pause.toggle());
+        assert!(!pause.is_paused());
+    }
+
+    #[test]
+    fn test_bounded_pause_expires() {
+        let pause = RuntimePause::new();
+        pause.pause(Some(Duration::from_secs(1800)));
+        let remaining = pause.remaining().unwrap();
+        assert!(remaining > Duration::from_secs(1799), "{:?}", remaining);
+
+        let now = pause.flag.elapsed_ms();
+        assert!(pause.flag.is_raised_at(now + 1_799_000));
+        assert!(!pause.flag.is_raised_at(now + 1_800_001));
+        assert!(!pause.is_paused());
+        assert_eq!(pause.remaining(), None);
+    }
+}
@@ -0,0 +1,140 @@
+//! Steady-state precheck for experiments.
+//!
+//! An experiment with a precheck only injects while its probe reports the
+//! expected status. A background task polls the URL; when steady state is
+//! violated injection pauses, and it resumes once the probe recovers.
+
+use crate::config::PrecheckConfig;
+use crate::probe::{probe, ProbeUrl};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Steady-state gate for a single experiment.
+pub struct Precheck {
+    experiment_id: String,
+    url: ProbeUrl,
+    expect_status: u16,
+    interval: Duration,
+    /// Whether the most recent probe matched `expect_status`. Starts false
+    /// so nothing is injected before steady state has been observed.
+    steady: AtomicBool,
+}
+
+impl Precheck {
+    /// Create a precheck for an experiment, or `None` if the URL is invalid.
+    pub fn new(experiment_id: &str, config: &PrecheckConfig) -> Option<Self> {
+        match ProbeUrl::parse(&config.url) {
+            Ok(url) => Some(Self {
+                experiment_id: experiment_id.to_string(),
+                url,
+                expect_status: config.expect_status,
+                interval: Duration::from_secs(config.interval_seconds),
+                steady: AtomicBool::new(false),
+            }),
+            Err(e) => {
+                warn!(experiment = %experiment_id, error = %e, "Ignoring invalid precheck URL");
+                None
+            }
+        }
+    }
+
+    /// Check whether the experiment may inject.
+    pub fn is_steady(&self) -> bool {
+        self.steady.load(Ordering::Acquire)
+    }
+
+    /// Spawn the background polling task.
+    ///
+    /// The task holds only a weak reference and exits once the precheck is
+    /// dropped, e.g. after a reload changes the experiment.
+    pub fn spawn(self: &Arc<Self>) {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(run_precheck(Arc::downgrade(self)));
+            }
+            Err(_) => warn!(
+                experiment = %self.experiment_id,
+                "No async runtime available, precheck disabled"
+            ),
+        }
+    }
+
+    /// Probe once and update the gate.
+    pub async fn probe_once(&self) {
+        let result = probe(&self.url).await;
+        let steady = result == Ok(self.expect_status);
+        let was_steady = self.steady.swap(steady, Ordering::AcqRel);
+        if steady && !was_steady {
+            info!(experiment = %self.experiment_id, "Steady state confirmed, injection enabled");
+        } else if !steady && was_steady {
+            match result {
+                Ok(status) => warn!(
+                    experiment = %self.experiment_id,
+                    status = status,
+                    expected = self.expect_status,
+                    "Steady state violated, pausing injection"
+                ),
+                Err(e) => warn!(
+                    experiment = %self.experiment_id,
+                    error = %e,
+                    "Precheck probe failed, pausing injection"
+                ),
+            }
+        }
+    }
+}
+
+async fn run_precheck(precheck: Weak<Precheck>) {
+    loop {
+        let interval = match precheck.upgrade() {
+            Some(precheck) => {
+                precheck.probe_once().await;
+                precheck.interval
+            }
+            None => return,
+        };
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::tests::mock_server;
+    use std::sync::atomic::AtomicU16;
+
+    #[tokio::test]
+    async fn test_gate_follows_probe() {
+        let status = Arc::new(AtomicU16::new(200));
+        let config = PrecheckConfig {
+            url: mock_server(Arc::clone(&status)).await,
+            expect_status: 200,
+            interval_seconds: 10,
+        };
+        let precheck = Precheck::new("exp", &config).unwrap();
+        assert!(!precheck.is_steady());
+
+        precheck.probe_once().await;
+        assert!(precheck.is_steady());
+
+        status.store(503, Ordering::SeqCst);
+        precheck.probe_once().await;
+        assert!(!precheck.is_steady());
+
+        status.store(200, Ordering::SeqCst);
+        precheck.probe_once().await;
+        assert!(precheck.is_steady());
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        let config = PrecheckConfig {
+            url: "https://upstream/health".to_string(),
+            expect_status: 200,
+            interval_seconds: 10,
+        };
+        assert!(Precheck::new("exp", &config).is_none());
+    }
+}
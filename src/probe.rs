@@ -0,0 +1,139 @@
+//! Minimal HTTP health probe.
+//!
+//! Probes target plain `http://` endpoints inside the cluster (health checks,
+//! sidecars), so a single `GET` over a TCP connection is enough and avoids
+//! pulling in a full HTTP client.
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Timeout for a single probe, including connect.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Parsed `http://host[:port]/path` probe target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl ProbeUrl {
+    /// Parse a probe URL. Only `http://` URLs are supported.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("probe URL must start with http://, got '{}'", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(format!("probe URL '{}' has no host", url));
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("probe URL '{}' has invalid port", url))?,
+            ),
+            None => (authority, 80),
+        };
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Send a `GET` to the probe target and return the response status.
+pub async fn probe(url: &ProbeUrl) -> Result<u16, String> {
+    tokio::time::timeout(PROBE_TIMEOUT, fetch_status(url))
+        .await
+        .map_err(|_| "probe timed out".to_string())?
+}
+
+async fn fetch_status(url: &ProbeUrl) -> Result<u16, String> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port))
+        .await
+        .map_err(|e| format!("connect failed: {}", e))?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: zentinel-agent-chaos\r\nConnection: close\r\n\r\n",
+        url.path, url.host
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("write failed: {}", e))?;
+
+    // The status line fits comfortably in the first read
+    let mut buf = [0u8; 256];
+    let n = stream
+        .read(&mut buf)
+        .await
+        .map_err(|e| format!("read failed: {}", e))?;
+    parse_status_line(&buf[..n])
+}
+
+fn parse_status_line(response: &[u8]) -> Result<u16, String> {
+    let text = String::from_utf8_lossy(response);
+    let line = text.lines().next().unwrap_or_default();
+    line.split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("malformed status line '{}'", line))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Serve `status` to every connection, returning the server's URL.
+    pub(crate) async fn mock_server(
+        status: std::sync::Arc<std::sync::atomic::AtomicU16>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let code = status.load(std::sync::atomic::Ordering::SeqCst);
+                let response = format!("HTTP/1.1 {} Status\r\nContent-Length: 0\r\n\r\n", code);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/health", addr)
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let url = ProbeUrl::parse("http://upstream:8080/health").unwrap();
+        assert_eq!(url.host, "upstream");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/health");
+
+        let url = ProbeUrl::parse("http://upstream").unwrap();
+        assert_eq!(url.port, 80);
+        assert_eq!(url.path, "/");
+
+        assert!(ProbeUrl::parse("https://upstream/health").is_err());
+        assert!(ProbeUrl::parse("http://upstream:port/").is_err());
+    }
+
+    #[test]
+    fn test_parse_status_line() {
+        assert_eq!(parse_status_line(b"HTTP/1.1 503 Unavailable\r\n"), Ok(503));
+        assert!(parse_status_line(b"garbage").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_probe_mock_server() {
+        let status = std::sync::Arc::new(std::sync::atomic::AtomicU16::new(204));
+        let url = ProbeUrl::parse(&mock_server(status).await).unwrap();
+        assert_eq!(probe(&url).await, Ok(204));
+    }
+}
@@ -0,0 +1,378 @@
+//! Prometheus `/metrics` endpoint, enabled with `--metrics-address`.
+//!
+//! Renders the same report the agent sends the proxy, always as running
+//! totals, in the Prometheus text exposition format. Like the admin API it
+//! is served by the minimal listener in [`crate::http`].
+//!
+//! Histograms are reported as `_bucket` counters with an `le` label plus
+//! `_sum` and `_count`; they are typed `histogram` here so Prometheus keeps
+//! them together. Experiment ids may contain any character, so label values
+//! are escaped, and metric and label names are reduced to the characters
+//! Prometheus accepts.
+
+use crate::agent::ChaosControl;
+use crate::http::{self, read_request, Request, READ_TIMEOUT};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::info;
+use zentinel_agent_protocol::v2::MetricsReport;
+
+/// Content type of the text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Bind the metrics listener and serve it in a background task.
+pub async fn spawn_metrics_server(
+    address: SocketAddr,
+    control: ChaosControl,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("cannot bind metrics address {}", address))?;
+    info!(address = %listener.local_addr()?, "Prometheus metrics listening");
+    Ok(serve(listener, control))
+}
+
+/// Serve scrapes from a bound listener in a background task.
+pub fn serve(listener: TcpListener, control: ChaosControl) -> JoinHandle<()> {
+    http::serve(listener, "Metrics", move |stream, _| {
+        let control = control.clone();
+        async move { handle(stream, &control).await }
+    })
+}
+
+async fn handle(mut stream: TcpStream, control: &ChaosControl) -> Result<()> {
+    let (read, mut write) = stream.split();
+    let mut reader = BufReader::new(read);
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader))
+        .await
+        .context("timed out reading the request")??;
+    respond(&mut write, &request, control).await
+}
+
+/// Answer a scrape of `/metrics`, or `404` for any other path. Also used by
+/// the admin API when it shares its listener with the metrics endpoint.
+pub(crate) async fn respond<W: AsyncWrite + Unpin>(
+    write: &mut W,
+    request: &Request,
+    control: &ChaosControl,
+) -> Result<()> {
+    let (status, reason, body) = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => (200, "OK", render(&control.collect_metrics())),
+        (_, "/metrics") => (
+            405,
+            "Method Not Allowed",
+            "method not allowed\n".to_string(),
+        ),
+        _ => (404, "Not Found", "not found\n".to_string()),
+    };
+    let allow = if status == 405 { "Allow: GET\r\n" } else { "" };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        allow,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    );
+    write.write_all(response.as_bytes()).await?;
+    write.flush().await?;
+    Ok(())
+}
+
+/// One metric family: its type and its samples, in report order.
+struct Family {
+    kind: &'static str,
+    samples: Vec<String>,
+}
+
+/// Render a metrics report in the Prometheus text exposition format.
+///
+/// Families appear in the order the report first mentions them, each with
+/// a single `# TYPE` line followed by all of its samples.
+pub fn render(report: &MetricsReport) -> String {
+    let histograms: Vec<String> = report
+        .counters
+        .iter()
+        .filter(|c| c.labels.contains_key("le"))
+        .filter_map(|c| c.name.strip_suffix("_bucket"))
+        .map(sanitize_name)
+        .collect();
+
+    let mut order: Vec<String> = Vec::new();
+    let mut families: HashMap<String, Family> = HashMap::new();
+    let mut add = |name: &str, kind: &'static str, labels: &HashMap<String, String>, value| {
+        let name = sanitize_name(name);
+        let family = ["_bucket", "_sum", "_count"]
+            .iter()
+            .filter_map(|suffix| name.strip_suffix(suffix))
+            .find(|base| histograms.iter().any(|h| h == base))
+            .map(|base| (base.to_string(), "histogram"))
+            .unwrap_or_else(|| (name.clone(), kind));
+        let sample = format!("{}{} {}", name, render_labels(labels), value);
+        families
+            .entry(family.0.clone())
+            .or_insert_with(|| {
+                order.push(family.0);
+                Family {
+                    kind: family.1,
+                    samples: Vec::new(),
+                }
+            })
+            .samples
+            .push(sample);
+    };
+    for counter in &report.counters {
+        add(
+            &counter.name,
+            "counter",
+            &counter.labels,
+            counter.value.to_string(),
+        );
+    }
+    for gauge in &report.gauges {
+        add(
+            &gauge.name,
+            "gauge",
+            &gauge.labels,
+            format_value(gauge.value),
+        );
+    }
+
+    let mut out = String::new();
+    for name in order {
+        let family = &families[&name];
+        let _ = writeln!(out, "# TYPE {} {}", name, family.kind);
+        for sample in &family.samples {
+            out.push_str(sample);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Render labels as `{a="1",b="2"}`, sorted by name, or nothing when
+/// there are none.
+fn render_labels(labels: &HashMap<String, String>) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let mut pairs: Vec<(String, String)> = labels
+        .iter()
+        .map(|(name, value)| (sanitize_label_name(name), escape_label_value(value)))
+        .collect();
+    pairs.sort();
+    let pairs: Vec<String> = pairs
+        .into_iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, value))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Reduce a metric name to `[a-zA-Z_:][a-zA-Z0-9_:]*`, replacing anything
+/// else with `_`.
+pub fn sanitize_name(name: &str) -> String {
+    sanitize(name, true)
+}
+
+/// Reduce a label name to `[a-zA-Z_][a-zA-Z0-9_]*`, replacing anything
+/// else with `_`.
+pub fn sanitize_label_name(name: &str) -> String {
+    sanitize(name, false)
+}
+
+fn sanitize(name: &str, allow_colon: bool) -> String {
+    let mut out: String = name
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let valid = c.is_ascii_alphabetic()
+                || c == '_'
+                || (allow_colon && c == ':')
+                || (i > 0 && c.is_ascii_digit());
+            if valid {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if out.is_empty() {
+        out.push('_');
+    }
+    out
+}
+
+/// Escape a label value: backslash, double quote and newline are the only
+/// characters the format needs escaped.
+pub fn escape_label_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Format a gauge value, spelling out infinities and NaN the way the
+/// format expects.
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChaosAgent, Config};
+    use tokio::io::AsyncReadExt;
+    use zentinel_agent_protocol::v2::{CounterMetric, GaugeMetric};
+
+    fn counter(name: &str, labels: &[(&str, &str)], value: u64) -> CounterMetric {
+        let mut metric = CounterMetric::new(name, value);
+        for (k, v) in labels {
+            metric.labels.insert(k.to_string(), v.to_string());
+        }
+        metric
+    }
+
+    #[test]
+    fn test_render_groups_families() {
+        let mut report = MetricsReport::new("test", 10_000);
+        report
+            .counters
+            .push(counter("chaos_requests_total", &[], 7));
+        report.counters.push(counter(
+            "chaos_delay_bucket",
+            &[("le", "10"), ("x", "a")],
+            1,
+        ));
+        report.counters.push(counter(
+            "chaos_delay_bucket",
+            &[("x", "a"), ("le", "+Inf")],
+            2,
+        ));
+        report
+            .counters
+            .push(counter("chaos_delay_sum", &[("x", "a")], 30));
+        report
+            .counters
+            .push(counter("chaos_delay_count", &[("x", "a")], 2));
+        report
+            .counters
+            .push(counter("chaos_requests_total", &[("k", "v")], 3));
+        report
+            .gauges
+            .push(GaugeMetric::new("chaos_paused", f64::INFINITY));
+        report.gauges.push(GaugeMetric::new("chaos_intensity", 0.5));
+
+        assert_eq!(
+            render(&report),
+            "# TYPE chaos_requests_total counter\n\
+             chaos_requests_total 7\n\
+             chaos_requests_total{k=\"v\"} 3\n\
+             # TYPE chaos_delay histogram\n\
+             chaos_delay_bucket{le=\"10\",x=\"a\"} 1\n\
+             chaos_delay_bucket{le=\"+Inf\",x=\"a\"} 2\n\
+             chaos_delay_sum{x=\"a\"} 30\n\
+             chaos_delay_count{x=\"a\"} 2\n\
+             # TYPE chaos_paused gauge\n\
+             chaos_paused +Inf\n\
+             # TYPE chaos_intensity gauge\n\
+             chaos_intensity 0.5\n"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_and_escape() {
+        assert_eq!(sanitize_name("chaos_ok:total"), "chaos_ok:total");
+        assert_eq!(sanitize_name("9lives-metric.x"), "_lives_metric_x");
+        assert_eq!(sanitize_label_name("fault:type"), "fault_type");
+        assert_eq!(sanitize_label_name(""), "_");
+        assert_eq!(
+            escape_label_value("a\\b \"quoted\"\nnext ✓"),
+            "a\\\\b \\\"quoted\\\"\\nnext ✓"
+        );
+        assert_eq!(format_value(f64::NAN), "NaN");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_value(2.0), "2");
+    }
+
+    async fn scrape(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_scrape_running_agent() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+safety:
+  max_affected_percent: 100
+experiments:
+  - id: "api \"errors\"\nv2"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 100
+    fault: { type: error, status: 503 }
+"#,
+        )
+        .unwrap();
+        let agent = ChaosAgent::new(config);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve(listener, agent.control());
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+
+        let response = scrape(addr, "GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        assert!(head.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(body.contains("# TYPE chaos_requests_total counter\nchaos_requests_total 1\n"));
+        assert!(body.contains("\nchaos_faults_injected_total 1\n"));
+        assert!(
+            body.contains(
+                "chaos_experiment_injections_total{experiment=\"api \\\"errors\\\"\\nv2\"} 1\n"
+            ),
+            "{}",
+            body
+        );
+        assert!(body.contains("# TYPE chaos_agent_draining gauge\nchaos_agent_draining 0\n"));
+        assert!(body.contains("# TYPE chaos_schedule_active gauge\n"));
+        // The newline in the id does not split a sample across lines
+        assert!(body
+            .lines()
+            .all(|line| line.starts_with("chaos_") || line.starts_with("# ")));
+
+        // Scraping does not disturb the totals
+        let again = scrape(addr, "GET /metrics?x=1 HTTP/1.1\r\n\r\n").await;
+        assert!(again.contains("\nchaos_requests_total 1\n"));
+
+        let response = scrape(addr, "POST /metrics HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        let response = scrape(addr, "GET /status HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Counters shared by the agent, its control handles and the metrics
+//! endpoints.
+//!
+//! The request path updates a single [`Registry`]. The proxy's metrics
+//! report and the Prometheus endpoint are both rendered from it by
+//! [`ChaosControl::collect_metrics`](crate::ChaosControl::collect_metrics),
+//! so the two never disagree.
+
+use crate::agent::{
+    EXCLUSION_REASONS, MATCH_EVAL_BUCKETS_NANOS, RATE_WINDOW_SECS, REQUEST_SKIP_REASONS,
+};
+use crate::config::Fault;
+use crate::histogram::Histogram;
+use crate::window::SlidingWindow;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Agent-wide counters, kept for the lifetime of the agent.
+pub(crate) struct Registry {
+    /// Total requests processed.
+    pub(crate) requests_total: AtomicU64,
+    /// Total faults injected.
+    pub(crate) faults_injected: AtomicU64,
+    /// Faults injected per fault type.
+    pub(crate) fault_type_counts: HashMap<&'static str, AtomicU64>,
+    /// Faults a dry run would have injected, counted instead of
+    /// `faults_injected`.
+    pub(crate) dry_run_would_inject: AtomicU64,
+    /// Injections suppressed because the global budget was exhausted.
+    pub(crate) budget_suppressed: AtomicU64,
+    /// Faults shed because the concurrency limit was reached.
+    pub(crate) faults_shed: AtomicU64,
+    /// Fault applications that panicked and were contained.
+    pub(crate) fault_panics: AtomicU64,
+    /// Requests hit by an internal error, handled per `settings.fail_mode`.
+    pub(crate) internal_errors: AtomicU64,
+    /// Requests exempted by a valid bypass header.
+    pub(crate) bypassed: AtomicU64,
+    /// Faults injected because of the force header.
+    pub(crate) forced_injections: AtomicU64,
+    /// Faults injected by armed triggers.
+    pub(crate) triggered_injections: AtomicU64,
+    /// Requests shielded per exclusion reason.
+    pub(crate) exclusions: HashMap<&'static str, AtomicU64>,
+    /// Requests passed through per reason in [`REQUEST_SKIP_REASONS`].
+    pub(crate) skips: SkipCounters,
+    /// Configuration reloads rejected because loading or validation failed.
+    pub(crate) reload_errors: AtomicU64,
+    /// Admin API requests refused for a missing or wrong token.
+    pub(crate) admin_unauthorized: AtomicU64,
+    /// Recent requests, for the request rate gauge.
+    pub(crate) request_rate: SlidingWindow,
+    /// Recent injections, for the fault rate gauge.
+    pub(crate) fault_rate: SlidingWindow,
+    /// Time spent matching experiments, when `settings.profile_matching` is on.
+    pub(crate) match_eval: Histogram,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Self {
+            requests_total: AtomicU64::new(0),
+            faults_injected: AtomicU64::new(0),
+            fault_type_counts: Fault::TYPE_NAMES
+                .iter()
+                .map(|name| (*name, AtomicU64::new(0)))
+                .collect(),
+            dry_run_would_inject: AtomicU64::new(0),
+            budget_suppressed: AtomicU64::new(0),
+            faults_shed: AtomicU64::new(0),
+            fault_panics: AtomicU64::new(0),
+            internal_errors: AtomicU64::new(0),
+            bypassed: AtomicU64::new(0),
+            forced_injections: AtomicU64::new(0),
+            triggered_injections: AtomicU64::new(0),
+            exclusions: EXCLUSION_REASONS
+                .iter()
+                .map(|reason| (*reason, AtomicU64::new(0)))
+                .collect(),
+            skips: SkipCounters::new(REQUEST_SKIP_REASONS),
+            reload_errors: AtomicU64::new(0),
+            admin_unauthorized: AtomicU64::new(0),
+            request_rate: SlidingWindow::new(RATE_WINDOW_SECS),
+            fault_rate: SlidingWindow::new(RATE_WINDOW_SECS),
+            match_eval: Histogram::new(MATCH_EVAL_BUCKETS_NANOS),
+        }
+    }
+
+    /// Requests excluded for a reason in [`EXCLUSION_REASONS`].
+    pub(crate) fn exclusion_count(&self, reason: &str) -> u64 {
+        self.exclusions
+            .get(reason)
+            .map(|c| c.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+}
+
+/// Counters per skip reason.
+pub(crate) struct SkipCounters(HashMap<&'static str, AtomicU64>);
+
+impl SkipCounters {
+    pub(crate) fn new(reasons: &[&'static str]) -> Self {
+        Self(
+            reasons
+                .iter()
+                .map(|reason| (*reason, AtomicU64::new(0)))
+                .collect(),
+        )
+    }
+
+    /// Count one for `reason`; reasons not given at creation are ignored.
+    pub(crate) fn record(&self, reason: &str) {
+        if let Some(counter) = self.0.get(reason) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn get(&self, reason: &str) -> u64 {
+        self.0
+            .get(reason)
+            .map(|c| c.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_counters_ignore_unknown_reasons() {
+        let skips = SkipCounters::new(&["paused", "draining"]);
+        skips.record("paused");
+        skips.record("paused");
+        skips.record("meteor");
+        assert_eq!(skips.get("paused"), 2);
+        assert_eq!(skips.get("draining"), 0);
+        assert_eq!(skips.get("meteor"), 0);
+
+        let registry = Registry::new();
+        assert_eq!(registry.fault_type_counts.len(), Fault::TYPE_NAMES.len());
+        assert_eq!(registry.skips.get("excluded_path"), 0);
+    }
+}
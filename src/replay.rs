@@ -0,0 +1,346 @@
+//! Blast-radius estimates from real traffic, used by the `replay`
+//! subcommand.
+//!
+//! Every request in an access log is run through the simulator and counted
+//! per experiment: requests its targeting matches and requests it would
+//! have faulted, overall and per path prefix. Injections are either sampled
+//! like the agent does, or with `deterministic` the expected value.
+
+use crate::accesslog::LogParser;
+use crate::config::Config;
+use crate::simulate::{SimulatedRequest, Simulator};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::BufRead;
+
+/// Malformed lines kept as examples in a report.
+pub const MAX_MALFORMED_EXAMPLES: usize = 10;
+
+/// Replay settings.
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Count expected injections instead of sampling.
+    pub deterministic: bool,
+    /// Path segments per prefix in the breakdown, e.g. 1 for `/api`.
+    pub prefix_depth: usize,
+    /// Instant for schedules when a line has no timestamp.
+    pub now: DateTime<Utc>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            deterministic: false,
+            prefix_depth: 1,
+            now: Utc::now(),
+        }
+    }
+}
+
+/// Per-experiment counts over a replayed log.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    /// Lines read, including malformed ones.
+    pub lines: u64,
+    /// Requests parsed and replayed.
+    pub requests: u64,
+    /// Requests that would have received any fault.
+    pub faulted: f64,
+    /// Lines that could not be parsed.
+    pub malformed: u64,
+    /// The first malformed lines.
+    pub malformed_examples: Vec<MalformedLine>,
+    /// Enabled experiments, in evaluation order.
+    pub experiments: Vec<ExperimentReplay>,
+}
+
+/// A line that could not be parsed.
+#[derive(Debug, Clone, Serialize)]
+pub struct MalformedLine {
+    /// 1-based line number.
+    pub line: u64,
+    pub error: String,
+}
+
+/// Counts for one experiment.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentReplay {
+    pub id: String,
+    #[serde(flatten)]
+    pub counts: ReplayCounts,
+    /// Counts by path prefix.
+    pub prefixes: BTreeMap<String, ReplayCounts>,
+}
+
+/// Matched and injected requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ReplayCounts {
+    /// Requests the targeting matches.
+    pub matched: u64,
+    /// Requests that would have been faulted.
+    pub injected: f64,
+}
+
+impl ReplayCounts {
+    fn add(&mut self, injected: f64) {
+        self.matched += 1;
+        self.injected += injected;
+    }
+}
+
+/// Replay an access log against a configuration.
+pub fn replay(
+    config: &Config,
+    input: impl BufRead,
+    parser: &LogParser,
+    options: &ReplayOptions,
+) -> Result<ReplayReport> {
+    let simulator = Simulator::new(config);
+    let mut report = ReplayReport {
+        lines: 0,
+        requests: 0,
+        faulted: 0.0,
+        malformed: 0,
+        malformed_examples: Vec::new(),
+        experiments: config
+            .experiments
+            .iter()
+            .filter(|exp| exp.enabled)
+            .map(|exp| ExperimentReplay {
+                id: exp.id.clone(),
+                counts: ReplayCounts::default(),
+                prefixes: BTreeMap::new(),
+            })
+            .collect(),
+    };
+    let samples = if options.deterministic { 0 } else { 1 };
+
+    for line in input.lines() {
+        let line = line?;
+        report.lines += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = match parser.parse(&line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.malformed += 1;
+                if report.malformed_examples.len() < MAX_MALFORMED_EXAMPLES {
+                    report.malformed_examples.push(MalformedLine {
+                        line: report.lines,
+                        error: e.to_string(),
+                    });
+                }
+                continue;
+            }
+        };
+        report.requests += 1;
+
+        let prefix = path_prefix(&entry.path, options.prefix_depth);
+        let request = SimulatedRequest {
+            at: entry.time.unwrap_or(options.now),
+            method: entry.method,
+            path: entry.path,
+            headers: entry.headers,
+            run_time_left: None,
+        };
+        let simulation = simulator.simulate(&request, samples);
+        for exp in &simulation.experiments {
+            let injected = if options.deterministic {
+                exp.expected_rate
+            } else {
+                exp.injected as f64
+            };
+            report.faulted += injected;
+            if let Some(counts) = report.experiments.iter_mut().find(|e| e.id == exp.id) {
+                counts.counts.add(injected);
+                counts
+                    .prefixes
+                    .entry(prefix.clone())
+                    .or_default()
+                    .add(injected);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// First `depth` segments of a path, without the query string.
+fn path_prefix(path: &str, depth: usize) -> String {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .take(depth)
+        .collect();
+    format!("/{}", segments.join("/"))
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} lines, {} requests, {} malformed",
+            self.lines, self.requests, self.malformed
+        )?;
+        for malformed in &self.malformed_examples {
+            writeln!(f, "  line {}: {}", malformed.line, malformed.error)?;
+        }
+        let share = if self.requests == 0 {
+            0.0
+        } else {
+            self.faulted * 100.0 / self.requests as f64
+        };
+        writeln!(
+            f,
+            "{} requests would have been faulted ({:.1}%)",
+            count(self.faulted),
+            share
+        )?;
+        writeln!(f)?;
+
+        let mut rows = vec![(
+            "EXPERIMENT".to_string(),
+            "MATCHED".to_string(),
+            "INJECTED".to_string(),
+        )];
+        for exp in &self.experiments {
+            rows.push((
+                exp.id.clone(),
+                exp.counts.matched.to_string(),
+                count(exp.counts.injected),
+            ));
+            for (prefix, counts) in &exp.prefixes {
+                rows.push((
+                    format!("  {}", prefix),
+                    counts.matched.to_string(),
+                    count(counts.injected),
+                ));
+            }
+        }
+        let width = |column: fn(&(String, String, String)) -> &String| {
+            rows.iter().map(|row| column(row).len()).max().unwrap_or(0)
+        };
+        let (name, matched, injected) = (width(|r| &r.0), width(|r| &r.1), width(|r| &r.2));
+        for row in &rows {
+            writeln!(
+                f,
+                "{:<name$}  {:>matched$}  {:>injected$}",
+                row.0, row.1, row.2
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Render a count, with two decimals for expected values.
+fn count(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accesslog::{JsonFields, LogFormat};
+
+    const LOG: &str = r#"10.0.0.1 - - [03/Jan/2024:12:00:00 +0000] "GET /api/orders HTTP/1.1" 200 512
+10.0.0.2 - - [03/Jan/2024:12:00:01 +0000] "POST /api/payments/charge HTTP/1.1" 200 64
+not a log line
+
+10.0.0.3 - - [03/Jan/2024:12:00:02 +0000] "GET /health HTTP/1.1" 200 2
+10.0.0.4 - - [03/Jan/2024:12:00:03 +0000] "GET /static/app.js HTTP/1.1" 200 9000
+"#;
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+safety:
+  excluded_paths: ["/health"]
+experiments:
+  - id: "api-errors"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 20
+    fault: { type: error, status: 500 }
+  - id: "all-latency"
+    targeting:
+      percentage: 50
+    fault: { type: latency, fixed_ms: 100 }
+"#,
+        )
+        .unwrap()
+    }
+
+    fn run(options: &ReplayOptions) -> ReplayReport {
+        let parser = LogParser::new(LogFormat::Common, JsonFields::default());
+        replay(&config(), LOG.as_bytes(), &parser, options).unwrap()
+    }
+
+    #[test]
+    fn test_deterministic_replay() {
+        let report = run(&ReplayOptions {
+            deterministic: true,
+            ..ReplayOptions::default()
+        });
+        assert_eq!(report.lines, 6);
+        assert_eq!(report.requests, 4);
+        assert_eq!(report.malformed, 1);
+        assert_eq!(report.malformed_examples[0].line, 3);
+
+        let api = &report.experiments[0];
+        assert_eq!(api.counts.matched, 2);
+        assert!((api.counts.injected - 0.4).abs() < 1e-9);
+        assert_eq!(api.prefixes.keys().collect::<Vec<_>>(), vec!["/api"]);
+
+        // The excluded health check matches but is never faulted; API
+        // requests reach this experiment only when the first one passes
+        let all = &report.experiments[1];
+        assert_eq!(all.counts.matched, 4);
+        assert!((all.counts.injected - (0.8 * 0.5 * 2.0 + 0.5)).abs() < 1e-9);
+        assert_eq!(
+            all.prefixes["/health"],
+            ReplayCounts {
+                matched: 1,
+                injected: 0.0
+            }
+        );
+        assert_eq!(all.prefixes["/static"].matched, 1);
+
+        let text = report.to_string();
+        assert!(
+            text.contains("6 lines, 4 requests, 1 malformed"),
+            "{}",
+            text
+        );
+        assert!(text.contains("line 3: not a common log line"), "{}", text);
+        assert!(text.contains("api-errors         2      0.40"), "{}", text);
+    }
+
+    #[test]
+    fn test_sampled_replay_counts_whole_requests() {
+        let report = run(&ReplayOptions::default());
+        let faulted: f64 = report.experiments.iter().map(|e| e.counts.injected).sum();
+        assert_eq!(faulted, report.faulted);
+        assert_eq!(report.faulted.fract(), 0.0);
+        assert!(report.faulted <= 3.0);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["experiments"][0]["id"], "api-errors");
+        assert_eq!(json["experiments"][0]["matched"], 2);
+    }
+
+    #[test]
+    fn test_path_prefix() {
+        assert_eq!(path_prefix("/api/v1/users?id=1", 1), "/api");
+        assert_eq!(path_prefix("/api/v1/users?id=1", 2), "/api/v1");
+        assert_eq!(path_prefix("/", 1), "/");
+        assert_eq!(path_prefix("/?q", 2), "/");
+    }
+}
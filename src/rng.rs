@@ -0,0 +1,201 @@
+//! Fast random number source for the request hot path.
+//!
+//! `rand::thread_rng()` is a reseeding ChaCha generator; sampling decisions
+//! only need uniform draws, so each worker thread keeps its own `SmallRng`
+//! seeded once from the OS. Access is lock-free and never touches the OS
+//! after the first use on a thread.
+//!
+//! With `settings.seed`, each request instead runs with its own generator
+//! derived from the seed and the request's sequence number, so the same
+//! seed and request sequence make the same decisions on every run. The
+//! number is assigned in the request phase and remembered by correlation id,
+//! so a request's body and response phases draw from the same request
+//! however many other requests arrive in between.
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Requests whose sequence number is remembered for their later phases;
+/// the oldest is forgotten first.
+pub const MAX_PENDING_REQUESTS: usize = 10_000;
+
+thread_local! {
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_entropy());
+}
+
+tokio::task_local! {
+    static REQUEST_RNG: RefCell<SmallRng>;
+}
+
+/// Run a closure with the current request's seeded generator, or this
+/// thread's generator outside a seeded request.
+pub fn with_rng<T>(f: impl FnOnce(&mut SmallRng) -> T) -> T {
+    let mut f = Some(f);
+    let seeded = REQUEST_RNG.try_with(|rng| {
+        let f = f.take().expect("closure runs once");
+        f(&mut rng.borrow_mut())
+    });
+    match seeded {
+        Ok(value) => value,
+        Err(_) => {
+            let f = f.take().expect("closure runs once");
+            RNG.with(|rng| f(&mut rng.borrow_mut()))
+        }
+    }
+}
+
+/// Drive a future with `rng` as the generator behind [`with_rng`], or
+/// with the thread generators when `rng` is `None`.
+pub async fn with_request_rng<F: Future>(rng: Option<SmallRng>, fut: F) -> F::Output {
+    match rng {
+        Some(rng) => REQUEST_RNG.scope(RefCell::new(rng), fut).await,
+        None => fut.await,
+    }
+}
+
+/// Per-request generators derived from a fixed seed.
+#[derive(Debug)]
+pub struct SeededRng {
+    seed: u64,
+    sequence: AtomicU64,
+    /// Sequence numbers of requests with later phases to come.
+    pending: Mutex<PendingRequests>,
+}
+
+/// Sequence numbers by correlation id, with ids in arrival order for
+/// eviction.
+#[derive(Debug, Default)]
+struct PendingRequests {
+    by_id: HashMap<String, u64>,
+    order: VecDeque<String>,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            sequence: AtomicU64::new(0),
+            pending: Mutex::new(PendingRequests::default()),
+        }
+    }
+
+    /// Assign the next sequence number to a request in its request phase,
+    /// remembering it under `correlation_id` when `remember` is set because
+    /// later phases may draw.
+    pub fn start_request(&self, correlation_id: Option<&str>, remember: bool) -> u64 {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let Some(id) = correlation_id.filter(|_| remember) else {
+            return sequence;
+        };
+        let mut pending = self.lock_pending();
+        if pending.by_id.insert(id.to_string(), sequence).is_none() {
+            pending.order.push_back(id.to_string());
+        }
+        while pending.order.len() > MAX_PENDING_REQUESTS {
+            if let Some(oldest) = pending.order.pop_front() {
+                pending.by_id.remove(&oldest);
+            }
+        }
+        sequence
+    }
+
+    /// Sequence number a request was given in its request phase, forgotten
+    /// once `finished`, or the next one for a request never remembered.
+    pub fn request_sequence(&self, correlation_id: Option<&str>, finished: bool) -> u64 {
+        let remembered = correlation_id.and_then(|id| {
+            let mut pending = self.lock_pending();
+            if finished {
+                pending.by_id.remove(id)
+            } else {
+                pending.by_id.get(id).copied()
+            }
+        });
+        remembered.unwrap_or_else(|| self.sequence.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Generator for phase `phase` of the request with sequence number
+    /// `sequence`. Each phase draws its own stream.
+    pub fn phase_rng(&self, sequence: u64, phase: u64) -> SmallRng {
+        // Spread consecutive sequence numbers across the seed space
+        SmallRng::seed_from_u64(
+            self.seed
+                ^ sequence.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ phase.wrapping_mul(0xBF58_476D_1CE4_E5B9),
+        )
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, PendingRequests> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_with_rng_is_uniform() {
+        let trials = 100_000;
+        let mut buckets = [0u32; 10];
+        for _ in 0..trials {
+            let value = with_rng(|rng| rng.gen_range(0..10));
+            buckets[value] += 1;
+        }
+
+        // Each bucket should get roughly 10% of draws
+        for count in buckets {
+            let share = count as f64 / trials as f64;
+            assert!((0.09..=0.11).contains(&share), "share was {}", share);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seeded_requests_repeat() {
+        async fn draws(seeded: &SeededRng) -> Vec<u32> {
+            let mut draws = Vec::new();
+            for _ in 0..3 {
+                let sequence = seeded.start_request(None, false);
+                let rng = Some(seeded.phase_rng(sequence, 0));
+                draws.push(with_request_rng(rng, async { with_rng(|rng| rng.gen()) }).await);
+            }
+            draws
+        }
+
+        let first = draws(&SeededRng::new(7)).await;
+        assert_eq!(first, draws(&SeededRng::new(7)).await);
+        assert_ne!(first, draws(&SeededRng::new(8)).await);
+        assert_ne!(first[0], first[1]);
+    }
+
+    #[test]
+    fn test_later_phases_reuse_the_request_sequence() {
+        let seeded = SeededRng::new(7);
+        assert_eq!(seeded.start_request(Some("a"), true), 0);
+        assert_eq!(seeded.start_request(Some("b"), true), 1);
+        assert_eq!(seeded.start_request(Some("c"), false), 2);
+
+        // Phases of "a" keep its number whatever arrived since
+        assert_eq!(seeded.request_sequence(Some("a"), false), 0);
+        assert_eq!(seeded.request_sequence(Some("a"), true), 0);
+        // Finished and unknown requests get fresh numbers
+        assert_eq!(seeded.request_sequence(Some("a"), false), 3);
+        assert_eq!(seeded.request_sequence(Some("c"), false), 4);
+        assert_eq!(seeded.request_sequence(None, false), 5);
+
+        // The oldest pending request is forgotten first
+        for n in 0..MAX_PENDING_REQUESTS {
+            seeded.start_request(Some(&n.to_string()), true);
+        }
+        assert_ne!(seeded.request_sequence(Some("b"), false), 1);
+        assert_eq!(
+            seeded.request_sequence(Some(&(MAX_PENDING_REQUESTS - 1).to_string()), false),
+            6 + MAX_PENDING_REQUESTS as u64 - 1
+        );
+    }
+}
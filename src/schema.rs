@@ -0,0 +1,390 @@
+//! JSON Schema for the configuration format, printed by `--print-schema`.
+//!
+//! The schema is derived from the configuration types. Fields with custom
+//! deserializers (durations, schedule times and days, timezones, status
+//! pools) get hand-written schemas here, and field aliases are added, so
+//! the schema accepts exactly what the loader accepts.
+
+use crate::config::Config;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{
+    ArrayValidation, InstanceType, Metadata, RootSchema, Schema, SchemaObject, StringValidation,
+    SubschemaValidation,
+};
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
+
+/// Duration strings accepted by [`parse_duration_ms`](crate::config::parse_duration_ms).
+const DURATION_PATTERN: &str = r"^\s*([0-9]+\.?[0-9]*|\.[0-9]+)\s*(ms|s|m|h)?\s*$";
+
+/// Field aliases accepted by the loader, as (field, alias).
+pub(crate) const ALIASES: &[(&str, &str)] = &[
+    ("fixed_ms", "fixed"),
+    ("min_ms", "min"),
+    ("max_ms", "max"),
+    ("mean_ms", "mean"),
+    ("stddev_ms", "stddev"),
+    ("base_ms", "base"),
+    ("duration_ms", "duration"),
+    ("max_latency_ms", "max_latency"),
+    ("max_concurrent_faults", "max_concurrent_delays"),
+    ("drain_auto_resume_after_ms", "drain_auto_resume_after"),
+];
+
+/// Times accepted by schedule windows.
+const TIME_PATTERN: &str = r"^([01]?[0-9]|2[0-3]):[0-5][0-9]$";
+
+/// Weekday names accepted by schedule windows, matched case-insensitively.
+const WEEKDAYS: &[&str] = &[
+    "mon",
+    "monday",
+    "tue",
+    "tuesday",
+    "wed",
+    "wednesday",
+    "thu",
+    "thursday",
+    "fri",
+    "friday",
+    "sat",
+    "saturday",
+    "sun",
+    "sunday",
+];
+
+/// Generate the JSON Schema for configuration files.
+pub fn config_schema() -> RootSchema {
+    let mut schema = schemars::schema_for!(Config);
+
+    // Targeting and fault may come from `targeting_ref` and `extends`
+    // instead, which resolve before the typed parse
+    if let Some(Schema::Object(experiment)) = schema.definitions.get_mut("Experiment") {
+        if let Some(object) = experiment.object.as_mut() {
+            object
+                .required
+                .retain(|field| field != "targeting" && field != "fault");
+        }
+        experiment.subschemas().all_of = Some(vec![
+            any_required(&["targeting", "targeting_ref", "extends"]),
+            any_required(&["fault", "extends"]),
+        ]);
+    }
+    add_aliases(&mut schema.schema);
+    for definition in schema.definitions.values_mut() {
+        if let Schema::Object(object) = definition {
+            add_aliases(object);
+        }
+    }
+    schema
+}
+
+/// Accept the [`ALIASES`] of the fields of `schema` and of its
+/// alternatives, such as the fault variants, under the same schema. A
+/// required field may be given under either name.
+fn add_aliases(schema: &mut SchemaObject) {
+    if let Some(object) = schema.object.as_mut() {
+        let mut required = Vec::new();
+        for (field, alias) in ALIASES {
+            let Some(property) = object.properties.get(*field).cloned() else {
+                continue;
+            };
+            object.properties.insert(alias.to_string(), property);
+            if object.required.remove(*field) {
+                required.push(any_required(&[field, alias]));
+            }
+        }
+        if !required.is_empty() {
+            schema
+                .subschemas()
+                .all_of
+                .get_or_insert_with(Vec::new)
+                .extend(required);
+        }
+    }
+    if let Some(subschemas) = schema.subschemas.as_mut() {
+        let alternatives = [
+            &mut subschemas.all_of,
+            &mut subschemas.any_of,
+            &mut subschemas.one_of,
+        ];
+        for schemas in alternatives.into_iter().flatten() {
+            for subschema in schemas {
+                if let Schema::Object(object) = subschema {
+                    add_aliases(object);
+                }
+            }
+        }
+    }
+}
+
+/// Schema requiring at least one of `fields`.
+fn any_required(fields: &[&str]) -> Schema {
+    let any_of = fields
+        .iter()
+        .map(|field| {
+            let mut schema = SchemaObject::default();
+            schema.object().required.insert(field.to_string());
+            schema.into()
+        })
+        .collect();
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(any_of),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+fn string_matching(pattern: String) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(pattern),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+/// Regex matching `word` in any letter case.
+fn case_insensitive(word: &str) -> String {
+    word.chars()
+        .map(|c| format!("[{}{}]", c, c.to_ascii_uppercase()))
+        .collect()
+}
+
+/// Milliseconds as an integer or a duration string such as `2.5s`.
+pub(crate) fn duration_ms(gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(vec![
+                gen.subschema_for::<u64>(),
+                string_matching(DURATION_PATTERN.to_string()).into(),
+            ]),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Optional milliseconds, see [`duration_ms`].
+pub(crate) fn optional_duration_ms(gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(vec![
+                duration_ms(gen),
+                SchemaObject {
+                    instance_type: Some(InstanceType::Null.into()),
+                    ..Default::default()
+                }
+                .into(),
+            ]),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Time of day as `HH:MM`.
+pub(crate) fn time(_: &mut SchemaGenerator) -> Schema {
+    string_matching(TIME_PATTERN.to_string()).into()
+}
+
+/// List of weekday names such as `mon` or `Friday`.
+pub(crate) fn weekdays(_: &mut SchemaGenerator) -> Schema {
+    let names: Vec<String> = WEEKDAYS.iter().map(|day| case_insensitive(day)).collect();
+    let day = string_matching(format!("^({})$", names.join("|")));
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(Schema::Object(day).into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// IANA timezone name, shared through the `Timezone` definition.
+pub(crate) fn timezone(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<Timezone>()
+}
+
+/// Weighted statuses as a `{status: weight}` map or `[status, weight]` pairs.
+pub(crate) fn status_pool(gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(vec![
+                gen.subschema_for::<BTreeMap<u16, u32>>(),
+                gen.subschema_for::<Vec<(u16, u32)>>(),
+            ]),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Weighted path matcher: one of the plain matcher keys plus `weight`.
+pub(crate) fn weighted_path(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        ..Default::default()
+    };
+    let object = schema.object();
+    for key in ["exact", "prefix", "regex", "glob"] {
+        object
+            .properties
+            .insert(key.to_string(), gen.subschema_for::<String>());
+    }
+    let mut weight = gen.subschema_for::<u32>().into_object();
+    weight.number().minimum = Some(1.0);
+    object
+        .properties
+        .insert("weight".to_string(), weight.into());
+    object.required.insert("weight".to_string());
+    schema.subschemas().one_of = Some(
+        ["exact", "prefix", "regex", "glob"]
+            .iter()
+            .map(|key| any_required(&[key]))
+            .collect(),
+    );
+    schema.into()
+}
+
+/// Marker for the timezone definition.
+struct Timezone;
+
+impl JsonSchema for Timezone {
+    fn schema_name() -> String {
+        "Timezone".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some("IANA timezone name, e.g. \"America/New_York\".".to_string()),
+                ..Default::default()
+            })),
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(
+                chrono_tz::TZ_VARIANTS
+                    .iter()
+                    .map(|tz| tz.name().into())
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::example::example_yaml;
+    use jsonschema::JSONSchema;
+
+    fn compile() -> JSONSchema {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+        JSONSchema::compile(&schema).unwrap()
+    }
+
+    fn errors(schema: &JSONSchema, yaml: &str) -> Vec<String> {
+        let instance: serde_json::Value = serde_yaml::from_str(yaml).unwrap();
+        let problems = match schema.validate(&instance) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.map(|e| e.to_string()).collect(),
+        };
+        problems
+    }
+
+    #[test]
+    fn test_schema_shape() {
+        let printed = serde_json::to_string_pretty(&config_schema()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&printed).unwrap();
+        assert_eq!(schema["properties"]["experiments"]["type"], "array");
+
+        // Untagged path matchers are alternatives, faults are told apart
+        // by their `type` tag
+        let definitions = &schema["definitions"];
+        assert!(definitions["PathMatcher"]["anyOf"].is_array());
+        let tags: Vec<&serde_json::Value> = definitions["Fault"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["properties"]["type"]["enum"][0])
+            .collect();
+        assert_eq!(tags, crate::config::Fault::TYPE_NAMES);
+    }
+
+    #[test]
+    fn test_example_config_matches_schema() {
+        let schema = compile();
+        let problems = errors(&schema, &example_yaml().unwrap());
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn test_custom_formats() {
+        let schema = compile();
+        let yaml = r#"
+settings:
+  drain_auto_resume_after: 5s
+safety:
+  max_latency: 5s
+  schedule:
+    - days: [Mon, friday]
+      start: "9:00"
+      end: "17:30"
+      timezone: Europe/Berlin
+experiments:
+  - id: "jitter"
+    targeting_ref: api
+    targeting:
+      paths:
+        - { prefix: "/api/a", weight: 3 }
+        - { glob: "/api/*/b", weight: 1 }
+    fault: { type: jitter, mean_ms: 100ms, stddev_ms: 30, max_ms: 1.5s }
+  - id: "pool"
+    extends: base
+    fault:
+      type: error
+      status: 500
+      status_pool: { 500: 3, 503: 1 }
+  - id: "aliases"
+    targeting: {}
+    fault: { type: latency, fixed: 250ms, min: 100, max: 1s }
+  - id: "jitter-aliases"
+    targeting: {}
+    fault: { type: jitter, mean: 100ms, stddev: 30, max: 1.5s }
+  - id: "escalating-aliases"
+    targeting: {}
+    fault: { type: escalating_latency, base: 100ms, factor: 2, max: 1s, reset_after_seconds: 10 }
+  - id: "timeout-alias"
+    targeting: {}
+    fault: { type: timeout, duration: 30s }
+"#;
+        let problems = errors(&schema, yaml);
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        for invalid in [
+            "safety: { schedule: [{ days: [someday], start: \"09:00\", end: \"17:00\" }] }",
+            "safety: { schedule: [{ days: [mon], start: \"9am\", end: \"17:00\" }] }",
+            "safety: { timezone: Mars/Olympus }",
+            "experiments: [{ id: x, targeting: {}, fault: { type: latency, fixed_ms: soon } }]",
+            "experiments: [{ id: x, targeting: {}, fault: { type: latency, fixed: soon } }]",
+            "experiments: [{ id: x, targeting: {}, fault: { type: timeout } }]",
+            "experiments: [{ id: x, targeting: {}, fault: { type: jitter, mean: 1s, stddev: 1 } }]",
+            "safety: { max_latency: forever }",
+            "experiments: [{ id: x, targeting: {}, fault: { type: explode } }]",
+            "experiments: [{ id: x, fault: { type: reset } }]",
+        ] {
+            assert!(!errors(&schema, invalid).is_empty(), "{}", invalid);
+        }
+    }
+}
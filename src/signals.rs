@@ -0,0 +1,54 @@
+//! Unix signal handling for runtime control.
+//!
+//! - `SIGUSR1` toggles suspension, a temporary kill switch
+//! - `SIGUSR2` resets all per-experiment injection counters
+//! - `SIGHUP` reloads the configuration file
+
+use crate::agent::ChaosControl;
+use crate::config::Config;
+use anyhow::Result;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Spawn a task that applies control signals to the agent.
+///
+/// `load` is called on `SIGHUP` to read the new configuration; if it fails
+/// the current configuration stays active.
+pub fn spawn_signal_handler<F>(control: ChaosControl, load: F) -> Result<JoinHandle<()>>
+where
+    F: Fn() -> Result<Config> + Send + 'static,
+{
+    let mut usr1 = signal(SignalKind::user_defined1())?;
+    let mut usr2 = signal(SignalKind::user_defined2())?;
+    let mut hup = signal(SignalKind::hangup())?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(()) = usr1.recv() => {
+                    if control.toggle_suspended() {
+                        warn!(signal = "SIGUSR1", "Chaos injections suspended");
+                    } else {
+                        info!(signal = "SIGUSR1", "Chaos injections resumed");
+                    }
+                }
+                Some(()) = usr2.recv() => {
+                    control.reset_injection_counts();
+                    info!(signal = "SIGUSR2", "Experiment injection counters reset");
+                }
+                Some(()) = hup.recv() => {
+                    match control.reload_with(&load) {
+                        Ok(()) => info!(signal = "SIGHUP", "Configuration reloaded"),
+                        Err(e) => error!(
+                            signal = "SIGHUP",
+                            error = %e,
+                            "Configuration reload failed, keeping current configuration"
+                        ),
+                    }
+                }
+                else => break,
+            }
+        }
+    }))
+}
@@ -0,0 +1,403 @@
+//! Dry simulation of a single request, used by the `simulate` subcommand.
+//!
+//! Runs the agent's own checks: the global switch, kill switch file,
+//! `settings.once`, exclusions, bypass tokens, targeting, schedules, the
+//! force header and the scaled percentage roll, against a configuration
+//! compiled without probes. Nothing is counted, no fault is applied and no
+//! traffic is sent.
+
+use crate::agent::{AgentState, Gates, Plan};
+use crate::config::{Config, Fault};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Request to simulate.
+#[derive(Debug, Clone)]
+pub struct SimulatedRequest {
+    pub method: String,
+    pub path: String,
+    /// Headers, by any letter case.
+    pub headers: HashMap<String, String>,
+    /// Instant used for schedule windows and date rules.
+    pub at: DateTime<Utc>,
+    /// Time left before a time-limited run ends, for `ramp_down_seconds`;
+    /// `None` outside a time-limited run.
+    pub run_time_left: Option<Duration>,
+}
+
+/// How the configuration treats a simulated request.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// Why the whole request passes through untouched, if it does.
+    pub spared: Option<String>,
+    /// Enabled experiments whose targeting matches, in evaluation order.
+    pub experiments: Vec<SimulatedExperiment>,
+    /// Number of sampled requests.
+    pub samples: u64,
+    /// Sampled requests that received at least one fault.
+    pub faulted: u64,
+}
+
+/// A matching experiment.
+#[derive(Debug, Clone)]
+pub struct SimulatedExperiment {
+    pub id: String,
+    /// Percentage after the `safety.max_affected_percent` clamp.
+    pub percentage: u8,
+    pub fault: Fault,
+    /// Why the experiment is skipped for this request, if it is.
+    pub suppressed: Option<String>,
+    /// Probability (0.0-1.0) that this experiment faults the request,
+    /// after earlier experiments for the same phase have had their turn.
+    pub expected_rate: f64,
+    /// Sampled requests that received this experiment's fault.
+    pub injected: u64,
+}
+
+impl Simulation {
+    /// Faults injected into sampled requests, across all phases.
+    pub fn injected(&self) -> u64 {
+        self.experiments.iter().map(|e| e.injected).sum()
+    }
+}
+
+/// Simulate a request against a configuration, sampling the percentage
+/// roll `samples` times.
+pub fn simulate(config: &Config, request: &SimulatedRequest, samples: u64) -> Simulation {
+    Simulator::new(config).simulate(request, samples)
+}
+
+/// A configuration compiled once for simulating many requests.
+///
+/// Budgets, concurrency limits, cooldowns, client caps, prechecks, armed
+/// triggers and the runtime pause depend on a running agent and are not
+/// simulated.
+pub struct Simulator {
+    state: AgentState,
+}
+
+impl Simulator {
+    /// Compile a configuration for simulation.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            state: AgentState::offline(config.clone()),
+        }
+    }
+
+    /// Simulate one request, sampling the percentage roll `samples` times.
+    pub fn simulate(&self, request: &SimulatedRequest, samples: u64) -> Simulation {
+        let state = &self.state;
+        let config = &state.config;
+        let headers: HashMap<String, String> = request
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.clone()))
+            .collect();
+        let (method, path) = (request.method.as_str(), request.path.as_str());
+
+        // An offline agent is never paused or draining
+        let plan = state.plan_at(
+            Gates::default(),
+            method,
+            path,
+            &headers,
+            request.at,
+            |_, _| {},
+        );
+        let (spared, forced) = match plan {
+            Plan::Spared(spared) => (Some(spared.to_string()), None),
+            Plan::Matching { forced, .. } => (None, forced),
+        };
+
+        // In each phase the first experiment that wins its roll is applied,
+        // as in the agent; a forced experiment skips the roll
+        let ceiling = config.safety.max_affected_percent;
+        let mut experiments: Vec<SimulatedExperiment> = Vec::new();
+        let mut candidates = Vec::new();
+        let mut remaining = [1.0; 3];
+        for exp in &state.compiled_experiments {
+            if !exp.is_enabled() || !exp.targeting.matches(method, path, &headers) {
+                continue;
+            }
+            let suppressed = if !state.is_experiment_scheduled_at(exp, request.at) {
+                Some("outside its schedule".to_string())
+            } else if forced.is_some_and(|forced| forced.id != exp.id) {
+                Some("the force header names another experiment".to_string())
+            } else {
+                spared.clone()
+            };
+            let scaled = state.roll_intensity(exp, path, request.run_time_left);
+            let mut expected_rate = 0.0;
+            if suppressed.is_none() {
+                let phase = phase_index(&exp.experiment.fault);
+                let rate = if forced.is_some() {
+                    1.0
+                } else {
+                    exp.targeting.scaled_rate(scaled, ceiling)
+                };
+                expected_rate = remaining[phase] * rate;
+                remaining[phase] *= 1.0 - rate;
+                candidates.push((experiments.len(), exp, phase, scaled));
+            }
+            experiments.push(SimulatedExperiment {
+                id: exp.id.clone(),
+                percentage: exp.targeting.percentage(),
+                fault: exp.experiment.fault.clone(),
+                suppressed,
+                expected_rate,
+                injected: 0,
+            });
+        }
+
+        let mut faulted = 0;
+        for _ in 0..samples {
+            let mut applied = [false; 3];
+            for (index, exp, phase, scaled) in &candidates {
+                if applied[*phase] {
+                    continue;
+                }
+                if forced.is_some() || exp.targeting.should_apply_scaled(*scaled, ceiling) {
+                    applied[*phase] = true;
+                    experiments[*index].injected += 1;
+                }
+            }
+            if applied.contains(&true) {
+                faulted += 1;
+            }
+        }
+
+        Simulation {
+            spared,
+            experiments,
+            samples,
+            faulted,
+        }
+    }
+}
+
+/// Index of the phase a fault is applied in: request headers, request
+/// body or upstream response.
+fn phase_index(fault: &Fault) -> usize {
+    if fault.is_body_phase() {
+        1
+    } else if fault.is_response_phase() {
+        2
+    } else {
+        0
+    }
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(reason) = &self.spared {
+            writeln!(f, "request passes through: {}", reason)?;
+        }
+        if self.experiments.is_empty() {
+            return writeln!(f, "no experiment matches");
+        }
+        for exp in &self.experiments {
+            writeln!(f, "experiment \"{}\"", exp.id)?;
+            writeln!(f, "  percentage: {}%", exp.percentage)?;
+            writeln!(f, "  fault: {}", exp.fault)?;
+            match &exp.suppressed {
+                Some(reason) => writeln!(f, "  suppressed: {}", reason)?,
+                None => {
+                    writeln!(
+                        f,
+                        "  expected: {:.1}% of requests",
+                        exp.expected_rate * 100.0
+                    )?;
+                    if self.samples > 0 {
+                        writeln!(
+                            f,
+                            "  injected: {}/{} ({:.1}%)",
+                            exp.injected,
+                            self.samples,
+                            rate(exp.injected, self.samples)
+                        )?;
+                    }
+                }
+            }
+        }
+        if self.samples > 0 {
+            writeln!(
+                f,
+                "total: {}/{} requests faulted ({:.1}%)",
+                self.faulted,
+                self.samples,
+                rate(self.faulted, self.samples)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn rate(count: u64, samples: u64) -> f64 {
+    count as f64 * 100.0 / samples as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+safety:
+  max_affected_percent: 100
+  excluded_paths: ["/health"]
+experiments:
+  - id: "payments-errors"
+    targeting:
+      paths: [{ prefix: "/api/payments" }]
+      headers: { x-user-id: "42" }
+      percentage: 100
+    fault: { type: error, status: 503 }
+  - id: "api-latency"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 50
+    fault: { type: latency, fixed_ms: 100 }
+  - id: "weekend-only"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+    fault: { type: reset }
+    schedule:
+      - days: [sat, sun]
+        start: "00:00"
+        end: "23:59"
+"#,
+        )
+        .unwrap()
+    }
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> SimulatedRequest {
+        SimulatedRequest {
+            method: "POST".to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            // A Wednesday
+            at: Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap(),
+            run_time_left: None,
+        }
+    }
+
+    #[test]
+    fn test_matching_experiments_and_sampling() {
+        let simulation = simulate(
+            &config(),
+            &request("/api/payments", &[("X-User-Id", "42")]),
+            1000,
+        );
+        assert!(simulation.spared.is_none());
+        let ids: Vec<&str> = simulation
+            .experiments
+            .iter()
+            .map(|e| e.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["payments-errors", "api-latency", "weekend-only"]);
+
+        // The first experiment always wins, so later ones never inject
+        assert_eq!(simulation.experiments[0].injected, 1000);
+        assert_eq!(simulation.experiments[1].injected, 0);
+        assert_eq!(simulation.experiments[0].expected_rate, 1.0);
+        assert_eq!(simulation.experiments[1].expected_rate, 0.0);
+        assert_eq!(
+            simulation.experiments[2].suppressed.as_deref(),
+            Some("outside its schedule")
+        );
+
+        let simulation = simulate(&config(), &request("/api/orders", &[]), 1000);
+        let injected = simulation.experiments[0].injected;
+        assert!((400..=600).contains(&injected), "{}", injected);
+        assert_eq!(simulation.experiments[0].expected_rate, 0.5);
+        assert_eq!(simulation.injected(), injected);
+    }
+
+    #[test]
+    fn test_spared_requests() {
+        let simulation = simulate(&config(), &request("/health", &[]), 100);
+        assert_eq!(
+            simulation.spared.as_deref(),
+            Some("excluded by safety.excluded_paths")
+        );
+        assert!(simulation.experiments.is_empty());
+
+        let mut disabled = config();
+        disabled.settings.enabled = false;
+        let simulation = simulate(&disabled, &request("/api/orders", &[]), 100);
+        assert_eq!(simulation.injected(), 0);
+        assert!(simulation
+            .to_string()
+            .contains("request passes through: chaos is disabled (settings.enabled: false)"));
+        assert!(simulation.experiments[0].suppressed.is_some());
+
+        let dir = std::env::temp_dir().join(format!("chaos-simulate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut killed = config();
+        killed.safety.kill_switch_file = Some(dir.join("chaos-disable"));
+        std::fs::write(dir.join("chaos-disable"), "").unwrap();
+        let simulation = simulate(&killed, &request("/api/orders", &[]), 100);
+        assert_eq!(
+            simulation.spared.as_deref(),
+            Some("the kill switch file exists")
+        );
+        assert_eq!(simulation.injected(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_phases_force_header_and_ramp_down() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+settings:
+  allow_force_header: true
+safety:
+  max_affected_percent: 100
+experiments:
+  - id: "slow-requests"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 50
+    fault: { type: latency, fixed_ms: 100 }
+    ramp_down_seconds: 600
+  - id: "slow-responses"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 100
+    fault: { type: latency, fixed_ms: 100, when: after }
+"#,
+        )
+        .unwrap();
+
+        // Each phase applies its own first winning experiment
+        let simulation = simulate(&config, &request("/api/orders", &[]), 1000);
+        assert_eq!(simulation.experiments[0].expected_rate, 0.5);
+        assert_eq!(simulation.experiments[1].expected_rate, 1.0);
+        assert_eq!(simulation.experiments[1].injected, 1000);
+        assert_eq!(simulation.faulted, 1000);
+
+        // Halfway through the ramp-down, half the percentage is left
+        let mut ramping = request("/api/orders", &[]);
+        ramping.run_time_left = Some(Duration::from_secs(300));
+        let simulation = simulate(&config, &ramping, 0);
+        assert_eq!(simulation.experiments[0].expected_rate, 0.25);
+
+        // Only the forced experiment fires, without a roll
+        let forced = request("/api/orders", &[("X-Chaos-Force", "slow-requests")]);
+        let simulation = simulate(&config, &forced, 100);
+        assert_eq!(simulation.experiments[0].expected_rate, 1.0);
+        assert_eq!(simulation.experiments[0].injected, 100);
+        assert_eq!(
+            simulation.experiments[1].suppressed.as_deref(),
+            Some("the force header names another experiment")
+        );
+        assert_eq!(simulation.experiments[1].injected, 0);
+    }
+}
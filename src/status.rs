@@ -0,0 +1,330 @@
+//! Summary of a running agent, used by the `status` subcommand.
+//!
+//! Fetches `GET /status` and `GET /experiments` from the admin API with the
+//! same minimal HTTP/1.1 the server speaks, and renders them for a terminal
+//! or as JSON for scripts.
+
+use crate::agent::{AgentStatus, ExperimentStatus};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Timeout for one admin API request, including connect.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Agent and experiment state as reported by the admin API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub agent: AgentStatus,
+    pub experiments: Vec<ExperimentStatus>,
+}
+
+impl StatusReport {
+    /// Why no experiment injects right now, empty when injections run.
+    pub fn suppressions(&self) -> Vec<String> {
+        let agent = &self.agent;
+        let mut suppressions = Vec::new();
+        if !agent.enabled {
+            suppressions.push("chaos is disabled (settings.enabled: false)".to_string());
+        }
+        if agent.paused {
+            suppressions.push("injections are paused".to_string());
+        }
+        if agent.draining {
+            suppressions.push("agent is draining".to_string());
+        }
+        if !agent.schedule.active {
+            suppressions.push("outside the schedule window".to_string());
+        }
+        if agent.auto_aborted {
+            suppressions.push("auto-abort tripped".to_string());
+        }
+        if agent.budget_exhausted {
+            suppressions.push("global injection budget exhausted".to_string());
+        }
+        suppressions
+    }
+}
+
+/// Fetch the status of the agent whose admin API listens on `addr`,
+/// authenticating with `token` if given.
+pub async fn fetch_status(addr: SocketAddr, token: Option<&str>) -> Result<StatusReport> {
+    let agent = get_json(addr, "/status", token).await?;
+    let experiments = get_json(addr, "/experiments", token).await?;
+    Ok(StatusReport {
+        agent: serde_json::from_value(agent).context("unexpected /status response")?,
+        experiments: serde_json::from_value(experiments)
+            .context("unexpected /experiments response")?,
+    })
+}
+
+/// Send a `GET` to the admin API and parse the JSON body of a `200`.
+async fn get_json(addr: SocketAddr, path: &str, token: Option<&str>) -> Result<Value> {
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, fetch(addr, path, token))
+        .await
+        .map_err(|_| anyhow!("admin API at {} timed out", addr))??;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| anyhow!("malformed response from {}", addr))?;
+    let status: u16 = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("malformed status line from {}", addr))?;
+    let body: Value = serde_json::from_str(body)
+        .with_context(|| format!("GET {} returned a body that is not JSON", path))?;
+    if status != 200 {
+        let message = body["error"].as_str().unwrap_or("unexpected response");
+        bail!("GET {} failed with {}: {}", path, status, message);
+    }
+    Ok(body)
+}
+
+async fn fetch(addr: SocketAddr, path: &str, token: Option<&str>) -> Result<String> {
+    let mut stream = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("cannot connect to the admin API at {}", addr))?;
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {}\r\n", token))
+        .unwrap_or_default();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\n{}Connection: close\r\n\r\n",
+        path, addr, authorization
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let agent = &self.agent;
+        let mut state = vec![if agent.enabled { "enabled" } else { "disabled" }.to_string()];
+        match (agent.paused, agent.pause_remaining_seconds) {
+            (true, Some(remaining)) => state.push(format!(
+                "paused (resumes in {})",
+                duration(remaining as i64)
+            )),
+            (true, None) => state.push("paused".to_string()),
+            (false, _) => {}
+        }
+        if agent.draining {
+            state.push("draining".to_string());
+        }
+        if agent.dry_run {
+            state.push("dry run".to_string());
+        }
+        writeln!(f, "state: {}", state.join(", "))?;
+        writeln!(f, "uptime: {}", duration(agent.uptime_seconds as i64))?;
+        if let Some(version) = &agent.config_version {
+            writeln!(f, "config version: {}", version)?;
+        }
+        let schedule = &agent.schedule;
+        let window = match (schedule.active, schedule.seconds_remaining) {
+            (true, -1) => "always active".to_string(),
+            (true, remaining) => format!("active, window ends in {}", duration(remaining)),
+            (false, _) if schedule.seconds_until_next >= 0 => format!(
+                "inactive, next window in {}",
+                duration(schedule.seconds_until_next)
+            ),
+            (false, _) => "inactive, no upcoming window".to_string(),
+        };
+        writeln!(f, "schedule: {}", window)?;
+        writeln!(
+            f,
+            "requests: {}, faults injected: {}, reload errors: {}",
+            agent.requests_total, agent.faults_injected, agent.config_reload_errors
+        )?;
+        let suppressions = self.suppressions();
+        if !suppressions.is_empty() {
+            writeln!(f, "suppressed: {}", suppressions.join(", "))?;
+        }
+        writeln!(f)?;
+
+        if self.experiments.is_empty() {
+            return writeln!(f, "no experiments");
+        }
+        let mut rows = vec![[
+            "EXPERIMENT".to_string(),
+            "STATE".to_string(),
+            "PERCENT".to_string(),
+            "INJECTIONS".to_string(),
+            "LAST INJECTION".to_string(),
+            "NOTES".to_string(),
+        ]];
+        for exp in &self.experiments {
+            let mut notes = exp.suppressions.clone();
+            if !exp.scheduled {
+                notes.insert(0, "outside its schedule".to_string());
+            }
+            if exp.armed > 0 {
+                notes.push(format!("armed {}", exp.armed));
+            }
+            if exp.ephemeral {
+                notes.push("runtime".to_string());
+            }
+            rows.push([
+                exp.id.clone(),
+                if exp.enabled { "enabled" } else { "disabled" }.to_string(),
+                format!("{}%", exp.effective_percentage),
+                exp.injections.to_string(),
+                exp.last_injection
+                    .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                notes.join(", "),
+            ]);
+        }
+        let mut widths = [0; 6];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        for row in &rows {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell))
+                .collect();
+            writeln!(f, "{}", line.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Render seconds as e.g. `2h 5m` or `42s`.
+fn duration(seconds: i64) -> String {
+    let (days, hours) = (seconds / 86_400, seconds % 86_400 / 3600);
+    let (minutes, seconds) = (seconds % 3600 / 60, seconds % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m {}s", minutes, seconds),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::serve;
+    use crate::{ChaosAgent, Config};
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+
+    const TOKEN: &str = "s3cret";
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+admin:
+  auth_token: "s3cret"
+safety:
+  max_affected_percent: 100
+experiments:
+  - id: "api-errors"
+    targeting:
+      paths: [{ prefix: "/api/" }]
+      percentage: 100
+    fault: { type: error, status: 503 }
+    max_injections: 1
+  - id: "static-latency"
+    enabled: false
+    targeting:
+      paths: [{ prefix: "/static/" }]
+      percentage: 10
+    fault: { type: latency, fixed_ms: 100 }
+"#,
+        )
+        .unwrap()
+    }
+
+    async fn start(agent: &ChaosAgent) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        serve(listener, agent.control());
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_fetch_status_from_running_agent() {
+        let agent = ChaosAgent::new(config());
+        let addr = start(&agent).await;
+        assert!(agent
+            .evaluate("GET", "/api/x", &HashMap::new())
+            .await
+            .is_some());
+
+        let report = fetch_status(addr, Some(TOKEN)).await.unwrap();
+        assert!(report.agent.enabled);
+        assert_eq!(report.agent.faults_injected, 1);
+        assert!(report.suppressions().is_empty());
+        let api = &report.experiments[0];
+        assert_eq!(api.injections, 1);
+        assert!(api.last_injection.is_some());
+        assert_eq!(api.suppressions, vec!["max_injections reached"]);
+        assert!(report.experiments[1].last_injection.is_none());
+
+        let text = report.to_string();
+        assert!(text.starts_with("state: enabled\nuptime: 0s\n"), "{}", text);
+        assert!(text.contains("schedule: always active\n"), "{}", text);
+        assert!(text.contains("requests: 1, faults injected: 1"), "{}", text);
+        let rows: Vec<&str> = text.lines().skip_while(|l| !l.is_empty()).collect();
+        assert!(rows[1].starts_with("EXPERIMENT      STATE     PERCENT  INJECTIONS"));
+        assert!(rows[2].ends_with("max_injections reached"), "{}", text);
+        assert!(rows[3].starts_with("static-latency  disabled  10%      0           -"));
+
+        // The JSON form round-trips
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<StatusReport>(&json).unwrap(), report);
+
+        agent.control().pause();
+        let report = fetch_status(addr, Some(TOKEN)).await.unwrap();
+        assert_eq!(report.suppressions(), vec!["injections are paused"]);
+        assert!(report
+            .to_string()
+            .contains("suppressed: injections are paused"));
+
+        agent.control().pause_for(Duration::from_secs(7200));
+        let report = fetch_status(addr, Some(TOKEN)).await.unwrap();
+        let text = report.to_string();
+        assert!(
+            text.starts_with("state: enabled, paused (resumes in 2h 0m)\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_status_errors() {
+        let agent = ChaosAgent::new(config());
+        let addr = start(&agent).await;
+        let err = fetch_status(addr, None).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "GET /status failed with 401: missing bearer token"
+        );
+
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = closed.local_addr().unwrap();
+        drop(closed);
+        let err = fetch_status(addr, Some(TOKEN)).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("cannot connect to the admin API"));
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(duration(42), "42s");
+        assert_eq!(duration(125), "2m 5s");
+        assert_eq!(duration(7500), "2h 5m");
+        assert_eq!(duration(90_000), "1d 1h");
+    }
+}
@@ -1,47 +1,247 @@
 //! Request targeting and matching logic.
 
-use crate::config::{PathMatcher, Targeting};
+use crate::config::{normalize_http_version, PathMatcher, PathNormalization, Settings, Targeting};
+use crate::rng::with_rng;
+use anyhow::{anyhow, Result};
 use rand::Rng;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::str::Split;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Pseudo-header carrying the request's HTTP version, as reported by the
+/// proxy. The leading colon keeps it apart from real header names.
+pub const HTTP_VERSION_HEADER: &str = ":http-version";
 
 /// Compiled targeting rules for efficient matching.
 pub struct CompiledTargeting {
     paths: Vec<CompiledPathMatcher>,
+    /// Per-path percentage scale from weighted matchers, parallel to
+    /// `paths`; empty when the paths are not weighted.
+    path_scales: Vec<f64>,
     methods: Vec<String>,
     headers: HashMap<String, String>,
-    percentage: u8,
+    headers_absent: Vec<String>,
+    /// Canonical HTTP versions; empty matches any.
+    http_versions: Vec<&'static str>,
+    min_content_length: Option<u64>,
+    max_content_length: Option<u64>,
+    /// Changeable at runtime, see [`set_percentage`](Self::set_percentage).
+    percentage: AtomicU8,
+    /// Fault every Nth request, replacing the percentage roll.
+    every_n: Option<u64>,
+    /// Requests that reached the roll, for `every_n`.
+    rolls: AtomicU64,
+    normalization: PathNormalization,
 }
 
 enum CompiledPathMatcher {
     Exact(String),
     Prefix(String),
     Regex(Regex),
+    Glob(PathGlob),
+}
+
+/// A path glob split into segments once at load time.
+///
+/// `*` matches exactly one non-empty segment and `**` any number of
+/// segments, including none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathGlob {
+    segments: Vec<GlobSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GlobSegment {
+    Literal(String),
+    One,
+    Any,
+}
+
+impl PathGlob {
+    /// Parse a glob, lowercasing literal segments if `case_insensitive`.
+    /// Consecutive `**` segments are collapsed into one.
+    pub fn parse(glob: &str, case_insensitive: bool) -> Result<Self> {
+        let rest = glob
+            .strip_prefix('/')
+            .ok_or_else(|| anyhow!("Invalid glob '{}': must start with '/'", glob))?;
+        let mut segments = rest
+            .split('/')
+            .map(|segment| match segment {
+                "*" => Ok(GlobSegment::One),
+                "**" => Ok(GlobSegment::Any),
+                s if s.contains('*') => Err(anyhow!(
+                    "Invalid glob '{}': '*' and '**' must be whole segments",
+                    glob
+                )),
+                s if case_insensitive => Ok(GlobSegment::Literal(s.to_lowercase())),
+                s => Ok(GlobSegment::Literal(s.to_string())),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        segments.dedup_by(|a, b| *a == GlobSegment::Any && *b == GlobSegment::Any);
+        Ok(Self { segments })
+    }
+
+    /// Check whether `path` matches the glob.
+    pub fn matches(&self, path: &str) -> bool {
+        path.strip_prefix('/')
+            .is_some_and(|rest| self.match_segments(rest, false))
+    }
+
+    /// Check whether the glob can match `path` or a path beneath it.
+    fn matches_under(&self, path: &str) -> bool {
+        path.strip_prefix('/')
+            .is_some_and(|rest| self.match_segments(rest, true))
+    }
+
+    /// Match the segments of `path`, which has no leading `/`. With
+    /// `under`, running out of path before the pattern still counts, since
+    /// deeper paths may supply the remaining segments.
+    ///
+    /// On a mismatch the latest `**` takes one more segment and matching
+    /// resumes after it. Earlier `**` never need revisiting, so a match
+    /// takes at most path segments × pattern segments steps, whatever the
+    /// path.
+    fn match_segments(&self, path: &str, under: bool) -> bool {
+        let pattern = &self.segments;
+        let mut index = 0;
+        let mut remaining = path.split('/');
+        // Pattern index after the latest `**`, and the path it resumes on
+        let mut backtrack: Option<(usize, Split<'_, char>)> = None;
+        loop {
+            let mut after = remaining.clone();
+            let Some(segment) = after.next() else {
+                return under || pattern[index..].iter().all(|s| *s == GlobSegment::Any);
+            };
+            let matched = match pattern.get(index) {
+                Some(GlobSegment::Any) => {
+                    index += 1;
+                    backtrack = Some((index, remaining.clone()));
+                    continue;
+                }
+                Some(GlobSegment::Literal(literal)) => literal == segment,
+                Some(GlobSegment::One) => !segment.is_empty(),
+                None => false,
+            };
+            if matched {
+                index += 1;
+                remaining = after;
+                continue;
+            }
+            let Some((resume, from)) = &mut backtrack else {
+                return false;
+            };
+            from.next();
+            index = *resume;
+            remaining = from.clone();
+        }
+    }
+}
+
+/// Spacing of `every_n` under `intensity` and a `ceiling` percentage, or
+/// `None` when nothing may be faulted.
+fn scaled_every_n(n: u64, intensity: f64, ceiling: u8) -> Option<u64> {
+    if intensity <= 0.0 || ceiling == 0 {
+        return None;
+    }
+    let scaled = (n.max(1) as f64 / intensity).ceil();
+    let narrowest = (100.0 / f64::from(ceiling.min(100))).ceil();
+    Some(scaled.max(narrowest) as u64)
 }
 
 impl CompiledTargeting {
     /// Compile targeting rules from configuration.
-    pub fn new(targeting: &Targeting) -> Self {
+    pub fn new(targeting: &Targeting) -> Result<Self> {
+        Self::with_settings(targeting, &Settings::default())
+    }
+
+    /// Compile targeting rules, matching paths under `normalization`.
+    pub fn with_normalization(
+        targeting: &Targeting,
+        normalization: PathNormalization,
+    ) -> Result<Self> {
+        let settings = Settings {
+            path_normalization: normalization,
+            ..Settings::default()
+        };
+        Self::with_settings(targeting, &settings)
+    }
+
+    /// Count the path matchers and HTTP versions in `targeting` that do
+    /// not compile under `settings`.
+    pub fn invalid_matchers(targeting: &Targeting, settings: &Settings) -> usize {
         let paths = targeting
             .paths
             .iter()
-            .filter_map(|p| match p {
-                PathMatcher::Exact { exact } => Some(CompiledPathMatcher::Exact(exact.clone())),
-                PathMatcher::Prefix { prefix } => Some(CompiledPathMatcher::Prefix(prefix.clone())),
-                PathMatcher::Regex { regex } => {
-                    Regex::new(regex).ok().map(CompiledPathMatcher::Regex)
-                }
-            })
-            .collect();
+            .filter(|p| CompiledPathMatcher::compile(p, settings.path_normalization).is_err())
+            .count();
+        let versions = targeting
+            .http_versions
+            .iter()
+            .filter(|v| normalize_http_version(v).is_none())
+            .count();
+        paths + versions
+    }
+
+    /// Compile targeting rules under the global settings: path
+    /// normalization and the headers carrying SNI and ALPN.
+    ///
+    /// Fails if a regex does not compile. Dropping the matcher instead
+    /// could leave `paths` empty, which would match every path.
+    pub fn with_settings(targeting: &Targeting, settings: &Settings) -> Result<Self> {
+        let normalization = settings.path_normalization;
+        let paths = targeting
+            .paths
+            .iter()
+            .map(|p| CompiledPathMatcher::compile(p, normalization))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Weights are relative: the heaviest path gets the full percentage
+        let weights: Vec<u32> = targeting.paths.iter().filter_map(|p| p.weight()).collect();
+        let path_scales = match weights.iter().max() {
+            Some(&max) if weights.len() == paths.len() => weights
+                .iter()
+                .map(|w| f64::from(*w) / f64::from(max))
+                .collect(),
+            _ => Vec::new(),
+        };
 
         let methods = targeting.methods.iter().map(|m| m.to_uppercase()).collect();
 
-        Self {
+        // SNI and ALPN are plain header matches on the configured names
+        let mut headers = targeting.headers.clone();
+        if let Some(sni) = &targeting.sni {
+            headers.insert(settings.sni_header.clone(), sni.clone());
+        }
+        if let Some(alpn) = &targeting.alpn {
+            headers.insert(settings.alpn_header.clone(), alpn.clone());
+        }
+
+        Ok(Self {
             paths,
+            path_scales,
             methods,
-            headers: targeting.headers.clone(),
-            percentage: targeting.percentage,
-        }
+            headers,
+            headers_absent: targeting
+                .headers_absent
+                .iter()
+                .map(|h| h.to_lowercase())
+                .collect(),
+            http_versions: targeting
+                .http_versions
+                .iter()
+                .map(|v| {
+                    normalize_http_version(v).ok_or_else(|| anyhow!("unknown HTTP version '{}'", v))
+                })
+                .collect::<Result<_>>()?,
+            min_content_length: targeting.min_content_length,
+            max_content_length: targeting.max_content_length,
+            percentage: AtomicU8::new(targeting.percentage),
+            every_n: targeting.every_n,
+            rolls: AtomicU64::new(0),
+            normalization,
+        })
     }
 
     /// Check if a request matches the targeting rules.
@@ -61,27 +261,220 @@ impl CompiledTargeting {
             return false;
         }
 
+        // An unreported version matches any
+        if !self.http_versions.is_empty() {
+            let version = headers
+                .get(HTTP_VERSION_HEADER)
+                .and_then(|v| normalize_http_version(v));
+            if version.is_some_and(|v| !self.http_versions.contains(&v)) {
+                return false;
+            }
+        }
+
+        // A size bound fails closed on requests without a length
+        if self.min_content_length.is_some() || self.max_content_length.is_some() {
+            let Some(length) = headers
+                .get("content-length")
+                .and_then(|v| v.trim().parse::<u64>().ok())
+            else {
+                return false;
+            };
+            if self.min_content_length.is_some_and(|min| length < min)
+                || self.max_content_length.is_some_and(|max| length > max)
+            {
+                return false;
+            }
+        }
+
         true
     }
 
-    /// Check if the request should be affected based on percentage.
+    /// Effective percentage of matching requests to affect.
+    pub fn percentage(&self) -> u8 {
+        self.percentage.load(Ordering::Relaxed)
+    }
+
+    /// Clamp the percentage to a ceiling, returning whether it was lowered.
+    pub fn clamp_percentage(&mut self, max_percent: u8) -> bool {
+        let percentage = self.percentage.get_mut();
+        if *percentage > max_percent {
+            *percentage = max_percent;
+            return true;
+        }
+        false
+    }
+
+    /// Replace the percentage of a live experiment, clamped to
+    /// `max_percent`, returning the new value.
+    pub fn set_percentage(&self, percentage: u8, max_percent: u8) -> u8 {
+        let percentage = percentage.min(max_percent);
+        self.percentage.store(percentage, Ordering::Relaxed);
+        percentage
+    }
+
+    /// Check if the request should be affected based on percentage, or
+    /// whether it is the Nth since the last one with `every_n`.
     pub fn should_apply(&self) -> bool {
-        if self.percentage >= 100 {
+        if let Some(n) = self.every_n {
+            return self.is_nth(n);
+        }
+        let percentage = self.percentage();
+        if percentage >= 100 {
             return true;
         }
-        if self.percentage == 0 {
+        if percentage == 0 {
             return false;
         }
-        let mut rng = rand::thread_rng();
-        rng.gen_range(0..100) < self.percentage
+        with_rng(|rng| rng.gen_range(0..100) < percentage)
+    }
+
+    /// Like [`should_apply`](Self::should_apply), with the percentage
+    /// multiplied by `intensity` and clamped to `0..=ceiling`.
+    ///
+    /// With `every_n` the spacing is divided by `intensity` instead, and
+    /// widened until one in N stays within the ceiling.
+    pub fn should_apply_scaled(&self, intensity: f64, ceiling: u8) -> bool {
+        if let Some(n) = self.every_n {
+            return scaled_every_n(n, intensity, ceiling).is_some_and(|n| self.is_nth(n));
+        }
+        if intensity == 1.0 {
+            return self.should_apply();
+        }
+        let effective = self.scaled_percentage(intensity, ceiling);
+        if effective >= 100.0 {
+            return true;
+        }
+        if effective <= 0.0 {
+            return false;
+        }
+        with_rng(|rng| rng.gen::<f64>() * 100.0 < effective)
+    }
+
+    /// Probability (0.0-1.0) that [`should_apply_scaled`](Self::should_apply_scaled)
+    /// returns true.
+    pub fn scaled_rate(&self, intensity: f64, ceiling: u8) -> f64 {
+        if let Some(n) = self.every_n {
+            return scaled_every_n(n, intensity, ceiling).map_or(0.0, |n| 1.0 / n as f64);
+        }
+        if intensity == 1.0 {
+            return f64::from(self.percentage().min(100)) / 100.0;
+        }
+        self.scaled_percentage(intensity, ceiling).min(100.0) / 100.0
+    }
+
+    /// Count a roll, returning whether it is a multiple of `n`.
+    fn is_nth(&self, n: u64) -> bool {
+        let count = self.rolls.fetch_add(1, Ordering::Relaxed) + 1;
+        count.is_multiple_of(n.max(1))
+    }
+
+    fn scaled_percentage(&self, intensity: f64, ceiling: u8) -> f64 {
+        (f64::from(self.percentage()) * intensity).clamp(0.0, f64::from(ceiling))
+    }
+
+    /// Whether the experiment spaces faults with `every_n` rather than a
+    /// percentage.
+    pub fn uses_every_n(&self) -> bool {
+        self.every_n.is_some()
+    }
+
+    /// Check whether every request matched by `other` is also matched by
+    /// these rules (ignoring percentage).
+    ///
+    /// This is a conservative, best-effort check: regex matchers only cover
+    /// exact paths they match and identical patterns.
+    pub fn covers(&self, other: &CompiledTargeting) -> bool {
+        if !self.methods.is_empty()
+            && (other.methods.is_empty() || !other.methods.iter().all(|m| self.methods.contains(m)))
+        {
+            return false;
+        }
+
+        for (name, value) in &self.headers {
+            let required = other
+                .headers
+                .iter()
+                .any(|(n, v)| n.eq_ignore_ascii_case(name) && v == value);
+            if !required {
+                return false;
+            }
+        }
+
+        if !self
+            .headers_absent
+            .iter()
+            .all(|name| other.headers_absent.contains(name))
+        {
+            return false;
+        }
+
+        let within_min = self
+            .min_content_length
+            .is_none_or(|min| other.min_content_length.is_some_and(|o| o >= min));
+        let within_max = self
+            .max_content_length
+            .is_none_or(|max| other.max_content_length.is_some_and(|o| o <= max));
+        if !within_min || !within_max {
+            return false;
+        }
+
+        if self.paths.is_empty() {
+            return true;
+        }
+        if other.paths.is_empty() {
+            return false;
+        }
+        other
+            .paths
+            .iter()
+            .all(|o| self.paths.iter().any(|s| s.covers(o)))
+    }
+
+    /// Return the excluded paths that these rules could otherwise match.
+    pub fn excluded_overlaps<'a>(&self, excluded_paths: &'a [String]) -> Vec<&'a str> {
+        excluded_paths
+            .iter()
+            .filter(|excluded| {
+                self.paths.is_empty() || self.paths.iter().any(|p| p.overlaps_excluded(excluded))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Scale applied to the percentage for a request on `path`: the weight
+    /// of the first matching path relative to the heaviest, or 1.0 when the
+    /// paths are not weighted.
+    pub fn path_scale(&self, path: &str) -> f64 {
+        if self.path_scales.is_empty() {
+            return 1.0;
+        }
+        let path = self.normalization.apply(path);
+        self.paths
+            .iter()
+            .zip(&self.path_scales)
+            .find(|(matcher, _)| matcher.matches(&path, self.normalization))
+            .map_or(1.0, |(_, scale)| *scale)
+    }
+
+    /// For each path matcher, the excluded paths it could otherwise match.
+    pub fn path_excluded_overlaps<'a>(&self, excluded_paths: &'a [String]) -> Vec<Vec<&'a str>> {
+        self.paths
+            .iter()
+            .map(|matcher| {
+                excluded_paths
+                    .iter()
+                    .filter(|excluded| matcher.overlaps_excluded(excluded))
+                    .map(String::as_str)
+                    .collect()
+            })
+            .collect()
     }
 
     fn matches_path(&self, path: &str) -> bool {
-        self.paths.iter().any(|matcher| match matcher {
-            CompiledPathMatcher::Exact(s) => path == s,
-            CompiledPathMatcher::Prefix(s) => path.starts_with(s),
-            CompiledPathMatcher::Regex(r) => r.is_match(path),
-        })
+        let path = self.normalization.apply(path);
+        self.paths
+            .iter()
+            .any(|matcher| matcher.matches(&path, self.normalization))
     }
 
     fn matches_headers(&self, headers: &HashMap<String, String>) -> bool {
@@ -94,21 +487,123 @@ impl CompiledTargeting {
                 _ => return false,
             }
         }
+        for name in &self.headers_absent {
+            if headers.keys().any(|k| k.to_lowercase() == *name) {
+                return false;
+            }
+        }
         true
     }
 }
 
+impl CompiledPathMatcher {
+    fn compile(matcher: &PathMatcher, normalization: PathNormalization) -> Result<Self> {
+        match matcher {
+            PathMatcher::Exact { exact } => Ok(CompiledPathMatcher::Exact(
+                normalization.apply(exact).into_owned(),
+            )),
+            // Prefixes keep their trailing slash so "/api/" does not match "/apiary"
+            PathMatcher::Prefix { prefix } => {
+                let prefix = if normalization.case_insensitive {
+                    prefix.to_lowercase()
+                } else {
+                    prefix.clone()
+                };
+                Ok(CompiledPathMatcher::Prefix(prefix))
+            }
+            PathMatcher::Regex { regex } => RegexBuilder::new(regex)
+                .case_insensitive(normalization.case_insensitive)
+                .build()
+                .map(CompiledPathMatcher::Regex)
+                .map_err(|e| anyhow!("Invalid regex pattern '{}': {}", regex, e)),
+            PathMatcher::Glob { glob } => {
+                PathGlob::parse(glob, normalization.case_insensitive).map(CompiledPathMatcher::Glob)
+            }
+            PathMatcher::Weighted { matcher, .. } => match **matcher {
+                PathMatcher::Weighted { .. } => {
+                    Err(anyhow!("Weighted path matchers cannot be nested"))
+                }
+                ref inner => Self::compile(inner, normalization),
+            },
+        }
+    }
+
+    /// Match a path already normalized under `normalization`.
+    fn matches(&self, path: &str, normalization: PathNormalization) -> bool {
+        match self {
+            CompiledPathMatcher::Exact(s) => path == s,
+            CompiledPathMatcher::Prefix(s) => {
+                // "/api/" stripped to "/api" still belongs to the "/api/" prefix
+                path.starts_with(s.as_str())
+                    || (normalization.strip_trailing_slash && s.strip_suffix('/') == Some(path))
+            }
+            CompiledPathMatcher::Regex(r) => r.is_match(path),
+            CompiledPathMatcher::Glob(g) => g.matches(path),
+        }
+    }
+
+    fn covers(&self, other: &CompiledPathMatcher) -> bool {
+        match (self, other) {
+            (CompiledPathMatcher::Exact(a), CompiledPathMatcher::Exact(b)) => a == b,
+            (CompiledPathMatcher::Prefix(a), CompiledPathMatcher::Exact(b)) => b.starts_with(a),
+            (CompiledPathMatcher::Prefix(a), CompiledPathMatcher::Prefix(b)) => b.starts_with(a),
+            (CompiledPathMatcher::Regex(r), CompiledPathMatcher::Exact(b)) => r.is_match(b),
+            (CompiledPathMatcher::Regex(a), CompiledPathMatcher::Regex(b)) => {
+                a.as_str() == b.as_str()
+            }
+            (CompiledPathMatcher::Glob(g), CompiledPathMatcher::Exact(b)) => g.matches(b),
+            (CompiledPathMatcher::Glob(a), CompiledPathMatcher::Glob(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn overlaps_excluded(&self, excluded: &str) -> bool {
+        match self {
+            CompiledPathMatcher::Exact(p) => is_under_path(p, excluded),
+            CompiledPathMatcher::Prefix(p) => {
+                excluded.starts_with(p.as_str()) || is_under_path(p, excluded)
+            }
+            CompiledPathMatcher::Regex(r) => {
+                r.is_match(excluded) || r.is_match(&format!("{}/", excluded))
+            }
+            CompiledPathMatcher::Glob(g) => g.matches_under(excluded),
+        }
+    }
+}
+
+impl PathNormalization {
+    /// Normalize a request path or configured path.
+    ///
+    /// The root path `/` is never stripped.
+    pub fn apply<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let mut path = Cow::Borrowed(path);
+        if self.strip_trailing_slash && path.len() > 1 && path.ends_with('/') {
+            let trimmed = path.trim_end_matches('/');
+            path = Cow::Owned(if trimmed.is_empty() { "/" } else { trimmed }.to_string());
+        }
+        if self.case_insensitive && path.chars().any(char::is_uppercase) {
+            path = Cow::Owned(path.to_lowercase());
+        }
+        path
+    }
+}
+
 /// Check if a path matches any of the excluded paths.
 pub fn is_excluded_path(path: &str, excluded_paths: &[String]) -> bool {
     excluded_paths
         .iter()
-        .any(|excluded| path == excluded || path.starts_with(&format!("{}/", excluded)))
+        .any(|excluded| is_under_path(path, excluded))
+}
+
+/// Check if a path equals `base` or lies beneath it.
+fn is_under_path(path: &str, base: &str) -> bool {
+    path == base || path.starts_with(&format!("{}/", base))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Targeting;
+    use crate::config::{PathNormalization, Targeting};
 
     fn create_targeting(
         paths: Vec<PathMatcher>,
@@ -123,7 +618,16 @@ mod tests {
                 .into_iter()
                 .map(|(k, v)| (k.to_string(), v.to_string()))
                 .collect(),
+            headers_absent: Vec::new(),
             percentage,
+            every_n: None,
+            client_cooldown: None,
+            sni: None,
+            alpn: None,
+            http_versions: Vec::new(),
+            min_content_length: None,
+            max_content_length: None,
+            days: Vec::new(),
         }
     }
 
@@ -137,7 +641,7 @@ mod tests {
             HashMap::new(),
             100,
         );
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         assert!(compiled.matches("GET", "/api/users", &HashMap::new()));
         assert!(!compiled.matches("GET", "/api/users/123", &HashMap::new()));
@@ -154,7 +658,7 @@ mod tests {
             HashMap::new(),
             100,
         );
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         assert!(compiled.matches("GET", "/api/users", &HashMap::new()));
         assert!(compiled.matches("GET", "/api/orders/123", &HashMap::new()));
@@ -171,17 +675,106 @@ mod tests {
             HashMap::new(),
             100,
         );
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         assert!(compiled.matches("GET", "/api/v1/users", &HashMap::new()));
         assert!(compiled.matches("GET", "/api/v2/orders", &HashMap::new()));
         assert!(!compiled.matches("GET", "/api/users", &HashMap::new()));
     }
 
+    #[test]
+    fn test_glob_path_matching() {
+        let compile = |glob: &str| {
+            CompiledTargeting::new(&create_targeting(
+                vec![PathMatcher::Glob {
+                    glob: glob.to_string(),
+                }],
+                vec![],
+                HashMap::new(),
+                100,
+            ))
+            .unwrap()
+        };
+        let none = HashMap::new();
+
+        // `*` matches exactly one segment
+        let single = compile("/api/*/users");
+        assert!(single.matches("GET", "/api/v1/users", &none));
+        assert!(!single.matches("GET", "/api/v1/v2/users", &none));
+        assert!(!single.matches("GET", "/api//users", &none));
+        assert!(!single.matches("GET", "/api/v1/orders", &none));
+
+        // `**` matches any depth, including none
+        let multi = compile("/api/**");
+        assert!(multi.matches("GET", "/api", &none));
+        assert!(multi.matches("GET", "/api/v1/users/123", &none));
+        assert!(!multi.matches("GET", "/apiary", &none));
+
+        let middle = compile("/api/**/orders");
+        assert!(middle.matches("GET", "/api/orders", &none));
+        assert!(middle.matches("GET", "/api/v1/eu/orders", &none));
+        assert!(!middle.matches("GET", "/api/v1/orders/1", &none));
+
+        assert_eq!(
+            compile("/**").excluded_overlaps(&["/health".to_string()]),
+            vec!["/health"]
+        );
+        assert!(compile("/api/*/users")
+            .excluded_overlaps(&["/health".to_string()])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_glob_many_wildcards() {
+        // Consecutive `**` collapse into one
+        assert_eq!(
+            PathGlob::parse("/a/**/**/**/b", false).unwrap(),
+            PathGlob::parse("/a/**/b", false).unwrap()
+        );
+
+        // Patterns with many `**` stay fast on long paths that almost match
+        let glob = PathGlob::parse("/**/a/**/b/**/c/**/d/**/e/**/f", false).unwrap();
+        let near_miss = format!("{}/e", "/a/b/c/d".repeat(2000));
+        assert!(!glob.matches(&near_miss));
+        assert!(glob.matches(&format!("{}/f", near_miss)));
+        assert!(glob.matches_under(&near_miss));
+        assert!(glob.matches_under("/x/a/b"));
+    }
+
+    #[test]
+    fn test_invalid_glob_fails_to_compile() {
+        for glob in ["api/*", "/api/v*/users"] {
+            let targeting = create_targeting(
+                vec![PathMatcher::Glob {
+                    glob: glob.to_string(),
+                }],
+                vec![],
+                HashMap::new(),
+                100,
+            );
+            let err = CompiledTargeting::new(&targeting).err().unwrap();
+            assert!(err.to_string().contains(glob), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_invalid_regex_fails_to_compile() {
+        let targeting = create_targeting(
+            vec![PathMatcher::Regex {
+                regex: "^/api/(users".to_string(),
+            }],
+            vec![],
+            HashMap::new(),
+            100,
+        );
+        let err = CompiledTargeting::new(&targeting).err().unwrap();
+        assert!(err.to_string().contains("^/api/(users"), "{}", err);
+    }
+
     #[test]
     fn test_method_matching() {
         let targeting = create_targeting(vec![], vec!["GET", "POST"], HashMap::new(), 100);
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         assert!(compiled.matches("GET", "/test", &HashMap::new()));
         assert!(compiled.matches("POST", "/test", &HashMap::new()));
@@ -197,7 +790,7 @@ mod tests {
             HashMap::from([("x-chaos-enabled", "true")]),
             100,
         );
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         let mut headers = HashMap::new();
         headers.insert("x-chaos-enabled".to_string(), "true".to_string());
@@ -218,13 +811,113 @@ mod tests {
             HashMap::from([("X-Chaos-Enabled", "true")]),
             100,
         );
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         let mut headers = HashMap::new();
         headers.insert("x-chaos-enabled".to_string(), "true".to_string());
         assert!(compiled.matches("GET", "/test", &headers));
     }
 
+    #[test]
+    fn test_alpn_and_sni_matching() {
+        let mut targeting = create_targeting(vec![], vec![], HashMap::new(), 100);
+        targeting.alpn = Some("h2".to_string());
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+
+        let h2 = HashMap::from([("X-ALPN".to_string(), "h2".to_string())]);
+        let http11 = HashMap::from([("x-alpn".to_string(), "http/1.1".to_string())]);
+        assert!(compiled.matches("GET", "/test", &h2));
+        assert!(!compiled.matches("GET", "/test", &http11));
+        assert!(!compiled.matches("GET", "/test", &HashMap::new()));
+
+        // Header names come from the settings
+        targeting.sni = Some("api.example.com".to_string());
+        let settings = Settings {
+            alpn_header: "x-forwarded-alpn".to_string(),
+            sni_header: "x-forwarded-sni".to_string(),
+            ..Settings::default()
+        };
+        let compiled = CompiledTargeting::with_settings(&targeting, &settings).unwrap();
+        let headers = HashMap::from([
+            ("x-forwarded-alpn".to_string(), "h2".to_string()),
+            ("x-forwarded-sni".to_string(), "api.example.com".to_string()),
+        ]);
+        assert!(compiled.matches("GET", "/test", &headers));
+        assert!(!compiled.matches("GET", "/test", &h2));
+    }
+
+    #[test]
+    fn test_http_version_matching() {
+        let mut targeting = create_targeting(vec![], vec![], HashMap::new(), 100);
+        targeting.http_versions = vec!["http/1.1".to_string(), "HTTP/2.0".to_string()];
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+
+        let version = |v: &str| HashMap::from([(HTTP_VERSION_HEADER.to_string(), v.to_string())]);
+        assert!(compiled.matches("GET", "/test", &version("HTTP/1.1")));
+        assert!(compiled.matches("GET", "/test", &version("HTTP/2")));
+        assert!(!compiled.matches("GET", "/test", &version("HTTP/1.0")));
+        assert!(!compiled.matches("GET", "/test", &version("HTTP/3")));
+
+        // Without a reported version every request matches
+        assert!(compiled.matches("GET", "/test", &HashMap::new()));
+
+        targeting.http_versions = vec!["SPDY/3".to_string()];
+        assert!(CompiledTargeting::new(&targeting).is_err());
+        assert!(targeting
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("targeting.http_versions: unknown HTTP version 'SPDY/3'"));
+    }
+
+    #[test]
+    fn test_content_length_matching() {
+        let mut targeting = create_targeting(vec![], vec![], HashMap::new(), 100);
+        targeting.min_content_length = Some(1_048_576);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+
+        let length = |n: &str| HashMap::from([("content-length".to_string(), n.to_string())]);
+        assert!(!compiled.matches("POST", "/upload", &length("1024")));
+        assert!(compiled.matches("POST", "/upload", &length("1048576")));
+        assert!(compiled.matches("POST", "/upload", &length("5000000")));
+
+        // Missing or unparsable lengths fail closed
+        assert!(!compiled.matches("POST", "/upload", &HashMap::new()));
+        assert!(!compiled.matches("POST", "/upload", &length("lots")));
+
+        targeting.max_content_length = Some(2_000_000);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+        assert!(compiled.matches("POST", "/upload", &length("2000000")));
+        assert!(!compiled.matches("POST", "/upload", &length("5000000")));
+
+        // A bounded range covers narrower ranges only
+        let unbounded =
+            CompiledTargeting::new(&create_targeting(vec![], vec![], HashMap::new(), 100)).unwrap();
+        assert!(unbounded.covers(&compiled));
+        assert!(!compiled.covers(&unbounded));
+
+        targeting.max_content_length = Some(1024);
+        assert!(targeting.validate().unwrap_err().to_string().contains(
+            "targeting.min_content_length: 1048576 is greater than max_content_length 1024"
+        ));
+    }
+
+    #[test]
+    fn test_header_absent_matching() {
+        let mut targeting = create_targeting(vec![], vec![], HashMap::new(), 100);
+        targeting.headers_absent = vec!["X-Api-Version".to_string()];
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+
+        assert!(compiled.matches("GET", "/test", &HashMap::new()));
+
+        let headers = HashMap::from([("x-api-version".to_string(), "2".to_string())]);
+        assert!(!compiled.matches("GET", "/test", &headers));
+
+        // An empty value still counts as present
+        let headers = HashMap::from([("x-api-version".to_string(), String::new())]);
+        assert!(!compiled.matches("GET", "/test", &headers));
+    }
+
     #[test]
     fn test_combined_matching() {
         let targeting = create_targeting(
@@ -235,7 +928,7 @@ mod tests {
             HashMap::from([("x-test", "yes")]),
             100,
         );
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         let mut headers = HashMap::new();
         headers.insert("x-test".to_string(), "yes".to_string());
@@ -256,7 +949,7 @@ mod tests {
     #[test]
     fn test_empty_targeting_matches_all() {
         let targeting = create_targeting(vec![], vec![], HashMap::new(), 100);
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         assert!(compiled.matches("GET", "/anything", &HashMap::new()));
         assert!(compiled.matches("POST", "/whatever", &HashMap::new()));
@@ -265,7 +958,7 @@ mod tests {
     #[test]
     fn test_percentage_zero_never_applies() {
         let targeting = create_targeting(vec![], vec![], HashMap::new(), 0);
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         // Run multiple times to ensure it never applies
         for _ in 0..100 {
@@ -276,7 +969,7 @@ mod tests {
     #[test]
     fn test_percentage_100_always_applies() {
         let targeting = create_targeting(vec![], vec![], HashMap::new(), 100);
-        let compiled = CompiledTargeting::new(&targeting);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
 
         // Run multiple times to ensure it always applies
         for _ in 0..100 {
@@ -294,4 +987,239 @@ mod tests {
         assert!(!is_excluded_path("/api/users", &excluded));
         assert!(!is_excluded_path("/healthy", &excluded));
     }
+
+    #[test]
+    fn test_case_insensitive_prefix_matching() {
+        let targeting = create_targeting(
+            vec![PathMatcher::Prefix {
+                prefix: "/API/".to_string(),
+            }],
+            vec![],
+            HashMap::new(),
+            100,
+        );
+        let normalization = PathNormalization {
+            case_insensitive: true,
+            strip_trailing_slash: true,
+        };
+        let compiled = CompiledTargeting::with_normalization(&targeting, normalization).unwrap();
+
+        assert!(compiled.matches("GET", "/api/users", &HashMap::new()));
+        assert!(compiled.matches("GET", "/Api/Users", &HashMap::new()));
+        assert!(compiled.matches("GET", "/api/", &HashMap::new()));
+        assert!(!compiled.matches("GET", "/apiary", &HashMap::new()));
+
+        // Default keeps matching case-sensitive
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+        assert!(!compiled.matches("GET", "/api/users", &HashMap::new()));
+    }
+
+    #[test]
+    fn test_trailing_slash_exclusion() {
+        let normalization = PathNormalization {
+            case_insensitive: false,
+            strip_trailing_slash: true,
+        };
+        let excluded = vec![normalization.apply("/health/").into_owned()];
+
+        assert!(is_excluded_path(
+            &normalization.apply("/health/"),
+            &excluded
+        ));
+        assert!(is_excluded_path(&normalization.apply("/health"), &excluded));
+        assert!(is_excluded_path(
+            &normalization.apply("/health//"),
+            &excluded
+        ));
+        assert!(!is_excluded_path(
+            &normalization.apply("/Health/"),
+            &excluded
+        ));
+        assert_eq!(normalization.apply("/"), "/");
+
+        // Default preserves the slash
+        let excluded = vec!["/health/".to_string()];
+        assert!(!is_excluded_path("/health", &excluded));
+    }
+
+    #[test]
+    fn test_covers() {
+        let broad = CompiledTargeting::new(&create_targeting(
+            vec![PathMatcher::Prefix {
+                prefix: "/api/".to_string(),
+            }],
+            vec![],
+            HashMap::new(),
+            100,
+        ))
+        .unwrap();
+        let narrow = CompiledTargeting::new(&create_targeting(
+            vec![PathMatcher::Exact {
+                exact: "/api/users".to_string(),
+            }],
+            vec!["GET"],
+            HashMap::from([("x-test", "yes")]),
+            100,
+        ))
+        .unwrap();
+
+        assert!(broad.covers(&narrow));
+        assert!(!narrow.covers(&broad));
+    }
+
+    #[test]
+    fn test_excluded_overlaps() {
+        let excluded = vec!["/health".to_string(), "/metrics".to_string()];
+
+        let root = CompiledTargeting::new(&create_targeting(
+            vec![PathMatcher::Prefix {
+                prefix: "/".to_string(),
+            }],
+            vec![],
+            HashMap::new(),
+            100,
+        ))
+        .unwrap();
+        assert_eq!(
+            root.excluded_overlaps(&excluded),
+            vec!["/health", "/metrics"]
+        );
+
+        let regex = CompiledTargeting::new(&create_targeting(
+            vec![PathMatcher::Regex {
+                regex: "^/health".to_string(),
+            }],
+            vec![],
+            HashMap::new(),
+            100,
+        ))
+        .unwrap();
+        assert_eq!(regex.excluded_overlaps(&excluded), vec!["/health"]);
+
+        let api = CompiledTargeting::new(&create_targeting(
+            vec![PathMatcher::Prefix {
+                prefix: "/api/".to_string(),
+            }],
+            vec![],
+            HashMap::new(),
+            100,
+        ))
+        .unwrap();
+        assert!(api.excluded_overlaps(&excluded).is_empty());
+    }
+
+    #[test]
+    fn test_path_excluded_overlaps_per_matcher() {
+        let excluded = vec!["/health".to_string(), "/metrics".to_string()];
+        let targeting = create_targeting(
+            vec![
+                PathMatcher::Exact {
+                    exact: "/health/live".to_string(),
+                },
+                PathMatcher::Exact {
+                    exact: "/healthz".to_string(),
+                },
+                PathMatcher::Prefix {
+                    prefix: "/".to_string(),
+                },
+                PathMatcher::Prefix {
+                    prefix: "/metrics/".to_string(),
+                },
+                PathMatcher::Regex {
+                    regex: "^/(health|status)$".to_string(),
+                },
+                PathMatcher::Regex {
+                    regex: "^/api/".to_string(),
+                },
+                PathMatcher::Glob {
+                    glob: "/*/live".to_string(),
+                },
+                PathMatcher::Weighted {
+                    matcher: Box::new(PathMatcher::Glob {
+                        glob: "/metrics/**".to_string(),
+                    }),
+                    weight: 1,
+                },
+            ],
+            vec![],
+            HashMap::new(),
+            100,
+        );
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+        let none: Vec<&str> = Vec::new();
+        assert_eq!(
+            compiled.path_excluded_overlaps(&excluded),
+            vec![
+                vec!["/health"],
+                none.clone(),
+                vec!["/health", "/metrics"],
+                vec!["/metrics"],
+                vec!["/health"],
+                none,
+                vec!["/health", "/metrics"],
+                vec!["/metrics"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_n_ignores_percentage() {
+        let mut targeting = create_targeting(vec![], vec![], HashMap::new(), 0);
+        targeting.every_n = Some(4);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+        let applied: Vec<bool> = (0..8)
+            .map(|_| compiled.should_apply_scaled(1.0, 100))
+            .collect();
+        assert_eq!(
+            applied,
+            vec![false, false, false, true, false, false, false, true]
+        );
+        assert_eq!(compiled.scaled_rate(1.0, 100), 0.25);
+        assert!(compiled.uses_every_n());
+
+        targeting.every_n = Some(0);
+        assert!(targeting
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("targeting.every_n: must be at least 1"));
+    }
+
+    #[test]
+    fn test_every_n_respects_intensity_and_ceiling() {
+        assert_eq!(scaled_every_n(4, 1.0, 100), Some(4));
+        assert_eq!(scaled_every_n(4, 0.5, 100), Some(8));
+        assert_eq!(scaled_every_n(4, 0.3, 100), Some(14));
+        assert_eq!(scaled_every_n(4, 2.0, 100), Some(2));
+        assert_eq!(scaled_every_n(4, 2.0, 10), Some(10));
+        assert_eq!(scaled_every_n(1, 1.0, 5), Some(20));
+        assert_eq!(scaled_every_n(4, 0.0, 100), None);
+        assert_eq!(scaled_every_n(4, 1.0, 0), None);
+
+        // every_n: 1 under a 5% ceiling faults 5 requests in 100
+        let mut targeting = create_targeting(vec![], vec![], HashMap::new(), 0);
+        targeting.every_n = Some(1);
+        let compiled = CompiledTargeting::new(&targeting).unwrap();
+        let faulted = (0..100)
+            .filter(|_| compiled.should_apply_scaled(1.0, 5))
+            .count();
+        assert_eq!(faulted, 5);
+        assert_eq!(compiled.scaled_rate(1.0, 5), 0.05);
+        assert_eq!(compiled.scaled_rate(0.0, 5), 0.0);
+    }
+
+    #[test]
+    fn test_clamp_percentage() {
+        let mut compiled =
+            CompiledTargeting::new(&create_targeting(vec![], vec![], HashMap::new(), 80)).unwrap();
+
+        assert!(!compiled.clamp_percentage(90));
+        assert_eq!(compiled.percentage(), 80);
+        assert!(compiled.clamp_percentage(50));
+        assert_eq!(compiled.percentage(), 50);
+
+        assert_eq!(compiled.set_percentage(100, 60), 60);
+        assert_eq!(compiled.set_percentage(0, 60), 0);
+        assert!(!compiled.should_apply());
+    }
 }
@@ -0,0 +1,265 @@
+//! Hot reload of the configuration file.
+//!
+//! Polls the modification time and size of the file, the files it
+//! includes and the files in its `experiments_dir`, and on change reloads
+//! through [`ChaosControl::reload_with`]. A new configuration that fails
+//! to parse or validate is rejected and the current one stays active, so
+//! a half-saved file never takes experiments down. After each successful
+//! reload the watched files are taken from the new configuration.
+
+use crate::agent::ChaosControl;
+use crate::config::{experiment_files, Config};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+/// How often the configuration file is checked for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Files whose changes reload a configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchedFiles {
+    /// Files read to build the configuration, besides the main file.
+    files: Vec<PathBuf>,
+    experiments_dir: Option<PathBuf>,
+}
+
+impl WatchedFiles {
+    /// The files `config`, loaded from `path`, was built from, and its
+    /// experiments directory.
+    pub fn of(config: &Config, path: &Path) -> Self {
+        Self {
+            files: config
+                .files
+                .iter()
+                .filter(|file| file.as_path() != path)
+                .cloned()
+                .collect(),
+            experiments_dir: config.experiments_dir_path(path),
+        }
+    }
+}
+
+/// Spawn a task that reloads the configuration when `path`, or one of the
+/// `watched` files it was built from, changes.
+///
+/// `load` reads the new configuration; it is only called after a file's
+/// modification time or size has changed, or a file was added or removed
+/// from the experiments directory.
+pub fn spawn_config_watcher<F>(
+    control: ChaosControl,
+    path: PathBuf,
+    mut watched: WatchedFiles,
+    interval: Duration,
+    load: F,
+) -> JoinHandle<()>
+where
+    F: Fn() -> Result<Config> + Send + 'static,
+{
+    // Taken up front so a change made right after spawning is not missed
+    let mut last = fingerprint(&path, &watched);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let current = fingerprint(&path, &watched);
+            if current == last {
+                continue;
+            }
+            let available = current.0.is_some();
+            last = current;
+            if !available {
+                debug!(path = %path.display(), "Config file unavailable, waiting for it to return");
+                continue;
+            }
+
+            // Includes and experiments_dir may have changed with the file
+            let mut reloaded = None;
+            let result = control.reload_with(|| {
+                let config = load()?;
+                reloaded = Some(WatchedFiles::of(&config, &path));
+                Ok(config)
+            });
+            match result {
+                Ok(()) => {
+                    if let Some(files) = reloaded.filter(|files| *files != watched) {
+                        watched = files;
+                        last = fingerprint(&path, &watched);
+                    }
+                    info!(path = %path.display(), "Config file changed, configuration reloaded")
+                }
+                Err(e) => error!(
+                    path = %path.display(),
+                    error = %e,
+                    "Config file changed but reload failed, keeping current configuration"
+                ),
+            }
+        }
+    })
+}
+
+/// Modification time and size of the main file, and of each other watched
+/// file and file in the experiments directory.
+type Fingerprint = (
+    Option<(SystemTime, u64)>,
+    Vec<(PathBuf, Option<(SystemTime, u64)>)>,
+);
+
+fn fingerprint(path: &Path, watched: &WatchedFiles) -> Fingerprint {
+    let mut files: Vec<_> = watched
+        .experiments_dir
+        .as_deref()
+        .and_then(|dir| experiment_files(dir).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .chain(watched.files.iter().cloned())
+        .map(|file| {
+            let stamp = file_stamp(&file);
+            (file, stamp)
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    (file_stamp(path), files)
+}
+
+/// Modification time and size of a file, or `None` if it cannot be read.
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChaosAgent;
+
+    const DISABLED: &str = r#"
+experiments:
+  - id: "errors"
+    enabled: false
+    targeting:
+      percentage: 100
+    fault:
+      type: error
+      status: 503
+"#;
+
+    async fn wait_for(check: impl Fn() -> bool) -> bool {
+        for _ in 0..200 {
+            if check() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_reloads_on_change() {
+        let path = std::env::temp_dir().join(format!("chaos-watch-{}.yaml", std::process::id()));
+        std::fs::write(&path, DISABLED).unwrap();
+
+        let agent = ChaosAgent::new(Config::from_file(&path).unwrap());
+        let load_path = path.clone();
+        let watcher = spawn_config_watcher(
+            agent.control(),
+            path.clone(),
+            WatchedFiles::default(),
+            Duration::from_millis(10),
+            move || Config::from_file(&load_path),
+        );
+        assert_eq!(agent.experiments_enabled(), 0);
+
+        std::fs::write(&path, DISABLED.replace("enabled: false", "enabled: true")).unwrap();
+        assert!(wait_for(|| agent.experiments_enabled() == 1).await);
+
+        // An invalid file is rejected and the current configuration kept
+        std::fs::write(&path, DISABLED.replace("status: 503", "status: 9999")).unwrap();
+        assert!(wait_for(|| agent.total_reload_errors() == 1).await);
+        assert_eq!(agent.experiments_enabled(), 1);
+
+        watcher.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_reloads_on_experiments_dir_change() {
+        let base = std::env::temp_dir().join(format!("chaos-watch-dir-{}", std::process::id()));
+        let dir = base.join("chaos.d");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = base.join("chaos.yaml");
+        std::fs::write(&path, "experiments_dir: chaos.d\n").unwrap();
+
+        let config = Config::from_file(&path).unwrap();
+        let watched = WatchedFiles::of(&config, &path);
+        let agent = ChaosAgent::new(config);
+        let load_path = path.clone();
+        let watcher = spawn_config_watcher(
+            agent.control(),
+            path.clone(),
+            watched,
+            Duration::from_millis(10),
+            move || Config::from_file(&load_path),
+        );
+        assert_eq!(agent.experiments_enabled(), 0);
+
+        // A new file in the directory is picked up
+        std::fs::write(
+            dir.join("team.yaml"),
+            DISABLED.replace("enabled: false", "enabled: true"),
+        )
+        .unwrap();
+        assert!(wait_for(|| agent.experiments_enabled() == 1).await);
+
+        watcher.abort();
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[tokio::test]
+    async fn test_watches_files_of_the_reloaded_config() {
+        let base = std::env::temp_dir().join(format!("chaos-watch-set-{}", std::process::id()));
+        std::fs::create_dir_all(base.join("old.d")).unwrap();
+        std::fs::create_dir_all(base.join("new.d")).unwrap();
+        let path = base.join("chaos.yaml");
+        let include = base.join("team.yaml");
+        std::fs::write(&path, "experiments_dir: old.d\n").unwrap();
+        std::fs::write(&include, DISABLED).unwrap();
+
+        let config = Config::from_file(&path).unwrap();
+        let watched = WatchedFiles::of(&config, &path);
+        let agent = ChaosAgent::new(config);
+        let load_path = path.clone();
+        let watcher = spawn_config_watcher(
+            agent.control(),
+            path.clone(),
+            watched,
+            Duration::from_millis(10),
+            move || Config::from_file(&load_path),
+        );
+
+        // Add an include and move the experiments directory
+        std::fs::write(&path, "experiments_dir: new.d\nincludes: [team.yaml]\n").unwrap();
+        assert!(wait_for(|| agent.control().experiment("errors").is_some()).await);
+
+        // The included file is now watched
+        std::fs::write(
+            &include,
+            DISABLED.replace("enabled: false", "enabled: true"),
+        )
+        .unwrap();
+        assert!(wait_for(|| agent.experiments_enabled() == 1).await);
+
+        // So is the new directory, and no longer the old one
+        let other = DISABLED.replace("\"errors\"", "\"other\"");
+        std::fs::write(base.join("old.d/other.yaml"), &other).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(agent.control().experiment("other").is_none());
+        std::fs::write(base.join("new.d/other.yaml"), &other).unwrap();
+        assert!(wait_for(|| agent.control().experiment("other").is_some()).await);
+
+        watcher.abort();
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
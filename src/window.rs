@@ -0,0 +1,111 @@
+//! Lock-free sliding-window counters.
+//!
+//! A window is a ring of one-second buckets. Each bucket remembers which
+//! second it was last written for, so stale buckets are reset lazily by the
+//! first writer in a new second and ignored by readers. There is no
+//! background task; reads sum the buckets that fall inside the window.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+struct Bucket {
+    /// Second this bucket currently holds counts for.
+    second: AtomicU64,
+    count: AtomicU64,
+}
+
+/// Event counter over the last N seconds.
+pub struct SlidingWindow {
+    buckets: Vec<Bucket>,
+}
+
+impl SlidingWindow {
+    /// Create a window covering `seconds` seconds (at least one).
+    pub fn new(seconds: u64) -> Self {
+        let buckets = (0..seconds.max(1))
+            .map(|_| Bucket {
+                second: AtomicU64::new(u64::MAX),
+                count: AtomicU64::new(0),
+            })
+            .collect();
+        Self { buckets }
+    }
+
+    /// Length of the window in seconds.
+    pub fn seconds(&self) -> u64 {
+        self.buckets.len() as u64
+    }
+
+    /// Record `n` events at second `now`.
+    ///
+    /// Rotation is approximate: increments racing with a bucket reset at a
+    /// second boundary may be dropped, which is acceptable for rate limits.
+    pub fn record(&self, now: u64, n: u64) {
+        let bucket = &self.buckets[(now % self.seconds()) as usize];
+        let second = bucket.second.load(Ordering::Acquire);
+        if second != now
+            && bucket
+                .second
+                .compare_exchange(second, now, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            bucket.count.store(0, Ordering::Release);
+        }
+        bucket.count.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Average events per second over the window ending at second `now`.
+    pub fn rate(&self, now: u64) -> f64 {
+        self.sum(now) as f64 / self.seconds() as f64
+    }
+
+    /// Sum of events recorded in the window ending at second `now`.
+    pub fn sum(&self, now: u64) -> u64 {
+        let oldest = now.saturating_sub(self.seconds() - 1);
+        self.buckets
+            .iter()
+            .filter(|b| {
+                let second = b.second.load(Ordering::Acquire);
+                second != u64::MAX && second >= oldest && second <= now
+            })
+            .map(|b| b.count.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        let window = SlidingWindow::new(10);
+        for second in 0..10 {
+            window.record(second, 3);
+        }
+        assert_eq!(window.rate(9), 3.0);
+        assert_eq!(window.rate(14), 1.5);
+    }
+
+    #[test]
+    fn test_sum_within_window() {
+        let window = SlidingWindow::new(3);
+        window.record(10, 1);
+        window.record(11, 2);
+        window.record(12, 3);
+
+        assert_eq!(window.sum(12), 6);
+        // Second 10 falls out of the window
+        assert_eq!(window.sum(13), 5);
+        assert_eq!(window.sum(20), 0);
+    }
+
+    #[test]
+    fn test_bucket_reuse_resets_count() {
+        let window = SlidingWindow::new(2);
+        window.record(0, 5);
+        window.record(2, 1);
+
+        // Second 2 reuses second 0's bucket
+        assert_eq!(window.sum(2), 1);
+    }
+}